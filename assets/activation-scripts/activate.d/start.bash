@@ -1,3 +1,4 @@
+_bash="@bash@/bin/bash"
 _comm="@coreutils@/bin/comm"
 _daemonize="@daemonize@/bin/daemonize"
 _flox_activations="@flox_activations@"
@@ -69,7 +70,9 @@ if [ -n "${_FLOX_WATCHDOG_BIN:-}" ]; then
     --socket "$_FLOX_SERVICES_SOCKET" \
     --flox-env "$FLOX_ENV" \
     --activation-id "$_FLOX_ACTIVATION_ID" \
-    --runtime-dir "$FLOX_RUNTIME_DIR"
+    --runtime-dir "$FLOX_RUNTIME_DIR" \
+    --linger "${_FLOX_SERVICES_LINGER:-0}" \
+    --hook-shell "$_bash"
 fi
 
 # Source the hook-on-activate script if it exists.
@@ -53,7 +53,10 @@ let
       (builtins.toFile "envrc-vars" (
         builtins.concatStringsSep "" (
           builtins.map (n: "export ${n}=\"${builtins.getAttr n manifestData.vars}\"\n") (
-            builtins.attrNames manifestData.vars
+            # References to env files and secrets are resolved at activation time
+            builtins.filter (n: builtins.isString (builtins.getAttr n manifestData.vars)) (
+              builtins.attrNames manifestData.vars
+            )
           )
         )
         # alternative ... worth it?
@@ -103,6 +106,20 @@ let
         else
           ""
       )
+      (
+        if (builtins.hasAttr "on-deactivate" hook) then
+          let
+            v = builtins.getAttr "on-deactivate" hook;
+          in
+          if (v != null) then
+            ''
+              cp ${builtins.toFile "hook-on-deactivate" v} $out/activate.d/hook-on-deactivate
+            ''
+          else
+            ""
+        else
+          ""
+      )
       # service-config.yaml section
       (
         if (serviceConfigYaml == null) then
@@ -3,8 +3,9 @@ use std::path::{Path, PathBuf};
 use anyhow::Context;
 use fslock::LockFile;
 use log::debug;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Map, Value};
 use time::OffsetDateTime;
 
 use crate::path_hash;
@@ -16,7 +17,9 @@ type Error = anyhow::Error;
 /// - `flox` and `flox-activation-scripts`
 /// - `flox-activations` and `flox-watchdog`
 ///
-/// Incrementing this will require existing activations to exit.
+/// Incrementing this requires a migration path from the previous version,
+/// see [ActivationsVersion].
+/// Without one, existing activations will have to exit.
 const LATEST_VERSION: u8 = 1;
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -43,14 +46,26 @@ impl Default for CheckedVersion {
 ///
 /// [Activations::version] describes both the version of the file format,
 /// and its interpretation, i.e. the version of the `flox-activations` binary that wrote it.
-/// When read, [Activations] will be parsed with an arbitrary [UncheckedVersion],
-/// wich must first be validated or upgraded with [Activations::check_version].
+/// When read, activations.json is parsed as [UncheckedActivations],
+/// which must first be validated or upgraded with [UncheckedActivations::check_version].
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Activations<VERSION = CheckedVersion> {
     version: VERSION,
     activations: Vec<Activation>,
 }
 
+/// activations.json as read from disk, written by any version of `flox-activations`.
+///
+/// Only the version is parsed eagerly,
+/// the remaining contents are kept untyped until [UncheckedActivations::check_version]
+/// determined which typed representation they have to be parsed as.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct UncheckedActivations {
+    version: UncheckedVersion,
+    #[serde(flatten)]
+    contents: Map<String, Value>,
+}
+
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
 #[error(
     "This environment has already been activated with an incompatible version of 'flox'.\n\
@@ -63,47 +78,167 @@ pub struct Unsupported {
     pub pids: Vec<i32>,
 }
 
-impl Activations<UncheckedVersion> {
+impl UncheckedActivations {
     /// Check the version of the activations file, and upgrade it if necessary.
     ///
-    /// Currently, this only checks if the version is the [LATEST_VERSION].
-    ///
-    /// As we don't yet have any schema changes,
-    /// it only only handles the interpretation of the Activations file,
-    /// i.e. the version of the `flox-activations` binary that wrote it.
-    ///
     /// If there are no activations, the version will be upgraded to the [LATEST_VERSION].
-    /// If in the future we change the intepretation or schema with a clear migration path,
-    /// this method would also upgrade the [Activations] to the new version.
+    /// Files written with a previous version are parsed as the representation
+    /// of that version and upgraded step by step to the [LATEST_VERSION],
+    /// see [ActivationsVersion].
+    /// Files with an unknown version, e.g. written by a newer version of flox,
+    /// or contents that don't match the schema of their version, are [Unsupported].
+    ///
+    /// The upgrade happens in memory only.
+    /// Callers hold the lock acquired by [read_activations_json],
+    /// so writing the returned [Activations] with [write_activations_json]
+    /// upgrades the file in place.
     pub fn check_version(self) -> Result<Activations<CheckedVersion>, Unsupported> {
-        if self.activations.is_empty() {
-            return Ok(Activations {
-                version: CheckedVersion(LATEST_VERSION),
-                activations: self.activations,
-            });
+        if self.is_empty() {
+            return Ok(Activations::default());
         }
 
-        if self.version.0 == LATEST_VERSION {
-            return Ok(Activations {
-                version: CheckedVersion(self.version.0),
-                activations: self.activations,
-            });
+        let pids = self.attached_pids();
+        let mut contents = self.contents;
+        contents.insert("version".to_string(), Value::from(self.version.0));
+
+        match migrate(self.version.0, Value::Object(contents)) {
+            Some(Ok(activations)) => Ok(activations),
+            Some(Err(err)) => {
+                debug!(
+                    "failed to parse activations.json with version {}: {err}",
+                    self.version.0
+                );
+                Err(Unsupported {
+                    version: self.version,
+                    pids,
+                })
+            },
+            None => Err(Unsupported {
+                version: self.version,
+                pids,
+            }),
         }
+    }
 
-        return Err(Unsupported {
-            version: self.version,
-            pids: self
-                .activations
-                .iter()
-                .flat_map(|activation| {
-                    activation
-                        .attached_pids
-                        .iter()
-                        .map(|attached_pid| attached_pid.pid)
-                })
-                .collect(),
-        });
+    fn is_empty(&self) -> bool {
+        self.activations().next().is_none()
     }
+
+    /// Iterate over the untyped activations.
+    ///
+    /// This and the accessors built on it don't rely on the schema of a specific version,
+    /// so that a running `flox-watchdog` keeps working
+    /// after a newer version of flox upgraded activations.json.
+    fn activations(&self) -> impl Iterator<Item = &Map<String, Value>> {
+        self.contents
+            .get("activations")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object)
+    }
+
+    fn activation_for_id(&self, activation_id: &str) -> Option<&Map<String, Value>> {
+        self.activations()
+            .find(|activation| activation.get("id").and_then(Value::as_str) == Some(activation_id))
+    }
+
+    /// Collect the PIDs attached to any activation.
+    fn attached_pids(&self) -> Vec<i32> {
+        self.activations()
+            .filter_map(|activation| activation.get("attached_pids")?.as_array())
+            .flatten()
+            .filter_map(|attached_pid| attached_pid.get("pid")?.as_i64())
+            .map(|pid| pid as i32)
+            .collect()
+    }
+
+    /// Get the PIDs attached to the activation with the given ID.
+    ///
+    /// Expirations that can't be parsed are ignored.
+    pub fn attached_pids_for_id(&self, activation_id: impl AsRef<str>) -> Option<Vec<AttachedPid>> {
+        let attached_pids = self
+            .activation_for_id(activation_id.as_ref())?
+            .get("attached_pids")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|attached_pid| {
+                let pid = attached_pid.get("pid")?.as_i64()? as i32;
+                let expiration = attached_pid
+                    .get("expiration")
+                    .and_then(|expiration| serde_json::from_value(expiration.clone()).ok());
+                Some(AttachedPid { pid, expiration })
+            })
+            .collect();
+        Some(attached_pids)
+    }
+
+    /// Get the store path of the activation with the given ID.
+    pub fn store_path_for_id(&self, activation_id: impl AsRef<str>) -> Option<String> {
+        self.activation_for_id(activation_id.as_ref())?
+            .get("store_path")?
+            .as_str()
+            .map(ToString::to_string)
+    }
+
+    /// Whether there are activations other than the one with the given ID.
+    pub fn has_other_activations(&self, activation_id: impl AsRef<str>) -> bool {
+        self.activations().any(|activation| {
+            activation.get("id").and_then(Value::as_str) != Some(activation_id.as_ref())
+        })
+    }
+}
+
+/// A typed representation of a version of activations.json.
+///
+/// Every version that can still be migrated implements this trait.
+/// When changing the schema or interpretation of activations.json:
+///
+/// 1. Move the current definition of [Activations] and its members
+///    into a module for the previous version, e.g. `v1`,
+///    and implement this trait for it with the old [ActivationsVersion::VERSION].
+/// 2. Update [Activations] and increment [LATEST_VERSION].
+/// 3. Implement the upgrade from the previous version to the new one,
+///    and have [ActivationsVersion::into_latest] of the previous version
+///    call it and continue with the `into_latest` of the next version.
+/// 4. Add the previous version to [migrate].
+///
+/// That way each version only needs to know how to upgrade to its direct successor,
+/// while files of any known version are upgraded to the [LATEST_VERSION].
+pub trait ActivationsVersion: DeserializeOwned {
+    /// The value of `version` in files with this representation.
+    const VERSION: u8;
+
+    /// Upgrade to the latest representation, by way of all intermediate versions.
+    fn into_latest(self) -> Activations<CheckedVersion>;
+}
+
+impl ActivationsVersion for Activations<CheckedVersion> {
+    const VERSION: u8 = LATEST_VERSION;
+
+    fn into_latest(self) -> Activations<CheckedVersion> {
+        self
+    }
+}
+
+/// Parse the untyped contents of activations.json written with `version`
+/// and upgrade them to the [LATEST_VERSION].
+///
+/// Returns `None` if there is no migration path from `version`.
+/// No previous version has a typed representation yet,
+/// so currently only files of the [LATEST_VERSION] are accepted.
+fn migrate(version: u8, contents: Value) -> Option<Result<Activations, serde_json::Error>> {
+    match version {
+        <Activations as ActivationsVersion>::VERSION => Some(upgrade::<Activations>(contents)),
+        _ => None,
+    }
+}
+
+/// Parse `contents` as the representation `V` and upgrade it to the [LATEST_VERSION].
+fn upgrade<V: ActivationsVersion>(contents: Value) -> Result<Activations, serde_json::Error> {
+    let activations: V = serde_json::from_value(contents)?;
+    Ok(activations.into_latest())
 }
 
 impl Activations<CheckedVersion> {
@@ -216,6 +351,11 @@ impl Activation {
         self.id.clone()
     }
 
+    /// The store path of the built environment that is activated.
+    pub fn store_path(&self) -> String {
+        self.store_path.clone()
+    }
+
     /// Whether the activation is ready to be attached to.
     ///
     /// "Readiness" is a one way state change, set via [Self::set_ready].
@@ -335,7 +475,7 @@ pub fn activation_state_dir_path(
 /// which should be reused for writing, to avoid TOCTOU issues.
 pub fn read_activations_json(
     path: impl AsRef<Path>,
-) -> Result<(Option<Activations<UncheckedVersion>>, LockFile), Error> {
+) -> Result<(Option<UncheckedActivations>, LockFile), Error> {
     let path = path.as_ref();
     let lock_file = acquire_activations_json_lock(path).context("failed to acquire lockfile")?;
 
@@ -345,7 +485,7 @@ pub fn read_activations_json(
     }
 
     let contents = std::fs::read_to_string(path)?;
-    let parsed: Activations<UncheckedVersion> = serde_json::from_str(&contents)?;
+    let parsed: UncheckedActivations = serde_json::from_str(&contents)?;
     Ok((Some(parsed), lock_file))
 }
 
@@ -369,12 +509,29 @@ pub fn write_activations_json<V: Serialize>(
 mod test {
     use super::*;
 
+    fn unchecked_activations(version: u8, activations: Value) -> UncheckedActivations {
+        serde_json::from_value(json!({
+            "version": version,
+            "activations": activations,
+        }))
+        .unwrap()
+    }
+
+    fn activations_with_pids() -> Value {
+        json!([{
+            "id": "1",
+            "store_path": "/store/path",
+            "ready": false,
+            "attached_pids": [
+                { "pid": 123, "expiration": null },
+                { "pid": 456, "expiration": null },
+            ],
+        }])
+    }
+
     #[test]
     fn check_version_upgrade() {
-        let activations = Activations::<UncheckedVersion> {
-            version: UncheckedVersion(0),
-            activations: vec![],
-        };
+        let activations = unchecked_activations(0, json!([]));
 
         let checked_activations = activations.check_version().unwrap();
         assert_eq!(
@@ -385,52 +542,19 @@ mod test {
 
     #[test]
     fn activations_latest() {
-        let activations = Activations::<UncheckedVersion> {
-            version: UncheckedVersion(LATEST_VERSION),
-            activations: vec![Activation {
-                id: "1".to_string(),
-                store_path: "/store/path".to_string(),
-                ready: false,
-                attached_pids: vec![
-                    AttachedPid {
-                        pid: 123,
-                        expiration: None,
-                    },
-                    AttachedPid {
-                        pid: 456,
-                        expiration: None,
-                    },
-                ],
-            }],
-        };
+        let activations = unchecked_activations(LATEST_VERSION, activations_with_pids());
 
         let checked_activations = activations.check_version().unwrap();
         assert_eq!(
             checked_activations.version.0, LATEST_VERSION,
             "should not upgrade version when version is latest"
         );
+        assert_eq!(checked_activations.activations[0].attached_pids.len(), 2);
     }
 
     #[test]
     fn activations_refuse_upgrade() {
-        let activations = Activations::<UncheckedVersion> {
-            version: UncheckedVersion(0),
-            activations: vec![Activation {
-                id: "1".to_string(),
-                store_path: "/store/path".to_string(),
-                ready: false,
-                attached_pids: vec![
-                    AttachedPid {
-                        pid: 123,
-                        expiration: None,
-                    },
-                    AttachedPid {
-                        pid: 456,
-                        expiration: None,
-                    },
-                ],
-            }],
-        };
+        let activations = unchecked_activations(0, activations_with_pids());
 
         let unsupported = activations.check_version().unwrap_err();
         assert_eq!(
@@ -443,6 +567,143 @@ mod test {
         );
     }
 
+    #[test]
+    fn activations_refuse_newer_version() {
+        let activations = unchecked_activations(LATEST_VERSION + 1, activations_with_pids());
+
+        let unsupported = activations.check_version().unwrap_err();
+        assert_eq!(unsupported.version, UncheckedVersion(LATEST_VERSION + 1));
+        assert_eq!(unsupported.pids, vec![123, 456]);
+    }
+
+    #[test]
+    fn unchecked_activations_are_read_without_schema() {
+        let activations = unchecked_activations(
+            LATEST_VERSION + 1,
+            json!([
+                {
+                    "id": "1",
+                    "store_path": "/store/path",
+                    "attached_pids": [
+                        { "pid": 123, "expiration": null },
+                        { "pid": 456, "expiration": "in the future" },
+                    ],
+                    "unknown": true,
+                },
+                { "id": "2" },
+            ]),
+        );
+
+        assert_eq!(
+            activations.attached_pids_for_id("1"),
+            Some(vec![
+                AttachedPid {
+                    pid: 123,
+                    expiration: None
+                },
+                AttachedPid {
+                    pid: 456,
+                    expiration: None
+                },
+            ])
+        );
+        assert_eq!(activations.attached_pids_for_id("2"), Some(vec![]));
+        assert_eq!(activations.attached_pids_for_id("3"), None);
+        assert_eq!(
+            activations.store_path_for_id("1"),
+            Some("/store/path".to_string())
+        );
+        assert!(activations.has_other_activations("1"));
+        assert!(
+            !unchecked_activations(LATEST_VERSION + 1, json!([{ "id": "1" }]))
+                .has_other_activations("1")
+        );
+    }
+
+    #[test]
+    fn activations_refuse_invalid_contents() {
+        let activations = unchecked_activations(
+            LATEST_VERSION,
+            json!([{ "attached_pids": [{ "pid": 123 }] }]),
+        );
+
+        let unsupported = activations.check_version().unwrap_err();
+        assert_eq!(unsupported.pids, vec![123]);
+    }
+
+    /// A made up previous version, that tracked bare PIDs per store path.
+    #[derive(Deserialize)]
+    struct ActivationsV0 {
+        activations: Vec<ActivationV0>,
+    }
+
+    #[derive(Deserialize)]
+    struct ActivationV0 {
+        store_path: String,
+        pids: Vec<i32>,
+    }
+
+    impl ActivationsVersion for ActivationsV0 {
+        const VERSION: u8 = 0;
+
+        fn into_latest(self) -> Activations<CheckedVersion> {
+            let mut next = Activations::default();
+            for activation in self.activations {
+                let mut pids = activation.pids.into_iter();
+                let Some(first) = pids.next() else {
+                    continue;
+                };
+                let upgraded = next
+                    .create_activation(&activation.store_path, first)
+                    .unwrap();
+                upgraded.set_ready();
+                for pid in pids {
+                    upgraded.attach_pid(pid, None);
+                }
+            }
+            next.into_latest()
+        }
+    }
+
+    #[test]
+    fn upgrade_previous_version() {
+        let contents = json!({
+            "version": 0,
+            "activations": [{ "store_path": "/store/path", "pids": [123, 456] }],
+        });
+
+        let activations = upgrade::<ActivationsV0>(contents).unwrap();
+        assert_eq!(activations.version.0, LATEST_VERSION);
+
+        let activation = activations
+            .activation_for_store_path("/store/path")
+            .unwrap();
+        assert!(activation.ready());
+        assert_eq!(
+            activation
+                .attached_pids()
+                .iter()
+                .map(|attached_pid| attached_pid.pid)
+                .collect::<Vec<_>>(),
+            vec![123, 456]
+        );
+    }
+
+    #[test]
+    fn check_version_roundtrips_written_activations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("activations.json");
+
+        let mut activations = Activations::default();
+        activations.create_activation("/store/path", 123).unwrap();
+
+        let (_, lock) = read_activations_json(&path).unwrap();
+        write_activations_json(&activations, &path, lock).unwrap();
+
+        let (read, _lock) = read_activations_json(&path).unwrap();
+        assert_eq!(read.unwrap().check_version().unwrap(), activations);
+    }
+
     #[test]
     fn create_activation() {
         let mut activations = Activations::<CheckedVersion>::default();
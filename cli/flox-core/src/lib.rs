@@ -3,6 +3,7 @@ pub mod canonical_path;
 #[cfg(feature = "proc_status")]
 pub mod proc_status;
 mod version;
+pub mod watchdog_status;
 
 use std::io::BufWriter;
 use std::os::unix::ffi::OsStrExt;
@@ -21,6 +22,11 @@ pub fn path_hash(p: impl AsRef<Path>) -> String {
     chars.to_string()
 }
 
+/// Returns the hash of arbitrary content, e.g. to identify a version of a file
+pub fn content_hash(content: impl AsRef<[u8]>) -> String {
+    blake3::hash(content.as_ref()).to_hex().to_string()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum SerializeError {
     #[error("file stored in an invalid location: {0}")]
@@ -0,0 +1,222 @@
+//! Status endpoint of `flox-watchdog`
+//!
+//! Every watchdog listens on a Unix socket in the state dir of the activation
+//! it watches, see [watchdog_socket_path].
+//! Clients send a single [WatchdogRequest] as a line of JSON,
+//! and receive a single [WatchdogResponse] as a line of JSON in return.
+//! Since the activation state dir is removed by the watchdog during cleanup,
+//! a socket that can't be connected to belongs to a watchdog that is no longer running.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+type Error = anyhow::Error;
+
+/// File name of the watchdog socket within the activation state dir
+const WATCHDOG_SOCKET_NAME: &str = "watchdog.sock";
+
+/// How long clients wait for a watchdog to respond
+const WATCHDOG_SOCKET_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// {activation_state_dir}/watchdog.sock
+pub fn watchdog_socket_path(activation_state_dir: impl AsRef<Path>) -> PathBuf {
+    activation_state_dir.as_ref().join(WATCHDOG_SOCKET_NAME)
+}
+
+/// Socket paths cannot exceed 104 characters on macOS
+#[cfg(target_os = "macos")]
+const MAX_SOCKET_PATH_LENGTH: usize = 104;
+/// 108 minus a null character
+#[cfg(target_os = "linux")]
+const MAX_SOCKET_PATH_LENGTH: usize = 107;
+
+/// Ensure that a socket can be bound at `socket_path`,
+/// which may not be the case for long runtime dirs.
+pub fn check_watchdog_socket_path(socket_path: impl AsRef<Path>) -> Result<(), Error> {
+    let socket_path = socket_path.as_ref();
+    if socket_path.as_os_str().len() > MAX_SOCKET_PATH_LENGTH {
+        bail!(
+            "path for watchdog socket is too long: {}",
+            socket_path.display()
+        );
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum WatchdogRequest {
+    /// Report the [WatchdogStatus]
+    Status,
+    /// Clean up the activation and exit, as if all attached PIDs had terminated
+    Shutdown,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum WatchdogResponse {
+    Status(WatchdogStatus),
+    /// The watchdog accepted a [WatchdogRequest::Shutdown]
+    ShuttingDown,
+    /// The request could not be parsed
+    Error {
+        message: String,
+    },
+}
+
+/// The state of a running watchdog
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WatchdogStatus {
+    /// PID of the watchdog itself
+    pub pid: i32,
+    /// The activation symlink of the environment
+    pub flox_env: PathBuf,
+    /// The ID of the watched activation
+    pub activation_id: String,
+    /// PIDs that are attached to the activation and still considered running
+    pub watched_pids: Vec<i32>,
+    /// When the watchdog last read activations.json
+    pub last_poll: Option<OffsetDateTime>,
+    /// The process-compose socket of the environment's services
+    pub services_socket: PathBuf,
+    /// Whether all attached PIDs have terminated or cleanup was requested,
+    /// and cleanup is going to be performed
+    pub pending_cleanup: bool,
+}
+
+/// Send `request` to the watchdog listening on `socket_path` and wait for its response.
+pub fn query_watchdog(
+    socket_path: impl AsRef<Path>,
+    request: &WatchdogRequest,
+) -> Result<WatchdogResponse, Error> {
+    let socket_path = socket_path.as_ref();
+    let mut stream = UnixStream::connect(socket_path)
+        .with_context(|| format!("failed to connect to {}", socket_path.display()))?;
+    stream.set_read_timeout(Some(WATCHDOG_SOCKET_TIMEOUT))?;
+    stream.set_write_timeout(Some(WATCHDOG_SOCKET_TIMEOUT))?;
+
+    let mut request = serde_json::to_string(request)?;
+    request.push('\n');
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .context("failed to read response from watchdog")?;
+    let response = serde_json::from_str(&response).context("failed to parse watchdog response")?;
+    Ok(response)
+}
+
+/// Find the sockets of all watchdogs of all environments in `runtime_dir`.
+///
+/// Sockets are located at `{runtime_dir}/{path_hash(flox_env)}/{activation_id}/watchdog.sock`.
+/// Sockets are only found but not checked for liveness,
+/// use [query_watchdog] to determine whether a watchdog is still running.
+pub fn find_watchdog_sockets(runtime_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
+    let Ok(env_dirs) = std::fs::read_dir(runtime_dir.as_ref()) else {
+        return Ok(Vec::new());
+    };
+
+    let mut sockets = Vec::new();
+    for env_dir in env_dirs {
+        let env_dir = env_dir?.path();
+        if !env_dir.is_dir() {
+            continue;
+        }
+        for activation_dir in std::fs::read_dir(&env_dir)? {
+            let socket_path = watchdog_socket_path(activation_dir?.path());
+            if socket_path.exists() {
+                sockets.push(socket_path);
+            }
+        }
+    }
+    sockets.sort();
+    Ok(sockets)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixListener;
+
+    use super::*;
+
+    #[test]
+    fn requests_roundtrip() {
+        for request in [WatchdogRequest::Status, WatchdogRequest::Shutdown] {
+            let serialized = serde_json::to_string(&request).unwrap();
+            assert_eq!(
+                serde_json::from_str::<WatchdogRequest>(&serialized).unwrap(),
+                request
+            );
+        }
+        assert_eq!(
+            serde_json::to_string(&WatchdogRequest::Status).unwrap(),
+            r#"{"request":"status"}"#
+        );
+    }
+
+    #[test]
+    fn query_watchdog_reads_response() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = watchdog_socket_path(temp_dir.path());
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        let status = WatchdogStatus {
+            pid: 123,
+            activation_id: "id".to_string(),
+            watched_pids: vec![456],
+            ..Default::default()
+        };
+
+        let server = {
+            let status = status.clone();
+            std::thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let mut request = String::new();
+                BufReader::new(&stream).read_line(&mut request).unwrap();
+                assert_eq!(
+                    serde_json::from_str::<WatchdogRequest>(&request).unwrap(),
+                    WatchdogRequest::Status
+                );
+                let response = serde_json::to_string(&WatchdogResponse::Status(status)).unwrap();
+                writeln!(&stream, "{response}").unwrap();
+            })
+        };
+
+        let response = query_watchdog(&socket_path, &WatchdogRequest::Status).unwrap();
+        server.join().unwrap();
+        assert_eq!(response, WatchdogResponse::Status(status));
+    }
+
+    #[test]
+    fn rejects_long_socket_paths() {
+        let short = watchdog_socket_path("/run/flox/hash/id");
+        assert!(check_watchdog_socket_path(short).is_ok());
+
+        let long = watchdog_socket_path(format!("/run/{}", "a".repeat(MAX_SOCKET_PATH_LENGTH)));
+        assert!(check_watchdog_socket_path(long).is_err());
+    }
+
+    #[test]
+    fn finds_watchdog_sockets() {
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let activation_dir = runtime_dir.path().join("envhash").join("activation");
+        std::fs::create_dir_all(&activation_dir).unwrap();
+        std::fs::create_dir_all(runtime_dir.path().join("envhash").join("other")).unwrap();
+        std::fs::write(
+            runtime_dir.path().join("envhash").join("activations.json"),
+            "",
+        )
+        .unwrap();
+        let _listener = UnixListener::bind(watchdog_socket_path(&activation_dir)).unwrap();
+
+        let sockets = find_watchdog_sockets(runtime_dir.path()).unwrap();
+        assert_eq!(sockets, vec![watchdog_socket_path(&activation_dir)]);
+    }
+}
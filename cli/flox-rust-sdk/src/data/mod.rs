@@ -1,9 +1,11 @@
 mod flox_version;
+mod nix_version;
 
 use std::fmt::Display;
 
 pub use flox_core::canonical_path::{CanonicalPath, CanonicalizeError};
 pub use flox_version::FloxVersion;
+pub use nix_version::compare_versions;
 pub type System = String;
 
 /// Different representations of the same attribute path
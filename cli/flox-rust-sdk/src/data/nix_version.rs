@@ -0,0 +1,93 @@
+//! Comparison of version strings as done by Nix
+
+use std::cmp::Ordering;
+
+/// Compare two version strings the way `builtins.compareVersions` does in Nix.
+///
+/// Versions are split into components at `.` and `-`,
+/// and between digits and non-digits.
+/// Numeric components are compared numerically,
+/// `pre` orders before anything else,
+/// and strings order before numbers, so that `2.3a < 2.3.1`.
+pub fn compare_versions(v1: &str, v2: &str) -> Ordering {
+    fn components(version: &str) -> Vec<&str> {
+        let mut components = Vec::new();
+        let mut rest = version;
+        loop {
+            rest = rest.trim_start_matches(['.', '-']);
+            if rest.is_empty() {
+                return components;
+            }
+            let is_digit = rest.starts_with(|c: char| c.is_ascii_digit());
+            let end = rest
+                .find(|c: char| c == '.' || c == '-' || (c.is_ascii_digit() != is_digit))
+                .unwrap_or(rest.len());
+            components.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+
+    fn component_lt(c1: &str, c2: &str) -> bool {
+        let n1 = c1.parse::<u64>().ok();
+        let n2 = c2.parse::<u64>().ok();
+        match (n1, n2) {
+            (Some(n1), Some(n2)) => n1 < n2,
+            _ if c1.is_empty() && n2.is_some() => true,
+            _ if c1 == "pre" && c2 != "pre" => true,
+            _ if c2 == "pre" => false,
+            (_, Some(_)) => true,
+            (Some(_), _) => false,
+            _ => c1 < c2,
+        }
+    }
+
+    let c1 = components(v1);
+    let c2 = components(v2);
+    for i in 0..c1.len().max(c2.len()) {
+        let a = c1.get(i).copied().unwrap_or("");
+        let b = c2.get(i).copied().unwrap_or("");
+        if component_lt(a, b) {
+            return Ordering::Less;
+        }
+        if component_lt(b, a) {
+            return Ordering::Greater;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_versions_like_nix() {
+        // Examples from the Nix manual for builtins.compareVersions
+        let ordered = [
+            ("1.0", "2.3"),
+            ("2.3", "2.3"),
+            ("2.3", "2.3.1"),
+            ("2.3a", "2.3.1"),
+            ("2.3pre1", "2.3"),
+            ("2.3pre3", "2.3pre12"),
+            ("2.3a", "2.3c"),
+            ("2.3pre1", "2.3q"),
+        ];
+        for (lower, higher) in ordered {
+            if lower == higher {
+                assert_eq!(compare_versions(lower, higher), Ordering::Equal);
+            } else {
+                assert_eq!(
+                    compare_versions(lower, higher),
+                    Ordering::Less,
+                    "{lower} < {higher}"
+                );
+                assert_eq!(
+                    compare_versions(higher, lower),
+                    Ordering::Greater,
+                    "{higher} > {lower}"
+                );
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::LazyLock;
 
+use chrono::{DateTime, Utc};
 use jsonwebtoken::{DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use serde_with::DeserializeFromStr;
@@ -97,6 +98,9 @@ pub static DEFAULT_FLOXHUB_URL: LazyLock<Url> =
 struct FloxTokenClaims {
     #[serde(rename = "https://flox.dev/handle")]
     handle: String,
+    /// Expiry as a unix timestamp,
+    /// required to be present by [Validation::default]
+    exp: i64,
 }
 
 #[derive(Debug, Clone, DeserializeFromStr)]
@@ -120,6 +124,11 @@ impl FloxhubToken {
     pub fn handle(&self) -> &str {
         &self.token_data.handle
     }
+
+    /// Return the time at which the token expires
+    pub fn expiry(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.token_data.exp, 0)
+    }
 }
 
 impl Serialize for FloxhubToken {
@@ -167,6 +176,7 @@ pub struct Floxhub {
     base_url: Url,
     git_url: Url,
     git_url_overridden: bool,
+    git_remote: Option<String>,
 }
 
 impl Floxhub {
@@ -180,9 +190,19 @@ impl Floxhub {
             base_url,
             git_url,
             git_url_overridden,
+            git_remote: None,
         })
     }
 
+    /// Store environments in the given git repository instead of FloxHub
+    ///
+    /// The remote may be any url or path accepted by `git`,
+    /// and is accessed using the git configuration and credentials of the user.
+    pub fn with_git_remote(mut self, git_remote: Option<String>) -> Self {
+        self.git_remote = git_remote;
+        self
+    }
+
     /// Return the base url of the FloxHub instance
     /// might change to a more specific url in the future
     pub fn base_url(&self) -> &Url {
@@ -193,6 +213,11 @@ impl Floxhub {
         self.git_url_overridden.then_some(&self.git_url)
     }
 
+    /// Return the git repository used instead of FloxHub, if configured
+    pub fn git_remote(&self) -> Option<&str> {
+        self.git_remote.as_deref()
+    }
+
     /// Return the url of the FloxHub git interface
     ///
     /// If the environment variable `_FLOX_FLOXHUB_GIT_URL` is set,
@@ -359,6 +384,12 @@ pub mod tests {
         assert_eq!(token.handle(), "test");
     }
 
+    #[test]
+    fn test_token_expiry() {
+        let token = test_helpers::create_test_token("test");
+        assert_eq!(token.expiry().unwrap().timestamp(), 9999999999);
+    }
+
     #[tokio::test]
     async fn test_detect_expired() {
         let token_error =
@@ -0,0 +1,1085 @@
+//! Offline vulnerability audits of locked packages.
+//!
+//! Locked packages are matched by `pname` and `version`
+//! against an [AdvisoryDatabase] read from disk.
+//! The database is not provided by flox,
+//! users download an OSV or NVD JSON dump separately.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, instrument};
+use walkdir::WalkDir;
+
+use super::lockfile::{LockedManifestError, Lockfile, PackageToList};
+use super::manifest::AuditOptions;
+use crate::data::{compare_versions, System};
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error(
+        "unknown severity '{0}', expected one of 'unknown', 'low', 'medium', 'high' or 'critical'"
+    )]
+    UnknownSeverity(String),
+    #[error("failed to read vulnerability database '{}'", .0.display())]
+    ReadDatabase(PathBuf, #[source] std::io::Error),
+    #[error("failed to walk vulnerability database '{}'", .0.display())]
+    WalkDatabase(PathBuf, #[source] walkdir::Error),
+    #[error("'{}' is neither an OSV nor an NVD JSON file", .0.display())]
+    ParseDatabase(PathBuf, #[source] serde_json::Error),
+    #[error(transparent)]
+    ListPackages(#[from] LockedManifestError),
+}
+
+/// Severity of an advisory
+///
+/// Advisories without a known severity are [Severity::Unknown],
+/// which orders below all other severities.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// The qualitative severity of a CVSS score
+    fn from_score(score: f64) -> Self {
+        match score {
+            s if s >= 9.0 => Severity::Critical,
+            s if s >= 7.0 => Severity::High,
+            s if s >= 4.0 => Severity::Medium,
+            s if s > 0.0 => Severity::Low,
+            _ => Severity::Unknown,
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = AuditError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unknown" => Ok(Severity::Unknown),
+            "low" => Ok(Severity::Low),
+            // GitHub advisories use "moderate"
+            "medium" | "moderate" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(AuditError::UnknownSeverity(s.to_string())),
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Unknown => write!(f, "unknown"),
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A bound of a [VersionRange]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bound {
+    Including(String),
+    Excluding(String),
+}
+
+/// A range of affected versions, unbounded if `start` or `end` are `None`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionRange {
+    pub start: Option<Bound>,
+    pub end: Option<Bound>,
+}
+
+impl VersionRange {
+    fn contains(&self, version: &str) -> bool {
+        let after_start = match &self.start {
+            None => true,
+            Some(Bound::Including(start)) => compare_versions(version, start).is_ge(),
+            Some(Bound::Excluding(start)) => compare_versions(version, start).is_gt(),
+        };
+        let before_end = match &self.end {
+            None => true,
+            Some(Bound::Including(end)) => compare_versions(version, end).is_le(),
+            Some(Bound::Excluding(end)) => compare_versions(version, end).is_lt(),
+        };
+        after_start && before_end
+    }
+}
+
+/// A package affected by an [Advisory]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AffectedPackage {
+    pub name: String,
+    /// The OSV ecosystem of the package, e.g. `PyPI` or `Debian:12`,
+    /// [None] for NVD entries
+    pub ecosystem: Option<String>,
+    /// Individually listed affected versions
+    pub versions: Vec<String>,
+    pub ranges: Vec<VersionRange>,
+}
+
+impl AffectedPackage {
+    /// Whether a locked package is affected.
+    ///
+    /// `package_ecosystem` is the language ecosystem of the locked package,
+    /// see [package_ecosystem].
+    /// Packages of language ecosystems, e.g. `PyPI`,
+    /// only match locked packages of the same ecosystem.
+    fn matches(&self, pname: &str, version: &str, package_ecosystem: Option<&str>) -> bool {
+        let ecosystem_matches = match self.language_ecosystem() {
+            Some(ecosystem) => package_ecosystem == Some(ecosystem),
+            None => true,
+        };
+
+        ecosystem_matches
+            && normalize_name(&self.name) == normalize_name(pname)
+            && (self
+                .versions
+                .iter()
+                .any(|affected| compare_versions(affected, version).is_eq())
+                || self.ranges.iter().any(|range| range.contains(version)))
+    }
+
+    /// The ecosystem of the package if it's the ecosystem of a language package manager
+    fn language_ecosystem(&self) -> Option<&'static str> {
+        // Ecosystems may be followed by a release, e.g. `Debian:12`
+        let ecosystem = self.ecosystem.as_deref()?.split(':').next()?;
+        LANGUAGE_ECOSYSTEMS
+            .into_iter()
+            .find(|language_ecosystem| *language_ecosystem == ecosystem)
+    }
+}
+
+/// OSV ecosystems of language package managers
+///
+/// Packages of these ecosystems share names with unrelated packages in nixpkgs,
+/// so they are only matched against packages of the same ecosystem.
+const LANGUAGE_ECOSYSTEMS: [&str; 16] = [
+    "Bioconductor",
+    "CRAN",
+    "crates.io",
+    "GitHub Actions",
+    "Go",
+    "Hackage",
+    "Hex",
+    "Maven",
+    "npm",
+    "NuGet",
+    "opam",
+    "Packagist",
+    "Pub",
+    "PyPI",
+    "RubyGems",
+    "SwiftURL",
+];
+
+/// nixpkgs package sets of language ecosystems,
+/// as the attribute name without a version and the [LANGUAGE_ECOSYSTEMS] entry
+const LANGUAGE_PACKAGE_SETS: [(&str, &str); 6] = [
+    ("haskell", "Hackage"),
+    ("node", "npm"),
+    ("ocaml", "opam"),
+    ("python", "PyPI"),
+    ("r", "CRAN"),
+    ("ruby", "RubyGems"),
+];
+
+/// The language ecosystem of a package in nixpkgs,
+/// derived from the package set in its attribute path,
+/// e.g. `PyPI` for `python312Packages.requests`.
+///
+/// Returns [None] for packages that aren't part of a language package set.
+fn package_ecosystem(attr_path: &str) -> Option<&'static str> {
+    let (package_set, _) = attr_path.split_once('.')?;
+    LANGUAGE_PACKAGE_SETS
+        .into_iter()
+        .find(|(prefix, _)| {
+            // e.g. `python3Packages` or `rubyPackages_3_3`
+            let Some((version, suffix)) = package_set
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.split_once("Packages"))
+            else {
+                return false;
+            };
+            version.chars().all(|c| c.is_ascii_digit())
+                && suffix.chars().all(|c| c.is_ascii_digit() || c == '_')
+        })
+        .map(|(_, ecosystem)| ecosystem)
+}
+
+/// Package names are compared case-insensitively,
+/// and CPE products use `_` where nixpkgs uses `-`.
+fn normalize_name(name: &str) -> String {
+    name.to_ascii_lowercase().replace('_', "-")
+}
+
+/// A single vulnerability
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Advisory {
+    pub id: String,
+    pub aliases: Vec<String>,
+    pub summary: Option<String>,
+    pub severity: Severity,
+    pub affected: Vec<AffectedPackage>,
+}
+
+impl Advisory {
+    /// Whether the advisory is referred to by `id`, either by its own id or an alias
+    fn is_named(&self, id: &str) -> bool {
+        self.id == id || self.aliases.iter().any(|alias| alias == id)
+    }
+}
+
+// region: OSV
+
+/// An entry of an OSV database, see <https://ossf.github.io/osv-schema/>
+#[derive(Debug, Deserialize)]
+struct OsvEntry {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    summary: Option<String>,
+    details: Option<String>,
+    #[serde(default)]
+    severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+    #[serde(default)]
+    database_specific: OsvSpecific,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvSeverity {
+    #[serde(rename = "type")]
+    kind: String,
+    score: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OsvSpecific {
+    severity: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvAffected {
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    versions: Vec<String>,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    ecosystem_specific: OsvSpecific,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvPackage {
+    name: String,
+    ecosystem: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OsvEvent {
+    Introduced(String),
+    Fixed(String),
+    LastAffected(String),
+    Limit(String),
+}
+
+impl OsvEvent {
+    fn version(&self) -> &str {
+        match self {
+            OsvEvent::Introduced(version)
+            | OsvEvent::Fixed(version)
+            | OsvEvent::LastAffected(version)
+            | OsvEvent::Limit(version) => version,
+        }
+    }
+}
+
+impl OsvRange {
+    /// Convert the events of a range into [VersionRange]s.
+    ///
+    /// Events are sorted by version,
+    /// each `introduced` event opens a range that is closed
+    /// by the following `fixed` or `last_affected` event.
+    fn version_ranges(self) -> Vec<VersionRange> {
+        // Git ranges refer to commits, not versions
+        if self.kind == "GIT" {
+            return vec![];
+        }
+
+        // OSV does not require events to be ordered
+        let mut events = self.events;
+        events.sort_by(|a, b| compare_versions(a.version(), b.version()));
+
+        let mut ranges = Vec::new();
+        let mut open: Option<VersionRange> = None;
+        for event in events {
+            match event {
+                OsvEvent::Introduced(version) => {
+                    ranges.extend(open.take());
+                    let start = (version != "0").then_some(Bound::Including(version));
+                    open = Some(VersionRange { start, end: None });
+                },
+                OsvEvent::Fixed(version) => {
+                    let mut range = open.take().unwrap_or_default();
+                    range.end = Some(Bound::Excluding(version));
+                    ranges.push(range);
+                },
+                OsvEvent::LastAffected(version) => {
+                    let mut range = open.take().unwrap_or_default();
+                    range.end = Some(Bound::Including(version));
+                    ranges.push(range);
+                },
+                OsvEvent::Limit(_) => {},
+            }
+        }
+        ranges.extend(open);
+        ranges
+    }
+}
+
+impl From<OsvEntry> for Advisory {
+    fn from(entry: OsvEntry) -> Self {
+        let cvss_severity = entry
+            .severity
+            .iter()
+            .filter(|severity| severity.kind.starts_with("CVSS_V3"))
+            .filter_map(|severity| cvss_v3_base_score(&severity.score))
+            .map(Severity::from_score)
+            .max();
+        let specific_severity = entry
+            .database_specific
+            .severity
+            .iter()
+            .chain(
+                entry
+                    .affected
+                    .iter()
+                    .filter_map(|affected| affected.ecosystem_specific.severity.as_ref()),
+            )
+            .filter_map(|severity| severity.parse::<Severity>().ok())
+            .max();
+
+        let affected = entry
+            .affected
+            .into_iter()
+            .filter_map(|affected| {
+                let package = affected.package?;
+                Some(AffectedPackage {
+                    name: package.name,
+                    ecosystem: package.ecosystem,
+                    versions: affected.versions,
+                    ranges: affected
+                        .ranges
+                        .into_iter()
+                        .flat_map(OsvRange::version_ranges)
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Advisory {
+            id: entry.id,
+            aliases: entry.aliases,
+            summary: entry.summary.or(entry.details),
+            severity: cvss_severity.or(specific_severity).unwrap_or_default(),
+            affected,
+        }
+    }
+}
+
+/// Calculate the base score of a CVSS v3 vector,
+/// e.g. `CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H`
+fn cvss_v3_base_score(vector: &str) -> Option<f64> {
+    let mut metrics = std::collections::HashMap::new();
+    for part in vector.split('/').skip(1) {
+        let (metric, value) = part.split_once(':')?;
+        metrics.insert(metric, value);
+    }
+
+    let scope_changed = match *metrics.get("S")? {
+        "U" => false,
+        "C" => true,
+        _ => None?,
+    };
+    let cia = |metric| match *metrics.get(metric)? {
+        "H" => Some(0.56_f64),
+        "L" => Some(0.22),
+        "N" => Some(0.0),
+        _ => None,
+    };
+    let attack_vector = match *metrics.get("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => None?,
+    };
+    let attack_complexity = match *metrics.get("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => None?,
+    };
+    let privileges_required = match (*metrics.get("PR")?, scope_changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => None?,
+    };
+    let user_interaction = match *metrics.get("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => None?,
+    };
+
+    let iss = 1.0 - ((1.0 - cia("C")?) * (1.0 - cia("I")?) * (1.0 - cia("A")?));
+    let impact = if scope_changed {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15)
+    } else {
+        6.42 * iss
+    };
+    let exploitability =
+        8.22 * attack_vector * attack_complexity * privileges_required * user_interaction;
+
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let score = if scope_changed {
+        1.08 * (impact + exploitability)
+    } else {
+        impact + exploitability
+    };
+
+    // "Round up" as defined by the CVSS v3.1 specification
+    let int_input = (score.min(10.0) * 100_000.0).round() as u64;
+    if int_input % 10_000 == 0 {
+        Some(int_input as f64 / 100_000.0)
+    } else {
+        Some(((int_input / 10_000) + 1) as f64 / 10.0)
+    }
+}
+
+// endregion
+
+// region: NVD
+
+/// A page of the NVD CVE API 2.0, see <https://nvd.nist.gov/developers/vulnerabilities>
+#[derive(Debug, Deserialize)]
+struct NvdFeed {
+    vulnerabilities: Vec<NvdVulnerability>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NvdVulnerability {
+    cve: NvdCve,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NvdCve {
+    id: String,
+    #[serde(default)]
+    descriptions: Vec<NvdDescription>,
+    #[serde(default)]
+    metrics: NvdMetrics,
+    #[serde(default)]
+    configurations: Vec<NvdConfiguration>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NvdDescription {
+    lang: String,
+    value: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NvdMetrics {
+    #[serde(default)]
+    cvss_metric_v31: Vec<NvdCvssMetric>,
+    #[serde(default)]
+    cvss_metric_v30: Vec<NvdCvssMetric>,
+    #[serde(default)]
+    cvss_metric_v2: Vec<NvdCvssMetric>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NvdCvssMetric {
+    cvss_data: NvdCvssData,
+    /// CVSS v2 metrics carry the severity outside of `cvssData`
+    base_severity: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NvdCvssData {
+    base_score: Option<f64>,
+    base_severity: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NvdConfiguration {
+    #[serde(default)]
+    nodes: Vec<NvdNode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NvdNode {
+    #[serde(default)]
+    cpe_match: Vec<NvdCpeMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NvdCpeMatch {
+    vulnerable: bool,
+    criteria: String,
+    version_start_including: Option<String>,
+    version_start_excluding: Option<String>,
+    version_end_including: Option<String>,
+    version_end_excluding: Option<String>,
+}
+
+impl NvdCpeMatch {
+    /// Convert a vulnerable application CPE to an [AffectedPackage]
+    ///
+    /// CPEs have the form `cpe:2.3:<part>:<vendor>:<product>:<version>:...`.
+    fn affected_package(self) -> Option<AffectedPackage> {
+        if !self.vulnerable {
+            return None;
+        }
+        let parts = self.criteria.split(':').collect::<Vec<_>>();
+        let ["cpe", "2.3", "a", _vendor, product, version, ..] = parts[..] else {
+            return None;
+        };
+
+        let mut affected = AffectedPackage {
+            name: product.to_string(),
+            ..Default::default()
+        };
+        match version {
+            "*" => {
+                let start = self
+                    .version_start_including
+                    .map(Bound::Including)
+                    .or(self.version_start_excluding.map(Bound::Excluding));
+                let end = self
+                    .version_end_including
+                    .map(Bound::Including)
+                    .or(self.version_end_excluding.map(Bound::Excluding));
+                affected.ranges.push(VersionRange { start, end });
+            },
+            // not applicable
+            "-" => return None,
+            version => affected.versions.push(version.replace('\\', "")),
+        }
+        Some(affected)
+    }
+}
+
+impl From<NvdCve> for Advisory {
+    fn from(cve: NvdCve) -> Self {
+        let metrics = cve
+            .metrics
+            .cvss_metric_v31
+            .iter()
+            .chain(&cve.metrics.cvss_metric_v30)
+            .chain(&cve.metrics.cvss_metric_v2);
+        let severity = metrics
+            .filter_map(|metric| {
+                metric
+                    .cvss_data
+                    .base_severity
+                    .as_ref()
+                    .or(metric.base_severity.as_ref())
+                    .and_then(|severity| severity.parse().ok())
+                    .or(metric.cvss_data.base_score.map(Severity::from_score))
+            })
+            .next()
+            .unwrap_or_default();
+
+        let summary = cve
+            .descriptions
+            .into_iter()
+            .find(|description| description.lang == "en")
+            .map(|description| description.value);
+
+        let affected = cve
+            .configurations
+            .into_iter()
+            .flat_map(|configuration| configuration.nodes)
+            .flat_map(|node| node.cpe_match)
+            .filter_map(NvdCpeMatch::affected_package)
+            .collect();
+
+        Advisory {
+            id: cve.id,
+            aliases: vec![],
+            summary,
+            severity,
+            affected,
+        }
+    }
+}
+
+// endregion
+
+/// The formats of files that make up an [AdvisoryDatabase]
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DatabaseFile {
+    Nvd(NvdFeed),
+    OsvList(Vec<OsvEntry>),
+    Osv(OsvEntry),
+}
+
+/// A collection of [Advisory]s read from disk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdvisoryDatabase {
+    pub advisories: Vec<Advisory>,
+}
+
+impl AdvisoryDatabase {
+    /// Read a database from a single JSON file,
+    /// or from all `.json` files in a directory, e.g. an unpacked OSV dump.
+    ///
+    /// Each file may contain a single OSV entry, a list of OSV entries,
+    /// or a page of the NVD CVE API 2.0.
+    #[instrument(skip_all, fields(progress = "Reading vulnerability database"))]
+    pub fn load(path: &Path) -> Result<Self, AuditError> {
+        let files = if path.is_dir() {
+            let mut files = Vec::new();
+            for entry in WalkDir::new(path).sort_by_file_name() {
+                let entry = entry.map_err(|e| AuditError::WalkDatabase(path.to_path_buf(), e))?;
+                if entry.file_type().is_file()
+                    && entry.path().extension().is_some_and(|ext| ext == "json")
+                {
+                    files.push(entry.into_path());
+                }
+            }
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        let mut advisories = Vec::new();
+        for file in files {
+            let contents =
+                fs::read(&file).map_err(|e| AuditError::ReadDatabase(file.clone(), e))?;
+            let parsed = serde_json::from_slice::<DatabaseFile>(&contents)
+                .map_err(|e| AuditError::ParseDatabase(file.clone(), e))?;
+            match parsed {
+                DatabaseFile::Nvd(feed) => advisories.extend(
+                    feed.vulnerabilities
+                        .into_iter()
+                        .map(|vulnerability| Advisory::from(vulnerability.cve)),
+                ),
+                DatabaseFile::OsvList(entries) => {
+                    advisories.extend(entries.into_iter().map(Advisory::from))
+                },
+                DatabaseFile::Osv(entry) => advisories.push(entry.into()),
+            }
+        }
+
+        debug!(count = advisories.len(), "read advisories");
+        Ok(AdvisoryDatabase { advisories })
+    }
+}
+
+/// A locked package affected by an advisory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditFinding {
+    pub system: System,
+    pub install_id: String,
+    pub pname: String,
+    pub version: String,
+    pub advisory: String,
+    pub aliases: Vec<String>,
+    pub severity: Severity,
+    pub summary: Option<String>,
+    /// The OSV ecosystem the advisory was published for, if any,
+    /// e.g. a Linux distribution whose versions may differ from those in nixpkgs
+    pub ecosystem: Option<String>,
+    /// Whether the advisory is ignored by `options.audit.ignore`
+    pub ignored: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AuditReport {
+    pub findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+    /// Match the packages locked for all systems in `lockfile` against `database`.
+    ///
+    /// Packages without a version, e.g. store paths, can't be matched.
+    /// Advisories listed in `options.ignore` are reported, but marked as ignored.
+    pub fn new(
+        lockfile: &Lockfile,
+        database: &AdvisoryDatabase,
+        options: &AuditOptions,
+    ) -> Result<Self, AuditError> {
+        let systems = lockfile
+            .packages
+            .iter()
+            .map(|package| package.system().clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut findings = Vec::new();
+        for system in systems {
+            for package in lockfile.list_packages(&system)? {
+                let (install_id, pname, version, package_ecosystem) = match &package {
+                    PackageToList::CatalogOrPkgdb(pkg) => (
+                        &pkg.install_id,
+                        Some(&pkg.info.pname),
+                        pkg.info.version.as_ref(),
+                        package_ecosystem(&pkg.rel_path),
+                    ),
+                    PackageToList::Flake(_, pkg) => (
+                        &pkg.install_id,
+                        pkg.locked_installable.pname.as_ref(),
+                        pkg.locked_installable.version.as_ref(),
+                        None,
+                    ),
+                    PackageToList::StorePath(_) => continue,
+                };
+                let (Some(pname), Some(version)) = (pname, version) else {
+                    continue;
+                };
+
+                for advisory in &database.advisories {
+                    let Some(affected) = advisory
+                        .affected
+                        .iter()
+                        .find(|affected| affected.matches(pname, version, package_ecosystem))
+                    else {
+                        continue;
+                    };
+                    findings.push(AuditFinding {
+                        system: system.clone(),
+                        install_id: install_id.clone(),
+                        pname: pname.clone(),
+                        version: version.clone(),
+                        advisory: advisory.id.clone(),
+                        aliases: advisory.aliases.clone(),
+                        severity: advisory.severity,
+                        summary: advisory.summary.clone(),
+                        ecosystem: affected.ecosystem.clone(),
+                        ignored: options.ignore.iter().any(|id| advisory.is_named(id)),
+                    });
+                }
+            }
+        }
+
+        Ok(AuditReport { findings })
+    }
+
+    /// Findings that are not ignored and have at least the given severity
+    pub fn failing(&self, threshold: Severity) -> impl Iterator<Item = &AuditFinding> {
+        self.findings
+            .iter()
+            .filter(move |finding| !finding.ignored && finding.severity >= threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::models::lockfile::test_helpers::fake_catalog_package_lock;
+    use crate::models::lockfile::LockedPackage;
+
+    #[test]
+    fn cvss_scores() {
+        assert_eq!(
+            cvss_v3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"),
+            Some(9.8)
+        );
+        assert_eq!(
+            cvss_v3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H"),
+            Some(10.0)
+        );
+        assert_eq!(
+            cvss_v3_base_score("CVSS:3.1/AV:L/AC:H/PR:L/UI:R/S:U/C:L/I:N/A:N"),
+            Some(2.2)
+        );
+        assert_eq!(
+            cvss_v3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N"),
+            Some(0.0)
+        );
+        assert_eq!(cvss_v3_base_score("AV:N/AC:L/Au:N/C:P/I:P/A:P"), None);
+    }
+
+    #[test]
+    fn osv_ranges() {
+        let entry: OsvEntry = serde_json::from_str(
+            r#"{
+                "id": "OSV-2024-1",
+                "aliases": ["CVE-2024-1"],
+                "summary": "bad things",
+                "severity": [
+                    { "type": "CVSS_V3", "score": "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H" }
+                ],
+                "affected": [{
+                    "package": { "ecosystem": "Debian", "name": "openssl" },
+                    "versions": ["1.1.1k"],
+                    "ranges": [
+                        {
+                            "type": "ECOSYSTEM",
+                            "events": [
+                                { "introduced": "3.0.0" },
+                                { "last_affected": "3.0.6" },
+                                { "introduced": "0" },
+                                { "fixed": "1.0.2" }
+                            ]
+                        },
+                        {
+                            "type": "GIT",
+                            "events": [{ "introduced": "abc" }]
+                        }
+                    ]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let advisory = Advisory::from(entry);
+        assert_eq!(advisory, Advisory {
+            id: "OSV-2024-1".to_string(),
+            aliases: vec!["CVE-2024-1".to_string()],
+            summary: Some("bad things".to_string()),
+            severity: Severity::Critical,
+            affected: vec![AffectedPackage {
+                name: "openssl".to_string(),
+                ecosystem: Some("Debian".to_string()),
+                versions: vec!["1.1.1k".to_string()],
+                ranges: vec![
+                    VersionRange {
+                        start: None,
+                        end: Some(Bound::Excluding("1.0.2".to_string())),
+                    },
+                    VersionRange {
+                        start: Some(Bound::Including("3.0.0".to_string())),
+                        end: Some(Bound::Including("3.0.6".to_string())),
+                    },
+                ],
+            }],
+        });
+
+        let affected = &advisory.affected[0];
+        assert!(affected.matches("openssl", "1.0.1", None));
+        assert!(!affected.matches("openssl", "1.0.2", None));
+        assert!(affected.matches("openssl", "1.1.1k", None));
+        assert!(!affected.matches("openssl", "1.1.1l", None));
+        assert!(affected.matches("OpenSSL", "3.0.6", None));
+        assert!(!affected.matches("openssl", "3.0.7", None));
+        assert!(!affected.matches("libressl", "3.0.0", None));
+    }
+
+    #[test]
+    fn language_ecosystems_only_match_their_package_sets() {
+        assert_eq!(package_ecosystem("python3Packages.requests"), Some("PyPI"));
+        assert_eq!(
+            package_ecosystem("python312Packages.requests"),
+            Some("PyPI")
+        );
+        assert_eq!(
+            package_ecosystem("rubyPackages_3_3.rails"),
+            Some("RubyGems")
+        );
+        assert_eq!(package_ecosystem("rPackages.ggplot2"), Some("CRAN"));
+        assert_eq!(package_ecosystem("requests"), None);
+        assert_eq!(package_ecosystem("pythonFull.requests"), None);
+
+        let pypi = AffectedPackage {
+            name: "requests".to_string(),
+            ecosystem: Some("PyPI".to_string()),
+            versions: vec!["2.31.0".to_string()],
+            ..Default::default()
+        };
+        assert!(pypi.matches("requests", "2.31.0", Some("PyPI")));
+        assert!(!pypi.matches("requests", "2.31.0", None));
+        assert!(!pypi.matches("requests", "2.31.0", Some("npm")));
+
+        let distribution = AffectedPackage {
+            ecosystem: Some("Debian:12".to_string()),
+            ..pypi.clone()
+        };
+        assert!(distribution.matches("requests", "2.31.0", None));
+        assert!(distribution.matches("requests", "2.31.0", Some("PyPI")));
+    }
+
+    #[test]
+    fn nvd_cpe_matches() {
+        let feed: DatabaseFile = serde_json::from_str(
+            r#"{
+                "vulnerabilities": [{
+                    "cve": {
+                        "id": "CVE-2023-2",
+                        "descriptions": [{ "lang": "en", "value": "a flaw" }],
+                        "metrics": {
+                            "cvssMetricV31": [{ "cvssData": { "baseScore": 5.3, "baseSeverity": "MEDIUM" } }]
+                        },
+                        "configurations": [{
+                            "nodes": [{
+                                "cpeMatch": [
+                                    {
+                                        "vulnerable": true,
+                                        "criteria": "cpe:2.3:a:apache:http_server:*:*:*:*:*:*:*:*",
+                                        "versionStartIncluding": "2.4.0",
+                                        "versionEndExcluding": "2.4.58"
+                                    },
+                                    {
+                                        "vulnerable": true,
+                                        "criteria": "cpe:2.3:a:gnu:hello:2.10:*:*:*:*:*:*:*"
+                                    },
+                                    {
+                                        "vulnerable": false,
+                                        "criteria": "cpe:2.3:o:linux:linux_kernel:*:*:*:*:*:*:*:*"
+                                    }
+                                ]
+                            }]
+                        }]
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+        let DatabaseFile::Nvd(mut feed) = feed else {
+            panic!("expected an NVD feed");
+        };
+
+        let advisory = Advisory::from(feed.vulnerabilities.remove(0).cve);
+        assert_eq!(advisory.id, "CVE-2023-2");
+        assert_eq!(advisory.severity, Severity::Medium);
+        assert_eq!(advisory.summary.as_deref(), Some("a flaw"));
+        assert_eq!(advisory.affected.len(), 2);
+
+        let [http_server, hello] = &advisory.affected[..] else {
+            panic!("expected two affected packages");
+        };
+        assert!(http_server.matches("http-server", "2.4.57", None));
+        assert!(!http_server.matches("http-server", "2.4.58", None));
+        assert!(hello.matches("hello", "2.10", None));
+        assert!(!hello.matches("hello", "2.12.1", None));
+    }
+
+    fn locked_package(name: &str, version: &str, system: &str) -> LockedPackage {
+        let (_, _, mut locked) = fake_catalog_package_lock(name, None);
+        locked.version = version.to_string();
+        locked.system = system.to_string();
+        locked.into()
+    }
+
+    #[test]
+    fn report_findings_per_system() {
+        let lockfile = Lockfile {
+            packages: vec![
+                locked_package("openssl", "3.0.1", "aarch64-darwin"),
+                locked_package("openssl", "3.0.7", "x86_64-linux"),
+                locked_package("hello", "2.12.1", "x86_64-linux"),
+            ],
+            ..Default::default()
+        };
+        let database = AdvisoryDatabase {
+            advisories: vec![
+                Advisory {
+                    id: "CVE-2022-3602".to_string(),
+                    severity: Severity::High,
+                    affected: vec![AffectedPackage {
+                        name: "openssl".to_string(),
+                        ranges: vec![VersionRange {
+                            start: Some(Bound::Including("3.0.0".to_string())),
+                            end: Some(Bound::Excluding("3.0.7".to_string())),
+                        }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                Advisory {
+                    id: "GHSA-xxxx".to_string(),
+                    aliases: vec!["CVE-2024-9".to_string()],
+                    severity: Severity::Low,
+                    affected: vec![AffectedPackage {
+                        name: "hello".to_string(),
+                        versions: vec!["2.12.1".to_string()],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            ],
+        };
+        let options = AuditOptions {
+            ignore: vec!["CVE-2024-9".to_string()],
+            ..Default::default()
+        };
+
+        let report = AuditReport::new(&lockfile, &database, &options).unwrap();
+        let summary = report
+            .findings
+            .iter()
+            .map(|f| {
+                (
+                    f.system.as_str(),
+                    f.pname.as_str(),
+                    f.advisory.as_str(),
+                    f.ignored,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            ("aarch64-darwin", "openssl", "CVE-2022-3602", false),
+            ("x86_64-linux", "hello", "GHSA-xxxx", true),
+        ]);
+
+        assert_eq!(report.failing(Severity::Unknown).count(), 1);
+        assert_eq!(report.failing(Severity::High).count(), 1);
+        assert_eq!(report.failing(Severity::Critical).count(), 0);
+    }
+}
@@ -0,0 +1,397 @@
+//! Dev container definitions for environments.
+//!
+//! A [DevContainer] describes a `.devcontainer` directory
+//! that installs Flox into a stock image with a local feature
+//! and activates the environment from within the container,
+//! so that editors supporting dev containers, such as VS Code and Codespaces,
+//! provide the environment without a Dockerfile or Flox on the host.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use indoc::indoc;
+use serde::Serialize;
+use serde_json::json;
+use thiserror::Error;
+
+use super::manifest::Manifest;
+
+/// The directory dev container definitions are read from
+pub const DEVCONTAINER_DIR: &str = ".devcontainer";
+const DEVCONTAINER_JSON: &str = "devcontainer.json";
+/// The local feature installing Flox, relative to [DEVCONTAINER_DIR]
+const FEATURE_DIR: &str = "flox";
+/// A Debian image maintained for dev containers,
+/// Flox is installed from its Debian package
+const BASE_IMAGE: &str = "mcr.microsoft.com/devcontainers/base:debian";
+const WORKSPACE_FOLDER: &str = "${containerWorkspaceFolder}";
+
+const FEATURE_INSTALL_SCRIPT: &str = indoc! {r#"
+    #!/bin/sh
+    # Install Flox from its Debian package
+    set -eu
+
+    case "$(uname -m)" in
+      x86_64) system=x86_64-linux ;;
+      aarch64 | arm64) system=aarch64-linux ;;
+      *)
+        echo "Flox is not available for $(uname -m)" >&2
+        exit 1
+        ;;
+    esac
+
+    export DEBIAN_FRONTEND=noninteractive
+    apt-get update
+    apt-get install -y --no-install-recommends ca-certificates curl
+    curl -fsSL -o /tmp/flox.deb "https://downloads.flox.dev/by-env/stable/deb/flox.${system}.deb"
+    apt-get install -y /tmp/flox.deb
+    rm -f /tmp/flox.deb
+    rm -rf /var/lib/apt/lists/*
+"#};
+
+#[derive(Debug, Error)]
+pub enum DevContainerError {
+    #[error("'{0}' already exists")]
+    AlreadyExists(PathBuf),
+    #[error("failed to write '{0}'")]
+    Write(PathBuf, #[source] std::io::Error),
+    #[error("failed to serialize dev container definition")]
+    Serialize(#[source] serde_json::Error),
+}
+
+/// Where the environment is found inside the container
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerEnvironment {
+    /// The directory containing `.flox`, relative to the workspace folder
+    Workspace(PathBuf),
+    /// An environment on FloxHub, e.g. `owner/name`
+    Remote(String),
+}
+
+impl ContainerEnvironment {
+    /// Arguments of `flox activate` selecting the environment
+    fn activate_args(&self) -> Vec<String> {
+        match self {
+            ContainerEnvironment::Workspace(dir) if dir.as_os_str().is_empty() => {
+                vec!["-d".to_string(), WORKSPACE_FOLDER.to_string()]
+            },
+            ContainerEnvironment::Workspace(dir) => vec![
+                "-d".to_string(),
+                format!("{WORKSPACE_FOLDER}/{}", dir.display()),
+            ],
+            ContainerEnvironment::Remote(env_ref) => vec!["-r".to_string(), env_ref.clone()],
+        }
+    }
+}
+
+/// A file of a dev container definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevContainerFile {
+    /// Path relative to the directory containing [DEVCONTAINER_DIR]
+    pub path: PathBuf,
+    pub contents: String,
+    pub executable: bool,
+}
+
+/// A dev container activating an environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevContainer {
+    name: String,
+    environment: ContainerEnvironment,
+    vars: BTreeMap<String, String>,
+    /// Ports to forward, labeled by the service or variable they were found in
+    ports: BTreeMap<u16, String>,
+    has_services: bool,
+}
+
+impl DevContainer {
+    pub fn new(name: String, manifest: &Manifest, environment: ContainerEnvironment) -> Self {
+        Self {
+            name,
+            environment,
+            // References to env files and secrets are only resolved by `flox activate`
+            vars: manifest
+                .vars
+                .values()
+                .map(|(name, value)| (name.clone(), value.to_string()))
+                .collect(),
+            ports: forwarded_ports(manifest),
+            has_services: !manifest.services.0.is_empty(),
+        }
+    }
+
+    /// The files of the dev container definition
+    pub fn files(&self) -> Result<Vec<DevContainerFile>, DevContainerError> {
+        let devcontainer_json = serde_json::to_string_pretty(&self.devcontainer_json())
+            .map_err(DevContainerError::Serialize)?;
+        let feature_json = serde_json::to_string_pretty(&json!({
+            "id": "flox",
+            "version": "1.0.0",
+            "name": "Flox",
+            "description": "Installs Flox",
+            "installsAfter": ["ghcr.io/devcontainers/features/common-utils"],
+        }))
+        .map_err(DevContainerError::Serialize)?;
+
+        let dir = Path::new(DEVCONTAINER_DIR);
+        Ok(vec![
+            DevContainerFile {
+                path: dir.join(DEVCONTAINER_JSON),
+                contents: devcontainer_json + "\n",
+                executable: false,
+            },
+            DevContainerFile {
+                path: dir.join(FEATURE_DIR).join("devcontainer-feature.json"),
+                contents: feature_json + "\n",
+                executable: false,
+            },
+            DevContainerFile {
+                path: dir.join(FEATURE_DIR).join("install.sh"),
+                contents: FEATURE_INSTALL_SCRIPT.to_string(),
+                executable: true,
+            },
+        ])
+    }
+
+    /// Write the files of the dev container definition to `dir`,
+    /// refusing to overwrite an existing `devcontainer.json` unless `force` is set.
+    ///
+    /// Returns the paths of the written files.
+    pub fn write(&self, dir: &Path, force: bool) -> Result<Vec<PathBuf>, DevContainerError> {
+        let existing = dir.join(DEVCONTAINER_DIR).join(DEVCONTAINER_JSON);
+        if !force && existing.exists() {
+            return Err(DevContainerError::AlreadyExists(existing));
+        }
+
+        let mut written = vec![];
+        for file in self.files()? {
+            let path = dir.join(&file.path);
+            let write = || -> std::io::Result<()> {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&path, &file.contents)?;
+                if file.executable {
+                    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+                }
+                Ok(())
+            };
+            write().map_err(|e| DevContainerError::Write(path.clone(), e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    fn devcontainer_json(&self) -> DevContainerJson {
+        let activate = self.environment.activate_args();
+
+        // Build the environment and run its hooks once the container is created
+        let post_create_command = ["flox", "activate"]
+            .into_iter()
+            .map(String::from)
+            .chain(activate.iter().cloned())
+            .chain(["--".to_string(), "true".to_string()])
+            .collect();
+
+        // Terminals are activated, services run as long as a terminal is open
+        let mut terminal_args = vec!["activate".to_string()];
+        terminal_args.extend(activate);
+        if self.has_services {
+            terminal_args.push("--start-services".to_string());
+        }
+
+        DevContainerJson {
+            name: self.name.clone(),
+            image: BASE_IMAGE.to_string(),
+            features: BTreeMap::from([(format!("./{FEATURE_DIR}"), json!({}))]),
+            remote_env: self.vars.clone(),
+            forward_ports: self.ports.keys().copied().collect(),
+            ports_attributes: self
+                .ports
+                .iter()
+                .map(|(port, label)| {
+                    (port.to_string(), PortAttributes {
+                        label: label.clone(),
+                    })
+                })
+                .collect(),
+            post_create_command,
+            customizations: json!({
+                "vscode": {
+                    "settings": {
+                        "terminal.integrated.defaultProfile.linux": "flox",
+                        "terminal.integrated.profiles.linux": {
+                            "flox": {
+                                "path": "flox",
+                                "args": terminal_args,
+                            },
+                        },
+                    },
+                },
+            }),
+        }
+    }
+}
+
+/// Ports of services to forward to the host.
+///
+/// Services don't declare ports,
+/// so ports are taken from numeric variables named `PORT` or ending in `_PORT`,
+/// set for a service or for the whole environment.
+fn forwarded_ports(manifest: &Manifest) -> BTreeMap<u16, String> {
+    fn ports<'a>(
+        vars: impl IntoIterator<Item = (&'a String, &'a str)>,
+    ) -> impl Iterator<Item = (&'a String, u16)> {
+        vars.into_iter().filter_map(|(name, value)| {
+            if name != "PORT" && !name.ends_with("_PORT") {
+                return None;
+            }
+            value.parse::<u16>().ok().map(|port| (name, port))
+        })
+    }
+
+    let mut forwarded = BTreeMap::new();
+    for (service, descriptor) in manifest.services.0.iter() {
+        if let Some(vars) = &descriptor.vars {
+            for (_, port) in ports(vars.0.iter().map(|(name, value)| (name, value.as_str()))) {
+                forwarded.entry(port).or_insert_with(|| service.clone());
+            }
+        }
+    }
+    for (name, port) in ports(manifest.vars.values()) {
+        forwarded.entry(port).or_insert_with(|| name.clone());
+    }
+    forwarded
+}
+
+/// The subset of the dev container specification used by [DevContainer]
+///
+/// <https://containers.dev/implementors/json_reference/>
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DevContainerJson {
+    name: String,
+    image: String,
+    features: BTreeMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    remote_env: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    forward_ports: Vec<u16>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ports_attributes: BTreeMap<String, PortAttributes>,
+    post_create_command: Vec<String>,
+    customizations: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct PortAttributes {
+    label: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn manifest(toml: &str) -> Manifest {
+        toml_edit::de::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn devcontainer_json_with_vars_and_services() {
+        let manifest = manifest(indoc! {r#"
+            version = 1
+
+            [vars]
+            GREETING = "hello"
+            WEB_PORT = "3000"
+
+            [services.postgres]
+            command = "postgres -D $PGDATA"
+            vars.PGPORT = "5432"
+            vars.PORT = "5432"
+
+            [services.web]
+            command = "npm start"
+        "#});
+
+        let devcontainer = DevContainer::new(
+            "myproject".to_string(),
+            &manifest,
+            ContainerEnvironment::Workspace(PathBuf::new()),
+        );
+        let json = serde_json::to_value(devcontainer.devcontainer_json()).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "name": "myproject",
+                "image": BASE_IMAGE,
+                "features": { "./flox": {} },
+                "remoteEnv": { "GREETING": "hello", "WEB_PORT": "3000" },
+                "forwardPorts": [3000, 5432],
+                "portsAttributes": {
+                    "3000": { "label": "WEB_PORT" },
+                    "5432": { "label": "postgres" },
+                },
+                "postCreateCommand": [
+                    "flox", "activate", "-d", "${containerWorkspaceFolder}", "--", "true"
+                ],
+                "customizations": {
+                    "vscode": {
+                        "settings": {
+                            "terminal.integrated.defaultProfile.linux": "flox",
+                            "terminal.integrated.profiles.linux": {
+                                "flox": {
+                                    "path": "flox",
+                                    "args": [
+                                        "activate",
+                                        "-d",
+                                        "${containerWorkspaceFolder}",
+                                        "--start-services"
+                                    ],
+                                },
+                            },
+                        },
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn activate_args_for_subdirectory_and_remote_environments() {
+        assert_eq!(
+            ContainerEnvironment::Workspace(PathBuf::from("backend")).activate_args(),
+            vec!["-d", "${containerWorkspaceFolder}/backend"]
+        );
+        assert_eq!(
+            ContainerEnvironment::Remote("owner/name".to_string()).activate_args(),
+            vec!["-r", "owner/name"]
+        );
+    }
+
+    #[test]
+    fn write_refuses_to_overwrite_without_force() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let devcontainer = DevContainer::new(
+            "myproject".to_string(),
+            &manifest("version = 1"),
+            ContainerEnvironment::Workspace(PathBuf::new()),
+        );
+
+        let written = devcontainer.write(tempdir.path(), false).unwrap();
+        assert_eq!(written.len(), 3);
+        let install_script = tempdir.path().join(".devcontainer/flox/install.sh");
+        let mode = fs::metadata(&install_script).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+
+        let err = devcontainer.write(tempdir.path(), false).unwrap_err();
+        assert!(matches!(err, DevContainerError::AlreadyExists(_)));
+        devcontainer.write(tempdir.path(), true).unwrap();
+    }
+}
@@ -8,6 +8,7 @@ use pollster::FutureExt;
 use thiserror::Error;
 use tracing::debug;
 
+use super::include::{self, IncludeError};
 use super::{
     copy_dir_recursive,
     CanonicalizeError,
@@ -30,6 +31,7 @@ use crate::models::manifest::{
     TomlEditError,
 };
 use crate::models::pkgdb::{error_codes, CallPkgDbError, PkgDbError, PKGDB_BIN};
+use crate::models::secrets::{self, SecretsError};
 use crate::providers::buildenv::{
     BuildEnv,
     BuildEnvError,
@@ -56,6 +58,11 @@ pub struct CoreEnvironment<State = ReadOnly> {
     ///
     /// Commonly /.../.flox/env/
     env_dir: PathBuf,
+    /// The directory that relative local includes are resolved against
+    ///
+    /// Environments that aren't backed by a project directory don't have one,
+    /// in which case relative includes can only be used once they are locked.
+    include_base_dir: Option<PathBuf>,
     _state: State,
 }
 
@@ -130,8 +137,15 @@ impl<State> CoreEnvironment<State> {
             .map_err(CoreEnvironmentError::LockedManifest)?;
 
         // Check if the manifest embedded in the lockfile and the manifest
-        // itself have the same contents
-        let already_locked = manifest == lockfile.manifest;
+        // itself have the same contents.
+        // If the manifest includes other environments,
+        // the lockfile embeds the composed manifest,
+        // so compare against the manifest it was composed from instead.
+        let locked_manifest = match lockfile.compose {
+            Some(ref compose) => &compose.composer,
+            None => &lockfile.manifest,
+        };
+        let already_locked = manifest == *locked_manifest;
 
         if already_locked {
             Ok(Some(lockfile))
@@ -171,13 +185,23 @@ impl<State> CoreEnvironment<State> {
     /// is already locked.
     /// For that reason, this always writes the lockfile to disk.
     pub fn lock(&mut self, flox: &Flox) -> Result<Lockfile, CoreEnvironmentError> {
-        let manifest = self.manifest()?;
+        let existing_lockfile = self.existing_lockfile()?;
+        let (manifest, compose) = include::compose(
+            flox,
+            self.include_base_dir.as_deref(),
+            self.manifest()?,
+            existing_lockfile
+                .and_then(|lockfile| lockfile.compose)
+                .as_ref(),
+            false,
+        )?;
 
-        let lockfile = self.lock_with_catalog_client(
+        let mut lockfile = self.lock_with_catalog_client(
             &flox.catalog_client,
             &flox.installable_locker,
             manifest,
         )?;
+        lockfile.compose = compose;
         let environment_lockfile_path = self.lockfile_path();
 
         // Write the lockfile to disk
@@ -295,10 +319,17 @@ impl CoreEnvironment<ReadOnly> {
     pub fn new(env_dir: impl AsRef<Path>) -> Self {
         CoreEnvironment {
             env_dir: env_dir.as_ref().to_path_buf(),
+            include_base_dir: None,
             _state: ReadOnly {},
         }
     }
 
+    /// Resolve relative local includes against the given directory
+    pub fn with_include_base_dir(mut self, include_base_dir: impl AsRef<Path>) -> Self {
+        self.include_base_dir = Some(include_base_dir.as_ref().to_path_buf());
+        self
+    }
+
     /// Install packages to the environment atomically
     ///
     /// Returns the new manifest content if the environment was modified. Also
@@ -487,14 +518,42 @@ impl CoreEnvironment<ReadOnly> {
     /// First resolve a new lockfile with upgraded packages using either pkgdb or the catalog client.
     /// Then verify the new lockfile by building the environment.
     /// Finally replace the existing environment with the new, upgraded one.
+    ///
+    /// If `includes` is set, the included environments are fetched again
+    /// instead of upgrading packages, and `groups_or_iids` must be empty.
     pub fn upgrade(
         &mut self,
         flox: &Flox,
         groups_or_iids: &[&str],
+        includes: bool,
     ) -> Result<UpgradeResult, CoreEnvironmentError> {
-        tracing::debug!(to_upgrade = groups_or_iids.join(","), "upgrading");
-        let manifest = self.manifest()?;
-        let (lockfile, upgraded) = {
+        tracing::debug!(to_upgrade = groups_or_iids.join(","), includes, "upgrading");
+        if includes && !groups_or_iids.is_empty() {
+            return Err(CoreEnvironmentError::UpgradeFailedCatalog(
+                UpgradeError::IncludesWithPackages,
+            ));
+        }
+
+        let previous_compose = self
+            .existing_lockfile()?
+            .and_then(|lockfile| lockfile.compose);
+        let (manifest, compose) = include::compose(
+            flox,
+            self.include_base_dir.as_deref(),
+            self.manifest()?,
+            previous_compose.as_ref(),
+            includes,
+        )?;
+
+        let (mut lockfile, upgraded) = if includes {
+            tracing::debug!("refreshing included environments");
+            let lockfile = self.lock_with_catalog_client(
+                &flox.catalog_client,
+                &flox.installable_locker,
+                manifest,
+            )?;
+            (lockfile, vec![])
+        } else {
             Self::ensure_valid_upgrade(groups_or_iids, &manifest)?;
             tracing::debug!("using catalog client to upgrade");
 
@@ -519,6 +578,19 @@ impl CoreEnvironment<ReadOnly> {
             (lockfile, upgraded)
         };
 
+        let upgraded_includes = compose
+            .iter()
+            .flat_map(|compose| &compose.include)
+            .filter(|include| {
+                !previous_compose
+                    .iter()
+                    .flat_map(|compose| &compose.include)
+                    .any(|previous| previous == *include)
+            })
+            .map(|include| include.descriptor.to_string())
+            .collect();
+        lockfile.compose = compose;
+
         // SAFETY: serde_json::to_string_pretty is only documented to fail if
         // the "Serialize decides to fail, or if T contains a map with non-string keys",
         // neither of which should happen here.
@@ -527,6 +599,7 @@ impl CoreEnvironment<ReadOnly> {
 
         Ok(UpgradeResult {
             packages: upgraded,
+            includes: upgraded_includes,
             store_path: Some(store_path),
         })
     }
@@ -691,6 +764,7 @@ impl CoreEnvironment<ReadOnly> {
 
         Ok(CoreEnvironment {
             env_dir: tempdir.as_ref().to_path_buf(),
+            include_base_dir: self.include_base_dir.clone(),
             _state: ReadWrite {},
         })
     }
@@ -752,6 +826,7 @@ impl CoreEnvironment<ReadOnly> {
         let manifest: Manifest = toml::from_str(manifest_contents.as_ref())
             .map_err(CoreEnvironmentError::DeserializeManifest)?;
         manifest.services.validate()?;
+        secrets::validate(&manifest)?;
 
         let tempdir = tempfile::tempdir_in(&flox.temp_dir)
             .map_err(CoreEnvironmentError::MakeSandbox)?
@@ -876,6 +951,7 @@ impl EditResult {
             if old_manifest.hook != new_manifest.hook
                 || old_manifest.vars != new_manifest.vars
                 || old_manifest.profile != new_manifest.profile
+                || old_manifest.target != new_manifest.target
             {
                 Ok(Self::ReActivateRequired {
                     built_environment_store_paths,
@@ -904,6 +980,8 @@ impl EditResult {
 #[derive(Debug)]
 pub struct UpgradeResult {
     pub packages: Vec<String>,
+    /// Included environments that changed when refreshing includes
+    pub includes: Vec<String>,
     pub store_path: Option<BuildEnvOutputs>,
 }
 
@@ -977,6 +1055,12 @@ pub enum CoreEnvironmentError {
     #[error(transparent)]
     Services(#[from] ServiceError),
 
+    #[error(transparent)]
+    Include(#[from] IncludeError),
+
+    #[error(transparent)]
+    Secrets(#[from] SecretsError),
+
     #[error(transparent)]
     BuildEnv(#[from] BuildEnvError),
 }
@@ -1246,6 +1330,7 @@ mod tests {
             version: Version,
             packages: vec![foo_locked.into()],
             manifest: manifest.clone(),
+            compose: None,
         };
 
         let lockfile_str = serde_json::to_string_pretty(&lockfile).unwrap();
@@ -1432,6 +1517,7 @@ mod tests {
                     priority: None,
                     version: None,
                     systems: None,
+                    outputs: None,
                 }),
             );
         }
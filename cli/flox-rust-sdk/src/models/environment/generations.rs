@@ -30,7 +30,7 @@ use thiserror::Error;
 use super::core_environment::CoreEnvironment;
 use super::{copy_dir_recursive, ENV_DIR_NAME};
 use crate::flox::EnvironmentName;
-use crate::models::environment::MANIFEST_FILENAME;
+use crate::models::environment::{LOCKFILE_FILENAME, MANIFEST_FILENAME};
 use crate::providers::git::{
     GitCommandError,
     GitCommandOptions,
@@ -96,19 +96,42 @@ impl<S> Generations<S> {
 
     /// Read the manifest of a given generation and return its contents as a string
     pub fn manifest(&self, generation: usize) -> Result<String, GenerationsError> {
+        self.show_env_file(
+            generation,
+            MANIFEST_FILENAME,
+            GenerationsError::ShowManifest,
+        )
+    }
+
+    /// Read the lockfile of a given generation and return its contents as a string
+    pub fn lockfile(&self, generation: usize) -> Result<String, GenerationsError> {
+        self.show_env_file(
+            generation,
+            LOCKFILE_FILENAME,
+            GenerationsError::ShowLockfile,
+        )
+    }
+
+    /// Read a file in the environment directory of a given generation
+    fn show_env_file(
+        &self,
+        generation: usize,
+        file_name: &str,
+        show_error: fn(GitCommandError) -> GenerationsError,
+    ) -> Result<String, GenerationsError> {
         let metadata = self.metadata()?;
         if !metadata.generations.contains_key(&generation.into()) {
             return Err(GenerationsError::GenerationNotFound(generation));
         }
-        let manifest_osstr = self
+        let contents = self
             .repo
             .show(&format!(
                 "{}:{}/{}/{}",
-                self.branch, generation, ENV_DIR_NAME, MANIFEST_FILENAME
+                self.branch, generation, ENV_DIR_NAME, file_name
             ))
-            .map_err(GenerationsError::ShowManifest)?;
+            .map_err(show_error)?;
 
-        return Ok(manifest_osstr.to_string_lossy().to_string());
+        Ok(contents.to_string_lossy().to_string())
     }
 
     /// Read the manifest of the current generation and return its contents as a string
@@ -406,6 +429,8 @@ pub enum GenerationsError {
     WriteManifest(#[source] std::io::Error),
     #[error("could not show manifest file")]
     ShowManifest(#[source] GitCommandError),
+    #[error("could not show lockfile")]
+    ShowLockfile(#[source] GitCommandError),
     // endregion
 }
 
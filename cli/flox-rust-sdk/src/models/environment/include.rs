@@ -0,0 +1,506 @@
+//! Composition of environments from the `[include]` section of a manifest
+//!
+//! Included environments are fetched when an environment is first locked
+//! and are recorded in its lockfile as a [Compose].
+//! Subsequent locks reuse the recorded manifests,
+//! so changes to an included environment are only picked up
+//! once the includes are explicitly refreshed.
+//!
+//! The `install`, `vars`, `hook`, `profile` and `target` sections
+//! of the included manifests are merged into the including manifest:
+//!
+//! - packages and variables of the including manifest take precedence
+//!   over those of any included environment
+//! - two included environments defining the same package or variable
+//!   differently is a conflict, unless the including manifest defines it as well
+//! - hook and profile scripts are concatenated in the order of the includes,
+//!   followed by the scripts of the including manifest
+//! - `[target.<system>]` overrides are merged per system in the same way,
+//!   except that variables defined by several included environments
+//!   are taken from the first of them
+//!
+//! All other sections are taken from the including manifest only.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use tracing::debug;
+
+use super::generations::{Generations, GenerationsError};
+use super::managed_environment::remote_branch_name;
+use super::{ManagedPointer, DOT_FLOX, ENV_DIR_NAME, MANIFEST_FILENAME};
+use crate::flox::Flox;
+use crate::models::floxmeta::{FloxMeta, FloxMetaError};
+use crate::models::lockfile::{Compose, LockedInclude};
+use crate::models::manifest::{
+    IncludeDescriptor,
+    IncludeDescriptorLocal,
+    IncludeDescriptorRemote,
+    Manifest,
+    ManifestTarget,
+};
+
+#[derive(Debug, Error)]
+pub enum IncludeError {
+    #[error(
+        "cannot resolve relative include '{0}' for an environment that is not in a project directory, \
+         such as an environment used with '--remote'"
+    )]
+    NoBaseDirectory(PathBuf),
+    #[error("couldn't read manifest of included environment '{0}'")]
+    ReadManifest(String, #[source] std::io::Error),
+    #[error("couldn't parse manifest of included environment '{0}'")]
+    ParseManifest(String, #[source] toml::de::Error),
+    #[error("included environment '{0}' includes other environments, which is not supported")]
+    Nested(String),
+    #[error("couldn't make temporary directory to fetch included environment")]
+    MakeTempDir(#[source] std::io::Error),
+    #[error("couldn't fetch included environment '{0}'")]
+    Fetch(String, #[source] FloxMetaError),
+    #[error("couldn't read generation of included environment '{0}'")]
+    Generation(String, #[source] GenerationsError),
+    #[error("included environments '{first}' and '{second}' define {kind} '{name}' differently")]
+    Conflict {
+        kind: &'static str,
+        name: String,
+        first: String,
+        second: String,
+    },
+}
+
+/// Merge the environments included by `manifest` into it.
+///
+/// Includes already recorded in `locked` are reused,
+/// unless `refresh` is set, in which case every include is fetched again.
+/// Returns the merged manifest and the [Compose] to record in the lockfile,
+/// or `manifest` unchanged if it doesn't include any environments.
+///
+/// Relative local includes are resolved against `base_dir`.
+pub fn compose(
+    flox: &Flox,
+    base_dir: Option<&Path>,
+    manifest: Manifest,
+    locked: Option<&Compose>,
+    refresh: bool,
+) -> Result<(Manifest, Option<Compose>), IncludeError> {
+    if manifest.include.environments.is_empty() {
+        return Ok((manifest, None));
+    }
+
+    let mut include = Vec::new();
+    for descriptor in &manifest.include.environments {
+        let already_locked = locked
+            .filter(|_| !refresh)
+            .and_then(|compose| compose.include.iter().find(|i| &i.descriptor == descriptor));
+
+        let locked_include = match already_locked {
+            Some(locked_include) => {
+                debug!(%descriptor, "reusing locked include");
+                locked_include.clone()
+            },
+            None => fetch(flox, base_dir, descriptor)?,
+        };
+        include.push(locked_include);
+    }
+
+    let merged = merge(&manifest, &include)?;
+    Ok((
+        merged,
+        Some(Compose {
+            composer: manifest,
+            include,
+        }),
+    ))
+}
+
+/// Read the current manifest of an included environment
+fn fetch(
+    flox: &Flox,
+    base_dir: Option<&Path>,
+    descriptor: &IncludeDescriptor,
+) -> Result<LockedInclude, IncludeError> {
+    debug!(%descriptor, "fetching include");
+    let (contents, generation) = match descriptor {
+        IncludeDescriptor::Local(local) => (fetch_local(base_dir, local)?, None),
+        IncludeDescriptor::Remote(remote) => {
+            let (contents, generation) = fetch_remote(flox, remote)?;
+            (contents, Some(generation))
+        },
+    };
+
+    let manifest: Manifest = toml::from_str(&contents)
+        .map_err(|e| IncludeError::ParseManifest(descriptor.to_string(), e))?;
+    if !manifest.include.environments.is_empty() {
+        return Err(IncludeError::Nested(descriptor.to_string()));
+    }
+
+    Ok(LockedInclude {
+        descriptor: descriptor.clone(),
+        generation,
+        manifest,
+    })
+}
+
+fn fetch_local(
+    base_dir: Option<&Path>,
+    local: &IncludeDescriptorLocal,
+) -> Result<String, IncludeError> {
+    let dir = if local.dir.is_absolute() {
+        local.dir.clone()
+    } else {
+        base_dir
+            .ok_or_else(|| IncludeError::NoBaseDirectory(local.dir.clone()))?
+            .join(&local.dir)
+    };
+
+    let manifest_path = dir
+        .join(DOT_FLOX)
+        .join(ENV_DIR_NAME)
+        .join(MANIFEST_FILENAME);
+    fs::read_to_string(manifest_path)
+        .map_err(|e| IncludeError::ReadManifest(local.dir.display().to_string(), e))
+}
+
+/// Read the manifest of a remote environment at the requested generation
+/// or its current generation, and return it along with the generation.
+fn fetch_remote(
+    flox: &Flox,
+    remote: &IncludeDescriptorRemote,
+) -> Result<(String, u32), IncludeError> {
+    let env_ref = remote.remote.to_string();
+    let pointer = ManagedPointer::new(
+        remote.remote.owner().clone(),
+        remote.remote.name().clone(),
+        &flox.floxhub,
+    );
+
+    // Fetch into a throwaway clone
+    // to avoid interfering with local checkouts of the same environment.
+    let tempdir = tempfile::tempdir_in(&flox.temp_dir).map_err(IncludeError::MakeTempDir)?;
+    let floxmeta = FloxMeta::clone_to(tempdir.path(), flox, &pointer)
+        .map_err(|e| IncludeError::Fetch(env_ref.clone(), e))?;
+    let generations = Generations::new(floxmeta.git, remote_branch_name(&pointer));
+
+    let generation = match remote.generation {
+        Some(generation) => generation as usize,
+        None => *generations
+            .metadata()
+            .map_err(|e| IncludeError::Generation(env_ref.clone(), e))?
+            .current_gen
+            .ok_or(IncludeError::Generation(
+                env_ref.clone(),
+                GenerationsError::NoGenerations,
+            ))?,
+    };
+
+    let contents = generations
+        .manifest(generation)
+        .map_err(|e| IncludeError::Generation(env_ref, e))?;
+
+    Ok((contents, generation as u32))
+}
+
+/// Merge the manifests of `includes` into `composer`
+/// following the precedence rules described in the [module docs](self).
+pub fn merge(composer: &Manifest, includes: &[LockedInclude]) -> Result<Manifest, IncludeError> {
+    let mut merged = composer.clone();
+
+    for (index, include) in includes.iter().enumerate() {
+        // Find the include that first defined an entry, to report conflicts
+        let defined_by = |defines: &dyn Fn(&Manifest) -> bool| {
+            includes[..index]
+                .iter()
+                .find(|earlier| defines(&earlier.manifest))
+                .map(|earlier| earlier.descriptor.to_string())
+        };
+
+        for (install_id, descriptor) in include.manifest.install.iter() {
+            if composer.install.contains_key(install_id) {
+                continue;
+            }
+            match merged.install.get(install_id) {
+                Some(existing) if existing != descriptor => {
+                    return Err(IncludeError::Conflict {
+                        kind: "package",
+                        name: install_id.clone(),
+                        first: defined_by(&|m| m.install.contains_key(install_id))
+                            .unwrap_or_default(),
+                        second: include.descriptor.to_string(),
+                    });
+                },
+                Some(_) => {},
+                None => {
+                    merged
+                        .install
+                        .insert(install_id.clone(), descriptor.clone());
+                },
+            }
+        }
+
+        for (name, value) in include.manifest.vars.0.iter() {
+            if composer.vars.0.contains_key(name) {
+                continue;
+            }
+            match merged.vars.0.get(name) {
+                Some(existing) if existing != value => {
+                    return Err(IncludeError::Conflict {
+                        kind: "variable",
+                        name: name.clone(),
+                        first: defined_by(&|m| m.vars.0.contains_key(name)).unwrap_or_default(),
+                        second: include.descriptor.to_string(),
+                    });
+                },
+                Some(_) => {},
+                None => {
+                    merged.vars.0.insert(name.clone(), value.clone());
+                },
+            }
+        }
+    }
+
+    let scripts = |select: fn(&Manifest) -> &Option<String>| {
+        let joined = includes
+            .iter()
+            .map(|include| &include.manifest)
+            .chain([composer])
+            .filter_map(|manifest| select(manifest).as_deref())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(joined).filter(|joined| !joined.is_empty())
+    };
+
+    merged.hook.on_activate = scripts(|m| &m.hook.on_activate);
+    merged.hook.on_deactivate = scripts(|m| &m.hook.on_deactivate);
+    merged.profile.common = scripts(|m| &m.profile.common);
+    merged.profile.bash = scripts(|m| &m.profile.bash);
+    merged.profile.zsh = scripts(|m| &m.profile.zsh);
+    merged.profile.fish = scripts(|m| &m.profile.fish);
+    merged.profile.tcsh = scripts(|m| &m.profile.tcsh);
+
+    for include in includes {
+        for (system, target) in include.manifest.target.iter() {
+            let merged_target = merged.target.entry(system.clone()).or_default();
+            for (name, value) in target.vars.0.iter() {
+                merged_target
+                    .vars
+                    .0
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+    }
+
+    let target_scripts = |system: &str, select: fn(&ManifestTarget) -> &Option<String>| {
+        let joined = includes
+            .iter()
+            .map(|include| &include.manifest)
+            .chain([composer])
+            .filter_map(|manifest| manifest.target.get(system))
+            .filter_map(|target| select(target).as_deref())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(joined).filter(|joined| !joined.is_empty())
+    };
+
+    let systems = merged.target.keys().cloned().collect::<Vec<_>>();
+    for system in systems {
+        let on_activate = target_scripts(&system, |t| &t.hook.on_activate);
+        let on_deactivate = target_scripts(&system, |t| &t.hook.on_deactivate);
+        let common = target_scripts(&system, |t| &t.profile.common);
+        let bash = target_scripts(&system, |t| &t.profile.bash);
+        let zsh = target_scripts(&system, |t| &t.profile.zsh);
+        let fish = target_scripts(&system, |t| &t.profile.fish);
+        let tcsh = target_scripts(&system, |t| &t.profile.tcsh);
+
+        let target = merged
+            .target
+            .get_mut(&system)
+            .expect("system was collected from targets");
+        target.hook.on_activate = on_activate;
+        target.hook.on_deactivate = on_deactivate;
+        target.profile.common = common;
+        target.profile.bash = bash;
+        target.profile.zsh = zsh;
+        target.profile.fish = fish;
+        target.profile.tcsh = tcsh;
+    }
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::flox::test_helpers::flox_instance;
+
+    fn locked_include(dir: &str, contents: &str) -> LockedInclude {
+        LockedInclude {
+            descriptor: IncludeDescriptor::Local(IncludeDescriptorLocal { dir: dir.into() }),
+            generation: None,
+            manifest: toml::from_str(contents).unwrap(),
+        }
+    }
+
+    #[test]
+    fn merge_gives_composer_precedence() {
+        let composer: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+            [install]
+            hello.pkg-path = "hello"
+            [vars]
+            FOO = "composer"
+            [hook]
+            on-activate = "echo composer"
+        "#})
+        .unwrap();
+
+        let base = locked_include("base", indoc! {r#"
+            version = 1
+            [install]
+            hello.pkg-path = "other-hello"
+            curl.pkg-path = "curl"
+            [vars]
+            FOO = "base"
+            BAR = "base"
+            [hook]
+            on-activate = "echo base"
+            [profile]
+            common = "echo base profile"
+        "#});
+
+        let merged = merge(&composer, &[base]).unwrap();
+
+        let expected: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+            [install]
+            hello.pkg-path = "hello"
+            curl.pkg-path = "curl"
+            [vars]
+            FOO = "composer"
+            BAR = "base"
+            [hook]
+            on-activate = "echo base\necho composer"
+            [profile]
+            common = "echo base profile"
+        "#})
+        .unwrap();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn merge_targets() {
+        let composer: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+            [target.x86_64-linux.vars]
+            FOO = "composer"
+            [target.x86_64-linux.hook]
+            on-activate = "echo composer"
+        "#})
+        .unwrap();
+
+        let base = locked_include("base", indoc! {r#"
+            version = 1
+            [target.x86_64-linux.vars]
+            FOO = "base"
+            BAR = "base"
+            [target.x86_64-linux.hook]
+            on-activate = "echo base"
+            [target.aarch64-darwin.profile]
+            common = "echo base profile"
+        "#});
+
+        let merged = merge(&composer, &[base]).unwrap();
+
+        let expected: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+            [target.x86_64-linux.vars]
+            FOO = "composer"
+            BAR = "base"
+            [target.x86_64-linux.hook]
+            on-activate = "echo base\necho composer"
+            [target.aarch64-darwin.profile]
+            common = "echo base profile"
+        "#})
+        .unwrap();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn merge_errors_on_conflicting_includes() {
+        let composer: Manifest = toml::from_str("version = 1").unwrap();
+        let first = locked_include("first", indoc! {r#"
+            version = 1
+            [vars]
+            FOO = "first"
+        "#});
+        let second = locked_include("second", indoc! {r#"
+            version = 1
+            [vars]
+            FOO = "second"
+        "#});
+
+        let err = merge(&composer, &[first.clone(), second.clone()]).unwrap_err();
+        assert!(
+            matches!(err, IncludeError::Conflict { kind: "variable", ref name, ref first, ref second }
+                if name == "FOO" && first == "first" && second == "second"),
+            "{err:?}"
+        );
+
+        // Defining the variable in the composer resolves the conflict
+        let composer: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+            [vars]
+            FOO = "composer"
+        "#})
+        .unwrap();
+        merge(&composer, &[first, second]).unwrap();
+    }
+
+    #[test]
+    fn compose_reuses_locked_includes_unless_refreshed() {
+        let (flox, tempdir) = flox_instance();
+        let base_dir = tempdir.path().join("project");
+        let included_env_dir = base_dir.join("base").join(DOT_FLOX).join(ENV_DIR_NAME);
+        fs::create_dir_all(&included_env_dir).unwrap();
+        let write_included = |var: &str| {
+            fs::write(
+                included_env_dir.join(MANIFEST_FILENAME),
+                format!("version = 1\n[vars]\nFOO = \"{var}\"\n"),
+            )
+            .unwrap();
+        };
+
+        let manifest: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+            [include]
+            environments = [{ dir = "base" }]
+        "#})
+        .unwrap();
+
+        write_included("v1");
+        let (merged, compose) =
+            super::compose(&flox, Some(&base_dir), manifest.clone(), None, false).unwrap();
+        assert_eq!(merged.vars.0["FOO"].as_value(), Some("v1"));
+        let compose = compose.unwrap();
+        assert_eq!(compose.composer, manifest);
+
+        write_included("v2");
+        let (merged, _) = super::compose(
+            &flox,
+            Some(&base_dir),
+            manifest.clone(),
+            Some(&compose),
+            false,
+        )
+        .unwrap();
+        assert_eq!(merged.vars.0["FOO"].as_value(), Some("v1"));
+
+        let (merged, _) =
+            super::compose(&flox, Some(&base_dir), manifest, Some(&compose), true).unwrap();
+        assert_eq!(merged.vars.0["FOO"].as_value(), Some("v2"));
+    }
+}
@@ -43,19 +43,24 @@ use crate::models::env_registry::{
 };
 use crate::models::environment::copy_dir_recursive;
 use crate::models::environment_ref::{EnvironmentName, EnvironmentOwner};
-use crate::models::floxmeta::{floxmeta_git_options, FloxMeta, FloxMetaError};
+use crate::models::floxmeta::{floxmeta_remote, FloxMeta, FloxMetaError};
 use crate::models::lockfile::Lockfile;
 use crate::models::manifest::{Manifest, PackageToInstall};
+use crate::models::manifest_merge::{merge_manifests, ManifestMergeError};
 use crate::providers::buildenv::BuildEnvOutputs;
 use crate::providers::git::{
     GitCommandBranchHashError,
     GitCommandError,
+    GitCommandProvider,
     GitProvider,
     GitRemoteCommandError,
 };
 use crate::utils::mtime_of;
 
 pub const GENERATION_LOCK_FILENAME: &str = "env.lock";
+/// Records the upstream revision of a merge with unresolved conflicts,
+/// see [ManagedEnvironment::merge]
+pub const PENDING_MERGE_FILENAME: &str = "env.merge";
 
 #[derive(Debug)]
 pub struct ManagedEnvironment {
@@ -65,6 +70,9 @@ pub struct ManagedEnvironment {
     rendered_env_links: RenderedEnvironmentLinks,
     pointer: ManagedPointer,
     floxmeta: FloxMeta,
+    /// The directory that relative local includes are resolved against,
+    /// i.e. the directory containing `.flox`
+    include_base_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Error)]
@@ -119,6 +127,16 @@ pub enum ManagedEnvironmentError {
     ProjectNotFound { path: PathBuf, err: std::io::Error },
     #[error("upstream floxmeta branch diverged from local branch")]
     Diverged,
+    #[error("could not copy generations")]
+    CopyGenerations(#[source] GitRemoteCommandError),
+    #[error("could not merge upstream changes")]
+    Merge(#[source] ManifestMergeError),
+    #[error("could not write merged manifest")]
+    WriteMergedManifest(#[source] std::io::Error),
+    #[error("could not read pending merge")]
+    ReadPendingMerge(#[source] std::io::Error),
+    #[error("could not write pending merge")]
+    WritePendingMerge(#[source] std::io::Error),
     #[error("access to floxmeta repository was denied")]
     AccessDenied,
     #[error("environment '{env_ref}' does not exist at upstream '{upstream}'")]
@@ -295,6 +313,10 @@ impl Environment for ManagedEnvironment {
             .writable(flox.temp_dir.clone())
             .map_err(ManagedEnvironmentError::CreateFloxmetaDir)?;
 
+        if let Some(upstream_rev) = self.pending_merge()? {
+            return self.resolve_merge(flox, &upstream_rev, contents);
+        }
+
         let mut local_checkout = self.local_env_or_copy_current_generation(flox)?;
 
         let result = local_checkout.edit(flox, contents)?;
@@ -317,6 +339,7 @@ impl Environment for ManagedEnvironment {
         &mut self,
         flox: &Flox,
         groups_or_iids: &[&str],
+        includes: bool,
     ) -> Result<UpgradeResult, EnvironmentError> {
         let mut generations = self
             .generations()
@@ -335,7 +358,7 @@ impl Environment for ManagedEnvironment {
             ))?
         }
 
-        let result = local_checkout.upgrade(flox, groups_or_iids)?;
+        let result = local_checkout.upgrade(flox, groups_or_iids, includes)?;
 
         let metadata = format!("upgraded packages: {}", result.packages.join(", "));
 
@@ -586,11 +609,15 @@ impl ManagedEnvironment {
             },
             Err(FloxMetaError::CloneBranch(GitRemoteCommandError::RefNotFound(_)))
             | Err(FloxMetaError::FetchBranch(GitRemoteCommandError::RefNotFound(_))) => {
+                let upstream = match &pointer.git_remote {
+                    Some(git_remote) => git_remote.clone(),
+                    None => flox.floxhub.base_url().to_string(),
+                };
                 return Err(ManagedEnvironmentError::UpstreamNotFound {
                     env_ref: pointer.into(),
-                    upstream: flox.floxhub.base_url().to_string(),
+                    upstream,
                     user: flox.floxhub_token.as_ref().map(|t| t.handle().to_string()),
-                })
+                });
             },
             Err(e) => Err(ManagedEnvironmentError::OpenFloxmeta(e))?,
         };
@@ -640,11 +667,13 @@ impl ManagedEnvironment {
             &EnvironmentPointer::Managed(pointer.clone()),
         )?;
 
+        let include_base_dir = dot_flox_path.parent().map(Path::to_path_buf);
         let env = ManagedEnvironment {
             path: dot_flox_path,
             rendered_env_links,
             pointer,
             floxmeta,
+            include_base_dir,
         };
 
         Ok(env)
@@ -886,9 +915,30 @@ impl ManagedEnvironment {
     pub fn create_generation_from_local_env(
         &mut self,
         flox: &Flox,
+    ) -> Result<SyncToGenerationResult, ManagedEnvironmentError> {
+        self.commit_local_env(flox, "Synchronized manual changes to generation")
+    }
+
+    /// Lock, build and commit the local checkout as a new generation
+    /// with the given description.
+    ///
+    /// See [ManagedEnvironment::create_generation_from_local_env].
+    fn commit_local_env(
+        &mut self,
+        flox: &Flox,
+        description: &str,
     ) -> Result<SyncToGenerationResult, ManagedEnvironmentError> {
         let mut local_checkout = self.local_env_or_copy_current_generation(flox)?;
 
+        // Conflicts of a merge were resolved by editing the local checkout
+        if let Some(upstream_rev) = self.pending_merge()? {
+            let contents = local_checkout
+                .manifest_contents()
+                .map_err(ManagedEnvironmentError::ReadLocalManifest)?;
+            self.commit_merge(flox, &upstream_rev, &contents)?;
+            return Ok(SyncToGenerationResult::Synced);
+        }
+
         if Self::validate_checkout(&local_checkout, &self.get_current_generation(flox)?)? {
             debug!("local checkout and remote checkout equal, nothing to apply");
             return Ok(SyncToGenerationResult::UpToDate);
@@ -921,10 +971,7 @@ impl ManagedEnvironment {
             .map_err(ManagedEnvironmentError::CreateFloxmetaDir)?;
 
         generations
-            .add_generation(
-                &mut local_checkout,
-                "Synchronized manual changes to generation".to_string(),
-            )
+            .add_generation(&mut local_checkout, description.to_string())
             .map_err(ManagedEnvironmentError::CommitGeneration)?;
 
         self.lock_pointer()?;
@@ -943,6 +990,8 @@ impl ManagedEnvironment {
     /// Like [ManagedEnvironment::pull], downtream commands should check that the environment builds
     /// if applicable.
     ///
+    /// A pending merge is aborted.
+    ///
     /// TODO: Specific behavior for other files than the manifest should is undefined.
     /// Currently the entire environment directory is **deleted and recreated**.
     /// Any other files are lost.
@@ -953,6 +1002,8 @@ impl ManagedEnvironment {
         let current_generation = self.get_current_generation(flox)?;
         let env_dir = self.path.join(ENV_DIR_NAME);
 
+        self.set_pending_merge(None)?;
+
         if let Err(e) = fs::remove_dir_all(&env_dir) {
             return Err(ManagedEnvironmentError::DeleteEnvironment(env_dir, e));
         }
@@ -967,7 +1018,7 @@ impl ManagedEnvironment {
         )
         .map_err(ManagedEnvironmentError::CreateLocalEnvironmentView)?;
 
-        let local_checkout = CoreEnvironment::new(env_dir);
+        let local_checkout = self.core_environment(env_dir);
 
         Ok(local_checkout)
     }
@@ -994,10 +1045,29 @@ impl ManagedEnvironment {
             .map_err(ManagedEnvironmentError::CreateLocalEnvironmentView)?;
         }
 
-        let local = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        let local = self.core_environment(self.path.join(ENV_DIR_NAME));
         Ok(local)
     }
 
+    /// A [CoreEnvironment] for `env_dir`,
+    /// resolving relative includes against the directory containing `.flox`
+    fn core_environment(&self, env_dir: impl AsRef<Path>) -> CoreEnvironment {
+        let env = CoreEnvironment::new(env_dir);
+        match &self.include_base_dir {
+            Some(include_base_dir) => env.with_include_base_dir(include_base_dir),
+            None => env,
+        }
+    }
+
+    /// Resolve relative includes of this environment only if it is locked already.
+    ///
+    /// Used for environments that are not backed by a project directory,
+    /// such as [super::remote_environment::RemoteEnvironment].
+    pub(super) fn without_include_base_dir(mut self) -> Self {
+        self.include_base_dir = None;
+        self
+    }
+
     /// Validate that the local manifest checkout matches the one in the current generation.
     ///
     /// Returns true if they match, false otherwise.
@@ -1074,7 +1144,11 @@ impl ManagedEnvironment {
         &self.pointer
     }
 
-    fn generations(&self) -> Generations {
+    /// The generations of the environment
+    ///
+    /// Reads from the branch tracking this environment in the local floxmeta clone,
+    /// which may have local generations that aren't pushed yet.
+    pub fn generations(&self) -> Generations {
         Generations::new(
             self.floxmeta.git.clone(),
             branch_name(&self.pointer, &self.path),
@@ -1198,8 +1272,15 @@ fn branch_name(pointer: &ManagedPointer, dot_flox_path: &CanonicalPath) -> Strin
 /// [`remote_branch_name`] is primarily used when talking to upstream on FloxHub,
 /// during opening to reconciliate with the upstream repo
 /// as well as during [`ManagedEnvironment::pull`].
+///
+/// FloxHub stores the environments of each owner in a separate repository,
+/// while a generic git remote is shared by all owners,
+/// so branches in the latter are namespaced by owner.
 pub fn remote_branch_name(pointer: &ManagedPointer) -> String {
-    format!("{}", pointer.name)
+    match pointer.git_remote {
+        Some(_) => format!("{}/{}", pointer.owner, pointer.name),
+        None => format!("{}", pointer.name),
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -1210,6 +1291,23 @@ pub enum PullResult {
     Updated,
 }
 
+/// Result of [ManagedEnvironment::merge]
+#[derive(Clone, Debug, PartialEq)]
+pub enum MergeResult {
+    /// Upstream has no generations that are missing locally
+    UpToDate,
+    /// The local environment had no generations missing upstream
+    /// and was reset to the latest upstream version
+    Updated,
+    /// Local and upstream changes were merged into a new generation
+    Merged,
+    /// Local and upstream changes conflict.
+    ///
+    /// The merged manifest, annotated with the conflicting entries,
+    /// was written to the local checkout.
+    Conflicts(Vec<String>),
+}
+
 impl ManagedEnvironment {
     /// Create a new [ManagedEnvironment] from a [PathEnvironment]
     /// by pushing the contents of the original environment as a generation to floxhub.
@@ -1272,11 +1370,8 @@ impl ManagedEnvironment {
         let temp_floxmeta_path = tempfile::tempdir_in(&flox.temp_dir).unwrap().into_path();
 
         // Caller decides whether to set token
-        let token = flox.floxhub_token.as_ref();
-
-        let git_url = flox.floxhub.git_url();
-
-        let options = floxmeta_git_options(git_url, &pointer.owner, token);
+        let (remote_url, options) =
+            floxmeta_remote(flox, &pointer).map_err(ManagedEnvironmentError::OpenFloxmeta)?;
 
         // Initialize a new branch for this environment in a new, temporary,
         // bare repo. This acts like part of the bare repo that backs a user's
@@ -1306,10 +1401,7 @@ impl ManagedEnvironment {
             .map_err(ManagedEnvironmentError::CommitGeneration)?;
 
         temp_floxmeta_git
-            .add_remote(
-                "upstream",
-                &format!("{}/{}/floxmeta", &git_url, &pointer.owner),
-            )
+            .add_remote("upstream", &remote_url)
             .unwrap();
 
         // Push the branch for this environment to FloxHub
@@ -1505,6 +1597,224 @@ impl ManagedEnvironment {
         Ok(PullResult::Updated)
     }
 
+    /// Merge upstream generations into a diverged local environment
+    ///
+    /// The manifests of the current local and upstream generations
+    /// are merged with the manifest of the current generation
+    /// of their latest common commit using [merge_manifests].
+    ///
+    /// Without conflicts the merged manifest is locked, built,
+    /// and only then committed as a new generation on top of the upstream generations,
+    /// which can then be pushed without `force`.
+    /// Local generations that were not pushed are no longer part of the history,
+    /// but their changes are contained in the merged generation.
+    ///
+    /// With conflicts the local branch is left untouched,
+    /// and the merged manifest is written to the local checkout.
+    /// The upstream revision is recorded in [PENDING_MERGE_FILENAME],
+    /// so that committing the resolved manifest with [Environment::edit]
+    /// or [ManagedEnvironment::create_generation_from_local_env]
+    /// completes the merge.
+    ///
+    /// Requires the local checkout to be synched with the current generation.
+    #[instrument(skip(self, flox), fields(progress = "Merging updates from FloxHub"))]
+    pub fn merge(&mut self, flox: &Flox) -> Result<MergeResult, ManagedEnvironmentError> {
+        if self.has_local_changes(flox)? {
+            Err(ManagedEnvironmentError::CheckoutOutOfSync)?
+        }
+
+        let sync_branch = remote_branch_name(&self.pointer);
+        let project_branch = branch_name(&self.pointer, &self.path);
+
+        match self
+            .floxmeta
+            .git
+            .fetch_ref("dynamicorigin", &format!("+{sync_branch}:{sync_branch}"))
+        {
+            Ok(_) => {},
+            Err(GitRemoteCommandError::RefNotFound(_)) => {
+                Err(ManagedEnvironmentError::UpstreamNotFound {
+                    env_ref: self.pointer.clone().into(),
+                    upstream: self.pointer.floxhub_url.to_string(),
+                    user: flox.floxhub_token.as_ref().map(|t| t.handle().to_string()),
+                })?
+            },
+            Err(e) => Err(ManagedEnvironmentError::FetchUpdates(e))?,
+        };
+
+        let git = &self.floxmeta.git;
+        if git
+            .branch_contains_commit(&sync_branch, &project_branch)
+            .map_err(ManagedEnvironmentError::Git)?
+        {
+            return Ok(MergeResult::UpToDate);
+        }
+        if git
+            .branch_contains_commit(&project_branch, &sync_branch)
+            .map_err(ManagedEnvironmentError::Git)?
+        {
+            self.pull(flox, false)?;
+            return Ok(MergeResult::Updated);
+        }
+
+        let Some(merge_base) = git
+            .merge_base(&project_branch, &sync_branch)
+            .map_err(ManagedEnvironmentError::Git)?
+        else {
+            // Unrelated histories, e.g. after the environment was recreated upstream
+            Err(ManagedEnvironmentError::Diverged)?
+        };
+
+        let base_manifest = match Generations::new(git.clone(), merge_base).current_gen_manifest() {
+            Ok(manifest) => manifest,
+            // The environment was empty before it diverged
+            Err(GenerationsError::NoGenerations) => String::new(),
+            Err(e) => Err(ManagedEnvironmentError::ReadManifest(e))?,
+        };
+        let local_manifest = self
+            .generations()
+            .current_gen_manifest()
+            .map_err(ManagedEnvironmentError::ReadManifest)?;
+        let upstream_manifest = Generations::new(git.clone(), sync_branch.clone())
+            .current_gen_manifest()
+            .map_err(ManagedEnvironmentError::ReadManifest)?;
+
+        let merged = merge_manifests(&base_manifest, &local_manifest, &upstream_manifest)
+            .map_err(ManagedEnvironmentError::Merge)?;
+        let upstream_rev = git
+            .branch_hash(&sync_branch)
+            .map_err(ManagedEnvironmentError::GitBranchHash)?;
+
+        if !merged.conflicts.is_empty() {
+            // Leave the local branch untouched until the conflicts are resolved
+            let local_checkout = self.local_env_or_copy_current_generation(flox)?;
+            fs::write(local_checkout.manifest_path(), &merged.contents)
+                .map_err(ManagedEnvironmentError::WriteMergedManifest)?;
+            self.set_pending_merge(Some(&upstream_rev))?;
+
+            let paths = merged
+                .conflicts
+                .into_iter()
+                .map(|conflict| conflict.path)
+                .collect();
+            return Ok(MergeResult::Conflicts(paths));
+        }
+
+        self.commit_merge(flox, &upstream_rev, &merged.contents)?;
+        Ok(MergeResult::Merged)
+    }
+
+    /// Commit the resolution of a merge with conflicts, see [Environment::edit].
+    fn resolve_merge(
+        &mut self,
+        flox: &Flox,
+        upstream_rev: &str,
+        contents: String,
+    ) -> Result<EditResult, EnvironmentError> {
+        let local_checkout = self.local_env_or_copy_current_generation(flox)?;
+        let conflicted_contents = local_checkout
+            .manifest_contents()
+            .map_err(ManagedEnvironmentError::ReadLocalManifest)?;
+        if contents == conflicted_contents {
+            return Ok(EditResult::Unchanged);
+        }
+
+        let old_contents = self
+            .get_current_generation(flox)?
+            .manifest_contents()
+            .map_err(ManagedEnvironmentError::ReadGenerationManifest)?;
+        let store_paths = self.commit_merge(flox, upstream_rev, &contents)?;
+
+        // Even if the manifest equals the local generation,
+        // the environment now contains the upstream generations
+        match EditResult::new(&old_contents, &contents, Some(store_paths.clone()))? {
+            EditResult::Unchanged => Ok(EditResult::Success {
+                built_environment_store_paths: Some(store_paths),
+            }),
+            result => Ok(result),
+        }
+    }
+
+    /// Commit `contents` as a new generation on top of the upstream generations
+    /// at `upstream_rev` and reset the local checkout to it.
+    ///
+    /// The merged manifest is locked and built before the local branch is moved,
+    /// so local generations are kept if either fails.
+    fn commit_merge(
+        &mut self,
+        flox: &Flox,
+        upstream_rev: &str,
+        contents: &str,
+    ) -> Result<BuildEnvOutputs, ManagedEnvironmentError> {
+        let current_generation = self
+            .generations()
+            .writable(flox.temp_dir.clone())
+            .map_err(ManagedEnvironmentError::CreateFloxmetaDir)?
+            .get_current_generation()
+            .map_err(ManagedEnvironmentError::CreateGenerationFiles)?;
+        let mut merged = self.core_environment(current_generation.path());
+        fs::write(merged.manifest_path(), contents)
+            .map_err(ManagedEnvironmentError::WriteMergedManifest)?;
+        merged.lock(flox).map_err(ManagedEnvironmentError::Lock)?;
+        let store_paths = merged.build(flox).map_err(ManagedEnvironmentError::Build)?;
+
+        let project_branch = branch_name(&self.pointer, &self.path);
+        self.floxmeta
+            .git
+            .push_ref(
+                ".",
+                format!("{upstream_rev}:refs/heads/{project_branch}"),
+                true,
+            )
+            .map_err(ManagedEnvironmentError::ApplyUpdates)?;
+
+        let mut generations = self
+            .generations()
+            .writable(flox.temp_dir.clone())
+            .map_err(ManagedEnvironmentError::CreateFloxmetaDir)?;
+        let upstream = generations
+            .get_current_generation()
+            .map_err(ManagedEnvironmentError::CreateGenerationFiles)?;
+        if !Self::validate_checkout(&merged, &upstream)? {
+            generations
+                .add_generation(&mut merged, "Merged local and upstream changes".to_string())
+                .map_err(ManagedEnvironmentError::CommitGeneration)?;
+        }
+        self.lock_pointer()?;
+        self.reset_local_env_to_current_generation(flox)?;
+
+        // TODO: should use self.link but that returns an EnvironmentError
+        CoreEnvironment::link(&self.rendered_env_links.development, &store_paths.develop)
+            .map_err(ManagedEnvironmentError::Link)?;
+        CoreEnvironment::link(&self.rendered_env_links.runtime, &store_paths.runtime)
+            .map_err(ManagedEnvironmentError::Link)?;
+
+        Ok(store_paths)
+    }
+
+    /// The upstream revision of a merge whose conflicts are not yet resolved
+    fn pending_merge(&self) -> Result<Option<String>, ManagedEnvironmentError> {
+        match fs::read_to_string(self.path.join(PENDING_MERGE_FILENAME)) {
+            Ok(rev) => Ok(Some(rev.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ManagedEnvironmentError::ReadPendingMerge(e)),
+        }
+    }
+
+    /// Record or clear the upstream revision of a merge with conflicts
+    fn set_pending_merge(&self, upstream_rev: Option<&str>) -> Result<(), ManagedEnvironmentError> {
+        let path = self.path.join(PENDING_MERGE_FILENAME);
+        match upstream_rev {
+            Some(rev) => fs::write(path, rev).map_err(ManagedEnvironmentError::WritePendingMerge),
+            None => match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    Err(ManagedEnvironmentError::WritePendingMerge(e))
+                },
+                _ => Ok(()),
+            },
+        }
+    }
+
     /// Detach the environment from the remote repository.
     ///
     /// And return a [PathEnvironment] representing
@@ -1554,6 +1864,98 @@ impl ManagedEnvironment {
 
         Ok(path_env)
     }
+
+    /// Copy the environment to another owner and/or name
+    ///
+    /// Without `keep_history`, the copy starts out with a single generation
+    /// containing the current generation of this environment.
+    /// With `keep_history`, all generations of this environment are copied.
+    /// Only generations that were pushed are copied,
+    /// local changes and generations that weren't pushed yet are not.
+    ///
+    /// By default, copying fails with [ManagedEnvironmentError::Diverged]
+    /// if the destination environment already exists,
+    /// unless `force` is set to `true`.
+    #[instrument(skip(self, flox), fields(progress = format!(
+        "Copying environment to {}/{}", destination.owner, destination.name)))]
+    pub fn copy_to(
+        &self,
+        flox: &Flox,
+        destination: &ManagedPointer,
+        keep_history: bool,
+        force: bool,
+    ) -> Result<(), ManagedEnvironmentError> {
+        let (remote_url, options) =
+            floxmeta_remote(flox, destination).map_err(ManagedEnvironmentError::OpenFloxmeta)?;
+        let destination_branch = remote_branch_name(destination);
+        let temp_floxmeta_path = tempfile::tempdir_in(&flox.temp_dir).unwrap().into_path();
+
+        let temp_floxmeta_git = if keep_history {
+            let git = GitCommandProvider::init_with(options, &temp_floxmeta_path, true)
+                .map_err(|e| ManagedEnvironmentError::CopyGenerations(e.into()))?;
+            // The generations are fetched from the local floxmeta repository,
+            // which is up to date with upstream after opening the environment.
+            git.fetch_ref(
+                &self.floxmeta.git.path().to_string_lossy(),
+                &format!(
+                    "{}:refs/heads/{destination_branch}",
+                    remote_branch_name(&self.pointer)
+                ),
+            )
+            .map_err(ManagedEnvironmentError::CopyGenerations)?;
+            git
+        } else {
+            let checkedout_floxmeta_path =
+                tempfile::tempdir_in(&flox.temp_dir).unwrap().into_path();
+            let generations = Generations::init(
+                options,
+                checkedout_floxmeta_path,
+                temp_floxmeta_path,
+                destination_branch.clone(),
+                &destination.name,
+            )
+            .map_err(ManagedEnvironmentError::InitializeFloxmeta)?;
+            let git = generations.git().clone();
+
+            let mut current_generation = self.get_current_generation(flox)?;
+            generations
+                .writable(flox.temp_dir.clone())
+                .map_err(ManagedEnvironmentError::CreateFloxmetaDir)?
+                .add_generation(
+                    &mut current_generation,
+                    format!("Copied from {}/{}", self.pointer.owner, self.pointer.name),
+                )
+                .map_err(ManagedEnvironmentError::CommitGeneration)?;
+            git
+        };
+
+        if !force {
+            match temp_floxmeta_git.fetch_ref(
+                &remote_url,
+                &format!("refs/heads/{destination_branch}:refs/remotes/destination"),
+            ) {
+                Ok(()) => Err(ManagedEnvironmentError::Diverged)?,
+                Err(GitRemoteCommandError::RefNotFound(_)) => {},
+                Err(GitRemoteCommandError::AccessDenied) => {
+                    Err(ManagedEnvironmentError::AccessDenied)?
+                },
+                Err(e) => Err(ManagedEnvironmentError::FetchUpdates(e))?,
+            }
+        }
+
+        match temp_floxmeta_git.push_ref(
+            remote_url,
+            format!("{destination_branch}:refs/heads/{destination_branch}"),
+            force,
+        ) {
+            Err(GitRemoteCommandError::AccessDenied) => Err(ManagedEnvironmentError::AccessDenied)?,
+            Err(GitRemoteCommandError::Diverged) => Err(ManagedEnvironmentError::Diverged)?,
+            Err(e) => Err(ManagedEnvironmentError::Push(e))?,
+            _ => {},
+        }
+
+        Ok(())
+    }
 }
 
 pub mod test_helpers {
@@ -1586,6 +1988,7 @@ pub mod test_helpers {
                 &floxhub,
             ),
             floxmeta: unusable_mock_floxmeta(),
+            include_base_dir: None,
         }
     }
 
@@ -1674,14 +2077,13 @@ mod test {
         new_core_environment_with_lockfile,
     };
     use crate::models::environment::{DOT_FLOX, MANIFEST_FILENAME};
-    use crate::models::floxmeta::floxmeta_dir;
+    use crate::models::floxmeta::{floxmeta_dir, FLOXMETA_GIT_REMOTES_DIR_NAME};
     use crate::models::lockfile::test_helpers::fake_catalog_package_lock;
     use crate::models::lockfile::Lockfile;
     use crate::models::manifest::{Manifest, ManifestPackageDescriptorCatalog};
     use crate::providers::catalog::test_helpers::reset_mocks_from_file;
-    use crate::providers::catalog::{MockClient, GENERATED_DATA};
+    use crate::providers::catalog::{ApiErrorResponse, Client, MockClient, GENERATED_DATA};
     use crate::providers::git::tests::commit_file;
-    use crate::providers::git::GitCommandProvider;
 
     /// Create a [ManagedPointer] for testing with mock owner and name data
     /// as well as an override for the floxhub git url to fetch from local
@@ -1694,10 +2096,87 @@ mod test {
             floxhub_git_url_override: Some(
                 Url::from_directory_path(mock_floxhub_git_path).unwrap(),
             ),
+            git_remote: None,
             version: Version::<1> {},
         }
     }
 
+    /// An environment pushed to a generic git remote
+    /// can be opened in another location from its pointer alone
+    #[test]
+    fn push_to_git_remote_and_open_elsewhere() {
+        let (mut flox, tempdir) = flox_instance();
+        let git_remote = tempdir.path().join("environments.git");
+        fs::create_dir_all(&git_remote).unwrap();
+        GitCommandProvider::init(&git_remote, true).unwrap();
+        flox.floxhub = flox
+            .floxhub
+            .with_git_remote(Some(git_remote.to_string_lossy().into_owned()));
+
+        let owner = EnvironmentOwner::from_str("owner").unwrap();
+        let env = mock_managed_environment(&flox, "version = 1", owner);
+        assert_eq!(
+            env.pointer().git_remote.as_deref(),
+            Some(git_remote.to_string_lossy().as_ref())
+        );
+        assert!(GitCommandProvider::open(&git_remote)
+            .unwrap()
+            .has_branch("owner/name")
+            .unwrap());
+
+        // The floxmeta repository of the first environment is not reused
+        let floxmeta_git_dir = flox.data_dir.join(FLOXMETA_GIT_REMOTES_DIR_NAME);
+        fs::remove_dir_all(&floxmeta_git_dir).ok();
+
+        let flox = Flox {
+            floxhub: flox.floxhub.with_git_remote(None),
+            ..flox
+        };
+        let dot_flox_path =
+            CanonicalPath::new(tempfile::tempdir_in(&flox.temp_dir).unwrap().into_path()).unwrap();
+        let reopened =
+            ManagedEnvironment::open(&flox, env.pointer().clone(), dot_flox_path).unwrap();
+
+        assert_eq!(reopened.pointer(), env.pointer());
+        assert!(floxmeta_git_dir.exists());
+        assert!(!floxmeta_dir(&flox, &env.pointer().owner).exists());
+    }
+
+    /// Environments of different owners with the same name
+    /// don't overwrite each other in a shared git remote
+    #[test]
+    fn git_remote_branches_are_namespaced_by_owner() {
+        let (mut flox, tempdir) = flox_instance();
+        let git_remote = tempdir.path().join("environments.git");
+        fs::create_dir_all(&git_remote).unwrap();
+        GitCommandProvider::init(&git_remote, true).unwrap();
+        flox.floxhub = flox
+            .floxhub
+            .with_git_remote(Some(git_remote.to_string_lossy().into_owned()));
+
+        let alice = EnvironmentOwner::from_str("alice").unwrap();
+        let bob = EnvironmentOwner::from_str("bob").unwrap();
+        let alice_env = mock_managed_environment(&flox, "version = 1 # alice", alice);
+        let bob_env = mock_managed_environment(&flox, "version = 1 # bob", bob);
+
+        let remote = GitCommandProvider::open(&git_remote).unwrap();
+        assert!(remote.has_branch("alice/name").unwrap());
+        assert!(remote.has_branch("bob/name").unwrap());
+
+        // Each environment can be reopened with its own contents
+        for (env, contents) in [
+            (alice_env, "version = 1 # alice"),
+            (bob_env, "version = 1 # bob"),
+        ] {
+            let dot_flox_path =
+                CanonicalPath::new(tempfile::tempdir_in(&flox.temp_dir).unwrap().into_path())
+                    .unwrap();
+            let reopened =
+                ManagedEnvironment::open(&flox, env.pointer().clone(), dot_flox_path).unwrap();
+            assert_eq!(reopened.manifest_contents(&flox).unwrap(), contents);
+        }
+    }
+
     /// Create a .flox directory at dot_flox_path with a pointer
     /// and optional generation lock.
     ///
@@ -2415,6 +2894,268 @@ mod test {
         );
     }
 
+    /// Add a generation with the given manifest to the local branch
+    /// without locking or building it.
+    fn add_unlocked_generation(flox: &Flox, env: &mut ManagedEnvironment, manifest: &str) {
+        let mut generations = env.generations().writable(&flox.temp_dir).unwrap();
+        generations
+            .add_generation(
+                &mut new_core_environment(flox, manifest),
+                "test".to_string(),
+            )
+            .unwrap();
+        env.lock_pointer().unwrap();
+    }
+
+    /// Conflicting changes to a diverged environment are written to the local checkout
+    /// without moving the local branch,
+    /// and committed on top of the upstream generations once resolved
+    #[test]
+    fn merge_writes_conflicts_to_local_checkout() {
+        let owner = EnvironmentOwner::from_str("owner").unwrap();
+        let (flox, _temp_dir_handle) = flox_instance_with_optional_floxhub(Some(&owner));
+
+        let mut env = mock_managed_environment(
+            &flox,
+            indoc! {r#"
+                version = 1
+
+                [vars]
+                FOO = "base"
+            "#},
+            owner,
+        );
+        let project_branch = branch_name(&env.pointer, &env.path);
+        let sync_branch = remote_branch_name(&env.pointer);
+        let base = env.floxmeta.git.branch_hash(&project_branch).unwrap();
+
+        // Change the environment upstream
+        add_unlocked_generation(&flox, &mut env, indoc! {r#"
+            version = 1
+
+            [vars]
+            FOO = "upstream"
+        "#});
+        env.floxmeta
+            .git
+            .push_ref(
+                "dynamicorigin",
+                format!("{project_branch}:{sync_branch}"),
+                false,
+            )
+            .unwrap();
+
+        // Change the environment locally, starting from the common base
+        env.floxmeta
+            .git
+            .reset_branch(&project_branch, &base)
+            .unwrap();
+        add_unlocked_generation(&flox, &mut env, indoc! {r#"
+            version = 1
+
+            [vars]
+            FOO = "local"
+            BAR = "local"
+        "#});
+        env.local_env_or_copy_current_generation(&flox).unwrap();
+
+        let local = env.floxmeta.git.branch_hash(&project_branch).unwrap();
+
+        let result = env.merge(&flox).unwrap();
+        assert_eq!(result, MergeResult::Conflicts(vec!["vars.FOO".to_string()]));
+
+        // local generations are kept until the conflicts are resolved
+        assert_eq!(
+            env.floxmeta.git.branch_hash(&project_branch).unwrap(),
+            local
+        );
+        assert!(env.has_local_changes(&flox).unwrap());
+        assert_eq!(env.manifest_contents(&flox).unwrap(), indoc! {r#"
+                version = 1
+
+                [vars]
+                <<<<<<< local
+                FOO = "local"
+                =======
+                FOO = "upstream"
+                >>>>>>> upstream
+                BAR = "local"
+            "#});
+
+        // resolving the conflicts commits the merge on top of upstream
+        let resolved = indoc! {r#"
+            version = 1
+
+            [vars]
+            FOO = "resolved"
+            BAR = "local"
+        "#};
+        env.edit(&flox, resolved.to_string()).unwrap();
+
+        let upstream = env.floxmeta.git.branch_hash(&sync_branch).unwrap();
+        assert!(env
+            .floxmeta
+            .git
+            .branch_contains_commit(&upstream, &project_branch)
+            .unwrap());
+        assert!(!env.has_local_changes(&flox).unwrap());
+        assert!(env.pending_merge().unwrap().is_none());
+        assert_eq!(env.manifest_contents(&flox).unwrap(), resolved);
+    }
+
+    /// A merged manifest that fails to lock leaves the local branch untouched
+    #[test]
+    fn merge_keeps_local_generations_if_locking_fails() {
+        let owner = EnvironmentOwner::from_str("owner").unwrap();
+        let (mut flox, _temp_dir_handle) = flox_instance_with_optional_floxhub(Some(&owner));
+
+        let mut env = mock_managed_environment(&flox, "version = 1", owner);
+        let project_branch = branch_name(&env.pointer, &env.path);
+        let sync_branch = remote_branch_name(&env.pointer);
+        let base = env.floxmeta.git.branch_hash(&project_branch).unwrap();
+
+        // Change the environment upstream
+        add_unlocked_generation(&flox, &mut env, indoc! {r#"
+            version = 1
+
+            [vars]
+            FOO = "upstream"
+        "#});
+        env.floxmeta
+            .git
+            .push_ref(
+                "dynamicorigin",
+                format!("{project_branch}:{sync_branch}"),
+                false,
+            )
+            .unwrap();
+
+        // Install a package locally, which fails to resolve
+        env.floxmeta
+            .git
+            .reset_branch(&project_branch, &base)
+            .unwrap();
+        add_unlocked_generation(&flox, &mut env, indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+        "#});
+        env.local_env_or_copy_current_generation(&flox).unwrap();
+        let local = env.floxmeta.git.branch_hash(&project_branch).unwrap();
+        let local_manifest = env.manifest_contents(&flox).unwrap();
+
+        let Client::Mock(ref mut client) = flox.catalog_client else {
+            panic!("expected mock catalog client")
+        };
+        client.push_error_response(
+            ApiErrorResponse {
+                detail: "detail".to_string(),
+            },
+            500,
+        );
+        assert!(matches!(
+            env.merge(&flox),
+            Err(ManagedEnvironmentError::Lock(_))
+        ));
+
+        assert_eq!(
+            env.floxmeta.git.branch_hash(&project_branch).unwrap(),
+            local
+        );
+        assert_eq!(env.manifest_contents(&flox).unwrap(), local_manifest);
+        assert!(env.pending_merge().unwrap().is_none());
+    }
+
+    /// Relative local includes are resolved against the directory containing `.flox`
+    #[test]
+    fn relative_includes_resolve_against_project_dir() {
+        let owner = EnvironmentOwner::from_str("owner").unwrap();
+        let (flox, _temp_dir_handle) = flox_instance_with_optional_floxhub(Some(&owner));
+
+        let mut env = mock_managed_environment(&flox, "version = 1", owner);
+        let included_env_dir = env
+            .path
+            .parent()
+            .unwrap()
+            .join("base")
+            .join(DOT_FLOX)
+            .join(ENV_DIR_NAME);
+        fs::create_dir_all(&included_env_dir).unwrap();
+        fs::write(
+            included_env_dir.join(MANIFEST_FILENAME),
+            "version = 1\n[vars]\nFOO = \"included\"\n",
+        )
+        .unwrap();
+
+        env.edit(
+            &flox,
+            indoc! {r#"
+            version = 1
+
+            [include]
+            environments = [{ dir = "base" }]
+        "#}
+            .to_string(),
+        )
+        .unwrap();
+
+        let lockfile = env.lockfile(&flox).unwrap();
+        assert_eq!(lockfile.manifest.vars.0["FOO"].as_value(), Some("included"));
+    }
+
+    /// Copies contain all generations only if the history is kept,
+    /// and don't overwrite existing environments unless forced
+    #[test]
+    fn copy_to_keeps_history_if_requested() {
+        let owner = EnvironmentOwner::from_str("owner").unwrap();
+        let (flox, _temp_dir_handle) = flox_instance_with_optional_floxhub(Some(&owner));
+
+        let mut env = mock_managed_environment(&flox, "version = 1", owner.clone());
+        add_unlocked_generation(&flox, &mut env, "version = 1\n\n[vars]\nFOO = \"bar\"\n");
+        let project_branch = branch_name(&env.pointer, &env.path);
+        let sync_branch = remote_branch_name(&env.pointer);
+        env.floxmeta
+            .git
+            .push_ref(
+                "dynamicorigin",
+                format!("{project_branch}:{sync_branch}"),
+                false,
+            )
+            .unwrap();
+        env.floxmeta
+            .git
+            .fetch_ref("dynamicorigin", &format!("+{sync_branch}:{sync_branch}"))
+            .unwrap();
+
+        for (name, keep_history, generations) in [("copy", false, 1), ("fork", true, 2)] {
+            let destination =
+                ManagedPointer::new(owner.clone(), name.parse().unwrap(), &flox.floxhub);
+            env.copy_to(&flox, &destination, keep_history, false)
+                .unwrap();
+
+            let dot_flox_path =
+                CanonicalPath::new(tempfile::tempdir_in(&flox.temp_dir).unwrap().into_path())
+                    .unwrap();
+            let copy = ManagedEnvironment::open(&flox, destination, dot_flox_path).unwrap();
+            assert_eq!(
+                copy.generations().metadata().unwrap().generations.len(),
+                generations
+            );
+            assert_eq!(
+                copy.manifest_contents(&flox).unwrap(),
+                env.manifest_contents(&flox).unwrap()
+            );
+        }
+
+        let existing = ManagedPointer::new(owner, "copy".parse().unwrap(), &flox.floxhub);
+        assert!(matches!(
+            env.copy_to(&flox, &existing, false, false),
+            Err(ManagedEnvironmentError::Diverged)
+        ));
+        env.copy_to(&flox, &existing, false, true).unwrap();
+    }
+
     /// Test that a lockfile is created when a generation is created from a local environment
     #[test]
     fn create_generation_from_local_env_builds_and_locks() {
@@ -2442,6 +3183,7 @@ mod test {
                 priority: None,
                 version: None,
                 systems: None,
+                outputs: None,
             }
             .into(),
         );
@@ -2565,6 +3307,7 @@ mod test {
             name: EnvironmentName::from_str("name").unwrap(),
             floxhub_url: Url::from_str("https://hub.flox.dev").unwrap(),
             floxhub_git_url_override: None,
+            git_remote: None,
             version: Version::<1>,
         };
         let reg = EnvRegistry {
@@ -35,6 +35,7 @@ mod core_environment;
 pub use core_environment::{test_helpers, CoreEnvironment, CoreEnvironmentError, EditResult};
 
 pub mod generations;
+pub mod include;
 pub mod managed_environment;
 pub mod path_environment;
 pub mod remote_environment;
@@ -121,11 +122,13 @@ pub trait Environment: Send {
     /// Atomically edit this environment, ensuring that it still builds
     fn edit(&mut self, flox: &Flox, contents: String) -> Result<EditResult, EnvironmentError>;
 
-    /// Atomically upgrade packages in this environment
+    /// Atomically upgrade packages in this environment,
+    /// or refresh the environments it includes if `includes` is set
     fn upgrade(
         &mut self,
         flox: &Flox,
         groups_or_iids: &[&str],
+        includes: bool,
     ) -> Result<UpgradeResult, EnvironmentError>;
 
     /// Return the lockfile.
@@ -312,6 +315,7 @@ impl RenderedEnvironmentLinks {
 )]
 #[serde(untagged)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[allow(clippy::large_enum_variant)] // pointers are short lived and rarely moved
 pub enum EnvironmentPointer {
     /// Identifies an environment whose source of truth lies outside of the project itself
     Managed(ManagedPointer),
@@ -353,6 +357,10 @@ pub struct ManagedPointer {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[cfg_attr(test, proptest(value = "None"))]
     pub floxhub_git_url_override: Option<Url>,
+    /// A generic git repository that stores the environment instead of FloxHub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, proptest(value = "None"))]
+    pub git_remote: Option<String>,
     version: Version<1>,
 }
 
@@ -364,6 +372,7 @@ impl ManagedPointer {
             owner,
             floxhub_url: floxhub.base_url().clone(),
             floxhub_git_url_override: floxhub.git_url_override().cloned(),
+            git_remote: floxhub.git_remote().map(String::from),
             version: Version::<1>,
         }
     }
@@ -584,6 +593,8 @@ pub enum UpgradeError {
     PkgNotFound(#[from] ManifestError),
     #[error("'{pkg}' is a package in the group '{group}' with multiple packages")]
     NonEmptyNamedGroup { pkg: String, group: String },
+    #[error("included environments can't be upgraded together with packages")]
+    IncludesWithPackages,
 }
 
 /// Copy a whole directory recursively ignoring the original permissions
@@ -760,6 +771,7 @@ mod test {
             owner: EnvironmentOwner::from_str("owner").unwrap(),
             floxhub_url: DEFAULT_FLOXHUB_URL.clone(),
             floxhub_git_url_override: None,
+            git_remote: None,
             version: Version::<1> {},
         })
     });
@@ -771,6 +783,7 @@ mod test {
             owner: EnvironmentOwner::from_str("owner").unwrap(),
             floxhub_url: DEFAULT_FLOXHUB_URL.clone(),
             floxhub_git_url_override: None,
+            git_remote: None,
             version: Version::<1> {},
         });
 
@@ -13,6 +13,7 @@
 //! `ENVIRONMENT_DIR_NAME` contains the environment definition
 //! and is modified using [CoreEnvironment].
 
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fs::{self};
 use std::io::Write;
@@ -76,9 +77,10 @@ pub struct PathEnvironment {
     rendered_env_links: RenderedEnvironmentLinks,
 }
 
-/// A profile script or list of packages to install when initializing an environment
+/// Variables, scripts or a list of packages to add when initializing an environment
 #[derive(Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
 pub struct InitCustomization {
+    pub vars: Option<BTreeMap<String, String>>,
     pub hook_on_activate: Option<String>,
     pub profile_common: Option<String>,
     pub profile_bash: Option<String>,
@@ -142,7 +144,17 @@ impl PathEnvironment {
     /// This method should only be used to create [CoreEnvironment]s for a [PathEnvironment].
     /// To modify the environment, use the [PathEnvironment] methods instead.
     pub(super) fn into_core_environment(self) -> CoreEnvironment {
-        CoreEnvironment::new(self.path.join(ENV_DIR_NAME))
+        self.core_environment()
+    }
+
+    /// A [CoreEnvironment] for the `env` directory of this environment,
+    /// resolving relative includes against the directory containing `.flox`
+    fn core_environment(&self) -> CoreEnvironment {
+        let env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        match self.path.parent() {
+            Some(parent) => env_view.with_include_base_dir(parent),
+            None => env_view,
+        }
     }
 
     pub fn rename(&mut self, new_name: EnvironmentName) -> Result<(), EnvironmentError> {
@@ -174,7 +186,7 @@ impl PathEnvironment {
 impl Environment for PathEnvironment {
     /// This will lock the environment if it is not already locked.
     fn lockfile(&mut self, flox: &Flox) -> Result<Lockfile, EnvironmentError> {
-        let mut env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        let mut env_view = self.core_environment();
         Ok(env_view.ensure_locked(flox)?)
     }
 
@@ -191,7 +203,7 @@ impl Environment for PathEnvironment {
         packages: &[PackageToInstall],
         flox: &Flox,
     ) -> Result<InstallationAttempt, EnvironmentError> {
-        let mut env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        let mut env_view = self.core_environment();
         let result = env_view.install(packages, flox)?;
         if let Some(ref store_paths) = result.built_environments {
             self.link(flox, store_paths)?;
@@ -210,7 +222,7 @@ impl Environment for PathEnvironment {
         packages: Vec<String>,
         flox: &Flox,
     ) -> Result<UninstallationAttempt, EnvironmentError> {
-        let mut env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        let mut env_view = self.core_environment();
         let result = env_view.uninstall(packages, flox)?;
         if let Some(ref store_paths) = result.built_environment_store_paths {
             self.link(flox, store_paths)?;
@@ -221,7 +233,7 @@ impl Environment for PathEnvironment {
 
     /// Atomically edit this environment, ensuring that it still builds
     fn edit(&mut self, flox: &Flox, contents: String) -> Result<EditResult, EnvironmentError> {
-        let mut env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        let mut env_view = self.core_environment();
         let result = env_view.edit(flox, contents)?;
         if result != EditResult::Unchanged {
             if let Some(ref store_paths) = result.built_environment_store_paths() {
@@ -236,10 +248,11 @@ impl Environment for PathEnvironment {
         &mut self,
         flox: &Flox,
         groups_or_iids: &[&str],
+        includes: bool,
     ) -> Result<UpgradeResult, EnvironmentError> {
         tracing::debug!(to_upgrade = groups_or_iids.join(","), "upgrading");
-        let mut env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
-        let result = env_view.upgrade(flox, groups_or_iids)?;
+        let mut env_view = self.core_environment();
+        let result = env_view.upgrade(flox, groups_or_iids, includes)?;
         if let Some(ref store_paths) = result.store_path {
             self.link(flox, store_paths)?;
         }
@@ -254,7 +267,7 @@ impl Environment for PathEnvironment {
 
     /// Return the deserialized manifest
     fn manifest(&self, _flox: &Flox) -> Result<Manifest, EnvironmentError> {
-        let env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        let env_view = self.core_environment();
         env_view.manifest().map_err(EnvironmentError::Core)
     }
 
@@ -293,7 +306,7 @@ impl Environment for PathEnvironment {
     /// Build the environment
     /// This will lock the environment if it is not already locked.
     fn build(&mut self, flox: &Flox) -> Result<BuildEnvOutputs, EnvironmentError> {
-        let mut env_view = CoreEnvironment::new(self.path.join(ENV_DIR_NAME));
+        let mut env_view = self.core_environment();
         env_view.lock(flox)?;
         let store_paths = env_view.build(flox)?;
         Ok(store_paths)
@@ -408,7 +421,7 @@ impl PathEnvironment {
 
         // Build environment if customization installs at least one package
         if matches!(customization.packages.as_deref(), Some([_, ..])) {
-            let mut env_view = CoreEnvironment::new(environment.path.join(ENV_DIR_NAME));
+            let mut env_view = environment.core_environment();
             env_view.lock(flox)?;
             let store_paths = env_view.build(flox)?;
             environment.link(flox, &store_paths)?;
@@ -620,7 +633,7 @@ mod tests {
         assert!(env.needs_rebuild(&flox).unwrap());
 
         // build the environment -> out link is created -> no rebuild necessary
-        let mut env_view = CoreEnvironment::new(env.path.join(ENV_DIR_NAME));
+        let mut env_view = env.core_environment();
         env_view.lock(&flox).unwrap();
         let store_paths = env_view.build(&flox).unwrap();
         env.link(&flox, &store_paths).unwrap();
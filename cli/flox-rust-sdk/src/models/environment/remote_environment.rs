@@ -6,6 +6,7 @@ use thiserror::Error;
 use tracing::{debug, instrument};
 
 use super::core_environment::UpgradeResult;
+use super::generations::Generations;
 use super::managed_environment::{remote_branch_name, ManagedEnvironment, ManagedEnvironmentError};
 use super::{
     gcroots_dir,
@@ -129,6 +130,8 @@ impl RemoteEnvironment {
             )
         };
 
+        // The directory of a remote environment is managed by flox,
+        // so relative includes can't be resolved against it.
         let mut inner = ManagedEnvironment::open_with(
             floxmeta,
             flox,
@@ -136,7 +139,8 @@ impl RemoteEnvironment {
             dot_flox_path,
             inner_rendered_env_links,
         )
-        .map_err(RemoteEnvironmentError::OpenManagedEnvironment)?;
+        .map_err(RemoteEnvironmentError::OpenManagedEnvironment)?
+        .without_include_base_dir();
 
         // (force) Pull latest changes of the environment from upstream.
         // remote environments stay in sync with upstream without providing a local staging state.
@@ -193,6 +197,23 @@ impl RemoteEnvironment {
         self.inner.pointer()
     }
 
+    /// The generations of the environment, see [ManagedEnvironment::generations]
+    pub fn generations(&self) -> Generations {
+        self.inner.generations()
+    }
+
+    /// Copy the environment to another owner and/or name,
+    /// see [ManagedEnvironment::copy_to]
+    pub fn copy_to(
+        &self,
+        flox: &Flox,
+        destination: &ManagedPointer,
+        keep_history: bool,
+        force: bool,
+    ) -> Result<(), ManagedEnvironmentError> {
+        self.inner.copy_to(flox, destination, keep_history, force)
+    }
+
     /// Update the out link to point to the current version of the environment
     ///
     /// The inner out link points to the latest version of the managed environment.
@@ -292,8 +313,9 @@ impl Environment for RemoteEnvironment {
         &mut self,
         flox: &Flox,
         groups_or_iids: &[&str],
+        includes: bool,
     ) -> Result<UpgradeResult, EnvironmentError> {
-        let result = self.inner.upgrade(flox, groups_or_iids)?;
+        let result = self.inner.upgrade(flox, groups_or_iids, includes)?;
         self.inner
             .push(flox, false)
             .map_err(|e| RemoteEnvironmentError::UpdateUpstream(e).into())
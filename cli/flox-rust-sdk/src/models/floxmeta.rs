@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
 
+use flox_core::path_hash;
 use log::debug;
 use thiserror::Error;
 use tracing::instrument;
@@ -19,6 +20,8 @@ use crate::providers::git::{
 };
 
 pub const FLOXMETA_DIR_NAME: &str = "meta";
+/// Directory of floxmeta repositories of environments stored in generic git repositories
+pub const FLOXMETA_GIT_REMOTES_DIR_NAME: &str = "meta-git";
 
 #[derive(Debug)]
 pub struct FloxMeta {
@@ -57,27 +60,12 @@ impl FloxMeta {
         flox: &Flox,
         pointer: &ManagedPointer,
     ) -> Result<Self, FloxMetaError> {
-        let token = flox.floxhub_token.as_ref();
-
-        let floxhub = Floxhub::new(
-            pointer.floxhub_url.to_owned(),
-            pointer.floxhub_git_url_override.clone(),
-        )
-        .map_err(FloxMetaError::FloxhubError)?;
-
-        let git_url = floxhub.git_url();
-
-        let git_options = floxmeta_git_options(git_url, &pointer.owner, token);
+        let (remote_url, git_options) = floxmeta_remote(flox, pointer)?;
         let branch = remote_branch_name(pointer);
 
-        let git = GitCommandProvider::clone_branch_with(
-            git_options,
-            format!("{}/{}/floxmeta", git_url, pointer.owner),
-            path,
-            branch,
-            true,
-        )
-        .map_err(FloxMetaError::CloneBranch)?;
+        let git =
+            GitCommandProvider::clone_branch_with(git_options, remote_url, path, branch, true)
+                .map_err(FloxMetaError::CloneBranch)?;
 
         Ok(FloxMeta { git })
     }
@@ -91,7 +79,7 @@ impl FloxMeta {
     /// Like [`FloxmetaV2::clone_to`], but uses the system path for floxmeta repositories in XDG_DATA_HOME
     #[instrument(skip(flox), fields(progress = format!("Retrieving environment metadata for {}/{}", pointer.owner, pointer.name)))]
     pub fn clone(flox: &Flox, pointer: &ManagedPointer) -> Result<Self, FloxMetaError> {
-        Self::clone_to(floxmeta_dir(flox, &pointer.owner), flox, pointer)
+        Self::clone_to(floxmeta_dir_for(flox, pointer), flox, pointer)
     }
 
     /// Open a floxmeta repository at a given path
@@ -112,17 +100,7 @@ impl FloxMeta {
         flox: &Flox,
         pointer: &ManagedPointer,
     ) -> Result<Self, FloxMetaError> {
-        let token = flox.floxhub_token.as_ref();
-
-        let floxhub = Floxhub::new(
-            pointer.floxhub_url.to_owned(),
-            pointer.floxhub_git_url_override.clone(),
-        )
-        .map_err(FloxMetaError::FloxhubError)?;
-
-        let git_url = floxhub.git_url();
-
-        let git_options = floxmeta_git_options(git_url, &pointer.owner, token);
+        let (_, git_options) = floxmeta_remote(flox, pointer)?;
 
         if !user_floxmeta_dir.as_ref().exists() {
             Err(FloxMetaError::NotFound(pointer.owner.to_string()))?
@@ -147,7 +125,7 @@ impl FloxMeta {
     /// Like [`FloxmetaV2::open_at`], but uses the system path for floxmeta repositories in XDG_DATA_HOME.
     #[instrument(skip(flox), fields(progress = format!("Updating environment metadata for {}/{}", pointer.owner, pointer.name)))]
     pub fn open(flox: &Flox, pointer: &ManagedPointer) -> Result<Self, FloxMetaError> {
-        let user_floxmeta_dir = floxmeta_dir(flox, &pointer.owner);
+        let user_floxmeta_dir = floxmeta_dir_for(flox, pointer);
         Self::open_at(user_floxmeta_dir, flox, pointer)
     }
 
@@ -156,17 +134,7 @@ impl FloxMeta {
         flox: &Flox,
         pointer: &ManagedPointer,
     ) -> Result<Self, FloxMetaError> {
-        let token = flox.floxhub_token.as_ref();
-
-        let floxhub = Floxhub::new(
-            pointer.floxhub_url.to_owned(),
-            pointer.floxhub_git_url_override.clone(),
-        )
-        .map_err(FloxMetaError::FloxhubError)?;
-
-        let git_url = floxhub.git_url();
-
-        let git_options = floxmeta_git_options(git_url, &pointer.owner, token);
+        let (_, git_options) = floxmeta_remote(flox, pointer)?;
 
         let git = GitCommandProvider::init_with(git_options, user_floxmeta_dir, false).unwrap();
         git.rename_branch(&remote_branch_name(pointer)).unwrap();
@@ -175,6 +143,31 @@ impl FloxMeta {
     }
 }
 
+/// Returns the url of the upstream repository of the floxmeta repository
+/// of an environment, and the git options to interact with it.
+///
+/// Environments with a [ManagedPointer::git_remote] are stored in that repository,
+/// all other environments are stored in the floxmeta repository of their owner on FloxHub.
+pub(super) fn floxmeta_remote(
+    flox: &Flox,
+    pointer: &ManagedPointer,
+) -> Result<(String, GitCommandOptions), FloxMetaError> {
+    if let Some(git_remote) = &pointer.git_remote {
+        return Ok((git_remote.clone(), git_remote_options(git_remote)));
+    }
+
+    let floxhub = Floxhub::new(
+        pointer.floxhub_url.to_owned(),
+        pointer.floxhub_git_url_override.clone(),
+    )
+    .map_err(FloxMetaError::FloxhubError)?;
+
+    let git_url = floxhub.git_url();
+    let git_options = floxmeta_git_options(git_url, &pointer.owner, flox.floxhub_token.as_ref());
+
+    Ok((format!("{git_url}/{}/floxmeta", pointer.owner), git_options))
+}
+
 /// Returns the git options for interacting with floxmeta repositories
 ///
 /// * Disable global and system config
@@ -236,12 +229,48 @@ pub fn floxmeta_git_options(
     options
 }
 
+/// Returns the git options for interacting with floxmeta repositories
+/// stored in a generic git repository rather than FloxHub
+///
+/// Unlike [floxmeta_git_options], the global and system git config is retained,
+/// so that the credential helpers, ssh configuration and url rewrites
+/// configured by the user apply when talking to the remote.
+/// Settings that would interfere with flox creating commits are overridden.
+pub fn git_remote_options(git_remote: &str) -> GitCommandOptions {
+    let mut options = GitCommandOptions::default();
+
+    options.add_config_flag("user.name", "Flox User");
+    options.add_config_flag("user.email", "floxuser@example.invalid");
+    options.add_config_flag("commit.gpgsign", "false");
+    options.add_config_flag("core.hooksPath", "/dev/null");
+
+    options.add_config_flag("remote.dynamicorigin.url", git_remote);
+
+    options
+}
+
 pub(super) fn floxmeta_dir(flox: &Flox, owner: &EnvironmentOwner) -> PathBuf {
     flox.data_dir
         .join(FLOXMETA_DIR_NAME)
         .join(owner.to_string())
 }
 
+/// Returns the path of the local floxmeta repository for an environment
+///
+/// Environments stored in a generic git repository
+/// are kept apart from FloxHub environments of the same owner,
+/// in a directory per remote.
+fn floxmeta_dir_for(flox: &Flox, pointer: &ManagedPointer) -> PathBuf {
+    match &pointer.git_remote {
+        Some(git_remote) => flox
+            .data_dir
+            .join(FLOXMETA_GIT_REMOTES_DIR_NAME)
+            .join(path_hash(git_remote))
+            .join(pointer.owner.to_string()),
+        None => floxmeta_dir(flox, &pointer.owner),
+    }
+}
+
 pub mod test_helpers {
     use super::*;
     use crate::providers::git::test_helpers::mock_provider;
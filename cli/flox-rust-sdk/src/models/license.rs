@@ -0,0 +1,496 @@
+//! Evaluation of package licenses against the license policy of a manifest.
+//!
+//! Licenses are parsed as SPDX license expressions,
+//! so that e.g. `MIT OR GPL-3.0-only` is allowed if either license is allowed,
+//! and `MIT AND GPL-3.0-only` only if both licenses are allowed.
+//! Licenses that can't be parsed as an expression are treated as a single license.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use super::lockfile::{LockedManifestError, Lockfile, PackageToList};
+use super::manifest::ManifestOptions;
+use crate::data::System;
+
+/// A parsed SPDX license expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum LicenseExpression {
+    /// A single license, optionally with an exception, e.g. `GPL-2.0-only WITH Classpath-exception-2.0`
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+    And(Vec<LicenseExpression>),
+    Or(Vec<LicenseExpression>),
+}
+
+impl LicenseExpression {
+    /// Parse an SPDX license expression.
+    ///
+    /// Operators are matched case-insensitively.
+    /// If `license` is not a valid expression,
+    /// it is treated as a single license as is.
+    pub fn parse(license: &str) -> Self {
+        let tokens = tokenize(license);
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        match parser.or_expression() {
+            Some(expression) if parser.pos == tokens.len() => expression,
+            _ => LicenseExpression::License {
+                id: license.trim().to_string(),
+                exception: None,
+            },
+        }
+    }
+
+    /// Evaluate the expression with `verdict` deciding about individual licenses.
+    ///
+    /// `OR` takes the most permissive verdict of its operands,
+    /// `AND` the most restrictive one.
+    fn evaluate(&self, verdict: &impl Fn(&str, Option<&str>) -> LicenseVerdict) -> LicenseVerdict {
+        match self {
+            LicenseExpression::License { id, exception } => verdict(id, exception.as_deref()),
+            LicenseExpression::And(operands) => operands
+                .iter()
+                .map(|operand| operand.evaluate(verdict))
+                .min()
+                .unwrap_or(LicenseVerdict::Allowed),
+            LicenseExpression::Or(operands) => operands
+                .iter()
+                .map(|operand| operand.evaluate(verdict))
+                .max()
+                .unwrap_or(LicenseVerdict::Allowed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Word(&'a str),
+}
+
+fn tokenize(license: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for word in license.split_whitespace() {
+        let mut rest = word;
+        while let Some(stripped) = rest.strip_prefix('(') {
+            tokens.push(Token::Open);
+            rest = stripped;
+        }
+        let mut closing = 0;
+        while let Some(stripped) = rest.strip_suffix(')') {
+            closing += 1;
+            rest = stripped;
+        }
+        if !rest.is_empty() {
+            tokens.push(match rest.to_ascii_uppercase().as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "WITH" => Token::With,
+                _ => Token::Word(rest),
+            });
+        }
+        tokens.extend(std::iter::repeat(Token::Close).take(closing));
+    }
+    tokens
+}
+
+/// Recursive descent parser for
+///
+/// ```text
+/// or   := and ("OR" and)*
+/// and  := atom ("AND" atom)*
+/// atom := "(" or ")" | license ["WITH" exception]
+/// ```
+struct Parser<'a> {
+    tokens: &'a [Token<'a>],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn next_if(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or_expression(&mut self) -> Option<LicenseExpression> {
+        let mut operands = vec![self.and_expression()?];
+        while self.next_if(&Token::Or) {
+            operands.push(self.and_expression()?);
+        }
+        Some(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            LicenseExpression::Or(operands)
+        })
+    }
+
+    fn and_expression(&mut self) -> Option<LicenseExpression> {
+        let mut operands = vec![self.atom()?];
+        while self.next_if(&Token::And) {
+            operands.push(self.atom()?);
+        }
+        Some(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            LicenseExpression::And(operands)
+        })
+    }
+
+    fn atom(&mut self) -> Option<LicenseExpression> {
+        if self.next_if(&Token::Open) {
+            let expression = self.or_expression()?;
+            return self.next_if(&Token::Close).then_some(expression);
+        }
+        let Some(Token::Word(id)) = self.tokens.get(self.pos) else {
+            return None;
+        };
+        self.pos += 1;
+
+        let exception = if self.next_if(&Token::With) {
+            let Some(Token::Word(exception)) = self.tokens.get(self.pos) else {
+                return None;
+            };
+            self.pos += 1;
+            Some(exception.to_string())
+        } else {
+            None
+        };
+
+        Some(LicenseExpression::License {
+            id: id.to_string(),
+            exception,
+        })
+    }
+}
+
+/// Whether a license is permitted by a [LicensePolicy]
+///
+/// Ordered from most restrictive to most permissive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LicenseVerdict {
+    /// The license is listed in `options.deny.licenses`
+    Denied,
+    /// `options.allow.licenses` is set, but doesn't list the license
+    NotAllowed,
+    /// The license is allowed by `options.license-exceptions` for this package
+    Exception,
+    Allowed,
+}
+
+impl Display for LicenseVerdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseVerdict::Denied => write!(f, "denied"),
+            LicenseVerdict::NotAllowed => write!(f, "not allowed"),
+            LicenseVerdict::Exception => write!(f, "allowed by exception"),
+            LicenseVerdict::Allowed => write!(f, "allowed"),
+        }
+    }
+}
+
+/// The license policy defined by `options.allow.licenses`,
+/// `options.deny.licenses` and `options.license-exceptions`
+#[derive(Debug, Clone, Copy)]
+pub struct LicensePolicy<'a> {
+    allow: &'a [String],
+    deny: &'a [String],
+    exceptions: &'a BTreeMap<String, Vec<String>>,
+}
+
+impl<'a> LicensePolicy<'a> {
+    pub fn new(options: &'a ManifestOptions) -> Self {
+        LicensePolicy {
+            allow: &options.allow.licenses,
+            deny: &options.deny.licenses,
+            exceptions: &options.license_exceptions,
+        }
+    }
+
+    /// Evaluate the license expression of the package `install_id`
+    pub fn evaluate(&self, install_id: &str, license: &str) -> LicenseVerdict {
+        let exceptions = self
+            .exceptions
+            .get(install_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        // License identifiers are case-insensitive,
+        // a license with an exception matches both the full expression
+        // and the license alone.
+        let matches = |list: &[String], id: &str, exception: Option<&str>| {
+            let full = exception.map(|exception| format!("{id} WITH {exception}"));
+            list.iter().any(|entry| {
+                entry.eq_ignore_ascii_case(id)
+                    || full
+                        .as_deref()
+                        .is_some_and(|full| entry.eq_ignore_ascii_case(full))
+            })
+        };
+
+        LicenseExpression::parse(license).evaluate(&|id, exception| {
+            if matches(exceptions, id, exception) {
+                LicenseVerdict::Exception
+            } else if matches(self.deny, id, exception) {
+                LicenseVerdict::Denied
+            } else if self.allow.is_empty() || matches(self.allow, id, exception) {
+                LicenseVerdict::Allowed
+            } else {
+                LicenseVerdict::NotAllowed
+            }
+        })
+    }
+}
+
+/// The license of a locked package and how it is evaluated by the license policy
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackageLicense {
+    pub install_id: String,
+    pub pname: Option<String>,
+    pub version: Option<String>,
+    /// The license expression, `None` if the package doesn't declare a license
+    pub license: Option<String>,
+    /// `None` if the package doesn't declare a license
+    pub verdict: Option<LicenseVerdict>,
+}
+
+/// Licenses of all packages locked for a system
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LicenseReport {
+    pub system: System,
+    pub packages: Vec<PackageLicense>,
+}
+
+impl LicenseReport {
+    /// Evaluate the licenses of all packages locked for `system`
+    /// against the license policy of the locked manifest.
+    ///
+    /// Multiple licenses of flake packages are combined with `AND`,
+    /// see [LockedPackageFlake::license](super::lockfile::LockedPackageFlake::license).
+    pub fn new(lockfile: &Lockfile, system: &System) -> Result<Self, LockedManifestError> {
+        let policy = LicensePolicy::new(&lockfile.manifest.options);
+
+        let packages = lockfile
+            .list_packages(system)?
+            .into_iter()
+            .map(|package| {
+                let (install_id, pname, version, license) = match package {
+                    PackageToList::CatalogOrPkgdb(pkg) => (
+                        pkg.install_id,
+                        Some(pkg.info.pname),
+                        pkg.info.version,
+                        pkg.info.license,
+                    ),
+                    PackageToList::Flake(_, pkg) => {
+                        let license = pkg.license();
+                        (
+                            pkg.install_id,
+                            pkg.locked_installable.pname,
+                            pkg.locked_installable.version,
+                            license,
+                        )
+                    },
+                    PackageToList::StorePath(pkg) => (pkg.install_id, None, None, None),
+                };
+                let verdict = license
+                    .as_deref()
+                    .map(|license| policy.evaluate(&install_id, license));
+                PackageLicense {
+                    install_id,
+                    pname,
+                    version,
+                    license,
+                    verdict,
+                }
+            })
+            .collect();
+
+        Ok(LicenseReport {
+            system: system.clone(),
+            packages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::models::lockfile::test_helpers::fake_catalog_package_lock;
+
+    fn license(id: &str) -> LicenseExpression {
+        LicenseExpression::License {
+            id: id.to_string(),
+            exception: None,
+        }
+    }
+
+    #[test]
+    fn parse_expressions() {
+        assert_eq!(LicenseExpression::parse("MIT"), license("MIT"));
+        assert_eq!(
+            LicenseExpression::parse("MIT OR GPL-3.0-only"),
+            LicenseExpression::Or(vec![license("MIT"), license("GPL-3.0-only")])
+        );
+        assert_eq!(
+            LicenseExpression::parse("(MIT or Apache-2.0) AND BSD-3-Clause"),
+            LicenseExpression::And(vec![
+                LicenseExpression::Or(vec![license("MIT"), license("Apache-2.0")]),
+                license("BSD-3-Clause"),
+            ])
+        );
+        assert_eq!(
+            LicenseExpression::parse("MIT AND BSD-3-Clause OR Apache-2.0"),
+            LicenseExpression::Or(vec![
+                LicenseExpression::And(vec![license("MIT"), license("BSD-3-Clause")]),
+                license("Apache-2.0"),
+            ])
+        );
+        assert_eq!(
+            LicenseExpression::parse("GPL-2.0-only WITH Classpath-exception-2.0"),
+            LicenseExpression::License {
+                id: "GPL-2.0-only".to_string(),
+                exception: Some("Classpath-exception-2.0".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn unparsable_expressions_are_a_single_license() {
+        assert_eq!(
+            LicenseExpression::parse("Unfree redistributable"),
+            license("Unfree redistributable")
+        );
+        assert_eq!(LicenseExpression::parse("(MIT"), license("(MIT"));
+        assert_eq!(LicenseExpression::parse("MIT OR"), license("MIT OR"));
+    }
+
+    fn options(allow: &[&str], deny: &[&str], exceptions: &[(&str, &[&str])]) -> ManifestOptions {
+        let mut options = ManifestOptions::default();
+        options.allow.licenses = allow.iter().map(|s| s.to_string()).collect();
+        options.deny.licenses = deny.iter().map(|s| s.to_string()).collect();
+        options.license_exceptions = exceptions
+            .iter()
+            .map(|(id, licenses)| {
+                (
+                    id.to_string(),
+                    licenses.iter().map(|s| s.to_string()).collect(),
+                )
+            })
+            .collect();
+        options
+    }
+
+    #[test]
+    fn evaluate_allow_list() {
+        let options = options(&["MIT", "Apache-2.0"], &[], &[]);
+        let policy = LicensePolicy::new(&options);
+
+        assert_eq!(policy.evaluate("foo", "mit"), LicenseVerdict::Allowed);
+        assert_eq!(
+            policy.evaluate("foo", "MIT OR GPL-3.0-only"),
+            LicenseVerdict::Allowed
+        );
+        assert_eq!(
+            policy.evaluate("foo", "MIT AND GPL-3.0-only"),
+            LicenseVerdict::NotAllowed
+        );
+        assert_eq!(
+            policy.evaluate("foo", "GPL-3.0-only"),
+            LicenseVerdict::NotAllowed
+        );
+    }
+
+    #[test]
+    fn evaluate_deny_list() {
+        let options = options(&[], &["GPL-3.0-only"], &[]);
+        let policy = LicensePolicy::new(&options);
+
+        assert_eq!(
+            policy.evaluate("foo", "BSD-3-Clause"),
+            LicenseVerdict::Allowed
+        );
+        assert_eq!(
+            policy.evaluate("foo", "MIT OR GPL-3.0-only"),
+            LicenseVerdict::Allowed
+        );
+        assert_eq!(
+            policy.evaluate("foo", "MIT AND GPL-3.0-only"),
+            LicenseVerdict::Denied
+        );
+        assert_eq!(
+            policy.evaluate("foo", "GPL-3.0-only WITH GCC-exception-3.1"),
+            LicenseVerdict::Denied
+        );
+    }
+
+    #[test]
+    fn evaluate_exceptions() {
+        let options = options(&["MIT"], &["GPL-3.0-only"], &[("foo", &[
+            "GPL-3.0-only",
+            "Unfree",
+        ])]);
+        let policy = LicensePolicy::new(&options);
+
+        assert_eq!(
+            policy.evaluate("foo", "GPL-3.0-only"),
+            LicenseVerdict::Exception
+        );
+        assert_eq!(
+            policy.evaluate("foo", "MIT AND Unfree"),
+            LicenseVerdict::Exception
+        );
+        assert_eq!(policy.evaluate("foo", "MIT"), LicenseVerdict::Allowed);
+        assert_eq!(
+            policy.evaluate("bar", "GPL-3.0-only"),
+            LicenseVerdict::Denied
+        );
+    }
+
+    #[test]
+    fn report_for_system() {
+        let (_, _, mut foo) = fake_catalog_package_lock("foo", None);
+        foo.license = Some("MIT OR GPL-3.0-only".to_string());
+        foo.version = "1.0".to_string();
+        let (_, _, bar) = fake_catalog_package_lock("bar", None);
+
+        let mut lockfile = Lockfile {
+            packages: vec![foo.into(), bar.into()],
+            ..Default::default()
+        };
+        lockfile.manifest.options = options(&["GPL-3.0-only"], &[], &[]);
+
+        let report = LicenseReport::new(&lockfile, &"aarch64-darwin".to_string()).unwrap();
+        assert_eq!(report.packages, vec![
+            PackageLicense {
+                install_id: "foo_install_id".to_string(),
+                pname: Some("foo".to_string()),
+                version: Some("1.0".to_string()),
+                license: Some("MIT OR GPL-3.0-only".to_string()),
+                verdict: Some(LicenseVerdict::Allowed),
+            },
+            PackageLicense {
+                install_id: "bar_install_id".to_string(),
+                pname: Some("bar".to_string()),
+                version: Some("".to_string()),
+                license: None,
+                verdict: None,
+            },
+        ]);
+    }
+}
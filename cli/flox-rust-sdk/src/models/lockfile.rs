@@ -21,9 +21,11 @@ use flox_core::Version;
 use log::debug;
 use thiserror::Error;
 
+use super::license::{LicensePolicy, LicenseVerdict};
 use super::manifest::{
-    Allows,
+    IncludeDescriptor,
     Manifest,
+    ManifestOptions,
     ManifestPackageDescriptor,
     ManifestPackageDescriptorCatalog,
     ManifestPackageDescriptorFlake,
@@ -82,6 +84,30 @@ pub struct Lockfile {
     pub manifest: Manifest,
     /// locked packages
     pub packages: Vec<LockedPackage>,
+    /// the manifest before included environments were merged into it,
+    /// and the included environments as they were at locking time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, proptest(value = "None"))]
+    pub compose: Option<Compose>,
+}
+
+/// Records how the manifest of a [Lockfile] was composed from included environments
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Compose {
+    /// The manifest of the environment itself
+    pub composer: Manifest,
+    /// The included environments in the order of the `include` section
+    pub include: Vec<LockedInclude>,
+}
+
+/// An included environment, frozen at the time it was locked
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockedInclude {
+    pub descriptor: IncludeDescriptor,
+    /// The generation of a remote environment that was included
+    pub generation: Option<u32>,
+    pub manifest: Manifest,
 }
 
 impl Lockfile {
@@ -118,6 +144,13 @@ impl LockedPackage {
         }
     }
 
+    pub(crate) fn as_flake_package_ref(&self) -> Option<&LockedPackageFlake> {
+        match self {
+            LockedPackage::Flake(pkg) => Some(pkg),
+            _ => None,
+        }
+    }
+
     pub fn install_id(&self) -> &str {
         match self {
             LockedPackage::Catalog(pkg) => &pkg.install_id,
@@ -300,6 +333,24 @@ impl LockedPackageFlake {
             locked_installable,
         }
     }
+
+    /// The licenses of the package as a single SPDX expression,
+    /// multiple licenses are combined with `AND`.
+    ///
+    /// Returns [None] if the package doesn't declare a license.
+    pub fn license(&self) -> Option<String> {
+        match self.locked_installable.licenses.as_deref()? {
+            [] => None,
+            [license] => Some(license.clone()),
+            licenses => Some(
+                licenses
+                    .iter()
+                    .map(|license| format!("({license})"))
+                    .collect::<Vec<_>>()
+                    .join(" AND "),
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -535,6 +586,8 @@ impl Lockfile {
         client: &impl catalog::ClientTrait,
         installable_locker: &impl InstallableLocker,
     ) -> Result<Lockfile, LockedManifestError> {
+        Self::check_target_systems(manifest)?;
+
         let catalog_groups = Self::collect_package_groups(manifest, seed_lockfile)?;
         let (mut already_locked_packages, groups_to_lock) =
             Self::split_fully_locked_groups(catalog_groups, seed_lockfile);
@@ -555,7 +608,13 @@ impl Lockfile {
             already_locked_packages
                 .iter()
                 .filter_map(LockedPackage::as_catalog_package_ref),
-            &manifest.options.allow,
+            &manifest.options,
+        )?;
+        Self::check_flake_licenses_are_allowed(
+            already_locked_installables
+                .iter()
+                .filter_map(LockedPackage::as_flake_package_ref),
+            &manifest.options,
         )?;
 
         // Update the priority of already locked packages to match the manifest.
@@ -572,6 +631,7 @@ impl Lockfile {
                     already_locked_installables,
                 ]
                 .concat(),
+                compose: None,
             });
         }
 
@@ -586,12 +646,13 @@ impl Lockfile {
         };
 
         // unpack locked packages from response
-        let locked_packages: Vec<LockedPackage> =
+        let mut locked_packages: Vec<LockedPackage> =
             Self::locked_packages_from_resolution(manifest, resolved)?
                 .map(Into::into)
                 .collect();
+        Self::select_outputs(&mut locked_packages, manifest)?;
 
-        let locked_installables = if !installables_to_lock.is_empty() {
+        let locked_installables: Vec<LockedPackage> = if !installables_to_lock.is_empty() {
             Self::lock_flake_installables(installable_locker, installables_to_lock)?
                 .map(Into::into)
                 .collect()
@@ -600,12 +661,19 @@ impl Lockfile {
         };
 
         // The server should be checking this,
-        // but double check
+        // but double check, which also evaluates license expressions
+        // and denied licenses
         Self::check_packages_are_allowed(
             locked_packages
                 .iter()
                 .filter_map(LockedPackage::as_catalog_package_ref),
-            &manifest.options.allow,
+            &manifest.options,
+        )?;
+        Self::check_flake_licenses_are_allowed(
+            locked_installables
+                .iter()
+                .filter_map(LockedPackage::as_flake_package_ref),
+            &manifest.options,
         )?;
 
         let lockfile = Lockfile {
@@ -619,29 +687,47 @@ impl Lockfile {
                 locked_installables,
             ]
             .concat(),
+            compose: None,
         };
 
         Ok(lockfile)
     }
 
-    /// Given locked packages and manifest options allows, verify that the
+    /// Verify that every `[target.<system>]` table overrides an enabled system.
+    ///
+    /// Overrides for other systems would never apply,
+    /// which most likely means the system is misspelled.
+    fn check_target_systems(manifest: &Manifest) -> Result<(), LockedManifestError> {
+        let enabled_systems = manifest
+            .options
+            .systems
+            .as_deref()
+            .unwrap_or(&*DEFAULT_SYSTEMS_STR);
+
+        for system in manifest.target.keys() {
+            if !enabled_systems.contains(system) {
+                return Err(LockedManifestError::TargetSystemUnavailable {
+                    system: system.clone(),
+                    enabled_systems: enabled_systems.to_vec(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Given locked packages and manifest options, verify that the
     /// locked packages are allowed.
+    ///
+    /// Licenses are evaluated as SPDX expressions by [LicensePolicy].
     fn check_packages_are_allowed<'a>(
         locked_packages: impl IntoIterator<Item = &'a LockedPackageCatalog>,
-        allow: &Allows,
+        options: &ManifestOptions,
     ) -> Result<(), LockedManifestError> {
+        let allow = &options.allow;
+        let license_policy = LicensePolicy::new(options);
         for package in locked_packages {
-            if !allow.licenses.is_empty() {
-                let Some(ref license) = package.license else {
-                    continue;
-                };
-
-                if !allow.licenses.iter().any(|allowed| allowed == license) {
-                    return Err(LockedManifestError::LicenseNotAllowed(
-                        package.install_id.to_string(),
-                        license.to_string(),
-                    ));
-                }
+            if let Some(ref license) = package.license {
+                Self::check_license_is_allowed(&license_policy, &package.install_id, license)?;
             }
 
             // Don't allow broken by default
@@ -668,6 +754,42 @@ impl Lockfile {
         Ok(())
     }
 
+    /// Verify that the licenses of locked flake packages are allowed.
+    ///
+    /// Licenses are evaluated like those of catalog packages,
+    /// see [Self::check_packages_are_allowed].
+    fn check_flake_licenses_are_allowed<'a>(
+        locked_installables: impl IntoIterator<Item = &'a LockedPackageFlake>,
+        options: &ManifestOptions,
+    ) -> Result<(), LockedManifestError> {
+        let license_policy = LicensePolicy::new(options);
+        for installable in locked_installables {
+            if let Some(license) = installable.license() {
+                Self::check_license_is_allowed(&license_policy, &installable.install_id, &license)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the license of a package against the license policy
+    fn check_license_is_allowed(
+        license_policy: &LicensePolicy,
+        install_id: &str,
+        license: &str,
+    ) -> Result<(), LockedManifestError> {
+        match license_policy.evaluate(install_id, license) {
+            LicenseVerdict::Allowed | LicenseVerdict::Exception => Ok(()),
+            LicenseVerdict::NotAllowed => Err(LockedManifestError::LicenseNotAllowed(
+                install_id.to_string(),
+                license.to_string(),
+            )),
+            LicenseVerdict::Denied => Err(LockedManifestError::LicenseDenied(
+                install_id.to_string(),
+                license.to_string(),
+            )),
+        }
+    }
+
     /// Update the priority of already locked packages to match the manifest.
     ///
     /// The `priority` field is originally set when constructing in [LockedPackageCatalog::from_parts],
@@ -752,10 +874,20 @@ impl Lockfile {
 
         let manifest_systems = manifest.options.systems.as_deref();
 
-        let maybe_licenses = if manifest.options.allow.licenses.is_empty() {
-            None
-        } else {
-            Some(manifest.options.allow.licenses.clone())
+        // Licenses allowed for a package, including its license exceptions
+        let allowed_licenses = |install_id: &str| {
+            let allowed = &manifest.options.allow.licenses;
+            if allowed.is_empty() {
+                return None;
+            }
+            let exceptions = manifest.options.license_exceptions.get(install_id);
+            Some(
+                allowed
+                    .iter()
+                    .chain(exceptions.into_iter().flatten())
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            )
         };
 
         for (install_id, manifest_descriptor) in manifest.install.iter() {
@@ -774,7 +906,11 @@ impl Lockfile {
                 // TODO: add support for insecure
                 allow_insecure: None,
                 allow_unfree: manifest.options.allow.unfree,
-                allowed_licenses: maybe_licenses.clone(),
+                // Let the catalog pick versions with an allowed license,
+                // the result is checked against the full license policy,
+                // including SPDX expressions and denied licenses,
+                // by [Self::check_packages_are_allowed].
+                allowed_licenses: allowed_licenses(install_id),
                 systems: vec![],
             };
 
@@ -944,6 +1080,47 @@ impl Lockfile {
         Ok(locked_pkg_iter)
     }
 
+    /// Restrict newly locked catalog packages to the `outputs` of their descriptor.
+    ///
+    /// The selected outputs replace `outputs_to_install`,
+    /// and other outputs are removed from the locked package,
+    /// so that only the selected outputs are realised and linked into the environment.
+    /// Already locked packages are locked again when their `outputs` change,
+    /// see [ManifestPackageDescriptorCatalog::invalidates_existing_resolution].
+    fn select_outputs(
+        packages: &mut [LockedPackage],
+        manifest: &Manifest,
+    ) -> Result<(), LockedManifestError> {
+        for package in packages.iter_mut() {
+            let LockedPackage::Catalog(locked) = package else {
+                continue;
+            };
+            let Some(outputs) = manifest
+                .catalog_pkg_descriptor_with_id(&locked.install_id)
+                .and_then(|descriptor| descriptor.outputs)
+            else {
+                continue;
+            };
+
+            let unknown = outputs
+                .iter()
+                .filter(|output| !locked.outputs.contains_key(*output))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                return Err(LockedManifestError::UnknownOutputs {
+                    install_id: locked.install_id.clone(),
+                    outputs: unknown,
+                    available: locked.outputs.keys().cloned().collect(),
+                });
+            }
+
+            locked.outputs.retain(|name, _| outputs.contains(name));
+            locked.outputs_to_install = Some(outputs);
+        }
+        Ok(())
+    }
+
     /// Constructs [ResolutionFailure]s from the failed groups
     fn collect_failures(
         failed_groups: &[ResolvedPackageGroup],
@@ -1423,11 +1600,33 @@ pub enum LockedManifestError {
         enabled_systems: Vec<String>,
     },
 
+    #[error(
+        "'target.{system}' overrides disabled or unknown system '{system}' (enabled systems: {enabled_systems})",
+        enabled_systems=enabled_systems.join(", ")
+    )]
+    TargetSystemUnavailable {
+        system: String,
+        enabled_systems: Vec<String>,
+    },
+
+    #[error(
+        "package '{install_id}' doesn't have the output(s) {outputs} (available outputs: {available})",
+        outputs=outputs.join(", "),
+        available=available.join(", ")
+    )]
+    UnknownOutputs {
+        install_id: String,
+        outputs: Vec<String>,
+        available: Vec<String>,
+    },
+
     #[error("Catalog lockfile does not support update")]
     UnsupportedLockfileForUpdate,
 
     #[error("The package '{0}' has license '{1}' which is not in the list of allowed licenses.\n\nAllow this license by adding it to 'options.allow.licenses' in manifest.toml")]
     LicenseNotAllowed(String, String),
+    #[error("The package '{0}' has license '{1}' which is denied by 'options.deny.licenses' in manifest.toml.\n\nAllow this license for this package by adding it to 'options.license-exceptions.{0}' in manifest.toml")]
+    LicenseDenied(String, String),
     #[error("The package '{0}' is marked as broken.\n\nAllow broken packages by setting 'options.allow.broken = true' in manifest.toml")]
     BrokenNotAllowed(String),
     #[error("The package '{0}' has an unfree license.\n\nAllow unfree packages by setting 'options.allow.unfree = true' in manifest.toml")]
@@ -1467,6 +1666,7 @@ pub mod test_helpers {
             systems: Some(vec![SystemEnum::Aarch64Darwin.to_string()]),
             version: None,
             priority: None,
+            outputs: None,
         }
         .into();
 
@@ -1634,7 +1834,7 @@ pub(crate) mod tests {
 
     use self::catalog::PackageResolutionInfo;
     use super::*;
-    use crate::models::manifest::{Manifest, RawManifest};
+    use crate::models::manifest::{Allows, Manifest, RawManifest};
     use crate::models::search::{SearchLimit, SearchResults};
     use crate::providers::flox_cpp_utils::{FlakeInstallableError, InstallableLockerMock};
 
@@ -1953,6 +2153,7 @@ pub(crate) mod tests {
             priority: 5,
         }
         .into()],
+        compose: None,
     });
 
     #[test]
@@ -2139,6 +2340,80 @@ pub(crate) mod tests {
         );
     }
 
+    /// Overrides for a system that is not enabled in `options.systems` are an error
+    #[test]
+    fn target_system_required_in_options() {
+        let manifest = toml::from_str(indoc! {r#"
+            version = 1
+
+            [target.aarch64-darwin.vars]
+            FOO = "bar"
+
+            [options]
+            systems = ["x86_64-linux"]
+        "#})
+        .unwrap();
+
+        let actual_result = Lockfile::check_target_systems(&manifest);
+
+        assert!(
+            matches!(actual_result, Err(LockedManifestError::TargetSystemUnavailable {
+                system,
+                enabled_systems
+            }) if system == "aarch64-darwin" && enabled_systems == vec!["x86_64-linux"])
+        );
+    }
+
+    /// Locked packages only contain the outputs selected in the manifest
+    #[test]
+    fn select_outputs_of_catalog_packages() {
+        let (foo_iid, _, mut foo_locked) = fake_catalog_package_lock("foo", None);
+        foo_locked.outputs = ["out", "man", "dev"]
+            .into_iter()
+            .map(|name| (name.to_string(), format!("/nix/store/foo-{name}")))
+            .collect();
+        foo_locked.outputs_to_install = Some(vec!["out".to_string()]);
+
+        let manifest: Manifest = toml::from_str(&formatdoc! {r#"
+            version = 1
+
+            [install]
+            {foo_iid}.pkg-path = "foo"
+            {foo_iid}.outputs = ["out", "man"]
+        "#})
+        .unwrap();
+
+        let mut packages = vec![foo_locked.clone().into()];
+        Lockfile::select_outputs(&mut packages, &manifest).unwrap();
+        let LockedPackage::Catalog(locked) = &packages[0] else {
+            panic!("expected a catalog package");
+        };
+        assert_eq!(locked.outputs.keys().collect::<Vec<_>>(), vec![
+            "man", "out"
+        ]);
+        assert_eq!(
+            locked.outputs_to_install,
+            Some(vec!["out".to_string(), "man".to_string()])
+        );
+
+        let manifest: Manifest = toml::from_str(&formatdoc! {r#"
+            version = 1
+
+            [install]
+            {foo_iid}.pkg-path = "foo"
+            {foo_iid}.outputs = ["doc"]
+        "#})
+        .unwrap();
+
+        let mut packages = vec![foo_locked.into()];
+        match Lockfile::select_outputs(&mut packages, &manifest) {
+            Err(LockedManifestError::UnknownOutputs { outputs, .. }) => {
+                assert_eq!(outputs, vec!["doc"])
+            },
+            other => panic!("expected unknown outputs, got {other:?}"),
+        }
+    }
+
     /// If packages specify different groups,
     /// create request groups for each group.
     #[test]
@@ -2213,6 +2488,7 @@ pub(crate) mod tests {
                 systems: None,
                 version: None,
                 priority: None,
+                outputs: None,
             }
             .into(),
         );
@@ -2272,6 +2548,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest_before.clone(),
             packages: vec![foo_before_locked.clone().into()],
+            compose: None,
         };
 
         // ---------------------------------------------------------------------
@@ -2303,6 +2580,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest_before.clone(),
             packages: vec![foo_before_locked.into()],
+            compose: None,
         };
 
         // ---------------------------------------------------------------------
@@ -2348,6 +2626,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest_before.clone(),
             packages: vec![foo_before_locked.clone().into()],
+            compose: None,
         };
 
         // ---------------------------------------------------------------------
@@ -2594,6 +2873,7 @@ pub(crate) mod tests {
                 baz_locked.into(),
                 qux_locked.clone().into(),
             ],
+            compose: None,
         };
 
         lockfile.unlock_packages_by_group_or_iid(&[&foo_iid, &baz_iid]);
@@ -2616,6 +2896,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.into(), bar_locked.into()],
+            compose: None,
         };
 
         lockfile.unlock_packages_by_group_or_iid(&["group"]);
@@ -2638,6 +2919,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.into(), bar_locked.into()],
+            compose: None,
         };
 
         lockfile.unlock_packages_by_group_or_iid(&[&foo_iid]);
@@ -2783,6 +3065,41 @@ pub(crate) mod tests {
         assert_eq!(&*systems, expected_systems.as_slice());
     }
 
+    /// Allowed licenses are passed to the catalog,
+    /// including the license exceptions of a package
+    #[test]
+    fn collect_package_groups_passes_allowed_licenses() {
+        let manifest_str = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+            jq.pkg-path = "jq"
+
+            [options]
+            systems = ["aarch64-darwin"]
+            allow.licenses = ["MIT"]
+            license-exceptions.jq = ["GPL-3.0-only"]
+        "#};
+        let manifest: Manifest = toml::from_str(manifest_str).unwrap();
+        let package_groups: Vec<_> = Lockfile::collect_package_groups(&manifest, None)
+            .unwrap()
+            .collect();
+
+        let allowed_licenses = package_groups[0]
+            .descriptors
+            .iter()
+            .map(|d| (d.install_id.as_str(), d.allowed_licenses.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(allowed_licenses, vec![
+            ("hello", Some(vec!["MIT".to_string()])),
+            (
+                "jq",
+                Some(vec!["MIT".to_string(), "GPL-3.0-only".to_string()])
+            ),
+        ]);
+    }
+
     #[test]
     fn test_split_out_fully_locked_packages() {
         let (foo_iid, foo_descriptor, foo_locked) =
@@ -2806,6 +3123,7 @@ pub(crate) mod tests {
             packages: [&foo_locked, &bar_locked, &baz_locked]
                 .map(|p| p.clone().into())
                 .to_vec(),
+            compose: None,
         };
 
         manifest
@@ -2899,6 +3217,7 @@ pub(crate) mod tests {
                 foo_locked.clone().into(),
                 foo_locked_second_system.clone().into(),
             ],
+            compose: None,
         };
 
         manifest
@@ -2953,6 +3272,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.into()],
+            compose: None,
         };
 
         manifest
@@ -3005,6 +3325,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![bar_locked.clone().into()],
+            compose: None,
         };
 
         let flake_installables = Lockfile::collect_flake_installables(&manifest);
@@ -3034,6 +3355,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![bar_locked.clone().into()],
+            compose: None,
         };
 
         let flake_installables = Lockfile::collect_flake_installables(&manifest);
@@ -3070,6 +3392,7 @@ pub(crate) mod tests {
                 foo_locked_system_1.clone().into(),
                 foo_locked_system_2.into(),
             ],
+            compose: None,
         };
 
         let flake_installables = Lockfile::collect_flake_installables(&manifest);
@@ -3098,6 +3421,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.clone().into()],
+            compose: None,
         };
 
         // system_2 is added to the manifest
@@ -3138,6 +3462,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.into(), bar_locked.into()],
+            compose: None,
         };
 
         let locked_manifest =
@@ -3168,6 +3493,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![bar_locked.into()],
+            compose: None,
         };
 
         let foo_catalog_descriptor = foo_descriptor.as_catalog_descriptor_ref().unwrap();
@@ -3236,6 +3562,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.into()],
+            compose: None,
         };
 
         let locker_mock = InstallableLockerMock::new();
@@ -3265,6 +3592,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.clone().into()],
+            compose: None,
         };
 
         let mut foo_descriptor_priority_after = foo_descriptor.unwrap_catalog_descriptor().unwrap();
@@ -3312,6 +3640,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest: manifest.clone(),
             packages: vec![foo_locked.into()],
+            compose: None,
         };
 
         // Set `options.allow.unfree = false` in the manifest, but not the lockfile
@@ -3379,15 +3708,49 @@ pub(crate) mod tests {
         foo_locked.license = Some("disallowed".to_string());
 
         assert!(matches!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: None,
-                broken: None,
-                licenses: vec!["allowed".to_string()]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: None,
+                    broken: None,
+                    licenses: vec!["allowed".to_string()],
+                },
+                ..Default::default()
             }),
             Err(LockedManifestError::LicenseNotAllowed { .. })
         ));
     }
 
+    /// [Lockfile::check_flake_licenses_are_allowed] applies the license policy
+    /// to all licenses of a flake package
+    #[test]
+    fn check_flake_licenses_are_allowed_combines_licenses() {
+        let (_, _, mut foo_locked) = fake_flake_installable_lock("foo");
+        foo_locked.locked_installable.licenses =
+            Some(vec!["MIT".to_string(), "GPL-3.0-only".to_string()]);
+        assert_eq!(
+            foo_locked.license().as_deref(),
+            Some("(MIT) AND (GPL-3.0-only)")
+        );
+
+        let options = |allowed: &[&str]| ManifestOptions {
+            allow: Allows {
+                licenses: allowed.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            Lockfile::check_flake_licenses_are_allowed([&foo_locked], &options(&["MIT"])),
+            Err(LockedManifestError::LicenseNotAllowed { .. })
+        ));
+        assert!(Lockfile::check_flake_licenses_are_allowed(
+            [&foo_locked],
+            &options(&["MIT", "GPL-3.0-only"])
+        )
+        .is_ok());
+    }
+
     /// [Lockfile::check_packages_are_allowed] does not error when
     /// a package's license is allowed
     #[test]
@@ -3396,10 +3759,13 @@ pub(crate) mod tests {
         foo_locked.license = Some("allowed".to_string());
 
         assert!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: None,
-                broken: None,
-                licenses: vec!["allowed".to_string()]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: None,
+                    broken: None,
+                    licenses: vec!["allowed".to_string()],
+                },
+                ..Default::default()
             })
             .is_ok()
         );
@@ -3413,10 +3779,13 @@ pub(crate) mod tests {
         foo_locked.broken = Some(true);
 
         assert!(matches!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: None,
-                broken: None,
-                licenses: vec![]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: None,
+                    broken: None,
+                    licenses: vec![],
+                },
+                ..Default::default()
             }),
             Err(LockedManifestError::BrokenNotAllowed { .. })
         ));
@@ -3430,10 +3799,13 @@ pub(crate) mod tests {
         foo_locked.broken = Some(true);
 
         assert!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: None,
-                broken: Some(true),
-                licenses: vec![]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: None,
+                    broken: Some(true),
+                    licenses: vec![],
+                },
+                ..Default::default()
             })
             .is_ok()
         );
@@ -3447,10 +3819,13 @@ pub(crate) mod tests {
         foo_locked.broken = Some(true);
 
         assert!(matches!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: None,
-                broken: Some(false),
-                licenses: vec![]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: None,
+                    broken: Some(false),
+                    licenses: vec![],
+                },
+                ..Default::default()
             }),
             Err(LockedManifestError::BrokenNotAllowed { .. })
         ));
@@ -3464,10 +3839,13 @@ pub(crate) mod tests {
         foo_locked.unfree = Some(true);
 
         assert!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: None,
-                broken: None,
-                licenses: vec![]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: None,
+                    broken: None,
+                    licenses: vec![],
+                },
+                ..Default::default()
             })
             .is_ok()
         );
@@ -3481,10 +3859,13 @@ pub(crate) mod tests {
         foo_locked.unfree = Some(true);
 
         assert!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: Some(true),
-                broken: None,
-                licenses: vec![]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: Some(true),
+                    broken: None,
+                    licenses: vec![],
+                },
+                ..Default::default()
             })
             .is_ok()
         );
@@ -3498,10 +3879,13 @@ pub(crate) mod tests {
         foo_locked.unfree = Some(true);
 
         assert!(matches!(
-            Lockfile::check_packages_are_allowed(&vec![foo_locked], &Allows {
-                unfree: Some(false),
-                broken: None,
-                licenses: vec![]
+            Lockfile::check_packages_are_allowed(&vec![foo_locked], &ManifestOptions {
+                allow: Allows {
+                    unfree: Some(false),
+                    broken: None,
+                    licenses: vec![],
+                },
+                ..Default::default()
             }),
             Err(LockedManifestError::UnfreeNotAllowed { .. })
         ));
@@ -3542,6 +3926,7 @@ pub(crate) mod tests {
                 bar_locked.clone().into(),
                 baz_locked.clone().into(),
             ],
+            compose: None,
         };
 
         let foo_pkg_path = foo_descriptor
@@ -3611,6 +3996,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest,
             packages: vec![foo_locked.clone().into(), baz_locked.clone().into()],
+            compose: None,
         };
 
         let actual = locked
@@ -3642,6 +4028,7 @@ pub(crate) mod tests {
             version: Version::<1>,
             manifest,
             packages: vec![foo_locked.clone().into(), baz_locked.clone().into()],
+            compose: None,
         };
 
         let actual = locked
@@ -8,14 +8,30 @@ use indoc::{formatdoc, indoc};
 use itertools::Itertools;
 #[cfg(test)]
 use proptest::prelude::*;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::de::Error;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use toml_edit::{self, Array, DocumentMut, Formatted, InlineTable, Item, Key, Table, Value};
+use toml_edit::{
+    self,
+    Array,
+    DocumentMut,
+    Formatted,
+    InlineTable,
+    Item,
+    Key,
+    Table,
+    TableLike,
+    Value,
+};
 use tracing::{debug, trace};
 use url::Url;
 
+use super::audit::Severity;
 use super::environment::path_environment::InitCustomization;
+use super::environment_ref::EnvironmentRef;
 use crate::data::System;
 use crate::providers::services::ServiceError;
 #[cfg(test)]
@@ -112,10 +128,18 @@ impl RawManifest {
             ## -------------------------------------------------------------------
         "#});
 
-        // [sic]: vars not customized using InitCustomization yet
-        vars_table.decor_mut().set_suffix(indoc! {r#"
+        match &customization.vars {
+            Some(vars) if !vars.is_empty() => {
+                for (name, value) in vars {
+                    vars_table.insert(name, toml_edit::value(value));
+                }
+            },
+            _ => {
+                vars_table.decor_mut().set_suffix(indoc! {r#"
 
-            # INTRO_MESSAGE = "It's gettin' Flox in here""#});
+                    # INTRO_MESSAGE = "It's gettin' Flox in here""#});
+            },
+        }
 
         manifest.insert(MANIFEST_VARS_KEY, Item::Table(vars_table));
 
@@ -310,6 +334,82 @@ impl RawManifest {
             None => Err(toml_edit::de::Error::custom("unsupported manifest version")),
         }
     }
+
+    /// The manifest as it applies to `system`, see [Manifest::for_system].
+    ///
+    /// Comments and formatting are preserved,
+    /// except for the entries that are overridden for `system`.
+    pub fn for_system(&self, system: &System) -> Result<RawManifest, toml_edit::de::Error> {
+        let manifest = self.to_typed()?;
+        let mut document = self.0.clone();
+        document.remove("target");
+
+        let Some(target) = manifest.target.get(system) else {
+            return Ok(RawManifest(document));
+        };
+        let effective = manifest.for_system(system);
+        let effective = ManifestTarget {
+            vars: effective.vars,
+            hook: effective.hook,
+            profile: effective.profile,
+        };
+
+        /// Get a top level table, creating a missing table as a regular table.
+        ///
+        /// Indexing would create missing tables as inline tables.
+        fn table<'a>(document: &'a mut DocumentMut, name: &str) -> &'a mut dyn TableLike {
+            document
+                .entry(name)
+                .or_insert(Item::Table(Table::new()))
+                .as_table_like_mut()
+                .expect("tables of a valid manifest are tables or inline tables")
+        }
+
+        /// Set the value of `key`, keeping the comments of an existing entry.
+        ///
+        /// [TableLike::insert] would replace the key and with it its comments.
+        fn set(table: &mut dyn TableLike, key: &str, mut value: Value) {
+            match table.get_mut(key) {
+                Some(Item::Value(existing)) => {
+                    *value.decor_mut() = existing.decor().clone();
+                    *existing = value;
+                },
+                Some(item) => *item = Item::Value(value),
+                None => {
+                    table.insert(key, Item::Value(value));
+                },
+            }
+        }
+
+        for name in target.vars.0.keys() {
+            let value = effective.vars.0[name]
+                .serialize(toml_edit::ser::ValueSerializer::default())
+                .expect("variables serialize to TOML values");
+            set(table(&mut document, MANIFEST_VARS_KEY), name, value);
+        }
+
+        /// Select a script of a [ManifestTarget]
+        type Select = fn(&ManifestTarget) -> &Option<String>;
+
+        let scripts: [(&str, &str, Select); 7] = [
+            (MANIFEST_HOOK_KEY, "on-activate", |t| &t.hook.on_activate),
+            (MANIFEST_HOOK_KEY, "on-deactivate", |t| {
+                &t.hook.on_deactivate
+            }),
+            (MANIFEST_PROFILE_KEY, "common", |t| &t.profile.common),
+            (MANIFEST_PROFILE_KEY, "bash", |t| &t.profile.bash),
+            (MANIFEST_PROFILE_KEY, "zsh", |t| &t.profile.zsh),
+            (MANIFEST_PROFILE_KEY, "fish", |t| &t.profile.fish),
+            (MANIFEST_PROFILE_KEY, "tcsh", |t| &t.profile.tcsh),
+        ];
+        for (table_name, key, select) in scripts {
+            if let (Some(_), Some(script)) = (select(target), select(&effective)) {
+                set(table(&mut document, table_name), key, script.clone().into());
+            }
+        }
+
+        Ok(RawManifest(document))
+    }
 }
 
 impl FromStr for RawManifest {
@@ -356,10 +456,12 @@ impl DerefMut for RawManifest {
 // the user provided manifest but allow unknown fields when deserializing the
 // lockfile,
 // but that doesn't seem worth the effort at the moment.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(deny_unknown_fields)]
 pub struct Manifest {
+    /// The version of the manifest format, currently always `1`
+    #[schemars(schema_with = "version_schema")]
     pub version: Version<1>,
     /// The packages to install in the form of a map from install_id
     /// to package descriptor.
@@ -388,9 +490,34 @@ pub struct Manifest {
     #[serde(default)]
     #[serde(skip_serializing_if = "ManifestBuild::skip_serializing")]
     pub build: ManifestBuild,
+    /// Other environments to compose this environment from
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ManifestInclude::skip_serializing")]
+    #[cfg_attr(test, proptest(value = "ManifestInclude::default()"))]
+    pub include: ManifestInclude,
+    /// Overrides of `vars`, `hook` and `profile` for individual systems
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ManifestTargets::skip_serializing")]
+    pub target: ManifestTargets,
+}
+
+/// The schema of [Manifest::version], which only accepts `1`
+fn version_schema(_: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Integer.into()),
+        const_value: Some(1.into()),
+        ..Default::default()
+    }
+    .into()
 }
 
 impl Manifest {
+    /// A JSON Schema of the manifest, e.g. for use by editors
+    pub fn json_schema() -> serde_json::Value {
+        let schema = schemars::schema_for!(Manifest);
+        serde_json::to_value(schema).expect("schema is valid JSON")
+    }
+
     /// Get the package descriptor with the specified install_id.
     pub fn pkg_descriptor_with_id(&self, id: impl AsRef<str>) -> Option<ManifestPackageDescriptor> {
         self.install.0.get(id.as_ref()).cloned()
@@ -451,6 +578,38 @@ impl Manifest {
     ) -> Result<bool, ManifestError> {
         pkg_belongs_to_non_empty_toplevel_group(pkg.as_ref(), &self.install.0)
     }
+
+    /// The manifest as it applies to `system`,
+    /// with the overrides in `[target.<system>]` merged into
+    /// `[vars]`, `[hook]` and `[profile]`.
+    ///
+    /// Variables of the target replace variables of the same name,
+    /// scripts of the target run after the general scripts.
+    /// The returned manifest has no `[target]` tables.
+    pub fn for_system(&self, system: &System) -> Manifest {
+        let mut manifest = self.clone();
+        let Some(target) = std::mem::take(&mut manifest.target).0.remove(system) else {
+            return manifest;
+        };
+
+        manifest.vars.0.extend(target.vars.0);
+
+        let append = |general: &mut Option<String>, specific: Option<String>| {
+            *general = match (general.take(), specific) {
+                (Some(general), Some(specific)) => Some(format!("{general}\n{specific}")),
+                (general, specific) => general.or(specific),
+            };
+        };
+        append(&mut manifest.hook.on_activate, target.hook.on_activate);
+        append(&mut manifest.hook.on_deactivate, target.hook.on_deactivate);
+        append(&mut manifest.profile.common, target.profile.common);
+        append(&mut manifest.profile.bash, target.profile.bash);
+        append(&mut manifest.profile.zsh, target.profile.zsh);
+        append(&mut manifest.profile.fish, target.profile.fish);
+        append(&mut manifest.profile.tcsh, target.profile.tcsh);
+
+        manifest
+    }
 }
 
 pub(crate) fn pkg_descriptors_in_toplevel_group(
@@ -562,6 +721,7 @@ fn pkg_belongs_to_non_empty_toplevel_group(
     Clone,
     Serialize,
     Deserialize,
+    JsonSchema,
     Default,
     PartialEq,
     derive_more::Deref,
@@ -584,7 +744,7 @@ impl ManifestInstall {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 // todo: this can make the error messages less clear and might call for a custom (de)serialize impl
 #[serde(
@@ -702,7 +862,7 @@ impl From<ManifestPackageDescriptorStorePath> for ManifestPackageDescriptor {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -719,6 +879,15 @@ pub struct ManifestPackageDescriptorCatalog {
         )
     )]
     pub(crate) systems: Option<Vec<System>>,
+    /// Outputs to install, e.g. `["out", "man"]`,
+    /// instead of the outputs the package installs by default
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::option::of(proptest::collection::vec(\"[a-z]{1,5}\", 1..3))"
+        )
+    )]
+    pub(crate) outputs: Option<Vec<String>>,
 }
 
 impl ManifestPackageDescriptorCatalog {
@@ -729,6 +898,8 @@ impl ManifestPackageDescriptorCatalog {
     /// * Descriptors are resolved per system,
     ///   changing the supported systems does not invalidate _existing_ resolutions.
     /// * Priority is not used in resolution, so it is ignored.
+    /// * Locked packages only contain the selected outputs,
+    ///   so changing the outputs requires locking the package again.
     pub(super) fn invalidates_existing_resolution(&self, other: &Self) -> bool {
         // unpack to avoid forgetting to update this method when new fields are added
         let ManifestPackageDescriptorCatalog {
@@ -737,14 +908,18 @@ impl ManifestPackageDescriptorCatalog {
             version,
             systems: _,
             priority: _,
+            outputs,
         } = self;
 
-        pkg_path != &other.pkg_path || pkg_group != &other.pkg_group || version != &other.version
+        pkg_path != &other.pkg_path
+            || pkg_group != &other.pkg_group
+            || version != &other.version
+            || outputs != &other.outputs
     }
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -761,7 +936,7 @@ pub struct ManifestPackageDescriptorFlake {
     pub(crate) systems: Option<Vec<System>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -779,52 +954,198 @@ pub struct ManifestPackageDescriptorStorePath {
     pub(crate) priority: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 pub struct ManifestVariables(
     #[cfg_attr(
         test,
-        proptest(strategy = "proptest_btree_map_alphanum_keys::<String>(10, 3)")
+        proptest(strategy = "proptest_btree_map_alphanum_keys::<ManifestVariable>(10, 3)")
     )]
-    pub(crate) BTreeMap<String, String>,
+    pub(crate) BTreeMap<String, ManifestVariable>,
 );
 
 impl ManifestVariables {
     fn skip_serializing(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// Variables with a value in the manifest
+    pub fn values(&self) -> impl Iterator<Item = (&String, &str)> {
+        self.0
+            .iter()
+            .filter_map(|(name, variable)| Some((name, variable.as_value()?)))
+    }
+
+    /// Variables that are fetched by running a command
+    pub fn secrets(&self) -> impl Iterator<Item = (&String, &SecretReference)> {
+        self.0.iter().filter_map(|(name, variable)| match variable {
+            ManifestVariable::Secret(secret) => Some((name, secret)),
+            _ => None,
+        })
+    }
+}
+
+/// The value of a variable in `[vars]`.
+///
+/// Values of references are resolved when the environment is activated,
+/// see [super::secrets].
+/// They are never written to the lockfile or the built environment.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(untagged)]
+pub enum ManifestVariable {
+    /// A value that may reference other variables
+    /// with `${NAME}` or `${NAME:-default}`
+    Value(String),
+    /// Read the variable from dotenv files
+    EnvFile(EnvFileReference),
+    /// Fetch the variable by running a command
+    Secret(SecretReference),
+}
+
+impl ManifestVariable {
+    /// The value of the variable, unless it is a reference
+    pub fn as_value(&self) -> Option<&str> {
+        match self {
+            ManifestVariable::Value(value) => Some(value),
+            ManifestVariable::EnvFile(_) | ManifestVariable::Secret(_) => None,
+        }
+    }
+}
+
+impl From<String> for ManifestVariable {
+    fn from(value: String) -> Self {
+        ManifestVariable::Value(value)
+    }
 }
 
+/// A variable that is read from dotenv files, e.g.
+/// `{ env-file = [".env", ".env.local"] }`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct EnvFileReference {
+    /// Dotenv files to read the variable from,
+    /// relative to the directory containing the `.flox` directory,
+    /// or the current directory for environments on FloxHub.
+    /// Later files take precedence over earlier ones.
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::collection::vec(any::<String>(), 1..3)")
+    )]
+    pub env_file: Vec<String>,
+}
+
+/// A variable that is fetched by a secret provider, e.g.
+/// `{ command = "op read", ref = "op://dev/db/password" }`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(deny_unknown_fields)]
+pub struct SecretReference {
+    /// A command that prints the secret given `ref` as its last argument
+    pub command: String,
+    /// The reference passed to the command, e.g. `op://dev/db/password`
+    #[serde(rename = "ref")]
+    pub reference: String,
+}
+
+/// Variables of a service
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct ManifestServiceVariables(
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest_btree_map_alphanum_keys::<String>(10, 3)")
+    )]
+    pub(crate) BTreeMap<String, String>,
+);
+
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct ManifestHook {
     /// A script that is run at activation time,
     /// in a flox provided bash shell
-    on_activate: Option<String>,
+    pub(crate) on_activate: Option<String>,
+    /// A script that is run by the watchdog in a flox provided bash shell,
+    /// after the last activation of the environment exited
+    /// and before its services are stopped
+    pub(crate) on_deactivate: Option<String>,
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(deny_unknown_fields)]
 pub struct ManifestProfile {
     /// When defined, this hook is run by _all_ shells upon activation
-    common: Option<String>,
+    pub(crate) common: Option<String>,
     /// When defined, this hook is run upon activation in a bash shell
-    bash: Option<String>,
+    pub(crate) bash: Option<String>,
     /// When defined, this hook is run upon activation in a zsh shell
-    zsh: Option<String>,
+    pub(crate) zsh: Option<String>,
     /// When defined, this hook is run upon activation in a fish shell
-    fish: Option<String>,
+    pub(crate) fish: Option<String>,
     /// When defined, this hook is run upon activation in a tcsh shell
-    tcsh: Option<String>,
+    pub(crate) tcsh: Option<String>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Default,
+    PartialEq,
+    derive_more::Deref,
+    derive_more::DerefMut,
+)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+pub struct ManifestTargets(
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::btree_map(any::<System>(), any::<ManifestTarget>(), 0..3)"
+        )
+    )]
+    pub(crate) BTreeMap<System, ManifestTarget>,
+);
+
+impl ManifestTargets {
+    fn skip_serializing(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether no system has overrides
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Overrides that only apply when the environment is built for one system,
+/// see [Manifest::for_system]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(deny_unknown_fields)]
+pub struct ManifestTarget {
+    /// Variables that are added to or replace the variables in `[vars]`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ManifestVariables::skip_serializing")]
+    pub vars: ManifestVariables,
+    /// Hooks that run after the hooks in `[hook]`
+    #[serde(default)]
+    pub hook: ManifestHook,
+    /// Profile scripts that run after the scripts in `[profile]`
+    #[serde(default)]
+    pub profile: ManifestProfile,
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -840,14 +1161,38 @@ pub struct ManifestOptions {
     /// Options that control what types of packages are allowed.
     #[serde(default)]
     pub allow: Allows,
+    /// Options that control what types of packages are denied.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Denies::skip_serializing")]
+    pub deny: Denies,
+    /// Licenses that are allowed for individual packages,
+    /// regardless of `allow.licenses` and `deny.licenses`.
+    ///
+    /// Keys are install IDs.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest_btree_map_alphanum_keys::<Vec<String>>(10, 3)")
+    )]
+    pub license_exceptions: BTreeMap<String, Vec<String>>,
     /// Options that control how semver versions are resolved.
     #[serde(default)]
     pub semver: SemverOptions,
     pub cuda_detection: Option<bool>,
+    /// Number of seconds to wait after the last activation exited,
+    /// before services are stopped and `hook.on-deactivate` is run.
+    ///
+    /// Activations started in the meantime keep the services running.
+    pub services_linger: Option<u32>,
+    /// Options that control how `flox audit` reports vulnerabilities.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "AuditOptions::skip_serializing")]
+    pub audit: AuditOptions,
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(deny_unknown_fields)]
 pub struct Allows {
@@ -864,8 +1209,50 @@ pub struct Allows {
     pub licenses: Vec<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(deny_unknown_fields)]
+pub struct Denies {
+    /// A list of license descriptors that are denied
+    #[serde(default)]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::collection::vec(any::<String>(), 0..3)")
+    )]
+    pub licenses: Vec<String>,
+}
+
+impl Denies {
+    fn skip_serializing(&self) -> bool {
+        self.licenses.is_empty()
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(proptest_derive::Arbitrary))]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct AuditOptions {
+    /// Advisory IDs or aliases that are not considered vulnerabilities
+    #[serde(default)]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::collection::vec(any::<String>(), 0..3)")
+    )]
+    pub ignore: Vec<String>,
+    /// The minimum severity of a vulnerability for `flox audit` to fail
+    pub fail_on: Option<Severity>,
+}
+
+impl AuditOptions {
+    fn skip_serializing(&self) -> bool {
+        self == &AuditOptions::default()
+    }
+}
+
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -881,6 +1268,7 @@ pub struct SemverOptions {
     Clone,
     Serialize,
     Deserialize,
+    JsonSchema,
     Default,
     PartialEq,
     derive_more::Deref,
@@ -904,7 +1292,7 @@ impl ManifestServices {
 }
 
 /// The definition of a service in a manifest
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -912,7 +1300,7 @@ pub struct ManifestServiceDescriptor {
     /// The command to run to start the service
     pub command: String,
     /// Service-specific environment variables
-    pub vars: Option<ManifestVariables>,
+    pub vars: Option<ManifestServiceVariables>,
     /// Whether the service spawns a background process (daemon)
     // TODO: This option _requires_ the shutdown command, so we'll need to add
     //       that explanation to the manifest.toml docs and service mgmt guide
@@ -970,7 +1358,7 @@ impl ManifestServices {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -985,6 +1373,7 @@ pub struct ManifestServiceShutdown {
     Clone,
     Serialize,
     Deserialize,
+    JsonSchema,
     Default,
     PartialEq,
     derive_more::Deref,
@@ -1006,7 +1395,7 @@ impl ManifestBuild {
 }
 
 /// The definition of a package built from within the environment
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -1024,7 +1413,9 @@ pub struct ManifestBuildDescriptor {
 }
 
 /// The definition of a package built from within the environment
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, derive_more::Display)]
+#[derive(
+    Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash, derive_more::Display,
+)]
 #[cfg_attr(test, derive(proptest_derive::Arbitrary))]
 #[serde(rename_all = "kebab-case")]
 pub enum ManifestBuildSandbox {
@@ -1032,6 +1423,63 @@ pub enum ManifestBuildSandbox {
     Pure,
 }
 
+/// Environments whose contents are merged into the including manifest
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestInclude {
+    /// The included environments, in the order their hooks and profile scripts run
+    #[serde(default)]
+    pub environments: Vec<IncludeDescriptor>,
+}
+
+impl ManifestInclude {
+    fn skip_serializing(&self) -> bool {
+        self.environments.is_empty()
+    }
+}
+
+/// A reference to an included environment
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(
+    untagged,
+    expecting = "Expected either a local include with a 'dir' or a remote include with a 'remote'."
+)]
+pub enum IncludeDescriptor {
+    Local(IncludeDescriptorLocal),
+    Remote(IncludeDescriptorRemote),
+}
+
+/// An environment in a local directory
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct IncludeDescriptorLocal {
+    /// The directory containing the environment's `.flox` directory,
+    /// relative paths are resolved relative to the including environment
+    pub dir: PathBuf,
+}
+
+/// A managed environment on FloxHub or a configured git remote
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct IncludeDescriptorRemote {
+    /// The environment in the form `<owner>/<name>`
+    #[schemars(with = "String")]
+    pub remote: EnvironmentRef,
+    /// The generation to include,
+    /// if omitted the current generation at the time of locking is used
+    pub generation: Option<u32>,
+}
+
+impl std::fmt::Display for IncludeDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncludeDescriptor::Local(local) => write!(f, "{}", local.dir.display()),
+            IncludeDescriptor::Remote(remote) => write!(f, "{}", remote.remote),
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ManifestError {
     #[error("couldn't parse descriptor '{}': {}", desc, msg)]
@@ -1083,6 +1531,20 @@ pub enum TomlEditError {
 
     #[error("'{0}' is not a supported attribute in manifest version 1")]
     UnsupportedAttributeV1(String),
+
+    #[error("'{0}' is not a valid key, expected a dotted key such as 'vars.FOO'")]
+    InvalidKeyPath(String),
+    #[error("'{0}' is not a known key in the manifest")]
+    UnknownKey(String),
+    #[error("'{path}' must be a table, but found {found} instead")]
+    NotATable { path: String, found: String },
+    #[error("'{0}' is not set in the manifest")]
+    KeyNotFound(String),
+    #[error("couldn't set '{path}': {err}")]
+    InvalidValue {
+        path: String,
+        err: toml_edit::de::Error,
+    },
 }
 
 /// Records the result of trying to install a collection of packages to the
@@ -1199,7 +1661,7 @@ pub fn catalog_packages_to_install(packages: &[PackageToInstall]) -> Vec<Catalog
 /// especially when the package is nested. This struct is the common
 /// denominator for packages with specified IDs and packages with
 /// default IDs.
-#[derive(Debug, Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct CatalogPackage {
     pub id: String,
     pub pkg_path: String,
@@ -1212,6 +1674,12 @@ pub struct CatalogPackage {
     ///
     /// [Environment::install]: crate::models::environment::Environment::install
     pub systems: Option<Vec<System>>,
+    /// Group to install the package to, `None` for the 'toplevel' group
+    pub pkg_group: Option<String>,
+    /// Priority of the package, `None` for the default priority
+    pub priority: Option<u64>,
+    /// Outputs to install, `None` for the outputs the package installs by default
+    pub outputs: Option<Vec<String>>,
 }
 
 impl FromStr for CatalogPackage {
@@ -1295,6 +1763,7 @@ impl FromStr for CatalogPackage {
             pkg_path: attr_path,
             version,
             systems: None,
+            ..Default::default()
         })
     }
 }
@@ -1429,6 +1898,15 @@ impl From<&CatalogPackage> for InlineTable {
         if let Some(ref version) = val.version {
             table.insert("version", Value::String(Formatted::new(version.clone())));
         }
+        if let Some(ref pkg_group) = val.pkg_group {
+            table.insert(
+                "pkg-group",
+                Value::String(Formatted::new(pkg_group.clone())),
+            );
+        }
+        if let Some(priority) = val.priority {
+            table.insert("priority", Value::Integer(Formatted::new(priority as i64)));
+        }
         if let Some(ref systems) = val.systems {
             table.insert(
                 "systems",
@@ -1440,6 +1918,17 @@ impl From<&CatalogPackage> for InlineTable {
                 ),
             );
         }
+        if let Some(ref outputs) = val.outputs {
+            table.insert(
+                "outputs",
+                Value::Array(
+                    outputs
+                        .iter()
+                        .map(|o| Value::String(Formatted::new(o.to_string())))
+                        .collect(),
+                ),
+            );
+        }
         table
     }
 }
@@ -1627,6 +2116,151 @@ pub fn add_system(toml: &str, system: &str) -> Result<DocumentMut, TomlEditError
     Ok(doc)
 }
 
+/// Parse a dotted key such as `services.db.command` or `vars."MY VAR"`
+fn parse_key_path(path: &str) -> Result<Vec<Key>, TomlEditError> {
+    match Key::parse(path) {
+        // Parsed keys keep the whitespace around the dots of `path`,
+        // recreate them so that new entries are formatted like the rest of the manifest
+        Ok(keys) if !keys.is_empty() => Ok(keys.iter().map(|key| Key::new(key.get())).collect()),
+        _ => Err(TomlEditError::InvalidKeyPath(path.to_string())),
+    }
+}
+
+/// Set the value at the dotted key `path` of a manifest,
+/// creating any missing tables along the way.
+///
+/// `value` is parsed as a TOML value, e.g. `true`, `["x86_64-linux"]`
+/// or `{ command = "postgres" }`.
+/// Values that aren't valid TOML, e.g. `hello`, are set as strings.
+/// Unquoted values that are invalid for the key, e.g. `8080` for `vars.PORT`,
+/// are set as strings as well.
+///
+/// The key is checked against the manifest schema
+/// and the edited manifest has to deserialize into a [Manifest].
+pub fn set_value(toml: &str, path: &str, value: &str) -> Result<DocumentMut, TomlEditError> {
+    let keys = parse_key_path(path)?;
+    let key_names = keys.iter().map(|key| key.get()).collect::<Vec<_>>();
+    if !super::manifest_lint::is_known_key(&key_names) {
+        return Err(TomlEditError::UnknownKey(path.to_string()));
+    }
+
+    let doc = toml
+        .parse::<RawManifest>()
+        .map_err(TomlEditError::ParseManifest)?
+        .0;
+
+    let parsed = value.parse::<Value>().ok();
+    let retry_as_string = parsed.as_ref().is_some_and(|parsed| !parsed.is_str());
+    let parsed = parsed.unwrap_or_else(|| Value::from(value));
+    match set_parsed_value(doc.clone(), &keys, path, parsed) {
+        Err(err @ TomlEditError::InvalidValue { .. }) if retry_as_string => {
+            set_parsed_value(doc, &keys, path, Value::from(value)).map_err(|_| err)
+        },
+        result => result,
+    }
+}
+
+/// Set `value` at `keys` in `doc` and validate the result, see [set_value]
+fn set_parsed_value(
+    mut doc: DocumentMut,
+    keys: &[Key],
+    path: &str,
+    mut value: Value,
+) -> Result<DocumentMut, TomlEditError> {
+    let key_names = keys.iter().map(|key| key.get()).collect::<Vec<_>>();
+    value.decor_mut().clear();
+
+    let (last, parents) = keys.split_last().expect("key paths are never empty");
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    let mut inline = false;
+    for (depth, key) in parents.iter().enumerate() {
+        let item = table.entry_format(key).or_insert_with(|| {
+            if inline {
+                Item::Value(Value::InlineTable(InlineTable::new()))
+            } else {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            }
+        });
+        inline = item.is_inline_table();
+        let found = item.type_name().to_string();
+        table = item
+            .as_table_like_mut()
+            .ok_or_else(|| TomlEditError::NotATable {
+                path: key_names[..=depth].join("."),
+                found,
+            })?;
+    }
+
+    let item = table.entry_format(last).or_insert(Item::None);
+    // keep comments attached to the value that is replaced
+    if let Some(existing) = item.as_value() {
+        *value.decor_mut() = existing.decor().clone();
+    }
+    *item = Item::Value(value);
+
+    RawManifest(doc.clone())
+        .to_typed()
+        .map_err(|err| TomlEditError::InvalidValue {
+            path: path.to_string(),
+            err,
+        })?;
+
+    Ok(doc)
+}
+
+/// Remove the dotted key `path` from a manifest
+pub fn unset_value(toml: &str, path: &str) -> Result<DocumentMut, TomlEditError> {
+    let keys = parse_key_path(path)?;
+    let mut doc = toml
+        .parse::<RawManifest>()
+        .map_err(TomlEditError::ParseManifest)?
+        .0;
+
+    let (last, parents) = keys.split_last().expect("key paths are never empty");
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    for key in parents {
+        table = table
+            .get_mut(key.get())
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(|| TomlEditError::KeyNotFound(path.to_string()))?;
+    }
+    if table.remove(last.get()).is_none() {
+        return Err(TomlEditError::KeyNotFound(path.to_string()));
+    }
+
+    RawManifest(doc.clone())
+        .to_typed()
+        .map_err(|err| TomlEditError::InvalidValue {
+            path: path.to_string(),
+            err,
+        })?;
+
+    Ok(doc)
+}
+
+/// Get the value at the dotted key `path` of a manifest
+pub fn get_value(toml: &str, path: &str) -> Result<toml::Value, TomlEditError> {
+    let keys = parse_key_path(path)?;
+    let doc = toml
+        .parse::<RawManifest>()
+        .map_err(TomlEditError::ParseManifest)?
+        .0;
+    let table: toml::Table =
+        toml_edit::de::from_document(doc).map_err(TomlEditError::ParseManifest)?;
+
+    let mut value = toml::Value::Table(table);
+    for key in keys {
+        value = match value {
+            toml::Value::Table(mut table) => table.remove(key.get()),
+            _ => None,
+        }
+        .ok_or_else(|| TomlEditError::KeyNotFound(path.to_string()))?;
+    }
+    Ok(value)
+}
+
 #[cfg(test)]
 pub(super) mod test {
     use pretty_assertions::assert_eq;
@@ -1704,6 +2338,7 @@ pub(super) mod test {
     fn create_documented_manifest_not_customized() {
         let systems = &*DEFAULT_SYSTEMS_STR.iter().collect::<Vec<_>>();
         let customization = InitCustomization {
+            vars: None,
             hook_on_activate: None,
             profile_common: None,
             profile_bash: None,
@@ -1799,10 +2434,37 @@ pub(super) mod test {
         assert_eq!(manifest.to_string(), expected_string.to_string());
     }
 
+    #[test]
+    fn create_documented_manifest_with_vars() {
+        let systems = &*DEFAULT_SYSTEMS_STR.iter().collect::<Vec<_>>();
+        let customization = InitCustomization {
+            vars: Some(BTreeMap::from([
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://localhost".to_string(),
+                ),
+                ("PORT".to_string(), "8080".to_string()),
+            ])),
+            ..Default::default()
+        };
+
+        let manifest = RawManifest::new_documented(systems, &customization);
+        assert!(manifest.to_string().contains(indoc! {r#"
+            [vars]
+            DATABASE_URL = "postgres://localhost"
+            PORT = "8080"
+        "#}));
+        assert!(!manifest.to_string().contains("# INTRO_MESSAGE ="));
+
+        let typed = manifest.to_typed().unwrap();
+        assert_eq!(typed.vars.0["PORT"].as_value(), Some("8080"));
+    }
+
     #[test]
     fn create_documented_manifest_with_packages() {
         let systems = &*DEFAULT_SYSTEMS_STR.iter().collect::<Vec<_>>();
         let customization = InitCustomization {
+            vars: None,
             hook_on_activate: None,
             profile_common: None,
             profile_bash: None,
@@ -1814,6 +2476,7 @@ pub(super) mod test {
                 pkg_path: "python3".to_string(),
                 version: Some("3.11.6".to_string()),
                 systems: None,
+                ..Default::default()
             }]),
         };
 
@@ -1906,6 +2569,7 @@ pub(super) mod test {
     fn create_documented_manifest_hook() {
         let systems = [&"x86_64-linux".to_string()];
         let customization = InitCustomization {
+            vars: None,
             hook_on_activate: Some(
                 indoc! {r#"
                     # Print something
@@ -2010,6 +2674,7 @@ pub(super) mod test {
     fn create_documented_profile_script() {
         let systems = [&"x86_64-linux".to_string()];
         let customization = InitCustomization {
+            vars: None,
             hook_on_activate: None,
             profile_common: Some(
                 indoc! { r#"
@@ -2231,6 +2896,34 @@ pub(super) mod test {
         assert_eq!(inserted_path, r#"foo."bar.baz".qux"#);
     }
 
+    #[test]
+    fn inserts_package_with_descriptor_options() {
+        let package = CatalogPackage {
+            pkg_group: Some("tools".to_string()),
+            priority: Some(3),
+            systems: Some(vec!["x86_64-linux".to_string()]),
+            outputs: Some(vec!["out".to_string(), "man".to_string()]),
+            ..CatalogPackage::from_str("hello").unwrap()
+        };
+        let insertion = insert_packages("version = 1", &[PackageToInstall::Catalog(package)])
+            .expect("couldn't add package");
+
+        let manifest = RawManifest(insertion.new_toml.unwrap()).to_typed().unwrap();
+        assert_eq!(
+            manifest.install.get("hello"),
+            Some(&ManifestPackageDescriptor::Catalog(
+                ManifestPackageDescriptorCatalog {
+                    pkg_path: "hello".to_string(),
+                    pkg_group: Some("tools".to_string()),
+                    priority: Some(3),
+                    version: None,
+                    systems: Some(vec!["x86_64-linux".to_string()]),
+                    outputs: Some(vec!["out".to_string(), "man".to_string()]),
+                }
+            ))
+        );
+    }
+
     #[test]
     fn parses_string_descriptor() {
         let parsed: CatalogPackage = "hello".parse().unwrap();
@@ -2239,6 +2932,7 @@ pub(super) mod test {
             pkg_path: "hello".to_string(),
             version: None,
             systems: None,
+            ..Default::default()
         });
         let parsed: CatalogPackage = "foo.bar@=1.2.3".parse().unwrap();
         assert_eq!(parsed, CatalogPackage {
@@ -2246,6 +2940,7 @@ pub(super) mod test {
             pkg_path: "foo.bar".to_string(),
             version: Some("=1.2.3".to_string()),
             systems: None,
+            ..Default::default()
         });
         let parsed: CatalogPackage = "foo.bar@23.11".parse().unwrap();
         assert_eq!(parsed, CatalogPackage {
@@ -2253,6 +2948,7 @@ pub(super) mod test {
             pkg_path: "foo.bar".to_string(),
             version: Some("23.11".to_string()),
             systems: None,
+            ..Default::default()
         });
         let parsed: CatalogPackage = "rubyPackages.\"http_parser.rb\"".parse().unwrap();
         assert_eq!(parsed, CatalogPackage {
@@ -2260,6 +2956,7 @@ pub(super) mod test {
             pkg_path: "rubyPackages.\"http_parser.rb\"".to_string(),
             version: None,
             systems: None,
+            ..Default::default()
         });
 
         // Attributes starting with `@` are allowed, the @ is not delimting the version if following a '.'
@@ -2269,6 +2966,7 @@ pub(super) mod test {
             pkg_path: "nodePackages.@angular".to_string(),
             version: Some("1.2.3".to_string()),
             systems: None,
+            ..Default::default()
         });
 
         // Attributes starting with `@` are allowed, the @ is not delimting the version
@@ -2279,6 +2977,7 @@ pub(super) mod test {
             pkg_path: "@1.2.3".to_string(),
             version: None,
             systems: None,
+            ..Default::default()
         });
 
         // Attributes starting with `@` are allowed, the @ is not delimting the version
@@ -2290,6 +2989,7 @@ pub(super) mod test {
             pkg_path: "@pkg".to_string(),
             version: Some("version".to_string()),
             systems: None,
+            ..Default::default()
         });
 
         CatalogPackage::from_str("foo.\"bar.baz.qux@1.2.3")
@@ -2297,6 +2997,116 @@ pub(super) mod test {
         CatalogPackage::from_str("foo@").expect_err("missing version should cause failure");
     }
 
+    #[test]
+    fn for_system_merges_target() {
+        let manifest: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+
+            [vars]
+            FOO = "general"
+            BAR = "general"
+
+            [hook]
+            on-activate = "echo general"
+
+            [profile]
+            bash = "echo general"
+
+            [target.x86_64-linux.vars]
+            FOO = "linux"
+            BAZ = "linux"
+
+            [target.x86_64-linux.hook]
+            on-activate = "echo linux"
+
+            [target.x86_64-linux.profile]
+            common = "echo linux"
+            bash = "echo linux"
+
+            [target.aarch64-darwin.vars]
+            FOO = "darwin"
+        "#})
+        .unwrap();
+
+        let linux = manifest.for_system(&"x86_64-linux".to_string());
+        assert!(linux.target.is_empty());
+        assert_eq!(
+            linux.vars.0,
+            BTreeMap::from_iter([
+                ("BAR".to_string(), "general".to_string().into()),
+                ("BAZ".to_string(), "linux".to_string().into()),
+                ("FOO".to_string(), "linux".to_string().into()),
+            ])
+        );
+        assert_eq!(
+            linux.hook.on_activate.as_deref(),
+            Some("echo general\necho linux")
+        );
+        assert_eq!(linux.profile.common.as_deref(), Some("echo linux"));
+        assert_eq!(
+            linux.profile.bash.as_deref(),
+            Some("echo general\necho linux")
+        );
+    }
+
+    #[test]
+    fn for_system_without_target_drops_targets() {
+        let manifest: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+
+            [vars]
+            FOO = "general"
+
+            [target.aarch64-darwin.vars]
+            FOO = "darwin"
+        "#})
+        .unwrap();
+
+        let linux = manifest.for_system(&"x86_64-linux".to_string());
+        assert_eq!(linux, Manifest {
+            target: ManifestTargets::default(),
+            ..manifest
+        });
+    }
+
+    #[test]
+    fn raw_for_system_preserves_comments() {
+        let raw: RawManifest = indoc! {r#"
+            version = 1
+
+            [vars]
+            # greeting
+            FOO = "general"
+
+            [hook]
+            on-activate = "echo general"
+
+            [target.x86_64-linux.vars]
+            FOO = "linux"
+
+            [target.x86_64-linux.profile]
+            bash = "echo linux"
+        "#}
+        .parse()
+        .unwrap();
+
+        let effective = raw.for_system(&"x86_64-linux".to_string()).unwrap();
+
+        assert_eq!(effective.to_string(), indoc! {r#"
+            version = 1
+
+            [vars]
+            # greeting
+            FOO = "linux"
+
+            [hook]
+            on-activate = "echo general"
+
+            [profile]
+            bash = "echo linux"
+        "#});
+    }
+
     proptest! {
         #[test]
         fn manifest_round_trip(manifest in any::<Manifest>()) {
@@ -2562,4 +3372,119 @@ pub(super) mod test {
         assert_eq!(filtered.len(), 1, "{:?}", filtered);
         assert!(filtered.contains_key("postgres"));
     }
+
+    #[test]
+    fn set_value_creates_tables_and_preserves_formatting() {
+        let manifest = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello" # greets
+
+            [vars]
+            FOO = "foo" # the foo
+        "#};
+
+        let doc = set_value(manifest, "vars.FOO", "bar").unwrap();
+        let doc = set_value(&doc.to_string(), "install.hello.priority", "3").unwrap();
+        let doc = set_value(&doc.to_string(), "services.db.command", r#""postgres""#).unwrap();
+
+        assert_eq!(doc.to_string(), indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello" # greets
+            hello.priority = 3
+
+            [vars]
+            FOO = "bar" # the foo
+
+            [services.db]
+            command = "postgres"
+        "#});
+    }
+
+    #[test]
+    fn set_value_rejects_unknown_keys_and_invalid_values() {
+        let manifest = "version = 1";
+
+        assert_eq!(
+            set_value(manifest, "services.db.comand", "postgres").unwrap_err(),
+            TomlEditError::UnknownKey("services.db.comand".to_string())
+        );
+        assert!(matches!(
+            set_value(manifest, "services.db.is-daemon", "yes"),
+            Err(TomlEditError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            set_value(manifest, "options.systems", "1"),
+            Err(TomlEditError::InvalidValue { .. })
+        ));
+        // unquoted values that are invalid for the key are set as strings
+        let doc = set_value(manifest, "vars.PORT", "8080").unwrap();
+        let typed = RawManifest(doc).to_typed().unwrap();
+        assert_eq!(typed.vars.0["PORT"].as_value(), Some("8080"));
+        assert!(matches!(
+            set_value(manifest, "version.minor", "1"),
+            Err(TomlEditError::NotATable { .. })
+        ));
+        assert_eq!(
+            set_value(manifest, "vars.", "1").unwrap_err(),
+            TomlEditError::InvalidKeyPath("vars.".to_string())
+        );
+    }
+
+    #[test]
+    fn unset_value_removes_key() {
+        let manifest = indoc! {r#"
+            version = 1
+
+            [vars]
+            FOO = "foo"
+            BAR = "bar"
+        "#};
+
+        let doc = unset_value(manifest, "vars.FOO").unwrap();
+        assert_eq!(doc.to_string(), indoc! {r#"
+            version = 1
+
+            [vars]
+            BAR = "bar"
+        "#});
+
+        assert_eq!(
+            unset_value(manifest, "vars.BAZ").unwrap_err(),
+            TomlEditError::KeyNotFound("vars.BAZ".to_string())
+        );
+        assert!(matches!(
+            unset_value(manifest, "version"),
+            Err(TomlEditError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn get_value_returns_nested_values() {
+        let manifest = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+        "#};
+
+        assert_eq!(
+            get_value(manifest, "install.hello.pkg-path").unwrap(),
+            toml::Value::String("hello".to_string())
+        );
+        assert_eq!(
+            get_value(manifest, "install.hello").unwrap(),
+            toml::Value::Table(toml::Table::from_iter([(
+                "pkg-path".to_string(),
+                toml::Value::String("hello".to_string())
+            )]))
+        );
+        assert_eq!(
+            get_value(manifest, "install.hello.pkg-path.nested").unwrap_err(),
+            TomlEditError::KeyNotFound("install.hello.pkg-path.nested".to_string())
+        );
+    }
 }
@@ -0,0 +1,580 @@
+//! Linting of manifests
+//!
+//! Unlike [RawManifest::to_typed], which stops at the first error,
+//! [lint_manifest] reports as many problems as possible at once.
+//! Unknown keys are found by comparing the document with [Manifest::json_schema],
+//! and are removed before the remaining checks run on the typed manifest.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use super::lockfile::DEFAULT_SYSTEMS_STR;
+use super::manifest::{Manifest, ManifestPackageDescriptor, ManifestVariable, RawManifest};
+use crate::data::System;
+
+/// How severe a [LintProblem] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, derive_more::Display)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    #[display(fmt = "warning")]
+    Warning,
+    #[display(fmt = "error")]
+    Error,
+}
+
+/// A problem found in a manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintProblem {
+    pub severity: LintSeverity,
+    /// Dotted path of the offending entry, e.g. `services.db.is-daemon`,
+    /// empty for problems with the manifest as a whole
+    pub path: String,
+    pub message: String,
+}
+
+impl LintProblem {
+    fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: LintSeverity::Error,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: LintSeverity::Warning,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Report all problems found in the manifest `contents`
+pub fn lint_manifest(contents: &str) -> Vec<LintProblem> {
+    let mut document = match contents.parse::<DocumentMut>() {
+        Ok(document) => document,
+        Err(err) => return vec![LintProblem::error("", err.message())],
+    };
+
+    let mut problems = Vec::new();
+
+    let schema = Manifest::json_schema();
+    let definitions = schema
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let root = object_schemas(&schema, &definitions);
+    remove_unknown_keys(
+        document.as_table_mut(),
+        &root,
+        &definitions,
+        "",
+        &mut problems,
+    );
+    // Report unknown keys by path rather than in document order
+    problems.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let manifest = match document
+        .to_string()
+        .parse::<RawManifest>()
+        .and_then(|raw| raw.to_typed())
+    {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            problems.push(LintProblem::error("", err.message()));
+            return problems;
+        },
+    };
+
+    check_services(&manifest, &mut problems);
+    check_builds(&manifest, &mut problems);
+    check_systems(&manifest, &mut problems);
+    check_vars(&manifest, &mut problems);
+    check_packages(&manifest, &mut problems);
+
+    problems
+}
+
+/// Report and remove keys of `table` that are not allowed by any of `schemas`
+fn remove_unknown_keys(
+    table: &mut dyn TableLike,
+    schemas: &[&Map<String, Value>],
+    definitions: &Map<String, Value>,
+    path: &str,
+    problems: &mut Vec<LintProblem>,
+) {
+    if schemas.is_empty() {
+        return;
+    }
+
+    let keys = table
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .collect::<Vec<_>>();
+    for key in keys {
+        let key_path = join_path(path, &key);
+
+        let Some(value_schemas) = property_schemas(schemas, &key) else {
+            problems.push(LintProblem::error(key_path, format!("unknown key '{key}'")));
+            table.remove(&key);
+            continue;
+        };
+
+        let Some(item) = table.get_mut(&key) else {
+            continue;
+        };
+        let objects = value_schemas
+            .iter()
+            .flat_map(|schema| object_schemas(schema, definitions))
+            .collect::<Vec<_>>();
+        let items = value_schemas
+            .iter()
+            .flat_map(|schema| leaf_schemas(schema, definitions))
+            .filter_map(|schema| schema.get("items"))
+            .flat_map(|schema| object_schemas(schema, definitions))
+            .collect::<Vec<_>>();
+
+        match item {
+            Item::Table(table) => {
+                remove_unknown_keys(table, &objects, definitions, &key_path, problems)
+            },
+            Item::Value(toml_edit::Value::InlineTable(table)) => {
+                remove_unknown_keys(table, &objects, definitions, &key_path, problems)
+            },
+            Item::Value(toml_edit::Value::Array(array)) => {
+                for (index, value) in array.iter_mut().enumerate() {
+                    if let toml_edit::Value::InlineTable(table) = value {
+                        let element_path = format!("{key_path}[{index}]");
+                        remove_unknown_keys(table, &items, definitions, &element_path, problems);
+                    }
+                }
+            },
+            Item::ArrayOfTables(tables) => {
+                for (index, table) in tables.iter_mut().enumerate() {
+                    let element_path = format!("{key_path}[{index}]");
+                    remove_unknown_keys(table, &items, definitions, &element_path, problems);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Whether the manifest schema allows the nested key `path`,
+/// e.g. `["services", "db", "command"]`
+///
+/// Keys below values that aren't tables, such as the keys of `vars`,
+/// are not checked.
+pub fn is_known_key(path: &[&str]) -> bool {
+    let schema = Manifest::json_schema();
+    let definitions = schema
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut schemas = object_schemas(&schema, &definitions);
+    for key in path {
+        if schemas.is_empty() {
+            return true;
+        }
+        let Some(value_schemas) = property_schemas(&schemas, key) else {
+            return false;
+        };
+        schemas = value_schemas
+            .into_iter()
+            .flat_map(|schema| object_schemas(schema, &definitions))
+            .collect();
+    }
+    true
+}
+
+/// The schemas of the value at `key` in a table described by `schemas`,
+/// or `None` if none of `schemas` allows `key`
+fn property_schemas<'a>(schemas: &[&'a Map<String, Value>], key: &str) -> Option<Vec<&'a Value>> {
+    let mut known = false;
+    let mut value_schemas = Vec::new();
+    for schema in schemas {
+        if let Some(property) = schema.get("properties").and_then(|p| p.get(key)) {
+            known = true;
+            value_schemas.push(property);
+            continue;
+        }
+        match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => {},
+            Some(additional @ Value::Object(_)) => {
+                known = true;
+                value_schemas.push(additional);
+            },
+            _ => known = true,
+        }
+    }
+    known.then_some(value_schemas)
+}
+
+/// The schemas that `schema` is composed of,
+/// following references and `allOf`, `anyOf` and `oneOf`
+fn leaf_schemas<'a>(
+    schema: &'a Value,
+    definitions: &'a Map<String, Value>,
+) -> Vec<&'a Map<String, Value>> {
+    let Some(object) = schema.as_object() else {
+        return vec![];
+    };
+
+    if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        return definitions
+            .get(name)
+            .map(|definition| leaf_schemas(definition, definitions))
+            .unwrap_or_default();
+    }
+
+    let mut leaves = ["allOf", "anyOf", "oneOf"]
+        .into_iter()
+        .filter_map(|combinator| object.get(combinator).and_then(Value::as_array))
+        .flatten()
+        .flat_map(|variant| leaf_schemas(variant, definitions))
+        .collect::<Vec<_>>();
+    if leaves.is_empty() {
+        leaves.push(object);
+    }
+    leaves
+}
+
+/// The schemas of tables that `schema` is composed of
+fn object_schemas<'a>(
+    schema: &'a Value,
+    definitions: &'a Map<String, Value>,
+) -> Vec<&'a Map<String, Value>> {
+    leaf_schemas(schema, definitions)
+        .into_iter()
+        .filter(|schema| {
+            schema.contains_key("properties") || schema.contains_key("additionalProperties")
+        })
+        .collect()
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    let is_bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    let key = if is_bare {
+        key.to_string()
+    } else {
+        format!("\"{key}\"")
+    };
+    if path.is_empty() {
+        key
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// Services that spawn a daemon need a shutdown command
+fn check_services(manifest: &Manifest, problems: &mut Vec<LintProblem>) {
+    for (name, service) in manifest.services.iter() {
+        if service.is_daemon == Some(true) && service.shutdown.is_none() {
+            problems.push(LintProblem::error(
+                format!("{}.is-daemon", join_path("services", name)),
+                "services that spawn a daemon must set 'shutdown.command'",
+            ));
+        }
+    }
+}
+
+/// Runtime packages of builds must be installed in the 'toplevel' group
+fn check_builds(manifest: &Manifest, problems: &mut Vec<LintProblem>) {
+    let toplevel = manifest
+        .pkg_descriptors_in_toplevel_group()
+        .into_iter()
+        .map(|(install_id, _)| install_id)
+        .collect::<Vec<_>>();
+
+    for (name, build) in manifest.build.iter() {
+        for package in build.runtime_packages.iter().flatten() {
+            let message = if !manifest.install.contains_key(package) {
+                format!("runtime package '{package}' is not installed")
+            } else if !toplevel.contains(package) {
+                format!("runtime package '{package}' is not in the 'toplevel' group")
+            } else {
+                continue;
+            };
+            problems.push(LintProblem::error(
+                format!("{}.runtime-packages", join_path("build", name)),
+                message,
+            ));
+        }
+    }
+}
+
+/// Systems of packages, services, builds and targets must be enabled
+fn check_systems(manifest: &Manifest, problems: &mut Vec<LintProblem>) {
+    let enabled = manifest
+        .options
+        .systems
+        .as_deref()
+        .unwrap_or(&*DEFAULT_SYSTEMS_STR);
+
+    let mut check = |path: String, systems: &[System]| {
+        for system in systems {
+            if !enabled.contains(system) {
+                problems.push(LintProblem::error(
+                    path.clone(),
+                    format!(
+                        "system '{system}' is not enabled (enabled systems: {})",
+                        enabled.join(", ")
+                    ),
+                ));
+            }
+        }
+    };
+
+    for (install_id, descriptor) in manifest.install.iter() {
+        let systems = match descriptor {
+            ManifestPackageDescriptor::Catalog(descriptor) => &descriptor.systems,
+            ManifestPackageDescriptor::FlakeRef(descriptor) => &descriptor.systems,
+            ManifestPackageDescriptor::StorePath(descriptor) => &descriptor.systems,
+        };
+        check(
+            format!("{}.systems", join_path("install", install_id)),
+            systems.as_deref().unwrap_or_default(),
+        );
+    }
+    for (name, service) in manifest.services.iter() {
+        check(
+            format!("{}.systems", join_path("services", name)),
+            service.systems.as_deref().unwrap_or_default(),
+        );
+    }
+    for (name, build) in manifest.build.iter() {
+        check(
+            format!("{}.systems", join_path("build", name)),
+            build.systems.as_deref().unwrap_or_default(),
+        );
+    }
+    for system in manifest.target.keys() {
+        check(join_path("target", system), std::slice::from_ref(system));
+    }
+}
+
+/// References in `[vars]` must list an env file or a command
+fn check_vars(manifest: &Manifest, problems: &mut Vec<LintProblem>) {
+    let targets = manifest.target.iter().map(|(system, target)| {
+        (
+            format!("{}.vars", join_path("target", system)),
+            &target.vars,
+        )
+    });
+    let all_vars = [("vars".to_string(), &manifest.vars)]
+        .into_iter()
+        .chain(targets);
+    for (vars_path, vars) in all_vars {
+        for (name, variable) in vars.0.iter() {
+            let path = join_path(&vars_path, name);
+            match variable {
+                ManifestVariable::EnvFile(reference) if reference.env_file.is_empty() => {
+                    problems.push(LintProblem::error(
+                        format!("{path}.env-file"),
+                        "at least one env file is required",
+                    ));
+                },
+                ManifestVariable::Secret(secret) if secret.command.trim().is_empty() => {
+                    problems.push(LintProblem::error(
+                        format!("{path}.command"),
+                        "a command is required to fetch the secret",
+                    ));
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+/// Warn about packages installed more than once and groups with a single package
+fn check_packages(manifest: &Manifest, problems: &mut Vec<LintProblem>) {
+    let mut by_pkg_path: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut by_group: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (install_id, descriptor) in manifest.install.iter() {
+        let Some(descriptor) = descriptor.as_catalog_descriptor_ref() else {
+            continue;
+        };
+        by_pkg_path
+            .entry(&descriptor.pkg_path)
+            .or_default()
+            .push(install_id);
+        if let Some(group) = &descriptor.pkg_group {
+            by_group.entry(group).or_default().push(install_id);
+        }
+    }
+
+    for (pkg_path, install_ids) in by_pkg_path {
+        let (first, others) = install_ids.split_first().expect("entries are never empty");
+        for install_id in others {
+            problems.push(LintProblem::warning(
+                format!("{}.pkg-path", join_path("install", install_id)),
+                format!("'{pkg_path}' is also installed as '{first}'"),
+            ));
+        }
+    }
+
+    for (group, install_ids) in by_group {
+        if let [install_id] = install_ids[..] {
+            problems.push(LintProblem::warning(
+                format!("{}.pkg-group", join_path("install", install_id)),
+                format!("group '{group}' only contains '{install_id}'"),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn paths(problems: &[LintProblem]) -> Vec<&str> {
+        problems
+            .iter()
+            .map(|problem| problem.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn valid_manifest_has_no_problems() {
+        let problems = lint_manifest(indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+
+            [services.db]
+            command = "postgres"
+        "#});
+        assert_eq!(problems, vec![]);
+    }
+
+    #[test]
+    fn reports_all_unknown_keys() {
+        let problems = lint_manifest(indoc! {r#"
+            version = 1
+            unknown = true
+
+            [install]
+            hello.pkg-path = "hello"
+            hello.pkg-paht = "hello"
+
+            [hook]
+            on-activte = "echo"
+
+            [options]
+            allow.unfre = true
+
+            [include]
+            environments = [{ dir = "../base", extra = 1 }]
+        "#});
+        assert_eq!(paths(&problems), vec![
+            "hook.on-activte",
+            "include.environments[0].extra",
+            "install.hello.pkg-paht",
+            "options.allow.unfre",
+            "unknown",
+        ]);
+        assert!(problems
+            .iter()
+            .all(|problem| problem.severity == LintSeverity::Error));
+    }
+
+    #[test]
+    fn reports_type_errors_after_unknown_keys() {
+        let problems = lint_manifest(indoc! {r#"
+            version = 1
+            unknown = true
+
+            [vars]
+            FOO = 1
+        "#});
+        assert_eq!(paths(&problems), vec!["unknown", ""]);
+    }
+
+    #[test]
+    fn reports_semantic_problems() {
+        let problems = lint_manifest(indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+            hello.pkg-group = "tools"
+            hello2.pkg-path = "hello"
+            linux-only.pkg-path = "strace"
+            linux-only.systems = ["aarch64-darwin"]
+
+            [vars]
+            TOKEN = { env-file = [] }
+
+            [services.db]
+            command = "postgres"
+            is-daemon = true
+            systems = ["x86_64-darwin"]
+
+            [build.app]
+            command = "make"
+            runtime-packages = ["hello", "missing"]
+
+            [options]
+            systems = ["x86_64-linux", "aarch64-darwin"]
+        "#});
+        assert_eq!(problems, vec![
+            LintProblem::error(
+                "services.db.is-daemon",
+                "services that spawn a daemon must set 'shutdown.command'"
+            ),
+            LintProblem::error(
+                "build.app.runtime-packages",
+                "runtime package 'hello' is not in the 'toplevel' group"
+            ),
+            LintProblem::error(
+                "build.app.runtime-packages",
+                "runtime package 'missing' is not installed"
+            ),
+            LintProblem::error(
+                "services.db.systems",
+                "system 'x86_64-darwin' is not enabled (enabled systems: x86_64-linux, aarch64-darwin)"
+            ),
+            LintProblem::error("vars.TOKEN.env-file", "at least one env file is required"),
+            LintProblem::warning(
+                "install.hello2.pkg-path",
+                "'hello' is also installed as 'hello'"
+            ),
+            LintProblem::warning("install.hello.pkg-group", "group 'tools' only contains 'hello'"),
+        ]);
+    }
+
+    #[test]
+    fn known_keys() {
+        assert!(is_known_key(&["services", "db", "command"]));
+        assert!(is_known_key(&["install", "hello", "pkg-path"]));
+        assert!(is_known_key(&["vars", "ANYTHING"]));
+        assert!(!is_known_key(&["services", "db", "comand"]));
+        assert!(!is_known_key(&["options", "allow", "unfre"]));
+        assert!(!is_known_key(&["unknown"]));
+    }
+
+    #[test]
+    fn schema_describes_manifest() {
+        let schema = Manifest::json_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        for key in ["version", "install", "vars", "hook", "services", "options"] {
+            assert!(properties.contains_key(key), "missing {key}");
+        }
+        assert_eq!(schema["additionalProperties"], Value::Bool(false));
+    }
+}
@@ -0,0 +1,469 @@
+//! Structural three-way merge of manifests
+//!
+//! Used to reconcile a managed environment whose local generations
+//! diverged from the generations on FloxHub.
+//! Rather than merging manifests line by line,
+//! entries of the tables in [MERGED_TABLES] are merged individually,
+//! e.g. adding different packages on both sides merges cleanly,
+//! while changing the same package differently is a conflict.
+//! Values are compared semantically, so formatting changes are not conflicts.
+//!
+//! The merged manifest retains the formatting of the local manifest.
+//! Conflicting entries are annotated with git-style conflict markers,
+//! which intentionally render the manifest invalid until they are resolved.
+
+use thiserror::Error;
+use toml_edit::{DocumentMut, Item, Key, Table, TableLike};
+
+/// Top-level tables whose entries are merged individually
+const MERGED_TABLES: [&str; 9] = [
+    "install", "vars", "hook", "profile", "options", "services", "build", "include", "target",
+];
+
+const CONFLICT_MARKER_LOCAL: &str = "<<<<<<< local";
+const CONFLICT_MARKER_SEPARATOR: &str = "=======";
+const CONFLICT_MARKER_UPSTREAM: &str = ">>>>>>> upstream";
+
+#[derive(Debug, Error)]
+pub enum ManifestMergeError {
+    #[error("could not parse {0} manifest")]
+    Parse(&'static str, #[source] toml_edit::TomlError),
+}
+
+/// An entry that was changed differently in the local and the upstream manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    /// Dotted path of the conflicting entry, e.g. `install.hello`
+    pub path: String,
+    /// The local entry rendered as TOML, empty if it was removed locally
+    local: String,
+    /// The upstream entry rendered as TOML, empty if it was removed upstream
+    upstream: String,
+}
+
+/// The result of [merge_manifests]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedManifest {
+    /// The merged manifest, annotated with conflict markers if there are conflicts
+    pub contents: String,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Merge the changes from `base` to `local` and from `base` to `upstream`.
+///
+/// `base` is the manifest of the last generation both sides have in common.
+pub fn merge_manifests(
+    base: &str,
+    local: &str,
+    upstream: &str,
+) -> Result<MergedManifest, ManifestMergeError> {
+    let parse = |contents: &str, side| {
+        contents
+            .parse::<DocumentMut>()
+            .map_err(|e| ManifestMergeError::Parse(side, e))
+    };
+    let base = parse(base, "base")?;
+    let local = parse(local, "local")?;
+    let upstream = parse(upstream, "upstream")?;
+
+    let mut merged = local.clone();
+    let mut conflicts = Vec::new();
+
+    for key in keys(&[local.as_table(), upstream.as_table(), base.as_table()]) {
+        let base_item = base.get(&key);
+        let local_item = local.get(&key);
+        let upstream_item = upstream.get(&key);
+
+        let all_tables = [base_item, local_item, upstream_item]
+            .into_iter()
+            .flatten()
+            .all(Item::is_table_like);
+
+        if MERGED_TABLES.contains(&key.as_str()) && all_tables {
+            merge_table(
+                &mut merged,
+                &key,
+                base_item.and_then(Item::as_table_like),
+                local_item.and_then(Item::as_table_like),
+                upstream_item.and_then(Item::as_table_like),
+                &mut conflicts,
+            );
+        } else {
+            merge_entry(
+                merged.as_table_mut(),
+                &key,
+                &key,
+                [base_item, local_item, upstream_item],
+                &mut conflicts,
+            );
+        }
+    }
+
+    Ok(MergedManifest {
+        contents: annotate_conflicts(merged.to_string(), &conflicts),
+        conflicts,
+    })
+}
+
+/// Whether `contents` contains unresolved conflict markers written by [merge_manifests]
+pub fn has_conflict_markers(contents: &str) -> bool {
+    contents
+        .lines()
+        .any(|line| line.trim_end() == CONFLICT_MARKER_LOCAL)
+}
+
+/// Merge the entries of the top-level table `name`
+fn merge_table(
+    merged: &mut DocumentMut,
+    name: &str,
+    base: Option<&dyn TableLike>,
+    local: Option<&dyn TableLike>,
+    upstream: Option<&dyn TableLike>,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    let tables = [local, upstream, base]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let keys = keys(&tables);
+
+    let section = merged
+        .entry(name)
+        .or_insert_with(|| Item::Table(Table::new()));
+
+    let target = section
+        .as_table_like_mut()
+        .expect("merged table is table-like");
+    for key in keys {
+        let items = [base, local, upstream].map(|table| table.and_then(|table| table.get(&key)));
+        merge_entry(target, &key, &format!("{name}.{key}"), items, conflicts);
+    }
+    let is_empty = target.is_empty();
+
+    // Conflict placeholders are values,
+    // which need an explicit header to be rendered
+    if let Some(table) = section.as_table_mut() {
+        if table.iter().any(|(_, item)| item.is_value()) {
+            table.set_implicit(false);
+        }
+    }
+
+    // Drop sections that were removed or emptied on either side
+    if is_empty && (local.is_none() || upstream.is_none()) {
+        merged.remove(name);
+    }
+}
+
+/// Merge a single entry into `target`
+///
+/// `items` are the entries in the base, local, and upstream manifest.
+/// `target` is expected to contain the local entry already.
+fn merge_entry(
+    target: &mut dyn TableLike,
+    key: &str,
+    path: &str,
+    [base, local, upstream]: [Option<&Item>; 3],
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    let [base_value, local_value, upstream_value] =
+        [base, local, upstream].map(|item| item.and_then(semantic_value));
+
+    if local_value == upstream_value || base_value == upstream_value {
+        // unchanged upstream or changed the same way on both sides
+        return;
+    }
+
+    if base_value == local_value {
+        // only changed upstream
+        match upstream {
+            Some(item) => {
+                target.insert(key, item.clone());
+            },
+            None => {
+                target.remove(key);
+            },
+        }
+        return;
+    }
+
+    target.insert(key, toml_edit::value(conflict_placeholder(conflicts.len())));
+    conflicts.push(MergeConflict {
+        path: path.to_string(),
+        local: render_entry(key, local),
+        upstream: render_entry(key, upstream),
+    });
+}
+
+/// Union of the keys of all tables, in order of first occurrence
+fn keys<T: TableLike + ?Sized>(tables: &[&T]) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for table in tables {
+        for (key, _) in table.iter() {
+            if !keys.iter().any(|existing| existing == key) {
+                keys.push(key.to_string());
+            }
+        }
+    }
+    keys
+}
+
+/// Deserialize an item, ignoring formatting and comments
+fn semantic_value(item: &Item) -> Option<toml::Value> {
+    let mut document = DocumentMut::new();
+    document.insert("value", item.clone());
+    let mut table = toml::from_str::<toml::Table>(&document.to_string()).ok()?;
+    table.remove("value")
+}
+
+fn conflict_placeholder(index: usize) -> String {
+    format!("__flox_merge_conflict_{index}__")
+}
+
+/// Render an entry as a single `key = value` line
+fn render_entry(key: &str, item: Option<&Item>) -> String {
+    let Some(item) = item else {
+        return String::new();
+    };
+    let Ok(mut value) = item.clone().into_value() else {
+        return String::new();
+    };
+    value.decor_mut().clear();
+    if let Some(table) = value.as_inline_table_mut() {
+        table.fmt();
+    }
+    format!("{} = {}\n", Key::new(key), value)
+}
+
+/// Replace the placeholder of every conflict with conflict markers
+/// surrounding the local and upstream entry.
+fn annotate_conflicts(contents: String, conflicts: &[MergeConflict]) -> String {
+    if conflicts.is_empty() {
+        return contents;
+    }
+
+    let mut annotated = String::new();
+    for line in contents.lines() {
+        let conflict = conflicts
+            .iter()
+            .enumerate()
+            .find(|(index, _)| line.contains(&format!("\"{}\"", conflict_placeholder(*index))));
+
+        match conflict {
+            Some((_, conflict)) => {
+                annotated.push_str(&format!(
+                    "{CONFLICT_MARKER_LOCAL}\n{}{CONFLICT_MARKER_SEPARATOR}\n{}{CONFLICT_MARKER_UPSTREAM}\n",
+                    conflict.local, conflict.upstream
+                ));
+            },
+            None => {
+                annotated.push_str(line);
+                annotated.push('\n');
+            },
+        }
+    }
+    annotated
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const BASE: &str = indoc! {r#"
+        version = 1
+
+        [install]
+        hello.pkg-path = "hello"
+
+        [vars]
+        FOO = "foo"
+    "#};
+
+    #[test]
+    fn merges_independent_changes() {
+        let local = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+            # added locally
+            curl.pkg-path = "curl"
+
+            [vars]
+            FOO = "foo"
+        "#};
+        let upstream = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+            jq.pkg-path = "jq"
+
+            [vars]
+            FOO = "bar"
+
+            [hook]
+            on-activate = "echo hi"
+        "#};
+
+        let merged = merge_manifests(BASE, local, upstream).unwrap();
+        assert_eq!(merged.conflicts, vec![]);
+        assert_eq!(merged.contents, indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+            # added locally
+            curl.pkg-path = "curl"
+            jq.pkg-path = "jq"
+
+            [vars]
+            FOO = "bar"
+
+            [hook]
+            on-activate = "echo hi"
+        "#});
+    }
+
+    #[test]
+    fn formatting_changes_are_not_conflicts() {
+        let local = indoc! {r#"
+            version = 1
+
+            [install]
+            hello = { pkg-path = "hello" }
+
+            [vars]
+            FOO = 'foo'
+        "#};
+        let upstream = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+
+            [vars]
+            FOO = "foo"
+            BAR = "bar"
+        "#};
+
+        let merged = merge_manifests(BASE, local, upstream).unwrap();
+        assert_eq!(merged.conflicts, vec![]);
+        assert!(merged
+            .contents
+            .contains(r#"hello = { pkg-path = "hello" }"#));
+        assert!(merged.contents.contains(r#"BAR = "bar""#));
+    }
+
+    #[test]
+    fn removals_are_merged() {
+        let local = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+        "#};
+        let upstream = BASE;
+
+        let merged = merge_manifests(BASE, local, upstream).unwrap();
+        assert_eq!(merged.conflicts, vec![]);
+        assert_eq!(merged.contents, local);
+
+        // and the other way around
+        let merged = merge_manifests(BASE, upstream, local).unwrap();
+        assert_eq!(merged.conflicts, vec![]);
+        assert!(!merged.contents.contains("[vars]"));
+    }
+
+    #[test]
+    fn conflicting_changes_are_annotated() {
+        let local = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+            hello.version = "1"
+
+            [vars]
+            FOO = "local"
+        "#};
+        let upstream = indoc! {r#"
+            version = 1
+
+            [install]
+            hello.pkg-path = "hello"
+            hello.version = "2"
+
+            [vars]
+        "#};
+
+        let merged = merge_manifests(BASE, local, upstream).unwrap();
+        let paths = merged
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["install.hello", "vars.FOO"]);
+        assert_eq!(merged.contents, indoc! {r#"
+            version = 1
+
+            [install]
+            <<<<<<< local
+            hello = { pkg-path = "hello", version = "1" }
+            =======
+            hello = { pkg-path = "hello", version = "2" }
+            >>>>>>> upstream
+
+            [vars]
+            <<<<<<< local
+            FOO = "local"
+            =======
+            >>>>>>> upstream
+        "#});
+        assert!(has_conflict_markers(&merged.contents));
+        assert!(merged.contents.parse::<DocumentMut>().is_err());
+    }
+
+    #[test]
+    fn conflicts_in_sub_tables_are_annotated() {
+        let base = indoc! {r#"
+            version = 1
+
+            [install.hello]
+            pkg-path = "hello"
+        "#};
+        let local = indoc! {r#"
+            version = 1
+
+            [install.hello]
+            pkg-path = "hello"
+            version = "1"
+        "#};
+        let upstream = indoc! {r#"
+            version = 1
+
+            [install.hello]
+            pkg-path = "hello"
+            version = "2"
+        "#};
+
+        let merged = merge_manifests(base, local, upstream).unwrap();
+        assert_eq!(merged.contents, indoc! {r#"
+            version = 1
+
+            [install]
+            <<<<<<< local
+            hello = { pkg-path = "hello", version = "1" }
+            =======
+            hello = { pkg-path = "hello", version = "2" }
+            >>>>>>> upstream
+        "#});
+    }
+
+    #[test]
+    fn no_conflict_markers_in_valid_manifest() {
+        assert!(!has_conflict_markers(BASE));
+    }
+}
@@ -1,10 +1,17 @@
 //# An attempt at defining a domain model for flox
+pub mod audit;
+pub mod devcontainer;
 pub mod env_registry;
 pub mod environment;
 pub mod environment_ref;
 pub mod floxmeta;
+pub mod license;
 pub mod lockfile;
 pub mod manifest;
+pub mod manifest_lint;
+pub mod manifest_merge;
 pub mod pkgdb;
+pub mod sbom;
 pub mod search;
+pub mod secrets;
 pub mod user_state;
@@ -0,0 +1,1203 @@
+//! Software bills of materials (SBOMs) for environments.
+//!
+//! An [Sbom] is created from the packages locked for a single system
+//! and can be rendered as a CycloneDX 1.5 or SPDX 2.3 JSON document.
+//! Optionally, the runtime closure of the locked packages
+//! can be read from the local Nix store and recorded
+//! as dependencies of the locked packages.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use thiserror::Error;
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+use super::lockfile::{LockedPackage, Lockfile};
+use crate::data::System;
+use crate::flox::FLOX_VERSION_STRING;
+use crate::providers::buildenv::NIX_BIN;
+use crate::utils::CommandExt;
+
+/// The bom-ref / SPDX element of the environment itself
+const ENVIRONMENT_REF: &str = "environment";
+const SPDX_NOASSERTION: &str = "NOASSERTION";
+
+#[derive(Debug, Error)]
+pub enum SbomError {
+    #[error("unknown SBOM format '{0}', expected 'cyclonedx-json' or 'spdx-json'")]
+    UnknownFormat(String),
+    #[error("failed to call nix")]
+    CallNix(#[source] std::io::Error),
+    #[error("failed to query the runtime closure of the environment:\n{0}")]
+    QueryClosure(String),
+    #[error("failed to parse the runtime closure of the environment")]
+    ParseClosure(#[source] serde_json::Error),
+    #[error("failed to serialize SBOM")]
+    Serialize(#[source] serde_json::Error),
+}
+
+/// The document formats an [Sbom] can be rendered as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    CycloneDxJson,
+    /// SPDX 2.3 JSON
+    SpdxJson,
+}
+
+impl FromStr for SbomFormat {
+    type Err = SbomError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cyclonedx-json" => Ok(SbomFormat::CycloneDxJson),
+            "spdx-json" => Ok(SbomFormat::SpdxJson),
+            _ => Err(SbomError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// A locked package as it is recorded in an SBOM
+///
+/// This unifies the information available for catalog, flake
+/// and store path packages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SbomPackage {
+    pub install_id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub licenses: Vec<String>,
+    /// The locked url of the package source, if any
+    pub locked_url: Option<String>,
+    /// The revision of the package source, if known
+    pub rev: Option<String>,
+    pub attr_path: Option<String>,
+    pub derivation: Option<String>,
+    /// Map of output names to their store paths
+    pub outputs: BTreeMap<String, String>,
+}
+
+impl From<&LockedPackage> for SbomPackage {
+    fn from(package: &LockedPackage) -> Self {
+        match package {
+            LockedPackage::Catalog(pkg) => SbomPackage {
+                install_id: pkg.install_id.clone(),
+                name: pkg.pname.clone(),
+                version: Some(pkg.version.clone()).filter(|version| !version.is_empty()),
+                description: pkg.description.clone(),
+                licenses: pkg.license.iter().cloned().collect(),
+                locked_url: Some(pkg.locked_url.clone()).filter(|url| !url.is_empty()),
+                rev: Some(pkg.rev.clone()).filter(|rev| !rev.is_empty()),
+                attr_path: Some(pkg.attr_path.clone()),
+                derivation: Some(pkg.derivation.clone()),
+                outputs: pkg.outputs.clone(),
+            },
+            LockedPackage::Flake(pkg) => {
+                let installable = &pkg.locked_installable;
+                SbomPackage {
+                    install_id: pkg.install_id.clone(),
+                    name: installable
+                        .pname
+                        .clone()
+                        .unwrap_or_else(|| installable.name.clone()),
+                    version: installable.version.clone(),
+                    description: installable.description.clone(),
+                    licenses: installable.licenses.clone().unwrap_or_default(),
+                    locked_url: Some(installable.locked_url.clone()),
+                    rev: None,
+                    attr_path: Some(installable.locked_flake_attr_path.clone()),
+                    derivation: Some(installable.derivation.clone()),
+                    outputs: installable.outputs.clone(),
+                }
+            },
+            LockedPackage::StorePath(pkg) => SbomPackage {
+                install_id: pkg.install_id.clone(),
+                name: store_path_name(&pkg.store_path).to_string(),
+                version: None,
+                description: None,
+                licenses: vec![],
+                locked_url: None,
+                rev: None,
+                attr_path: None,
+                derivation: None,
+                outputs: BTreeMap::from([("out".to_string(), pkg.store_path.clone())]),
+            },
+        }
+    }
+}
+
+/// The references of each store path in the runtime closure of a set of store paths
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreClosure(BTreeMap<String, BTreeSet<String>>);
+
+/// Output of `nix path-info --json`.
+///
+/// Nix 2.19 changed the output from a list of objects
+/// to an object keyed by store path.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PathInfoJson {
+    Map(BTreeMap<String, Option<PathInfo>>),
+    List(Vec<PathInfoWithPath>),
+}
+
+#[derive(Debug, Deserialize)]
+struct PathInfo {
+    #[serde(default)]
+    references: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PathInfoWithPath {
+    path: String,
+    #[serde(default)]
+    references: Vec<String>,
+}
+
+impl StoreClosure {
+    /// Query the runtime closure of `paths` from the local Nix store.
+    ///
+    /// Paths have to be present in the store,
+    /// i.e. the environment has to be built first.
+    #[instrument(
+        skip_all,
+        fields(progress = "Reading runtime closure from the Nix store")
+    )]
+    pub fn query(paths: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Result<Self, SbomError> {
+        let mut cmd = Command::new(&*NIX_BIN);
+        cmd.args([
+            "--option",
+            "extra-experimental-features",
+            "nix-command",
+            "path-info",
+            "--json",
+            "--recursive",
+            "--offline",
+        ]);
+        cmd.args(paths);
+
+        debug!(cmd=%cmd.display(), "querying runtime closure");
+
+        let output = cmd.output().map_err(SbomError::CallNix)?;
+        if !output.status.success() {
+            return Err(SbomError::QueryClosure(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+
+        Self::from_path_info_json(&output.stdout)
+    }
+
+    /// Parse the output of `nix path-info --json --recursive`
+    fn from_path_info_json(json: &[u8]) -> Result<Self, SbomError> {
+        let path_info: PathInfoJson =
+            serde_json::from_slice(json).map_err(SbomError::ParseClosure)?;
+
+        // Older versions of nix print references as store paths,
+        // newer versions print just the base names.
+        let store_dir = |path: &str| {
+            Path::new(path)
+                .parent()
+                .map(|parent| parent.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let qualify = |store_dir: &str, reference: String| {
+            if reference.starts_with('/') {
+                reference
+            } else {
+                format!("{store_dir}/{reference}")
+            }
+        };
+
+        let entries: Vec<(String, Vec<String>)> = match path_info {
+            PathInfoJson::Map(map) => map
+                .into_iter()
+                .map(|(path, info)| (path, info.map(|i| i.references).unwrap_or_default()))
+                .collect(),
+            PathInfoJson::List(list) => list
+                .into_iter()
+                .map(|info| (info.path, info.references))
+                .collect(),
+        };
+
+        let closure = entries
+            .into_iter()
+            .map(|(path, references)| {
+                let store_dir = store_dir(&path);
+                let references = references
+                    .into_iter()
+                    .map(|reference| qualify(&store_dir, reference))
+                    .filter(|reference| reference != &path)
+                    .collect();
+                (path, references)
+            })
+            .collect();
+
+        Ok(StoreClosure(closure))
+    }
+
+    /// Iterate over all store paths in the closure and their references
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &BTreeSet<String>)> {
+        self.0.iter()
+    }
+
+    fn references(&self, path: &str) -> impl Iterator<Item = &String> {
+        self.0.get(path).into_iter().flatten()
+    }
+}
+
+/// Metadata of a rendered SBOM document,
+/// that is not derived from the environment itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentInfo {
+    pub serial: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub tool_version: String,
+}
+
+impl Default for DocumentInfo {
+    fn default() -> Self {
+        DocumentInfo {
+            serial: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            tool_version: FLOX_VERSION_STRING.to_string(),
+        }
+    }
+}
+
+/// A software bill of materials for the packages of an environment
+/// on a single system
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sbom {
+    pub name: String,
+    pub system: System,
+    pub packages: Vec<SbomPackage>,
+    pub closure: Option<StoreClosure>,
+}
+
+impl Sbom {
+    /// Collect the packages locked for `system` in `lockfile`
+    pub fn from_lockfile(name: impl Into<String>, lockfile: &Lockfile, system: &System) -> Self {
+        let packages = lockfile
+            .packages
+            .iter()
+            .filter(|package| package.system() == system)
+            .map(SbomPackage::from)
+            .collect();
+
+        Sbom {
+            name: name.into(),
+            system: system.clone(),
+            packages,
+            closure: None,
+        }
+    }
+
+    /// All store paths of all outputs of the packages in this SBOM
+    pub fn output_paths(&self) -> impl Iterator<Item = &String> {
+        self.packages
+            .iter()
+            .flat_map(|package| package.outputs.values())
+    }
+
+    /// Record the runtime closure of the packages in this SBOM
+    pub fn with_closure(mut self, closure: StoreClosure) -> Self {
+        self.closure = Some(closure);
+        self
+    }
+
+    /// Render the SBOM as a pretty printed JSON document
+    pub fn render(&self, format: SbomFormat, info: &DocumentInfo) -> Result<String, SbomError> {
+        match format {
+            SbomFormat::CycloneDxJson => serde_json::to_string_pretty(&self.to_cyclonedx(info)),
+            SbomFormat::SpdxJson => serde_json::to_string_pretty(&self.to_spdx(info)),
+        }
+        .map_err(SbomError::Serialize)
+    }
+
+    /// Map store paths that are outputs of a package to the index of that package
+    fn output_owners(&self) -> HashMap<&str, usize> {
+        self.packages
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, package)| {
+                package
+                    .outputs
+                    .values()
+                    .map(move |path| (path.as_str(), idx))
+            })
+            .collect()
+    }
+
+    /// The dependencies of every package and closure path,
+    /// with references resolved to either a package index or a store path
+    /// that isn't an output of any package.
+    ///
+    /// Returns the dependencies of each package (by index)
+    /// and of each store path in the closure that isn't a package output.
+    fn dependency_graph(&self) -> (Vec<BTreeSet<Node>>, BTreeMap<&str, BTreeSet<Node>>) {
+        let mut package_deps = vec![BTreeSet::new(); self.packages.len()];
+        let mut path_deps = BTreeMap::new();
+
+        let Some(closure) = &self.closure else {
+            return (package_deps, path_deps);
+        };
+
+        let owners = self.output_owners();
+        let resolve = |path: &str| match owners.get(path) {
+            Some(idx) => Node::Package(*idx),
+            None => Node::StorePath(path.to_string()),
+        };
+
+        for (idx, package) in self.packages.iter().enumerate() {
+            package_deps[idx] = package
+                .outputs
+                .values()
+                .flat_map(|output| closure.references(output))
+                .map(|reference| resolve(reference))
+                .filter(|node| node != &Node::Package(idx))
+                .collect();
+        }
+
+        for (path, references) in closure.iter() {
+            if owners.contains_key(path.as_str()) {
+                continue;
+            }
+            path_deps.insert(
+                path.as_str(),
+                references
+                    .iter()
+                    .map(|reference| resolve(reference))
+                    .collect(),
+            );
+        }
+
+        (package_deps, path_deps)
+    }
+}
+
+/// A node in the dependency graph of an [Sbom]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    Package(usize),
+    StorePath(String),
+}
+
+/// The name part of a store path, i.e. the file name without the hash
+fn store_path_name(store_path: &str) -> &str {
+    let base = store_path.rsplit('/').next().unwrap_or(store_path);
+    match base.split_once('-') {
+        Some((_hash, name)) => name,
+        None => base,
+    }
+}
+
+/// The hash part of a store path
+fn store_path_hash(store_path: &str) -> &str {
+    let base = store_path.rsplit('/').next().unwrap_or(store_path);
+    base.split_once('-').map_or(base, |(hash, _name)| hash)
+}
+
+/// Percent-encode a purl name or version component
+fn purl_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b".-_~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// A package url for a package.
+///
+/// Nix has no registered purl type, so the generic type is used.
+fn purl(name: &str, version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("pkg:generic/{}@{}", purl_encode(name), purl_encode(version)),
+        None => format!("pkg:generic/{}", purl_encode(name)),
+    }
+}
+
+// region: CycloneDX
+
+const CYCLONEDX_SPEC_VERSION: &str = "1.5";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxBom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    serial_number: String,
+    version: u32,
+    metadata: CycloneDxMetadata,
+    components: Vec<CycloneDxComponent>,
+    dependencies: Vec<CycloneDxDependency>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxMetadata {
+    timestamp: String,
+    tools: CycloneDxTools,
+    component: CycloneDxComponent,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxTools {
+    components: Vec<CycloneDxComponent>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: Option<String>,
+    name: String,
+    version: Option<String>,
+    description: Option<String>,
+    licenses: Option<Vec<CycloneDxLicenseChoice>>,
+    purl: Option<String>,
+    external_references: Option<Vec<CycloneDxExternalReference>>,
+    properties: Option<Vec<CycloneDxProperty>>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxLicenseChoice {
+    license: CycloneDxLicense,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxLicense {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxExternalReference {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxProperty {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
+
+impl Sbom {
+    fn cyclonedx_package_ref(&self, idx: usize) -> String {
+        format!("pkg:{}", self.packages[idx].install_id)
+    }
+
+    fn cyclonedx_ref(&self, node: &Node) -> String {
+        match node {
+            Node::Package(idx) => self.cyclonedx_package_ref(*idx),
+            Node::StorePath(path) => path.clone(),
+        }
+    }
+
+    fn to_cyclonedx(&self, info: &DocumentInfo) -> CycloneDxBom {
+        let property = |name: &str, value: &str| CycloneDxProperty {
+            name: format!("flox:{name}"),
+            value: value.to_string(),
+        };
+
+        let mut components = Vec::new();
+        for (idx, package) in self.packages.iter().enumerate() {
+            let mut properties = vec![property("install_id", &package.install_id)];
+            properties.extend(package.attr_path.iter().map(|p| property("attr_path", p)));
+            properties.extend(package.rev.iter().map(|r| property("rev", r)));
+            properties.extend(package.derivation.iter().map(|d| property("derivation", d)));
+            properties.extend(
+                package
+                    .outputs
+                    .iter()
+                    .map(|(name, path)| property(&format!("output:{name}"), path)),
+            );
+
+            components.push(CycloneDxComponent {
+                kind: "library",
+                bom_ref: Some(self.cyclonedx_package_ref(idx)),
+                name: package.name.clone(),
+                version: package.version.clone(),
+                description: package.description.clone(),
+                licenses: Some(
+                    package
+                        .licenses
+                        .iter()
+                        .map(|license| CycloneDxLicenseChoice {
+                            license: CycloneDxLicense {
+                                name: license.clone(),
+                            },
+                        })
+                        .collect(),
+                )
+                .filter(|licenses: &Vec<_>| !licenses.is_empty()),
+                purl: Some(purl(&package.name, package.version.as_deref())),
+                external_references: package.locked_url.as_ref().map(|url| {
+                    vec![CycloneDxExternalReference {
+                        kind: "vcs",
+                        url: url.clone(),
+                    }]
+                }),
+                properties: Some(properties),
+            });
+        }
+
+        let (package_deps, path_deps) = self.dependency_graph();
+
+        for path in path_deps.keys() {
+            components.push(CycloneDxComponent {
+                kind: "library",
+                bom_ref: Some(path.to_string()),
+                name: store_path_name(path).to_string(),
+                properties: Some(vec![property("store_path", path)]),
+                ..Default::default()
+            });
+        }
+
+        let mut dependencies = vec![CycloneDxDependency {
+            reference: ENVIRONMENT_REF.to_string(),
+            depends_on: (0..self.packages.len())
+                .map(|idx| self.cyclonedx_package_ref(idx))
+                .collect(),
+        }];
+        dependencies.extend(package_deps.iter().enumerate().map(|(idx, deps)| {
+            CycloneDxDependency {
+                reference: self.cyclonedx_package_ref(idx),
+                depends_on: deps.iter().map(|node| self.cyclonedx_ref(node)).collect(),
+            }
+        }));
+        dependencies.extend(path_deps.iter().map(|(path, deps)| CycloneDxDependency {
+            reference: path.to_string(),
+            depends_on: deps.iter().map(|node| self.cyclonedx_ref(node)).collect(),
+        }));
+
+        CycloneDxBom {
+            bom_format: "CycloneDX",
+            spec_version: CYCLONEDX_SPEC_VERSION,
+            serial_number: format!("urn:uuid:{}", info.serial),
+            version: 1,
+            metadata: CycloneDxMetadata {
+                timestamp: info.timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                tools: CycloneDxTools {
+                    components: vec![CycloneDxComponent {
+                        kind: "application",
+                        name: "flox".to_string(),
+                        version: Some(info.tool_version.clone()),
+                        ..Default::default()
+                    }],
+                },
+                component: CycloneDxComponent {
+                    kind: "application",
+                    bom_ref: Some(ENVIRONMENT_REF.to_string()),
+                    name: self.name.clone(),
+                    properties: Some(vec![property("system", &self.system)]),
+                    ..Default::default()
+                },
+            },
+            components,
+            dependencies,
+        }
+    }
+}
+
+// endregion
+
+// region: SPDX
+
+const SPDX_VERSION: &str = "SPDX-2.3";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: String,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    relationships: Vec<SpdxRelationship>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    has_extracted_licensing_infos: Vec<SpdxExtractedLicensingInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    name: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    version_info: Option<String>,
+    download_location: String,
+    files_analyzed: bool,
+    license_concluded: String,
+    license_declared: String,
+    copyright_text: String,
+    description: Option<String>,
+    external_refs: Option<Vec<SpdxExternalRef>>,
+    comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxExternalRef {
+    reference_category: &'static str,
+    reference_type: &'static str,
+    reference_locator: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: &'static str,
+    related_spdx_element: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxExtractedLicensingInfo {
+    license_id: String,
+    name: String,
+    extracted_text: String,
+}
+
+/// Replace characters that are not allowed in SPDX identifiers
+fn spdx_sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Whether a license string can be used as an SPDX license identifier as is
+fn is_spdx_license_id(license: &str) -> bool {
+    !license.is_empty()
+        && license
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+')
+}
+
+impl Sbom {
+    fn spdx_package_id(&self, idx: usize) -> String {
+        format!(
+            "SPDXRef-Package-{idx}-{}",
+            spdx_sanitize(&self.packages[idx].install_id)
+        )
+    }
+
+    fn spdx_id(&self, node: &Node) -> String {
+        match node {
+            Node::Package(idx) => self.spdx_package_id(*idx),
+            Node::StorePath(path) => {
+                format!("SPDXRef-StorePath-{}", spdx_sanitize(store_path_hash(path)))
+            },
+        }
+    }
+
+    fn to_spdx(&self, info: &DocumentInfo) -> SpdxDocument {
+        let environment_id = format!("SPDXRef-{}", spdx_sanitize(ENVIRONMENT_REF));
+        let mut extracted_licenses = BTreeMap::new();
+
+        let mut packages = vec![SpdxPackage {
+            name: self.name.clone(),
+            spdx_id: environment_id.clone(),
+            version_info: None,
+            download_location: SPDX_NOASSERTION.to_string(),
+            files_analyzed: false,
+            license_concluded: SPDX_NOASSERTION.to_string(),
+            license_declared: SPDX_NOASSERTION.to_string(),
+            copyright_text: SPDX_NOASSERTION.to_string(),
+            description: None,
+            external_refs: None,
+            comment: Some(format!("Flox environment for {}", self.system)),
+        }];
+        let mut relationships = vec![SpdxRelationship {
+            spdx_element_id: "SPDXRef-DOCUMENT".to_string(),
+            relationship_type: "DESCRIBES",
+            related_spdx_element: environment_id.clone(),
+        }];
+
+        for (idx, package) in self.packages.iter().enumerate() {
+            let license_declared = if package.licenses.is_empty() {
+                SPDX_NOASSERTION.to_string()
+            } else {
+                package
+                    .licenses
+                    .iter()
+                    .map(|license| {
+                        if is_spdx_license_id(license) {
+                            return license.clone();
+                        }
+                        let license_id = format!("LicenseRef-{}", spdx_sanitize(license));
+                        extracted_licenses.insert(license_id.clone(), license.clone());
+                        license_id
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" AND ")
+            };
+
+            let comment = [
+                Some(format!("install_id: {}", package.install_id)),
+                package
+                    .attr_path
+                    .as_ref()
+                    .map(|p| format!("attr_path: {p}")),
+                package.rev.as_ref().map(|r| format!("rev: {r}")),
+                package
+                    .derivation
+                    .as_ref()
+                    .map(|d| format!("derivation: {d}")),
+            ]
+            .into_iter()
+            .flatten()
+            .chain(
+                package
+                    .outputs
+                    .iter()
+                    .map(|(name, path)| format!("output {name}: {path}")),
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+
+            packages.push(SpdxPackage {
+                name: package.name.clone(),
+                spdx_id: self.spdx_package_id(idx),
+                version_info: package.version.clone(),
+                download_location: package
+                    .locked_url
+                    .clone()
+                    .unwrap_or_else(|| SPDX_NOASSERTION.to_string()),
+                files_analyzed: false,
+                license_concluded: SPDX_NOASSERTION.to_string(),
+                license_declared,
+                copyright_text: SPDX_NOASSERTION.to_string(),
+                description: package.description.clone(),
+                external_refs: Some(vec![SpdxExternalRef {
+                    reference_category: "PACKAGE-MANAGER",
+                    reference_type: "purl",
+                    reference_locator: purl(&package.name, package.version.as_deref()),
+                }]),
+                comment: Some(comment),
+            });
+            relationships.push(SpdxRelationship {
+                spdx_element_id: environment_id.clone(),
+                relationship_type: "CONTAINS",
+                related_spdx_element: self.spdx_package_id(idx),
+            });
+        }
+
+        let (package_deps, path_deps) = self.dependency_graph();
+
+        for path in path_deps.keys() {
+            packages.push(SpdxPackage {
+                name: store_path_name(path).to_string(),
+                spdx_id: self.spdx_id(&Node::StorePath(path.to_string())),
+                version_info: None,
+                download_location: SPDX_NOASSERTION.to_string(),
+                files_analyzed: false,
+                license_concluded: SPDX_NOASSERTION.to_string(),
+                license_declared: SPDX_NOASSERTION.to_string(),
+                copyright_text: SPDX_NOASSERTION.to_string(),
+                description: None,
+                external_refs: None,
+                comment: Some(format!("store path: {path}")),
+            });
+        }
+
+        let depends_on = |from: String, deps: &BTreeSet<Node>| {
+            deps.iter()
+                .map(|node| SpdxRelationship {
+                    spdx_element_id: from.clone(),
+                    relationship_type: "DEPENDS_ON",
+                    related_spdx_element: self.spdx_id(node),
+                })
+                .collect::<Vec<_>>()
+        };
+        for (idx, deps) in package_deps.iter().enumerate() {
+            relationships.extend(depends_on(self.spdx_package_id(idx), deps));
+        }
+        for (path, deps) in &path_deps {
+            relationships.extend(depends_on(
+                self.spdx_id(&Node::StorePath(path.to_string())),
+                deps,
+            ));
+        }
+
+        SpdxDocument {
+            spdx_version: SPDX_VERSION,
+            data_license: "CC0-1.0",
+            spdx_id: "SPDXRef-DOCUMENT",
+            name: self.name.clone(),
+            document_namespace: format!(
+                "https://flox.dev/spdxdocs/{}-{}",
+                purl_encode(&self.name),
+                info.serial
+            ),
+            creation_info: SpdxCreationInfo {
+                created: info.timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                creators: vec![format!("Tool: flox-{}", info.tool_version)],
+            },
+            packages,
+            relationships,
+            has_extracted_licensing_infos: extracted_licenses
+                .into_iter()
+                .map(|(license_id, name)| SpdxExtractedLicensingInfo {
+                    license_id,
+                    extracted_text: name.clone(),
+                    name,
+                })
+                .collect(),
+        }
+    }
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::lockfile::test_helpers::{
+        fake_catalog_package_lock,
+        fake_store_path_lock,
+        LOCKED_NIX_EVAL_JOBS,
+    };
+
+    const SYSTEM: &str = "aarch64-darwin";
+
+    fn document_info() -> DocumentInfo {
+        DocumentInfo {
+            serial: Uuid::nil(),
+            timestamp: DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            tool_version: "1.0.0".to_string(),
+        }
+    }
+
+    fn hello_package() -> LockedPackage {
+        let (_, _, mut hello) = fake_catalog_package_lock("hello", None);
+        hello.version = "2.12.1".to_string();
+        hello.license = Some("GPL-3.0-or-later".to_string());
+        hello.locked_url = "https://github.com/flox/nixpkgs?rev=abc".to_string();
+        hello.rev = "abc".to_string();
+        hello.outputs = BTreeMap::from([(
+            "out".to_string(),
+            "/nix/store/aaaa-hello-2.12.1".to_string(),
+        )]);
+        hello.into()
+    }
+
+    fn sbom(packages: Vec<LockedPackage>) -> Sbom {
+        let lockfile = Lockfile {
+            packages,
+            ..Default::default()
+        };
+        Sbom::from_lockfile("myenv", &lockfile, &SYSTEM.to_string())
+    }
+
+    fn render(sbom: &Sbom, format: SbomFormat) -> Value {
+        serde_json::from_str(&sbom.render(format, &document_info()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parse_format() {
+        assert_eq!(
+            "cyclonedx-json".parse::<SbomFormat>().unwrap(),
+            SbomFormat::CycloneDxJson
+        );
+        assert_eq!(
+            "spdx-json".parse::<SbomFormat>().unwrap(),
+            SbomFormat::SpdxJson
+        );
+        assert!("cyclonedx-xml".parse::<SbomFormat>().is_err());
+    }
+
+    #[test]
+    fn packages_of_other_systems_are_excluded() {
+        let (_, _, mut linux) = fake_catalog_package_lock("linux-only", None);
+        linux.system = "x86_64-linux".to_string();
+
+        let sbom = sbom(vec![hello_package(), linux.into()]);
+        assert_eq!(sbom.packages.len(), 1);
+        assert_eq!(sbom.packages[0].name, "hello");
+    }
+
+    #[test]
+    fn sbom_package_from_each_kind_of_package() {
+        let (_, _, store_path) = fake_store_path_lock("abcd-mytool-1.0");
+        let sbom = sbom(vec![
+            hello_package(),
+            LOCKED_NIX_EVAL_JOBS.clone().into(),
+            store_path.into(),
+        ]);
+
+        assert_eq!(sbom.packages[0].version.as_deref(), Some("2.12.1"));
+        assert_eq!(sbom.packages[0].licenses, vec!["GPL-3.0-or-later"]);
+
+        assert_eq!(sbom.packages[1].name, "nix-eval-jobs");
+        assert_eq!(sbom.packages[1].licenses, vec!["GPL-3.0"]);
+        assert_eq!(
+            sbom.packages[1].locked_url.as_deref(),
+            Some("github:nix-community/nix-eval-jobs/c132534bc68eb48479a59a3116ee7ce0f16ce12b")
+        );
+
+        assert_eq!(sbom.packages[2].name, "mytool-1.0");
+        assert_eq!(
+            sbom.packages[2].outputs,
+            BTreeMap::from([("out".to_string(), "/nix/store/abcd-mytool-1.0".to_string())])
+        );
+    }
+
+    #[test]
+    fn cyclonedx_document() {
+        let bom = render(&sbom(vec![hello_package()]), SbomFormat::CycloneDxJson);
+
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["specVersion"], "1.5");
+        assert_eq!(
+            bom["serialNumber"],
+            "urn:uuid:00000000-0000-0000-0000-000000000000"
+        );
+        assert_eq!(bom["metadata"]["timestamp"], "2024-01-01T00:00:00Z");
+        assert_eq!(bom["metadata"]["component"]["name"], "myenv");
+
+        assert_eq!(
+            bom["components"],
+            json!([{
+                "type": "library",
+                "bom-ref": "pkg:hello_install_id",
+                "name": "hello",
+                "version": "2.12.1",
+                "licenses": [{ "license": { "name": "GPL-3.0-or-later" } }],
+                "purl": "pkg:generic/hello@2.12.1",
+                "externalReferences": [
+                    { "type": "vcs", "url": "https://github.com/flox/nixpkgs?rev=abc" }
+                ],
+                "properties": [
+                    { "name": "flox:install_id", "value": "hello_install_id" },
+                    { "name": "flox:attr_path", "value": "hello" },
+                    { "name": "flox:rev", "value": "abc" },
+                    { "name": "flox:derivation", "value": "derivation" },
+                    { "name": "flox:output:out", "value": "/nix/store/aaaa-hello-2.12.1" },
+                ],
+            }])
+        );
+        assert_eq!(
+            bom["dependencies"],
+            json!([
+                { "ref": "environment", "dependsOn": ["pkg:hello_install_id"] },
+                { "ref": "pkg:hello_install_id", "dependsOn": [] },
+            ])
+        );
+    }
+
+    #[test]
+    fn spdx_document() {
+        let (_, _, mut unfree) = fake_catalog_package_lock("unfree-thing", None);
+        unfree.license = Some("Unfree redistributable".to_string());
+
+        let doc = render(
+            &sbom(vec![hello_package(), unfree.into()]),
+            SbomFormat::SpdxJson,
+        );
+
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["dataLicense"], "CC0-1.0");
+        assert_eq!(doc["SPDXID"], "SPDXRef-DOCUMENT");
+        assert_eq!(doc["creationInfo"]["created"], "2024-01-01T00:00:00Z");
+        assert_eq!(doc["creationInfo"]["creators"], json!(["Tool: flox-1.0.0"]));
+
+        let hello = &doc["packages"][1];
+        assert_eq!(hello["SPDXID"], "SPDXRef-Package-0-hello-install-id");
+        assert_eq!(hello["versionInfo"], "2.12.1");
+        assert_eq!(hello["licenseDeclared"], "GPL-3.0-or-later");
+        assert_eq!(
+            hello["downloadLocation"],
+            "https://github.com/flox/nixpkgs?rev=abc"
+        );
+
+        let unfree = &doc["packages"][2];
+        assert_eq!(
+            unfree["licenseDeclared"],
+            "LicenseRef-Unfree-redistributable"
+        );
+        assert_eq!(unfree["downloadLocation"], "NOASSERTION");
+        assert_eq!(
+            doc["hasExtractedLicensingInfos"],
+            json!([{
+                "licenseId": "LicenseRef-Unfree-redistributable",
+                "name": "Unfree redistributable",
+                "extractedText": "Unfree redistributable",
+            }])
+        );
+
+        assert_eq!(
+            doc["relationships"],
+            json!([
+                {
+                    "spdxElementId": "SPDXRef-DOCUMENT",
+                    "relationshipType": "DESCRIBES",
+                    "relatedSpdxElement": "SPDXRef-environment",
+                },
+                {
+                    "spdxElementId": "SPDXRef-environment",
+                    "relationshipType": "CONTAINS",
+                    "relatedSpdxElement": "SPDXRef-Package-0-hello-install-id",
+                },
+                {
+                    "spdxElementId": "SPDXRef-environment",
+                    "relationshipType": "CONTAINS",
+                    "relatedSpdxElement": "SPDXRef-Package-1-unfree-thing-install-id",
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_path_info_map() {
+        let json = br#"{
+            "/nix/store/aaaa-hello-2.12.1": { "references": ["aaaa-hello-2.12.1", "bbbb-glibc-2.39"] },
+            "/nix/store/bbbb-glibc-2.39": { "references": ["bbbb-glibc-2.39"] }
+        }"#;
+
+        let closure = StoreClosure::from_path_info_json(json).unwrap();
+        assert_eq!(
+            closure,
+            StoreClosure(BTreeMap::from([
+                (
+                    "/nix/store/aaaa-hello-2.12.1".to_string(),
+                    BTreeSet::from(["/nix/store/bbbb-glibc-2.39".to_string()])
+                ),
+                ("/nix/store/bbbb-glibc-2.39".to_string(), BTreeSet::new()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn parse_path_info_list() {
+        let json = br#"[
+            { "path": "/nix/store/aaaa-hello-2.12.1", "references": ["/nix/store/bbbb-glibc-2.39"] },
+            { "path": "/nix/store/bbbb-glibc-2.39", "references": [] }
+        ]"#;
+
+        let closure = StoreClosure::from_path_info_json(json).unwrap();
+        assert_eq!(
+            closure,
+            StoreClosure(BTreeMap::from([
+                (
+                    "/nix/store/aaaa-hello-2.12.1".to_string(),
+                    BTreeSet::from(["/nix/store/bbbb-glibc-2.39".to_string()])
+                ),
+                ("/nix/store/bbbb-glibc-2.39".to_string(), BTreeSet::new()),
+            ]))
+        );
+    }
+
+    /// References to outputs of other packages are recorded as dependencies
+    /// on those packages, other references as store path components.
+    #[test]
+    fn closure_dependencies() {
+        let (_, _, mut glibc) = fake_catalog_package_lock("glibc", None);
+        glibc.outputs =
+            BTreeMap::from([("out".to_string(), "/nix/store/bbbb-glibc-2.39".to_string())]);
+
+        let closure = StoreClosure(BTreeMap::from([
+            (
+                "/nix/store/aaaa-hello-2.12.1".to_string(),
+                BTreeSet::from([
+                    "/nix/store/bbbb-glibc-2.39".to_string(),
+                    "/nix/store/cccc-libiconv-1.17".to_string(),
+                ]),
+            ),
+            ("/nix/store/bbbb-glibc-2.39".to_string(), BTreeSet::new()),
+            ("/nix/store/cccc-libiconv-1.17".to_string(), BTreeSet::new()),
+        ]));
+        let sbom = sbom(vec![hello_package(), glibc.into()]).with_closure(closure);
+
+        let bom = render(&sbom, SbomFormat::CycloneDxJson);
+        assert_eq!(
+            bom["components"][2]["bom-ref"],
+            "/nix/store/cccc-libiconv-1.17"
+        );
+        assert_eq!(bom["components"][2]["name"], "libiconv-1.17");
+        assert_eq!(
+            bom["dependencies"],
+            json!([
+                {
+                    "ref": "environment",
+                    "dependsOn": ["pkg:hello_install_id", "pkg:glibc_install_id"],
+                },
+                {
+                    "ref": "pkg:hello_install_id",
+                    "dependsOn": ["pkg:glibc_install_id", "/nix/store/cccc-libiconv-1.17"],
+                },
+                { "ref": "pkg:glibc_install_id", "dependsOn": [] },
+                { "ref": "/nix/store/cccc-libiconv-1.17", "dependsOn": [] },
+            ])
+        );
+
+        let doc = render(&sbom, SbomFormat::SpdxJson);
+        let depends_on = doc["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|r| r["relationshipType"] == "DEPENDS_ON")
+            .map(|r| {
+                (
+                    r["spdxElementId"].as_str().unwrap(),
+                    r["relatedSpdxElement"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(depends_on, vec![
+            (
+                "SPDXRef-Package-0-hello-install-id",
+                "SPDXRef-Package-1-glibc-install-id"
+            ),
+            (
+                "SPDXRef-Package-0-hello-install-id",
+                "SPDXRef-StorePath-cccc"
+            ),
+        ]);
+    }
+}
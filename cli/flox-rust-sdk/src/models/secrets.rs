@@ -0,0 +1,477 @@
+//! Resolution of references in the `[vars]` section of a manifest.
+//!
+//! Variables can reference values in dotenv files (`{ env-file = [".env"] }`)
+//! or secrets fetched by a command (`{ command = "op read", ref = "op://..." }`).
+//! References are resolved when an environment is activated
+//! and only passed to the shell or command that is activated.
+//! The manifest and lockfile only contain the references,
+//! so resolved values never end up in the lockfile or the built environment.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::{env, fs};
+
+use thiserror::Error;
+use tracing::debug;
+
+use super::manifest::{Manifest, ManifestVariable, ManifestVariables, SecretReference};
+
+#[derive(Debug, Error)]
+pub enum SecretsError {
+    #[error("variable '{0}' must list at least one env file")]
+    NoEnvFile(String),
+
+    #[error("variable '{0}' must set a command to fetch the secret")]
+    NoCommand(String),
+
+    #[error("failed to read env file '{}'", path.display())]
+    ReadEnvFile {
+        path: PathBuf,
+        #[source]
+        err: std::io::Error,
+    },
+
+    #[error("invalid env file '{}' at line {line}: {message}", path.display())]
+    ParseEnvFile {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    #[error("failed to run '{command}' for secret '{name}'")]
+    RunCommand {
+        name: String,
+        command: String,
+        #[source]
+        err: std::io::Error,
+    },
+
+    #[error("'{command}' failed to fetch secret '{name}' ({status})")]
+    CommandFailed {
+        name: String,
+        command: String,
+        status: ExitStatus,
+    },
+}
+
+/// Check that references in `[vars]` and `[target.<system>.vars]` are complete
+pub fn validate(manifest: &Manifest) -> Result<(), SecretsError> {
+    let all_vars = [&manifest.vars]
+        .into_iter()
+        .chain(manifest.target.values().map(|target| &target.vars));
+    for vars in all_vars {
+        for (name, variable) in vars.0.iter() {
+            match variable {
+                ManifestVariable::EnvFile(reference) if reference.env_file.is_empty() => {
+                    return Err(SecretsError::NoEnvFile(name.clone()));
+                },
+                ManifestVariable::Secret(secret) if secret.command.trim().is_empty() => {
+                    return Err(SecretsError::NoCommand(name.clone()));
+                },
+                _ => {},
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolve the references in `vars`.
+///
+/// Env files are read relative to `project_dir`, missing files are skipped.
+/// Variables that are not defined in any of their env files are left unset.
+/// Variables with a value in the manifest are not included in the result.
+pub fn resolve(
+    vars: &ManifestVariables,
+    project_dir: &Path,
+) -> Result<BTreeMap<String, String>, SecretsError> {
+    let mut env_files: HashMap<&str, BTreeMap<String, String>> = HashMap::new();
+    let mut resolved = BTreeMap::new();
+
+    for (name, variable) in vars.0.iter() {
+        match variable {
+            ManifestVariable::Value(_) => {},
+            ManifestVariable::EnvFile(reference) => {
+                let mut value = None;
+                for env_file in reference.env_file.iter() {
+                    if !env_files.contains_key(env_file.as_str()) {
+                        let parsed = read_env_file(&project_dir.join(env_file))?;
+                        env_files.insert(env_file.as_str(), parsed);
+                    }
+                    if let Some(defined) = env_files[env_file.as_str()].get(name) {
+                        value = Some(defined.clone());
+                    }
+                }
+                match value {
+                    Some(value) => {
+                        resolved.insert(name.clone(), value);
+                    },
+                    None => debug!(name, "variable is not defined in any env file"),
+                }
+            },
+            ManifestVariable::Secret(secret) => {
+                resolved.insert(name.clone(), fetch_secret(name, secret)?);
+            },
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Read the variables defined in the env file at `path`,
+/// or no variables if the file doesn't exist
+fn read_env_file(path: &Path) -> Result<BTreeMap<String, String>, SecretsError> {
+    if !path.exists() {
+        debug!(path = %path.display(), "skipping missing env file");
+        return Ok(BTreeMap::new());
+    }
+    let contents = fs::read_to_string(path).map_err(|err| SecretsError::ReadEnvFile {
+        path: path.to_path_buf(),
+        err,
+    })?;
+    let vars =
+        parse_env_file(&contents, |name| env::var(name).ok()).map_err(|(line, message)| {
+            SecretsError::ParseEnvFile {
+                path: path.to_path_buf(),
+                line,
+                message,
+            }
+        })?;
+    Ok(vars.into_iter().collect())
+}
+
+/// Run the command of `secret` and return its output
+/// without the trailing newline.
+///
+/// The command inherits stdin and stderr,
+/// so that it can prompt for authentication.
+fn fetch_secret(name: &str, secret: &SecretReference) -> Result<String, SecretsError> {
+    let script = format!(
+        "{} {}",
+        secret.command,
+        shell_escape::escape(Cow::Borrowed(&secret.reference))
+    );
+    debug!(name, command = %secret.command, "fetching secret");
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| SecretsError::RunCommand {
+            name: name.to_string(),
+            command: secret.command.clone(),
+            err,
+        })?;
+
+    if !output.status.success() {
+        return Err(SecretsError::CommandFailed {
+            name: name.to_string(),
+            command: secret.command.clone(),
+            status: output.status,
+        });
+    }
+
+    let value = String::from_utf8_lossy(&output.stdout);
+    let value = value
+        .strip_suffix('\n')
+        .map(|value| value.strip_suffix('\r').unwrap_or(value))
+        .unwrap_or(&value);
+    Ok(value.to_string())
+}
+
+/// Parse the contents of a dotenv file.
+///
+/// Supports `KEY=value` lines, optionally prefixed with `export`,
+/// `#` comments, and single or double quoted values.
+/// Unquoted and double quoted values expand `$NAME`, `${NAME}` and `${NAME:-default}`,
+/// looking up variables defined earlier in the file before calling `lookup`.
+///
+/// Errors are returned as the line number and a description of the error.
+pub fn parse_env_file(
+    contents: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<(String, String)>, (usize, String)> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+
+        let Some((name, value)) = line.split_once('=') else {
+            return Err((line_number, "expected 'NAME=value'".to_string()));
+        };
+        let name = name.trim();
+        if !is_valid_name(name) {
+            return Err((line_number, format!("invalid variable name '{name}'")));
+        }
+
+        let lookup = |name: &str| {
+            vars.iter()
+                .rev()
+                .find(|(defined, _)| defined == name)
+                .map(|(_, value)| value.clone())
+                .or_else(|| lookup(name))
+        };
+
+        let value = value.trim();
+        let value = if let Some(quoted) = value.strip_prefix('\'') {
+            let Some((value, _)) = quoted.split_once('\'') else {
+                return Err((line_number, "unterminated single quote".to_string()));
+            };
+            value.to_string()
+        } else if let Some(quoted) = value.strip_prefix('"') {
+            let Some(end) = closing_double_quote(quoted) else {
+                return Err((line_number, "unterminated double quote".to_string()));
+            };
+            expand(&quoted[..end], true, &lookup).map_err(|message| (line_number, message))?
+        } else {
+            let value = match value.find(" #") {
+                Some(comment) => value[..comment].trim_end(),
+                None => value,
+            };
+            expand(value, false, &lookup).map_err(|message| (line_number, message))?
+        };
+
+        vars.push((name.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+/// The index of the first double quote in `s` that is not escaped by a backslash
+fn closing_double_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in s.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(index),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Expand `$NAME`, `${NAME}` and `${NAME:-default}` in `value`.
+///
+/// Undefined variables expand to an empty string.
+/// If `escapes` is set, `\n`, `\"`, `\\` and `\$` are unescaped.
+fn expand(
+    value: &str,
+    escapes: bool,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut chars = value.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if escapes => match chars.next() {
+                Some((_, 'n')) => expanded.push('\n'),
+                Some((_, c @ ('"' | '\\' | '$'))) => expanded.push(c),
+                Some((_, c)) => {
+                    expanded.push('\\');
+                    expanded.push(c);
+                },
+                None => expanded.push('\\'),
+            },
+            '$' => match chars.peek() {
+                Some((_, '{')) => {
+                    let start = index + 2;
+                    let Some(length) = value[start..].find('}') else {
+                        return Err("unterminated '${'".to_string());
+                    };
+                    let expression = &value[start..start + length];
+                    let (name, default) = match expression.split_once(":-") {
+                        Some((name, default)) => (name, Some(default)),
+                        None => (expression, None),
+                    };
+                    if !is_valid_name(name) {
+                        return Err(format!("invalid variable name '{name}'"));
+                    }
+                    match (lookup(name).filter(|value| !value.is_empty()), default) {
+                        (Some(value), _) => expanded.push_str(&value),
+                        (None, Some(default)) => {
+                            expanded.push_str(&expand(default, escapes, lookup)?)
+                        },
+                        (None, None) => {},
+                    }
+                    while chars.next_if(|(i, _)| *i < start + length + 1).is_some() {}
+                },
+                Some((_, c)) if c.is_ascii_alphabetic() || *c == '_' => {
+                    let mut name = String::new();
+                    while let Some((_, c)) =
+                        chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                    {
+                        name.push(c);
+                    }
+                    expanded.push_str(&lookup(&name).unwrap_or_default());
+                },
+                _ => expanded.push('$'),
+            },
+            c => expanded.push(c),
+        }
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn parse(contents: &str) -> Vec<(String, String)> {
+        parse_env_file(contents, |name| match name {
+            "HOME" => Some("/home/user".to_string()),
+            _ => None,
+        })
+        .unwrap()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_env_file() {
+        let vars = parse(indoc! {r#"
+            # comment
+            PLAIN=value
+            export EXPORTED = spaced value # comment
+            SINGLE='$HOME stays'
+            DOUBLE="line\nbreak \"quoted\" \$HOME"
+            EMPTY=
+        "#});
+        assert_eq!(
+            vars,
+            pairs(&[
+                ("PLAIN", "value"),
+                ("EXPORTED", "spaced value"),
+                ("SINGLE", "$HOME stays"),
+                ("DOUBLE", "line\nbreak \"quoted\" $HOME"),
+                ("EMPTY", ""),
+            ])
+        );
+    }
+
+    #[test]
+    fn expands_variables() {
+        let vars = parse(indoc! {r#"
+            DIR=$HOME/project
+            DATA="${DIR}/data"
+            PORT=${PORT:-8080}
+            URL="http://${HOST:-localhost}:${PORT}"
+            MISSING=${MISSING}
+        "#});
+        assert_eq!(
+            vars,
+            pairs(&[
+                ("DIR", "/home/user/project"),
+                ("DATA", "/home/user/project/data"),
+                ("PORT", "8080"),
+                ("URL", "http://localhost:8080"),
+                ("MISSING", ""),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        let lookup = |_: &str| None;
+        assert_eq!(parse_env_file("A=1\nNOPE", lookup).unwrap_err().0, 2);
+        assert_eq!(parse_env_file("1A=1", lookup).unwrap_err().0, 1);
+        assert_eq!(parse_env_file("A=\"open", lookup).unwrap_err().0, 1);
+        assert_eq!(parse_env_file("A=${OPEN", lookup).unwrap_err().0, 1);
+    }
+
+    #[test]
+    fn validate_rejects_empty_references() {
+        let manifest: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+
+            [vars]
+            TOKEN = { env-file = [] }
+        "#})
+        .unwrap();
+        assert!(matches!(
+            validate(&manifest),
+            Err(SecretsError::NoEnvFile(name)) if name == "TOKEN"
+        ));
+
+        let manifest: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+
+            [target.x86_64-linux.vars]
+            TOKEN = { command = " ", ref = "op://dev/token" }
+        "#})
+        .unwrap();
+        assert!(matches!(
+            validate(&manifest),
+            Err(SecretsError::NoCommand(name)) if name == "TOKEN"
+        ));
+    }
+
+    #[test]
+    fn resolves_env_files_and_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".env"), "A=env\nB=env\n").unwrap();
+        fs::write(dir.path().join(".env.local"), "X=x\nB=\"${X}-local\"\n").unwrap();
+
+        let manifest: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+
+            [vars]
+            PLAIN = "${PLAIN:-default}"
+            A = { env-file = [".env", ".env.local"] }
+            B = { env-file = [".env", ".env.local", ".env.missing"] }
+            UNSET = { env-file = [".env"] }
+            C = { command = 'printf "%s\n"', ref = "it's secret" }
+        "#})
+        .unwrap();
+
+        let resolved = resolve(&manifest.vars, dir.path()).unwrap();
+        assert_eq!(
+            resolved,
+            BTreeMap::from_iter(pairs(&[
+                ("A", "env"),
+                ("B", "x-local"),
+                ("C", "it's secret")
+            ]))
+        );
+    }
+
+    #[test]
+    fn command_failure_is_an_error() {
+        let manifest: Manifest = toml::from_str(indoc! {r#"
+            version = 1
+
+            [vars]
+            TOKEN = { command = "false", ref = "anything" }
+        "#})
+        .unwrap();
+
+        assert!(matches!(
+            resolve(&manifest.vars, Path::new("/")),
+            Err(SecretsError::CommandFailed { name, .. }) if name == "TOKEN"
+        ));
+    }
+}
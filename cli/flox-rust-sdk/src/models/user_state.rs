@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use flox_core::{serialize_atomically, traceable_path, SerializeError};
@@ -5,7 +6,7 @@ use fslock::LockFile;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use crate::flox::Flox;
+use crate::flox::{EnvironmentRef, Flox};
 
 pub const USER_STATE_FILENAME: &str = "user_state.json";
 
@@ -26,6 +27,20 @@ pub enum UserStateError {
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct UserState {
     pub confirmed_create_default_env: Option<bool>,
+    /// Short names for environments,
+    /// that can be used to select an environment instead of its path or remote
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment_aliases: BTreeMap<String, EnvironmentAlias>,
+}
+
+/// The environment an alias refers to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvironmentAlias {
+    /// The directory containing the environment's `.flox` directory
+    Path(PathBuf),
+    /// A remote environment
+    Remote(EnvironmentRef),
 }
 
 // TODO: These functions are very close to their counterparts in
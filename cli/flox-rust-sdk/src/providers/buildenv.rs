@@ -6,6 +6,7 @@ use std::process::Command;
 use std::sync::LazyLock;
 
 use flox_core::canonical_path::CanonicalPath;
+use itertools::Itertools;
 use pollster::FutureExt as _;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -88,6 +89,11 @@ pub enum BuildEnvError {
     #[error("Unexpected error accessing cache: {0}")]
     CacheError(String),
 
+    /// An error that occurred while writing the lockfile
+    /// with the overrides for the current system applied.
+    #[error("Failed to write lockfile for the current system")]
+    WriteEffectiveLockfile(#[source] std::io::Error),
+
     /// An error that occurred while calling nix build.
     #[error("Failed to call 'nix build'")]
     CallNixBuild(#[source] std::io::Error),
@@ -261,8 +267,10 @@ impl BuildEnvNix {
     ///    i.e. `legacyPackages.<locked system>.<attr_path>`,
     ///    as [LockedPackageCatalog::attr_path] is incomplete.
     /// 3. building the package with essentially
-    ///    `nix build <flox-nixpkgs-url>#<resolved attr path>^*`,
+    ///    `nix build <flox-nixpkgs-url>#<resolved attr path>^<locked outputs>`,
     ///    which will realise the locked output paths.
+    ///    Locked packages only contain the outputs selected by the `outputs`
+    ///    of their descriptor, if any, so other outputs are not built.
     ///    We set `--option pure-eval true` to improve reproducibility
     ///    of the locked outputs, and allow the use of the eval-cache
     ///    to avoid costly re-evaluations.
@@ -307,8 +315,12 @@ impl BuildEnvNix {
                 todo!("Building published packages is not yet supported");
             }
 
-            // build all out paths
-            let attrpath = format!("legacyPackages.{}.{}^*", locked.system, locked.attr_path);
+            // build all locked out paths
+            let outputs = locked.outputs.keys().join(",");
+            let attrpath = format!(
+                "legacyPackages.{}.{}^{outputs}",
+                locked.system, locked.attr_path
+            );
 
             format!("{}#{}", locked_url, attrpath)
         };
@@ -585,7 +597,29 @@ impl BuildEnv for BuildEnvNix {
         // This will prevent failures due to e.g. non-deterministic,
         // non-sandboxed manifest builds which may produce different store paths,
         // than previously locked in the lockfile.
-        self.realise_lockfile(client, &lockfile, &env!("NIX_TARGET_SYSTEM").to_string())?;
+        let system = env!("NIX_TARGET_SYSTEM").to_string();
+        self.realise_lockfile(client, &lockfile, &system)?;
+
+        // Overrides in `[target.<system>]` are merged before building,
+        // so that `buildenv.nix` and the activation scripts
+        // only ever see the effective vars, hooks and profile scripts.
+        // Lockfiles without overrides are built as they are.
+        let effective_lockfile = if lockfile.manifest.target.is_empty() {
+            None
+        } else {
+            let effective = Lockfile {
+                manifest: lockfile.manifest.for_system(&system),
+                ..lockfile
+            };
+            let mut file =
+                tempfile::NamedTempFile::new().map_err(BuildEnvError::WriteEffectiveLockfile)?;
+            serde_json::to_writer_pretty(&mut file, &effective)
+                .map_err(|e| BuildEnvError::WriteEffectiveLockfile(e.into()))?;
+            Some(file)
+        };
+        let lockfile_path = effective_lockfile
+            .as_ref()
+            .map_or(lockfile_path, |file| file.path());
 
         // Build the lockfile by evaluating and building the `buildenv.nix` expression.
         let outputs = self.call_buildenv_nix(lockfile_path, service_config_path)?;
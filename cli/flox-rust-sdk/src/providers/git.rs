@@ -381,6 +381,23 @@ impl GitCommandProvider {
         }
     }
 
+    /// Return the best common ancestor of two revisions,
+    /// or [None] if their histories are unrelated
+    pub fn merge_base(&self, rev_a: &str, rev_b: &str) -> Result<Option<String>, GitCommandError> {
+        let result = GitCommandProvider::run_command(
+            self.new_command().arg("merge-base").arg(rev_a).arg(rev_b),
+        );
+        match result {
+            Ok(hash) => Ok(Some(hash.to_string_lossy().trim().to_string())),
+            Err(GitCommandError::BadExit(1, stdout, stderr))
+                if stdout.is_empty() && stderr.is_empty() =>
+            {
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
     /// Create branch at a specified revision
     pub fn create_branch(&self, name: &str, rev: &str) -> Result<(), GitCommandError> {
         GitCommandProvider::run_command(self.new_command().arg("branch").arg(name).arg(rev))?;
@@ -1306,6 +1323,25 @@ pub mod tests {
         assert!(!repo.branch_contains_commit("XXX", "branch_1").unwrap());
     }
 
+    #[test]
+    fn test_merge_base() {
+        let (repo, _tempdir_handle) = init_temp_repo(false);
+        repo.checkout("branch_1", true).unwrap();
+        commit_file(&repo, "dummy");
+        let base = repo.branch_hash("branch_1").unwrap();
+        repo.create_branch("branch_2", "branch_1").unwrap();
+        commit_file(&repo, "dummy_1");
+
+        repo.checkout("branch_2", false).unwrap();
+        commit_file(&repo, "dummy_2");
+
+        assert_eq!(repo.merge_base("branch_1", "branch_2").unwrap(), Some(base));
+
+        repo.checkout("branch_3", true).unwrap();
+        commit_file(&repo, "dummy_3");
+        assert_eq!(repo.merge_base("branch_1", "branch_3").unwrap(), None);
+    }
+
     #[test]
     fn test_status_no_head() {
         let (repo, _tempdir_handle) = init_temp_repo(false);
@@ -1,8 +1,11 @@
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::process::ExitCode;
+use std::process::{Command, ExitCode, Stdio};
 use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
 use clap::Parser;
@@ -12,6 +15,7 @@ use flox_core::activations::{
     read_activations_json,
     write_activations_json,
 };
+use flox_core::watchdog_status::{watchdog_socket_path, WatchdogStatus};
 use flox_rust_sdk::flox::FLOX_VERSION_STRING;
 use flox_rust_sdk::providers::services::process_compose_down;
 use flox_rust_sdk::utils::{maybe_traceable_path, traceable_path};
@@ -20,16 +24,21 @@ use nix::libc::{SIGINT, SIGQUIT, SIGTERM, SIGUSR1};
 use nix::unistd::{getpgid, getpid, setsid};
 use process::{LockedActivations, PidWatcher, WaitResult};
 use sentry::init_sentry;
-use tracing::{debug, error, info, instrument};
+use status::{spawn_status_server, SharedStatus};
+use tracing::{debug, error, info, instrument, warn};
 
 use crate::process::Watcher;
 
 mod logger;
 mod process;
 mod sentry;
+mod status;
 
 type Error = anyhow::Error;
 
+/// How long `hook.on-deactivate` may run before it is killed
+const ON_DEACTIVATE_HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
 const SHORT_HELP: &str = "Monitors activation lifecycle to perform cleanup.";
 const LONG_HELP: &str = "Monitors activation lifecycle to perform cleanup.
 
@@ -68,6 +77,14 @@ pub struct Cli {
     /// Disable metric reporting
     #[arg(long)]
     pub disable_metrics: bool,
+
+    /// Seconds to wait after all PIDs terminated before cleaning up
+    #[arg(long = "linger", value_name = "SECONDS", default_value_t = 0)]
+    pub linger_secs: u64,
+
+    /// The shell used to run the environment's `hook.on-deactivate` script
+    #[arg(long, value_name = "PATH", default_value = "bash")]
+    pub hook_shell: PathBuf,
 }
 
 fn main() -> ExitCode {
@@ -129,12 +146,34 @@ fn run_inner(
 ) -> Result<(), Error> {
     let activations_json_path = activations_json_path(&args.runtime_dir, &args.flox_env);
 
+    let activation_state_dir =
+        activation_state_dir_path(&args.runtime_dir, &args.flox_env, &args.activation_id)?;
+
+    let status = SharedStatus::new(Mutex::new(WatchdogStatus {
+        pid: getpid().as_raw(),
+        flox_env: args.flox_env.clone(),
+        activation_id: args.activation_id.clone(),
+        services_socket: args.socket_path.clone(),
+        ..Default::default()
+    }));
+    // The status socket is only used for observability,
+    // so failing to serve it shouldn't prevent cleanup.
+    if let Err(err) = spawn_status_server(
+        watchdog_socket_path(&activation_state_dir),
+        status.clone(),
+        should_clean_up.clone(),
+    ) {
+        warn!(%err, "failed to serve watchdog status");
+    }
+
     let mut watcher = PidWatcher::new(
         activations_json_path.clone(),
         args.activation_id.clone(),
         should_terminate,
         should_clean_up,
-    );
+    )
+    .with_linger(Duration::from_secs(args.linger_secs))
+    .with_status(status);
 
     debug!(
         path = traceable_path(&args.socket_path),
@@ -154,9 +193,6 @@ fn run_inner(
 
     debug!("waiting for termination");
 
-    let activation_state_dir =
-        activation_state_dir_path(&args.runtime_dir, &args.flox_env, &args.activation_id)?;
-
     match watcher.wait_for_termination() {
         Ok(WaitResult::CleanUp(locked_activations)) => {
             // Exit
@@ -164,6 +200,7 @@ fn run_inner(
             cleanup(
                 locked_activations,
                 &args.socket_path,
+                &args.hook_shell,
                 &activations_json_path,
                 &activation_state_dir,
                 &args.activation_id,
@@ -185,6 +222,7 @@ fn run_inner(
             let _ = cleanup(
                 (activations_json, lock),
                 &args.socket_path,
+                &args.hook_shell,
                 &activations_json_path,
                 &activation_state_dir,
                 &args.activation_id,
@@ -202,18 +240,59 @@ fn run_inner(
 fn cleanup(
     locked_activations: LockedActivations,
     socket_path: impl AsRef<Path>,
+    hook_shell: impl AsRef<Path>,
     activations_json_path: impl AsRef<Path>,
     activation_state_dir_path: impl AsRef<Path>,
     activation_id: impl AsRef<str>,
 ) -> Result<()> {
     debug!("running cleanup");
 
-    let (mut activations_json, lock) = locked_activations;
-    activations_json.remove_activation(activation_id);
+    let activation_id = activation_id.as_ref();
+    let (activations_json, lock) = locked_activations;
+    let store_path = activations_json.store_path_for_id(activation_id);
 
     // Even if this activation has no more attached PIDs, there may be other
     // activations for a different build of the same environment
-    if activations_json.is_empty() {
+    let last_activation = !activations_json.has_other_activations(activation_id);
+
+    // Release the lock while the hook runs, so that it can't block new activations.
+    // Since this activation is removed from the registry,
+    // new activations won't attach to it but start a new activation instead.
+    match activations_json.check_version() {
+        Ok(mut activations_json) => {
+            activations_json.remove_activation(activation_id);
+            write_activations_json(&activations_json, &activations_json_path, lock)?;
+        },
+        // Don't rewrite files we can't parse, e.g. written by a newer version of flox
+        Err(unsupported) => {
+            warn!(
+                version = ?unsupported.version,
+                "not removing activation from activations.json with unsupported version"
+            );
+            drop(lock);
+        },
+    }
+
+    if let (true, Some(store_path)) = (last_activation, store_path) {
+        if let Err(err) = run_on_deactivate_hook(hook_shell, store_path, ON_DEACTIVATE_HOOK_TIMEOUT)
+        {
+            error!(%err, "failed to run hook.on-deactivate");
+        }
+    }
+
+    // We want to hold the lock until
+    // - services are cleaned up
+    // - activation state dir is removed, otherwise the removal could occur
+    //   after a newly started activation has already put files in activation
+    //   state dir
+    let (activations_json, lock) = read_activations_json(&activations_json_path)?;
+
+    // A new activation may have been started while the hook was running,
+    // in which case it takes over the services.
+    let still_last_activation = activations_json.as_ref().map_or(true, |activations_json| {
+        !activations_json.has_other_activations(activation_id)
+    });
+    if last_activation && still_last_activation {
         let socket_path = socket_path.as_ref();
         if socket_path.exists() {
             if let Err(err) = process_compose_down(socket_path) {
@@ -227,18 +306,88 @@ fn cleanup(
     fs::remove_dir_all(activation_state_dir_path)
         .context("couldn't remove activations state dir")?;
 
-    // We want to hold the lock until
-    // - services are cleaned up
-    // - activation state dir is removed, otherwise the removal could occur
-    //   after a newly started activation has already put files in activation
-    //   state dir
-    write_activations_json(&activations_json, activations_json_path, lock)?;
+    drop(lock);
 
     debug!("finished cleanup");
 
     Ok(())
 }
 
+/// Run the `hook.on-deactivate` script of the environment built at `store_path`,
+/// if the environment defines one.
+///
+/// The hook runs in the environment the watchdog was started with,
+/// i.e. with the variables of the activation but without `hook.on-activate` applied.
+/// If the hook doesn't finish within `timeout`, it is killed.
+fn run_on_deactivate_hook(
+    hook_shell: impl AsRef<Path>,
+    store_path: impl AsRef<Path>,
+    timeout: Duration,
+) -> Result<()> {
+    let hook_path = store_path
+        .as_ref()
+        .join("activate.d")
+        .join("hook-on-deactivate");
+    if !hook_path.exists() {
+        debug!(reason = "no hook", "did not run hook.on-deactivate");
+        return Ok(());
+    }
+
+    info!(
+        hook = traceable_path(&hook_path),
+        "running hook.on-deactivate"
+    );
+    let mut child = Command::new(hook_shell.as_ref())
+        .arg(&hook_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run hook.on-deactivate")?;
+
+    // Read the output in the background,
+    // so that a hook writing a lot of output doesn't block on a full pipe
+    let stdout = read_to_string_in_background(child.stdout.take());
+    let stderr = read_to_string_in_background(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "hook.on-deactivate did not finish within {} seconds",
+                timeout.as_secs_f32()
+            );
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    debug!(
+        stdout = %stdout.join().unwrap_or_default(),
+        stderr = %stderr.join().unwrap_or_default(),
+        "hook.on-deactivate output"
+    );
+    if !status.success() {
+        bail!("hook.on-deactivate exited with {}", status);
+    }
+    Ok(())
+}
+
+/// Read `reader` to the end on a separate thread
+fn read_to_string_in_background(reader: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut reader) = reader {
+            let _ = reader.read_to_end(&mut output);
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    })
+}
+
 /// We want to make sure that the watchdog is detached from the terminal in case it sends
 /// any signals to the activation. A terminal sends signals to all processes in a process group,
 /// and we want to make sure that the watchdog is in its own process group to avoid receiving any
@@ -268,6 +417,112 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn cleanup_runs_on_deactivate_hook() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let runtime_dir = temp_dir.path().join("runtime");
+        let flox_env = PathBuf::from("flox_env");
+        let store_path = temp_dir.path().join("store_path");
+        let marker = temp_dir.path().join("deactivated");
+
+        let proc = start_process();
+        let pid = proc.id() as i32;
+        let start_or_attach = StartOrAttachArgs {
+            pid,
+            flox_env: flox_env.clone(),
+            store_path: store_path.to_string_lossy().to_string(),
+        };
+        let activation_id = start_or_attach.handle(&runtime_dir).unwrap();
+        stop_process(proc);
+
+        let activations_json_path = activations_json_path(&runtime_dir, &flox_env);
+        let activation_state_dir =
+            activation_state_dir_path(&runtime_dir, &flox_env, &activation_id).unwrap();
+        fs::create_dir_all(&activation_state_dir).unwrap();
+
+        // The hook only creates the marker if the activation has already been
+        // removed from the registry, i.e. the lock was released before running it
+        fs::create_dir_all(store_path.join("activate.d")).unwrap();
+        fs::write(
+            store_path.join("activate.d").join("hook-on-deactivate"),
+            format!(
+                "grep -q {activation_id} {} || touch {}",
+                activations_json_path.display(),
+                marker.display()
+            ),
+        )
+        .unwrap();
+
+        let (activations_json, lock) = read_activations_json(&activations_json_path).unwrap();
+        cleanup(
+            (activations_json.unwrap(), lock),
+            "/does_not_exist",
+            "bash",
+            &activations_json_path,
+            &activation_state_dir,
+            &activation_id,
+        )
+        .unwrap();
+
+        assert!(marker.exists(), "hook.on-deactivate should have run");
+    }
+
+    #[test]
+    fn cleanup_keeps_activations_of_unsupported_version() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let activations_json_path = temp_dir.path().join("activations.json");
+        let activation_state_dir = temp_dir.path().join("state");
+        fs::create_dir_all(&activation_state_dir).unwrap();
+
+        let contents = serde_json::json!({
+            "version": u8::MAX,
+            "activations": [{ "id": "1", "store_path": "/does_not_exist" }],
+        })
+        .to_string();
+        fs::write(&activations_json_path, &contents).unwrap();
+
+        let (activations_json, lock) = read_activations_json(&activations_json_path).unwrap();
+        cleanup(
+            (activations_json.unwrap(), lock),
+            "/does_not_exist",
+            "bash",
+            &activations_json_path,
+            &activation_state_dir,
+            "1",
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&activations_json_path).unwrap(),
+            contents
+        );
+        assert!(!activation_state_dir.exists());
+    }
+
+    #[test]
+    fn on_deactivate_hook_is_killed_after_timeout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("store_path");
+        let marker = temp_dir.path().join("deactivated");
+
+        fs::create_dir_all(store_path.join("activate.d")).unwrap();
+        fs::write(
+            store_path.join("activate.d").join("hook-on-deactivate"),
+            format!("sleep 5; touch {}", marker.display()),
+        )
+        .unwrap();
+
+        let start = Instant::now();
+        let result = run_on_deactivate_hook("bash", &store_path, Duration::from_millis(100));
+
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(
+            !marker.exists(),
+            "hook.on-deactivate should have been killed"
+        );
+    }
+
     #[test]
     fn cleanup_removes_activation() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -309,6 +564,8 @@ mod test {
             socket_path: PathBuf::from("/does_not_exist"),
             log_dir: Some(log_dir.to_path_buf()),
             disable_metrics: true,
+            linger_secs: 0,
+            hook_shell: PathBuf::from("bash"),
         };
 
         let (terminate_flag, cleanup_flag) = shutdown_flags();
@@ -12,14 +12,16 @@ use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Result};
-use flox_core::activations::{read_activations_json, Activations, AttachedPid, UncheckedVersion};
+use flox_core::activations::{read_activations_json, AttachedPid, UncheckedActivations};
 use flox_core::proc_status::pid_is_running;
 use fslock::LockFile;
 use time::OffsetDateTime;
 use tracing::trace;
+
+use crate::status::SharedStatus;
 /// How long to wait between watcher updates.
 pub const WATCHER_SLEEP_INTERVAL: Duration = Duration::from_millis(100);
 
@@ -27,8 +29,12 @@ type Error = anyhow::Error;
 
 /// A deserialized activations.json together with a lock preventing it from
 /// being modified
+///
+/// The activations are not checked against the latest version,
+/// so that the watchdog keeps working after a newer version of flox
+/// upgraded activations.json.
 /// TODO: there's probably a cleaner way to do this
-pub type LockedActivations = (Activations<UncheckedVersion>, LockFile);
+pub type LockedActivations = (UncheckedActivations, LockFile);
 
 #[derive(Debug)]
 pub enum WaitResult {
@@ -54,6 +60,13 @@ pub struct PidWatcher {
     activations_json_path: PathBuf,
     should_terminate_flag: Arc<AtomicBool>,
     should_clean_up_flag: Arc<AtomicBool>,
+    /// How long to keep watching after all PIDs terminated,
+    /// before cleanup is performed.
+    linger: Duration,
+    /// When the watcher last noticed that all PIDs terminated
+    all_terminated_at: Option<Instant>,
+    /// Status reported by the status server, updated on every poll
+    status: SharedStatus,
 }
 
 impl PidWatcher {
@@ -71,9 +84,25 @@ impl PidWatcher {
             activation_id,
             should_terminate_flag,
             should_clean_up_flag,
+            linger: Duration::ZERO,
+            all_terminated_at: None,
+            status: SharedStatus::default(),
         }
     }
 
+    /// Report the watched PIDs and polling progress to `status`.
+    pub fn with_status(mut self, status: SharedStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Keep watching for `linger` after all PIDs terminated,
+    /// so that PIDs attaching in the meantime prevent the cleanup.
+    pub fn with_linger(mut self, linger: Duration) -> Self {
+        self.linger = linger;
+        self
+    }
+
     /// Removes any PIDs that are no longer running from the watchlist.
     fn prune_terminations(&mut self) {
         let now = OffsetDateTime::now_utc();
@@ -137,28 +166,50 @@ impl Watcher for PidWatcher {
             None
         };
 
-        let Some(activation) = activations_json.activation_for_id_ref(&self.activation_id) else {
+        let Some(attached_pids) = activations_json.attached_pids_for_id(&self.activation_id) else {
             bail!("watchdog shouldn't be running with ID that isn't in activations.json");
         };
 
-        let all_attached_pids: HashSet<AttachedPid> = activation
-            .attached_pids()
-            .iter()
-            .map(AttachedPid::to_owned)
-            .collect();
+        let all_attached_pids: HashSet<AttachedPid> = attached_pids.into_iter().collect();
         // Add all PIDs, even if they're dead, but then immediately remove them
         self.pids_watching.extend(all_attached_pids);
         self.prune_terminations();
+        if self.pids_watching.is_empty() {
+            self.all_terminated_at.get_or_insert_with(Instant::now);
+        } else {
+            self.all_terminated_at = None;
+        }
+
+        let mut status = self.status.lock().expect("status lock is not poisoned");
+        status.watched_pids = self
+            .pids_watching
+            .iter()
+            .map(|attached_pid| attached_pid.pid)
+            .collect();
+        status.watched_pids.sort();
+        status.last_poll = Some(OffsetDateTime::now_utc());
+        status.pending_cleanup = self.pids_watching.is_empty()
+            || self
+                .should_clean_up_flag
+                .load(std::sync::atomic::Ordering::SeqCst);
+
         Ok(maybe_locked_activations)
     }
 
-    /// Returns true if the watcher is not currently watching any PIDs.
+    /// Returns true if the watcher is not currently watching any PIDs,
+    /// and hasn't been for the configured linger duration.
     fn should_clean_up(&self) -> Result<bool, super::Error> {
-        let should_clean_up = self.pids_watching.is_empty();
-        if !should_clean_up {
+        if !self.pids_watching.is_empty() {
             trace!("still watching PIDs {:?}", self.pids_watching);
+            return Ok(false);
+        }
+        let lingering = self
+            .all_terminated_at
+            .is_some_and(|terminated_at| terminated_at.elapsed() < self.linger);
+        if lingering {
+            trace!("all PIDs terminated, lingering before cleanup");
         }
-        Ok(should_clean_up)
+        Ok(!lingering)
     }
 }
 
@@ -467,6 +518,47 @@ pub mod test {
         assert!(watcher.should_clean_up().unwrap());
     }
 
+    #[test]
+    fn lingers_before_clean_up() {
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let flox_env = PathBuf::from("flox_env");
+        let store_path = "store_path".to_string();
+
+        let proc = start_process();
+        let pid = proc.id() as i32;
+        let start_or_attach = StartOrAttachArgs {
+            pid,
+            flox_env: flox_env.clone(),
+            store_path: store_path.clone(),
+        };
+        let activation_id = start_or_attach.handle(runtime_dir.path()).unwrap();
+
+        let activations_json_path = activations_json_path(&runtime_dir, &flox_env);
+        let (terminate_flag, cleanup_flag) = shutdown_flags();
+        let linger = Duration::from_millis(500);
+        let mut watcher = PidWatcher::new(
+            activations_json_path,
+            activation_id,
+            terminate_flag,
+            cleanup_flag,
+        )
+        .with_linger(linger);
+
+        watcher.update_watchlist(false).unwrap();
+        assert!(!watcher.should_clean_up().unwrap());
+
+        stop_process(proc);
+        watcher.update_watchlist(false).unwrap();
+        assert!(
+            !watcher.should_clean_up().unwrap(),
+            "should linger after all PIDs terminated"
+        );
+
+        std::thread::sleep(linger);
+        watcher.update_watchlist(false).unwrap();
+        assert!(watcher.should_clean_up().unwrap());
+    }
+
     #[test]
     fn terminates_on_shutdown_flag() {
         let runtime_dir = tempfile::tempdir().unwrap();
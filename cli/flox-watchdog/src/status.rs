@@ -0,0 +1,126 @@
+//! Serves the status of the watchdog on a Unix socket.
+//!
+//! See [flox_core::watchdog_status] for the protocol.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use flox_core::watchdog_status::{
+    check_watchdog_socket_path,
+    WatchdogRequest,
+    WatchdogResponse,
+    WatchdogStatus,
+};
+use flox_rust_sdk::utils::traceable_path;
+use tracing::{debug, warn};
+
+/// How long to wait for a client to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The status of the watchdog shared between the watcher and the status server
+pub type SharedStatus = Arc<Mutex<WatchdogStatus>>;
+
+/// Listen on `socket_path` and answer requests in a background thread.
+///
+/// A [WatchdogRequest::Shutdown] sets `should_clean_up`,
+/// which has the same effect as sending `SIGUSR1` to the watchdog.
+/// The socket is removed along with the activation state dir during cleanup.
+pub fn spawn_status_server(
+    socket_path: impl AsRef<Path>,
+    status: SharedStatus,
+    should_clean_up: Arc<AtomicBool>,
+) -> Result<()> {
+    let socket_path = socket_path.as_ref();
+    check_watchdog_socket_path(socket_path)?;
+    // A socket left behind by a watchdog that didn't clean up
+    // would prevent binding the socket.
+    if socket_path.exists() {
+        std::fs::remove_file(socket_path).context("failed to remove stale watchdog socket")?;
+    }
+    let listener = UnixListener::bind(socket_path).context("failed to bind watchdog socket")?;
+    debug!(
+        socket = traceable_path(socket_path),
+        "serving watchdog status"
+    );
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream
+                .context("failed to accept connection")
+                .and_then(|stream| handle_connection(stream, &status, &should_clean_up));
+            if let Err(err) = result {
+                warn!(%err, "failed to handle watchdog status request");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_connection(
+    stream: UnixStream,
+    status: &Mutex<WatchdogStatus>,
+    should_clean_up: &AtomicBool,
+) -> Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+
+    let response = match serde_json::from_str::<WatchdogRequest>(&request) {
+        Ok(WatchdogRequest::Status) => {
+            let status = status.lock().expect("status lock is not poisoned").clone();
+            WatchdogResponse::Status(status)
+        },
+        Ok(WatchdogRequest::Shutdown) => {
+            debug!("shutdown requested via watchdog socket");
+            should_clean_up.store(true, Ordering::SeqCst);
+            status
+                .lock()
+                .expect("status lock is not poisoned")
+                .pending_cleanup = true;
+            WatchdogResponse::ShuttingDown
+        },
+        Err(err) => WatchdogResponse::Error {
+            message: err.to_string(),
+        },
+    };
+
+    let mut response = serde_json::to_string(&response)?;
+    response.push('\n');
+    (&stream).write_all(response.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_core::watchdog_status::query_watchdog;
+
+    use super::*;
+
+    #[test]
+    fn serves_status_and_accepts_shutdown() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("watchdog.sock");
+        let status = SharedStatus::default();
+        status.lock().unwrap().watched_pids = vec![123];
+        let should_clean_up = Arc::new(AtomicBool::new(false));
+
+        spawn_status_server(&socket_path, status.clone(), should_clean_up.clone()).unwrap();
+
+        let WatchdogResponse::Status(reported) =
+            query_watchdog(&socket_path, &WatchdogRequest::Status).unwrap()
+        else {
+            panic!("expected status response");
+        };
+        assert_eq!(reported.watched_pids, vec![123]);
+
+        let response = query_watchdog(&socket_path, &WatchdogRequest::Shutdown).unwrap();
+        assert_eq!(response, WatchdogResponse::ShuttingDown);
+        assert!(should_clean_up.load(Ordering::SeqCst));
+        assert!(status.lock().unwrap().pending_cleanup);
+    }
+}
@@ -1,5 +1,5 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 use std::io::stdout;
 use std::os::unix::process::CommandExt;
@@ -30,6 +30,7 @@ use flox_rust_sdk::models::environment::{
     FLOX_PROMPT_ENVIRONMENTS_VAR,
     FLOX_SERVICES_SOCKET_VAR,
 };
+use flox_rust_sdk::models::secrets;
 use flox_rust_sdk::providers::build::FLOX_RUNTIME_DIR_VAR;
 use flox_rust_sdk::providers::services::shutdown_process_compose_if_all_processes_stopped;
 use flox_rust_sdk::utils::traceable_path;
@@ -59,6 +60,9 @@ pub static INTERACTIVE_BASH_BIN: LazyLock<PathBuf> = LazyLock::new(|| {
 });
 pub const FLOX_ACTIVATE_START_SERVICES_VAR: &str = "FLOX_ACTIVATE_START_SERVICES";
 pub const FLOX_SERVICES_TO_START_VAR: &str = "_FLOX_SERVICES_TO_START";
+/// Names of the variables resolved by [secrets::resolve],
+/// which the activation script only exports to the shell or command it invokes
+pub const FLOX_SECRET_VARS_VAR: &str = "_FLOX_SECRET_VARS";
 pub static WATCHDOG_BIN: LazyLock<PathBuf> = LazyLock::new(|| {
     PathBuf::from(env::var("WATCHDOG_BIN").unwrap_or(env!("WATCHDOG_BIN").to_string()))
 });
@@ -419,6 +423,9 @@ impl Activate {
             }
             .to_string(),
         );
+        if let Some(services_linger) = manifest.options.services_linger {
+            exports.insert("_FLOX_SERVICES_LINGER", services_linger.to_string());
+        }
 
         if self.start_services {
             ServicesEnvironment::from_environment_selection(&flox, &self.environment)?;
@@ -460,6 +467,15 @@ impl Activate {
 
         exports.extend(default_nix_env_vars());
 
+        // References in `[vars]` are only passed to the activation script,
+        // so that their values never end up in the built environment.
+        // They are added to the command after it is logged.
+        let secrets = secrets::resolve(
+            &manifest.for_system(&flox.system).vars,
+            &environment.project_path()?,
+        )
+        .context("Failed to resolve variables")?;
+
         let activate_path = interpreter_path.join("activate");
         // when output is not a tty, and no command is provided
         // we just print an activation script to stdout
@@ -470,7 +486,7 @@ impl Activate {
         //    eval "$(flox activate)"
         if in_place {
             let shell = Self::detect_shell_for_in_place()?;
-            Self::activate_in_place(&mode, &shell, &exports, &activate_path);
+            Self::activate_in_place(&mode, &shell, &exports, &secrets, &activate_path);
 
             return Ok(());
         }
@@ -478,13 +494,14 @@ impl Activate {
         let shell = Self::detect_shell_for_subshell();
         // These functions will only return if exec fails
         if interactive {
-            Self::activate_interactive(&mode, shell, exports, &activate_path)
+            Self::activate_interactive(&mode, shell, exports, &secrets, &activate_path)
         } else {
             Self::activate_command(
                 &mode,
                 self.run_args,
                 shell,
                 exports,
+                &secrets,
                 &activate_path,
                 is_ephemeral,
             )
@@ -497,6 +514,7 @@ impl Activate {
         run_args: Vec<String>,
         shell: Shell,
         exports: HashMap<&str, String>,
+        secrets: &BTreeMap<String, String>,
         activate_path: &Path,
         is_ephemeral: bool,
     ) -> Result<()> {
@@ -514,6 +532,7 @@ impl Activate {
         command.arg("--mode").arg(mode.to_string());
 
         debug!("running activation command: {:?}", command);
+        Self::add_secrets(&mut command, secrets);
 
         if is_ephemeral {
             let output = command
@@ -541,6 +560,7 @@ impl Activate {
         mode: &Mode,
         shell: Shell,
         exports: HashMap<&str, String>,
+        secrets: &BTreeMap<String, String>,
         activate_path: &Path,
     ) -> Result<()> {
         let mut command = Command::new(activate_path);
@@ -551,6 +571,7 @@ impl Activate {
         command.arg("--mode").arg(mode.to_string());
 
         debug!("running activation command: {:?}", command);
+        Self::add_secrets(&mut command, secrets);
 
         // exec should never return
         Err(command.exec().into())
@@ -561,6 +582,7 @@ impl Activate {
         mode: &Mode,
         shell: &Shell,
         exports: &HashMap<&str, String>,
+        secrets: &BTreeMap<String, String>,
         activate_path: &Path,
     ) {
         let mut command = Command::new(activate_path);
@@ -576,8 +598,12 @@ impl Activate {
         eprint!("{}", String::from_utf8_lossy(&output.stderr));
 
         // Render the exports in the correct shell dialect.
+        // Secrets are not passed to the activation script,
+        // they are only exported to the current shell.
         let exports_rendered = exports
             .iter()
+            .map(|(key, value)| (*key, value))
+            .chain(secrets.iter().map(|(key, value)| (key.as_str(), value)))
             .map(|(key, value)| (key, shell_escape::escape(Cow::Borrowed(value))))
             .map(|(key, value)| match shell {
                 Shell::Bash(_) => format!("export {key}={value};",),
@@ -597,6 +623,16 @@ impl Activate {
         print!("{script}");
     }
 
+    /// Pass resolved secrets to the activation script.
+    ///
+    /// The script hides them while it records the environment
+    /// in the activation state directory,
+    /// and only exports them to the shell or command that it invokes.
+    fn add_secrets(command: &mut Command, secrets: &BTreeMap<String, String>) {
+        command.envs(secrets);
+        command.env(FLOX_SECRET_VARS_VAR, secrets.keys().join(" "));
+    }
+
     /// Quote run args so that words don't get split,
     /// but don't escape all characters.
     ///
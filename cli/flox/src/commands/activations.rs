@@ -0,0 +1,204 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use bpaf::Bpaf;
+use flox_core::watchdog_status::{
+    find_watchdog_sockets,
+    query_watchdog,
+    WatchdogRequest,
+    WatchdogResponse,
+    WatchdogStatus,
+};
+use flox_rust_sdk::flox::Flox;
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+use crate::subcommand_metric;
+use crate::utils::message;
+
+#[derive(Bpaf, Debug, Clone)]
+pub struct Activations {
+    /// Format output as JSON
+    #[bpaf(long)]
+    json: bool,
+
+    /// Ask the watchdog of the activation with the given ID to clean up and exit
+    #[bpaf(long, argument("ID"))]
+    shutdown: Option<String>,
+}
+
+/// A watchdog socket found in the runtime dir,
+/// and the status reported by the watchdog if it is still running.
+#[derive(Debug, Serialize)]
+pub(crate) struct WatchdogEntry {
+    socket: PathBuf,
+    alive: bool,
+    status: Option<WatchdogStatus>,
+}
+
+impl WatchdogEntry {
+    /// Whether the watchdog is running and watches an activation of the
+    /// environment in the `.flox` directory at `dot_flox_path`
+    pub(crate) fn watches_environment(&self, dot_flox_path: &Path) -> bool {
+        self.status
+            .as_ref()
+            .is_some_and(|status| status.flox_env.starts_with(dot_flox_path))
+    }
+}
+
+impl Activations {
+    #[instrument(name = "activations", skip_all)]
+    pub fn handle(self, flox: Flox) -> Result<()> {
+        subcommand_metric!("activations");
+
+        let watchdogs = query_watchdogs(&flox)?;
+
+        if let Some(activation_id) = self.shutdown {
+            let Some(watchdog) = watchdogs.iter().find(|watchdog| {
+                watchdog
+                    .status
+                    .as_ref()
+                    .is_some_and(|status| status.activation_id == activation_id)
+            }) else {
+                bail!("No running watchdog found for activation '{activation_id}'");
+            };
+
+            match query_watchdog(&watchdog.socket, &WatchdogRequest::Shutdown)? {
+                WatchdogResponse::ShuttingDown => {
+                    message::updated(format!(
+                        "Requested the watchdog of activation '{activation_id}' to shut down"
+                    ));
+                },
+                response => bail!("Unexpected response from watchdog: {response:?}"),
+            }
+            return Ok(());
+        }
+
+        if self.json {
+            println!("{:#}", serde_json::json!(watchdogs));
+            return Ok(());
+        }
+
+        if watchdogs.is_empty() {
+            message::plain("No activations found");
+            return Ok(());
+        }
+
+        for watchdog in &watchdogs {
+            println!("{watchdog}");
+        }
+        Ok(())
+    }
+}
+
+/// Find the watchdogs of all activations and ask them for their status
+pub(crate) fn query_watchdogs(flox: &Flox) -> Result<Vec<WatchdogEntry>> {
+    let watchdogs = find_watchdog_sockets(&flox.runtime_dir)?
+        .into_iter()
+        .map(|socket| {
+            let status = match query_watchdog(&socket, &WatchdogRequest::Status) {
+                Ok(WatchdogResponse::Status(status)) => Some(status),
+                Ok(response) => {
+                    debug!(?response, "unexpected response from watchdog");
+                    None
+                },
+                Err(err) => {
+                    debug!(%err, socket = %socket.display(), "watchdog not responding");
+                    None
+                },
+            };
+            WatchdogEntry {
+                socket,
+                alive: status.is_some(),
+                status,
+            }
+        })
+        .collect();
+    Ok(watchdogs)
+}
+
+impl Display for WatchdogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(status) = &self.status else {
+            let state_dir = self.socket.parent().unwrap_or(&self.socket);
+            return write!(f, "{}: watchdog not running", state_dir.display());
+        };
+
+        let pids = status
+            .watched_pids
+            .iter()
+            .map(|pid| pid.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        write!(
+            f,
+            "{}: activation {}, watchdog PID {}, watching PIDs [{}]",
+            status.flox_env.display(),
+            status.activation_id,
+            status.pid,
+            pids
+        )?;
+        if status.pending_cleanup {
+            write!(f, ", cleanup pending")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_running_watchdog() {
+        let entry = WatchdogEntry {
+            socket: PathBuf::from("/run/flox/hash/id/watchdog.sock"),
+            alive: true,
+            status: Some(WatchdogStatus {
+                pid: 1,
+                flox_env: PathBuf::from("/project/.flox/run/env"),
+                activation_id: "id".to_string(),
+                watched_pids: vec![2, 3],
+                pending_cleanup: true,
+                ..Default::default()
+            }),
+        };
+        assert_eq!(
+            entry.to_string(),
+            "/project/.flox/run/env: activation id, watchdog PID 1, watching PIDs [2, 3], cleanup pending"
+        );
+    }
+
+    #[test]
+    fn watches_environment_of_dot_flox() {
+        let entry = WatchdogEntry {
+            socket: PathBuf::from("/run/flox/hash/id/watchdog.sock"),
+            alive: true,
+            status: Some(WatchdogStatus {
+                flox_env: PathBuf::from("/project/.flox/run/x86_64-linux.project.dev"),
+                ..Default::default()
+            }),
+        };
+        assert!(entry.watches_environment(Path::new("/project/.flox")));
+        assert!(!entry.watches_environment(Path::new("/other/.flox")));
+
+        let stopped = WatchdogEntry {
+            status: None,
+            alive: false,
+            ..entry
+        };
+        assert!(!stopped.watches_environment(Path::new("/project/.flox")));
+    }
+
+    #[test]
+    fn display_stopped_watchdog() {
+        let entry = WatchdogEntry {
+            socket: PathBuf::from("/run/flox/hash/id/watchdog.sock"),
+            alive: false,
+            status: None,
+        };
+        assert_eq!(entry.to_string(), "/run/flox/hash/id: watchdog not running");
+    }
+}
@@ -0,0 +1,200 @@
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use bpaf::Bpaf;
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::audit::{AdvisoryDatabase, AuditFinding, AuditReport, Severity};
+use indoc::indoc;
+use itertools::Itertools;
+use tracing::instrument;
+
+use super::{environment_select, EnvironmentSelect};
+use crate::config::Config;
+use crate::subcommand_metric;
+use crate::utils::message;
+
+// Check the packages of an environment for known vulnerabilities
+#[derive(Bpaf, Clone)]
+pub struct Audit {
+    #[bpaf(external(environment_select), fallback(Default::default()))]
+    environment: EnvironmentSelect,
+
+    /// Vulnerability database to check packages against,
+    /// an OSV or NVD JSON file or a directory of such files
+    /// (default: 'audit_database' config)
+    #[bpaf(long, argument("path"))]
+    db: Option<PathBuf>,
+
+    /// Fail if a vulnerability with at least this severity is found,
+    /// one of 'unknown', 'low', 'medium', 'high' or 'critical'
+    /// (default: 'options.audit.fail-on' in the manifest, or 'unknown')
+    #[bpaf(long, argument("severity"))]
+    fail_on: Option<Severity>,
+
+    /// Format output as JSON
+    #[bpaf(long)]
+    json: bool,
+}
+
+impl Audit {
+    #[instrument(name = "audit", skip_all)]
+    pub fn handle(self, config: Config, flox: Flox) -> Result<()> {
+        subcommand_metric!("audit");
+
+        let Some(db) = self.db.or(config.flox.audit_database) else {
+            bail!(indoc! {"
+                No vulnerability database specified.
+
+                Download an OSV or NVD JSON dump and pass it with '--db',
+                or set it with 'flox config --set audit_database <path>'.
+            "});
+        };
+
+        let mut env = self
+            .environment
+            .detect_concrete_environment(&flox, "Audit")?
+            .into_dyn_environment();
+        let lockfile = env.lockfile(&flox)?;
+        let options = &lockfile.manifest.options.audit;
+
+        let database = AdvisoryDatabase::load(&db)?;
+        let report = AuditReport::new(&lockfile, &database, options)?;
+
+        if self.json {
+            println!("{:#}", serde_json::json!(report));
+        } else {
+            Self::print_report(stdout().lock(), &report)?;
+        }
+
+        let threshold = self.fail_on.or(options.fail_on).unwrap_or_default();
+        let failing = report.failing(threshold).count();
+        if failing > 0 {
+            bail!(
+                "Found {failing} {vulnerabilities} with severity '{threshold}' or higher.",
+                vulnerabilities = if failing == 1 {
+                    "vulnerability"
+                } else {
+                    "vulnerabilities"
+                }
+            );
+        }
+
+        if !self.json && report.findings.iter().all(|finding| finding.ignored) {
+            message::updated("No vulnerabilities found.");
+        }
+
+        Ok(())
+    }
+
+    /// Print findings grouped by system
+    ///
+    /// e.g.
+    /// ```text
+    /// x86_64-linux:
+    ///   openssl 3.0.1: CVE-2022-3602 [high] X.509 Email Address Buffer Overflow
+    /// ```
+    fn print_report(mut out: impl Write, report: &AuditReport) -> Result<()> {
+        let by_system = report
+            .findings
+            .iter()
+            .filter(|finding| !finding.ignored)
+            .group_by(|finding| &finding.system);
+
+        for (system, findings) in &by_system {
+            writeln!(&mut out, "{system}:")?;
+            for AuditFinding {
+                install_id,
+                pname,
+                version,
+                advisory,
+                severity,
+                summary,
+                ecosystem,
+                ..
+            } in findings
+            {
+                let name = if install_id == pname {
+                    pname.to_string()
+                } else {
+                    format!("{install_id} ({pname})")
+                };
+                let summary = summary
+                    .as_deref()
+                    .and_then(|summary| summary.lines().next())
+                    .unwrap_or_default();
+                // Mark matches of advisories published for other ecosystems,
+                // e.g. Linux distributions
+                let ecosystem = ecosystem
+                    .as_ref()
+                    .map(|ecosystem| format!(" (reported for {ecosystem})"))
+                    .unwrap_or_default();
+                writeln!(
+                    &mut out,
+                    "  {name} {version}: {advisory} [{severity}] {summary}{ecosystem}"
+                )?;
+            }
+        }
+
+        let ignored = report
+            .findings
+            .iter()
+            .filter(|finding| finding.ignored)
+            .count();
+        if ignored > 0 {
+            message::plain(format!(
+                "{ignored} ignored by 'options.audit.ignore' in the manifest."
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn finding(system: &str, install_id: &str, advisory: &str, ignored: bool) -> AuditFinding {
+        AuditFinding {
+            system: system.to_string(),
+            install_id: install_id.to_string(),
+            pname: "openssl".to_string(),
+            version: "3.0.1".to_string(),
+            advisory: advisory.to_string(),
+            aliases: vec![],
+            severity: Severity::High,
+            summary: Some("X.509 buffer overflow\nmore details".to_string()),
+            ecosystem: None,
+            ignored,
+        }
+    }
+
+    #[test]
+    fn print_report_grouped_by_system() {
+        let report = AuditReport {
+            findings: vec![
+                finding("aarch64-darwin", "openssl", "CVE-2022-3602", false),
+                finding("aarch64-darwin", "ssl", "CVE-2022-3786", false),
+                finding("x86_64-linux", "openssl", "CVE-2022-3602", false),
+                AuditFinding {
+                    ecosystem: Some("Debian:12".to_string()),
+                    ..finding("x86_64-linux", "openssl", "DSA-5417-1", false)
+                },
+                finding("x86_64-linux", "openssl", "CVE-2022-0001", true),
+            ],
+        };
+
+        let mut out = Vec::new();
+        Audit::print_report(&mut out, &report).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), indoc! {"
+            aarch64-darwin:
+              openssl 3.0.1: CVE-2022-3602 [high] X.509 buffer overflow
+              ssl (openssl) 3.0.1: CVE-2022-3786 [high] X.509 buffer overflow
+            x86_64-linux:
+              openssl 3.0.1: CVE-2022-3602 [high] X.509 buffer overflow
+              openssl 3.0.1: DSA-5417-1 [high] X.509 buffer overflow (reported for Debian:12)
+        "});
+    }
+}
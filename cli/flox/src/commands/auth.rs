@@ -1,11 +1,16 @@
+use std::fmt::Display;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::{env, fs};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bpaf::Bpaf;
 use chrono::offset::Utc;
 use chrono::{DateTime, Duration};
-use flox_rust_sdk::flox::{Flox, FloxhubToken};
+use flox_rust_sdk::flox::{Flox, FloxhubToken, FloxhubTokenError};
 use flox_rust_sdk::providers::catalog::Client;
 use indoc::formatdoc;
 use log::debug;
@@ -22,16 +27,22 @@ use oauth2::{
     TokenUrl,
 };
 use serde::Serialize;
+use toml_edit::DocumentMut;
 use tracing::instrument;
 use url::Url;
 
 use crate::commands::general::update_config;
-use crate::config::Config;
+use crate::config::{Config, TokenStorage, FLOX_CONFIG_FILE};
 use crate::subcommand_metric;
 use crate::utils::dialog::{Checkpoint, Dialog};
 use crate::utils::message;
 use crate::utils::openers::Browser;
 
+/// Config key of the FloxHub token
+const FLOXHUB_TOKEN_KEY: &str = "floxhub_token";
+/// Environment variable that sets `floxhub_token`
+const FLOX_FLOXHUB_TOKEN_VAR: &str = "FLOX_FLOXHUB_TOKEN";
+
 #[derive(Debug, Default, Clone, Serialize)]
 pub struct Credential {
     pub token: String,
@@ -65,9 +76,14 @@ fn create_oauth_client() -> Result<BasicClient> {
     Ok(client)
 }
 
+/// Run the OAuth device flow
+///
+/// The user is asked to press enter to open the verification URL in a browser.
+/// If no browser can be found, the URL and code are printed instead.
+/// Non-interactive logins have to provide a token with `--token-file` or `--with-token`.
 pub async fn authorize(client: BasicClient, floxhub_url: &Url) -> Result<Credential> {
     if !Dialog::can_prompt() {
-        bail!("Cannot prompt for user input")
+        bail!("Cannot prompt for user input, use '--token-file' or '--with-token' instead")
     }
 
     let details: StandardDeviceAuthorizationResponse = client
@@ -177,7 +193,15 @@ fn calculate_expiry(expires_in: i64) -> String {
 pub enum Auth {
     /// Login to FloxHub
     #[bpaf(command)]
-    Login,
+    Login {
+        /// Read a token from <file> instead of running the login flow
+        #[bpaf(long, argument("file"))]
+        token_file: Option<PathBuf>,
+
+        /// Read a token from stdin instead of running the login flow
+        #[bpaf(long)]
+        with_token: bool,
+    },
 
     /// Logout from FloxHub
     #[bpaf(command)]
@@ -185,7 +209,11 @@ pub enum Auth {
 
     /// Print your current login status
     #[bpaf(command)]
-    Status,
+    Status {
+        /// Format output as JSON
+        #[bpaf(long)]
+        json: bool,
+    },
 }
 
 impl Auth {
@@ -193,42 +221,92 @@ impl Auth {
     pub async fn handle(self, config: Config, mut flox: Flox) -> Result<()> {
         subcommand_metric!("auth2");
 
+        let storage = config.flox.floxhub_token_storage.unwrap_or_default();
+
         match self {
-            Auth::Login => {
+            Auth::Login {
+                token_file,
+                with_token,
+            } => {
                 let span = tracing::info_span!("login");
                 let _guard = span.enter();
-                login_flox(&mut flox).await?;
+
+                let token = match (token_file, with_token) {
+                    (Some(_), true) => bail!("'--token-file' and '--with-token' can't be combined"),
+                    (Some(path), false) => {
+                        let token = fs::read_to_string(&path).with_context(|| {
+                            format!("Could not read token from '{}'", path.display())
+                        })?;
+                        parse_token(&token)?
+                    },
+                    (None, true) => {
+                        let mut token = String::new();
+                        std::io::stdin()
+                            .read_to_string(&mut token)
+                            .context("Could not read token from stdin")?;
+                        parse_token(&token)?
+                    },
+                    (None, false) => {
+                        login_flox(&mut flox, storage).await?;
+                        return Ok(());
+                    },
+                };
+
+                save_token(&mut flox, storage, token)?;
                 Ok(())
             },
             Auth::Logout => {
                 let span = tracing::info_span!("logout");
                 let _guard = span.enter();
-                if config.flox.floxhub_token.is_none() {
+                if flox.floxhub_token.is_none() {
                     message::warning("You are not logged in");
                     return Ok(());
                 }
 
-                update_config::<String>(&flox.config_dir, &flox.temp_dir, "floxhub_token", None)
-                    .context("Could not remove token from user config")?;
+                let source = token_source(&config);
+                if source == TokenSource::Environment {
+                    message::warning(
+                        "Your token is set via '$FLOX_FLOXHUB_TOKEN' and will remain in use until the variable is unset",
+                    );
+                }
+                token_store(source.storage(), &flox.config_dir, &flox.temp_dir)
+                    .remove()
+                    .context("Could not remove token")?;
 
                 message::updated("Logout successful");
 
                 Ok(())
             },
-            Auth::Status => {
+            Auth::Status { json } => {
                 let span = tracing::info_span!("status");
                 let _guard = span.enter();
-                let Some(token) = flox.floxhub_token else {
+
+                let status = flox.floxhub_token.as_ref().map(|token| AuthStatus {
+                    handle: token.handle().to_string(),
+                    expiry: token.expiry(),
+                    source: token_source(&config),
+                    floxhub_url: flox.floxhub.base_url().clone(),
+                });
+
+                if json {
+                    println!("{:#}", serde_json::json!(status));
+                    return Ok(());
+                }
+
+                let Some(status) = status else {
                     message::warning("You are not currently logged in to FloxHub.");
                     return Ok(());
                 };
 
-                let handle = token.handle();
-
-                message::plain(format!(
-                    "You are logged in as {handle} on {}",
-                    flox.floxhub.base_url()
-                ));
+                let mut status_message = format!(
+                    "You are logged in as {} on {}",
+                    status.handle, status.floxhub_url
+                );
+                if let Some(expiry) = status.expiry {
+                    status_message.push_str(&format!("\nToken expires at {}", expiry.to_rfc3339()));
+                }
+                status_message.push_str(&format!("\nToken read from {}", status.source));
+                message::plain(status_message);
 
                 Ok(())
             },
@@ -236,40 +314,403 @@ impl Auth {
     }
 }
 
+/// Login status as printed by `flox auth status`
+#[derive(Debug, Serialize)]
+struct AuthStatus {
+    handle: String,
+    expiry: Option<DateTime<Utc>>,
+    source: TokenSource,
+    floxhub_url: Url,
+}
+
+/// Validate a token provided via `--token-file` or `--with-token`
+fn parse_token(token: &str) -> Result<FloxhubToken> {
+    FloxhubToken::new(token.trim().to_string()).map_err(|err| match err {
+        FloxhubTokenError::Expired => anyhow!("The provided token has expired"),
+        FloxhubTokenError::InvalidToken(err) => {
+            anyhow!(err).context("The provided token is invalid")
+        },
+    })
+}
+
+/// Where the FloxHub token in use was read from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenSource {
+    /// `$FLOX_FLOXHUB_TOKEN`
+    Environment,
+    /// `floxhub_token` in a `flox.toml`
+    Config,
+    /// The system keyring
+    Keyring,
+}
+
+impl TokenSource {
+    /// The storage a token from this source is removed from on logout
+    ///
+    /// Tokens set in the environment can't be removed,
+    /// but we also remove any token from the user config.
+    pub fn storage(self) -> TokenStorage {
+        match self {
+            TokenSource::Environment | TokenSource::Config => TokenStorage::Config,
+            TokenSource::Keyring => TokenStorage::Keyring,
+        }
+    }
+}
+
+impl Display for TokenSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenSource::Environment => write!(f, "environment variable $FLOX_FLOXHUB_TOKEN"),
+            TokenSource::Config => write!(f, "config"),
+            TokenSource::Keyring => write!(f, "system keyring"),
+        }
+    }
+}
+
+/// Determine where the FloxHub token was read from
+///
+/// Tokens in the config or environment take precedence over the keyring,
+/// see [read_floxhub_token].
+pub fn token_source(config: &Config) -> TokenSource {
+    if env::var_os(FLOX_FLOXHUB_TOKEN_VAR).is_some() {
+        TokenSource::Environment
+    } else if config.flox.floxhub_token.is_some() {
+        TokenSource::Config
+    } else if config.flox.floxhub_token_storage == Some(TokenStorage::Keyring) {
+        TokenSource::Keyring
+    } else {
+        TokenSource::Config
+    }
+}
+
+/// Read the FloxHub token from the config or environment,
+/// falling back to the keyring if it's configured as token storage.
+///
+/// The token is not validated.
+pub fn read_floxhub_token(config: &Config) -> Option<String> {
+    if let Some(token) = &config.flox.floxhub_token {
+        return Some(token.clone());
+    }
+    if config.flox.floxhub_token_storage != Some(TokenStorage::Keyring) {
+        return None;
+    }
+    KeyringTokenStore.read().unwrap_or_else(|e| {
+        debug!("Could not read token from keyring: {e}");
+        None
+    })
+}
+
+/// Persistent storage for the FloxHub token
+pub trait TokenStore {
+    /// Read the stored token, if any
+    fn read(&self) -> Result<Option<String>>;
+
+    /// Store `token`, replacing any existing token
+    fn write(&self, token: &FloxhubToken) -> Result<()>;
+
+    /// Remove the stored token
+    fn remove(&self) -> Result<()>;
+}
+
+/// Create the [TokenStore] for `storage`
+pub fn token_store(
+    storage: TokenStorage,
+    config_dir: &Path,
+    temp_dir: &Path,
+) -> Box<dyn TokenStore> {
+    match storage {
+        TokenStorage::Config => Box::new(ConfigTokenStore {
+            config_dir: config_dir.to_path_buf(),
+            temp_dir: temp_dir.to_path_buf(),
+        }),
+        TokenStorage::Keyring => Box::new(KeyringTokenStore),
+    }
+}
+
+/// Stores the token as `floxhub_token` in the user's `flox.toml`
+#[derive(Debug)]
+pub struct ConfigTokenStore {
+    config_dir: PathBuf,
+    temp_dir: PathBuf,
+}
+
+impl TokenStore for ConfigTokenStore {
+    fn read(&self) -> Result<Option<String>> {
+        let config_file = self.config_dir.join(FLOX_CONFIG_FILE);
+        if !config_file.exists() {
+            return Ok(None);
+        }
+        let document = fs::read_to_string(config_file)?.parse::<DocumentMut>()?;
+        Ok(document
+            .get(FLOXHUB_TOKEN_KEY)
+            .and_then(|token| token.as_str())
+            .map(String::from))
+    }
+
+    fn write(&self, token: &FloxhubToken) -> Result<()> {
+        update_config(
+            &self.config_dir,
+            &self.temp_dir,
+            FLOXHUB_TOKEN_KEY,
+            Some(token.clone()),
+        )
+        .context("Could not write token to config")
+    }
+
+    fn remove(&self) -> Result<()> {
+        update_config::<String>(&self.config_dir, &self.temp_dir, FLOXHUB_TOKEN_KEY, None)
+            .context("Could not remove token from user config")
+    }
+}
+
+/// Stores the token in the system keyring
+///
+/// The keyring is accessed through `security` on macOS
+/// and `secret-tool` (libsecret) on Linux,
+/// rather than linking a keyring library into flox.
+#[derive(Debug)]
+pub struct KeyringTokenStore;
+
+const KEYRING_SERVICE: &str = "flox";
+const KEYRING_ACCOUNT: &str = "floxhub_token";
+
+impl KeyringTokenStore {
+    fn command(args: &[&str]) -> Command {
+        let mut command = if cfg!(target_os = "macos") {
+            Command::new("security")
+        } else {
+            Command::new("secret-tool")
+        };
+        command.args(args);
+        command
+    }
+
+    fn run(mut command: Command, stdin: Option<&str>) -> Result<Output> {
+        let program = command.get_program().to_string_lossy().to_string();
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Could not run '{program}' to access the keyring"))?;
+        if let Some(input) = stdin {
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(input.as_bytes())?;
+        }
+        Ok(child.wait_with_output()?)
+    }
+}
+
+impl TokenStore for KeyringTokenStore {
+    fn read(&self) -> Result<Option<String>> {
+        let command = if cfg!(target_os = "macos") {
+            Self::command(&[
+                "find-generic-password",
+                "-s",
+                KEYRING_SERVICE,
+                "-a",
+                KEYRING_ACCOUNT,
+                "-w",
+            ])
+        } else {
+            Self::command(&[
+                "lookup",
+                "service",
+                KEYRING_SERVICE,
+                "account",
+                KEYRING_ACCOUNT,
+            ])
+        };
+        let output = Self::run(command, None)?;
+        // Both tools exit with an error if there is no matching entry
+        if !output.status.success() {
+            debug!(
+                "No token found in keyring: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return Ok(None);
+        }
+        let token = String::from_utf8(output.stdout)?.trim().to_string();
+        Ok((!token.is_empty()).then_some(token))
+    }
+
+    fn write(&self, token: &FloxhubToken) -> Result<()> {
+        let output = if cfg!(target_os = "macos") {
+            // `security` only accepts the password as an argument,
+            // which would expose the token to other users via `ps`.
+            // In interactive mode, the command is read from stdin instead.
+            if token.secret().contains(['"', '\\', '\n']) {
+                bail!("Could not store token in keyring: token contains invalid characters");
+            }
+            let command = format!(
+                "add-generic-password -U -s {KEYRING_SERVICE} -a {KEYRING_ACCOUNT} -w \"{}\"\n",
+                token.secret()
+            );
+            let output = Self::run(Self::command(&["-i"]), Some(&command))?;
+            // `security -i` reports errors of the command on stderr
+            // but exits successfully
+            if !output.stderr.is_empty() {
+                bail!(
+                    "Could not store token in keyring: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            output
+        } else {
+            Self::run(
+                Self::command(&[
+                    "store",
+                    "--label",
+                    "FloxHub token",
+                    "service",
+                    KEYRING_SERVICE,
+                    "account",
+                    KEYRING_ACCOUNT,
+                ]),
+                Some(token.secret()),
+            )?
+        };
+        if !output.status.success() {
+            bail!(
+                "Could not store token in keyring: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<()> {
+        let command = if cfg!(target_os = "macos") {
+            Self::command(&[
+                "delete-generic-password",
+                "-s",
+                KEYRING_SERVICE,
+                "-a",
+                KEYRING_ACCOUNT,
+            ])
+        } else {
+            Self::command(&[
+                "clear",
+                "service",
+                KEYRING_SERVICE,
+                "account",
+                KEYRING_ACCOUNT,
+            ])
+        };
+        // Removing a token that doesn't exist is not an error
+        let output = Self::run(command, None)?;
+        if !output.status.success() {
+            debug!(
+                "Could not remove token from keyring: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(())
+    }
+}
+
 /// run the login flow
 ///
-/// * updates the config file with the received token
+/// * stores the received token in `storage`
 /// * updates the floxhub_token field in the config struct
-pub async fn login_flox(flox: &mut Flox) -> Result<&FloxhubToken> {
+pub async fn login_flox(flox: &mut Flox, storage: TokenStorage) -> Result<&FloxhubToken> {
     let client = create_oauth_client()?;
     let cred = authorize(client, flox.floxhub.base_url())
         .await
         .context("Could not authorize via oauth")?;
 
     debug!("Credentials received: {cred:#?}");
-    debug!("Writing token to config");
+
+    message::updated("Authentication complete");
+    save_token(flox, storage, FloxhubToken::new(cred.token)?)
+}
+
+/// Store `token` in `storage` and use it for the rest of this invocation
+fn save_token(
+    flox: &mut Flox,
+    storage: TokenStorage,
+    token: FloxhubToken,
+) -> Result<&FloxhubToken> {
+    debug!("Writing token to {storage:?}");
+
+    token_store(storage, &flox.config_dir, &flox.temp_dir).write(&token)?;
+
+    // A token in the config would shadow the one in the keyring
+    if storage == TokenStorage::Keyring {
+        token_store(TokenStorage::Config, &flox.config_dir, &flox.temp_dir).remove()?;
+    }
 
     // set the token in the runtime config
-    let token = flox.floxhub_token.insert(FloxhubToken::new(cred.token)?);
+    let token = flox.floxhub_token.insert(token);
     let handle = token.handle();
 
-    // write the token to the config file
-    update_config(
-        &flox.config_dir,
-        &flox.temp_dir,
-        "floxhub_token",
-        Some(token.clone()),
-    )
-    .context("Could not write token to config")?;
-
     // If the catalog client is catalog (not a mock), update the token by
     // creating a new client based on the old config with the updated token
     if let Client::Catalog(client) = &mut flox.catalog_client {
         client.update_config(|config| config.floxhub_token = Some(token.secret().to_string()));
     }
 
-    message::updated("Authentication complete");
     message::updated(format!("Logged in as {handle}"));
 
     Ok(token)
 }
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::flox::test_helpers::create_test_token;
+
+    use super::*;
+    use crate::config::FloxConfig;
+
+    #[test]
+    fn parse_token_trims_whitespace() {
+        let token = create_test_token("test");
+        let parsed = parse_token(&format!("  {}\n", token.secret())).unwrap();
+        assert_eq!(parsed.secret(), token.secret());
+        assert_eq!(parsed.handle(), "test");
+    }
+
+    #[test]
+    fn parse_token_rejects_invalid_token() {
+        let err = parse_token("not a token").unwrap_err();
+        assert_eq!(err.to_string(), "The provided token is invalid");
+    }
+
+    #[test]
+    fn config_token_store_roundtrip() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = token_store(TokenStorage::Config, config_dir.path(), temp_dir.path());
+        let token = create_test_token("test");
+
+        assert_eq!(store.read().unwrap(), None);
+        store.write(&token).unwrap();
+        assert_eq!(store.read().unwrap().as_deref(), Some(token.secret()));
+        store.remove().unwrap();
+        assert_eq!(store.read().unwrap(), None);
+    }
+
+    #[test]
+    fn token_source_prefers_config_over_keyring() {
+        let mut config = Config {
+            flox: FloxConfig {
+                floxhub_token_storage: Some(TokenStorage::Keyring),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        temp_env::with_var_unset(FLOX_FLOXHUB_TOKEN_VAR, || {
+            assert_eq!(token_source(&config), TokenSource::Keyring);
+            config.flox.floxhub_token = Some("token".to_string());
+            assert_eq!(token_source(&config), TokenSource::Config);
+        });
+        temp_env::with_var(FLOX_FLOXHUB_TOKEN_VAR, Some("token"), || {
+            assert_eq!(token_source(&config), TokenSource::Environment);
+        });
+    }
+}
@@ -18,6 +18,7 @@ use flox_rust_sdk::models::environment::{
     Environment,
     EnvironmentError,
 };
+use flox_rust_sdk::models::manifest_merge::has_conflict_markers;
 use flox_rust_sdk::providers::services::ServiceError;
 use itertools::Itertools;
 use log::debug;
@@ -78,7 +79,7 @@ impl Edit {
         subcommand_metric!("edit");
 
         // Ensure the user is logged in for the following remote operations
-        if let EnvironmentSelect::Remote(_) = self.environment {
+        if self.environment.is_remote(&flox)? {
             ensure_floxhub_token(&mut flox).await?;
         };
 
@@ -164,7 +165,10 @@ impl Edit {
         contents: Option<String>,
     ) -> Result<()> {
         if let ConcreteEnvironment::Managed(ref environment) = environment {
-            if environment.has_local_changes(flox)? && contents.is_none() {
+            // Conflicts left behind by 'flox pull --merge' or 'flox push --merge'
+            // are resolved by editing the local checkout.
+            let has_conflicts = has_conflict_markers(&environment.manifest_contents(flox)?);
+            if environment.has_local_changes(flox)? && contents.is_none() && !has_conflicts {
                 bail!(ManagedEnvironmentError::CheckoutOutOfSync)
             }
         };
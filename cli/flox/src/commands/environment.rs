@@ -0,0 +1,256 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use bpaf::Bpaf;
+use flox_rust_sdk::flox::{EnvironmentRef, Flox};
+use flox_rust_sdk::models::environment::managed_environment::ManagedEnvironmentError;
+use flox_rust_sdk::models::environment::remote_environment::RemoteEnvironment;
+use flox_rust_sdk::models::environment::{ManagedPointer, DOT_FLOX};
+use flox_rust_sdk::models::user_state::{
+    lock_and_read_user_state_file,
+    user_state_path,
+    write_user_state_file,
+    EnvironmentAlias,
+};
+use indoc::formatdoc;
+use tracing::instrument;
+
+use super::pull::Pull;
+use crate::commands::ensure_floxhub_token;
+use crate::subcommand_metric;
+use crate::utils::message;
+
+/// Environment Commands.
+#[derive(Debug, Clone, Bpaf)]
+pub enum EnvCommands {
+    /// Copy an environment to a new owner or name, or into a directory
+    #[bpaf(command, footer("Run 'man flox-env-copy' for more details."))]
+    Copy(#[bpaf(external(copy))] Copy),
+
+    /// Manage short names for environments
+    #[bpaf(command, footer("Run 'man flox-env-alias' for more details."))]
+    Alias(#[bpaf(external(alias_commands))] AliasCommands),
+}
+
+impl EnvCommands {
+    #[instrument(name = "env", skip_all)]
+    pub async fn handle(self, flox: Flox) -> Result<()> {
+        match self {
+            EnvCommands::Copy(args) => args.handle(flox).await?,
+            EnvCommands::Alias(args) => args.handle(flox)?,
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Bpaf)]
+enum CopyArgs {
+    ToPath {
+        /// Create a path environment from the environment in the given directory
+        #[bpaf(long("to-path"), argument("path"))]
+        path: PathBuf,
+
+        /// ID of the environment to copy
+        #[bpaf(positional("owner>/<name"))]
+        source: EnvironmentRef,
+    },
+    Remote {
+        /// ID of the environment to copy
+        #[bpaf(positional("owner>/<name"))]
+        source: EnvironmentRef,
+
+        /// ID of the environment to create
+        #[bpaf(positional("owner>/<name"))]
+        destination: EnvironmentRef,
+    },
+}
+
+// Copy an environment
+#[derive(Debug, Clone, Bpaf)]
+pub struct Copy {
+    /// Copy all generations of the environment
+    /// rather than only the current generation
+    #[bpaf(long)]
+    keep_history: bool,
+
+    /// Overwrite the destination if it already exists
+    #[bpaf(long, short)]
+    force: bool,
+
+    #[bpaf(external(copy_args))]
+    args: CopyArgs,
+}
+
+impl Copy {
+    #[instrument(name = "copy", skip_all)]
+    pub async fn handle(self, mut flox: Flox) -> Result<()> {
+        subcommand_metric!("env::copy");
+
+        let (source, destination) = match self.args {
+            CopyArgs::ToPath { path, source } => {
+                if self.keep_history {
+                    bail!("Path environments don't have generations, '--keep-history' can't be used with '--to-path'");
+                }
+                return Pull::pull_new_environment(&flox, path, source, true, self.force);
+            },
+            CopyArgs::Remote {
+                source,
+                destination,
+            } => (source, destination),
+        };
+
+        if destination == source {
+            bail!("Can't copy {source} onto itself");
+        }
+
+        // Generic git remotes use the user's git credentials instead of a FloxHub token
+        if flox.floxhub.git_remote().is_none() {
+            ensure_floxhub_token(&mut flox).await?;
+        }
+
+        let source_pointer =
+            ManagedPointer::new(source.owner().clone(), source.name().clone(), &flox.floxhub);
+        let destination_pointer = ManagedPointer::new(
+            destination.owner().clone(),
+            destination.name().clone(),
+            &flox.floxhub,
+        );
+
+        let source_env = RemoteEnvironment::new(&flox, source_pointer)?;
+        match source_env.copy_to(&flox, &destination_pointer, self.keep_history, self.force) {
+            Err(ManagedEnvironmentError::Diverged) => bail!(formatdoc! {"
+                An environment named {destination} already exists!
+
+                Use 'flox env copy --force' to overwrite it.
+            "}),
+            result => result?,
+        }
+
+        message::created(formatdoc! {"
+            Copied {source} to {destination}{history}
+
+            Use 'flox pull {destination}' to get this environment in any other location.
+            ",
+            history = if self.keep_history { " including its history" } else { "" },
+        });
+        Ok(())
+    }
+}
+
+/// Alias Commands.
+#[derive(Debug, Clone, Bpaf)]
+pub enum AliasCommands {
+    /// Add or replace an alias for an environment
+    #[bpaf(command)]
+    Add(#[bpaf(external(alias_add))] AliasAdd),
+
+    /// Remove an alias
+    #[bpaf(command)]
+    Remove(#[bpaf(external(alias_remove))] AliasRemove),
+}
+
+impl AliasCommands {
+    #[instrument(name = "alias", skip_all)]
+    pub fn handle(self, flox: Flox) -> Result<()> {
+        match self {
+            AliasCommands::Add(args) => args.handle(flox),
+            AliasCommands::Remove(args) => args.handle(flox),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Bpaf)]
+enum AliasTarget {
+    Remote {
+        /// The remote environment the alias refers to
+        #[bpaf(long("remote"), short('r'), argument("owner>/<name"))]
+        env_ref: EnvironmentRef,
+
+        /// Name of the alias
+        #[bpaf(positional("alias"))]
+        name: String,
+    },
+    Path {
+        /// Name of the alias
+        #[bpaf(positional("alias"))]
+        name: String,
+
+        /// Directory containing the environment the alias refers to
+        #[bpaf(positional("path"))]
+        path: PathBuf,
+    },
+}
+
+// Add an alias for an environment
+#[derive(Debug, Clone, Bpaf)]
+pub struct AliasAdd {
+    #[bpaf(external(alias_target))]
+    target: AliasTarget,
+}
+
+impl AliasAdd {
+    #[instrument(name = "add", skip_all)]
+    fn handle(self, flox: Flox) -> Result<()> {
+        subcommand_metric!("env::alias::add");
+
+        let (name, alias) = match self.target {
+            AliasTarget::Remote { name, env_ref } => (name, EnvironmentAlias::Remote(env_ref)),
+            AliasTarget::Path { name, path } => {
+                if !path.join(DOT_FLOX).exists() {
+                    bail!("Did not find an environment in '{}'", path.display());
+                }
+                let path = path
+                    .canonicalize()
+                    .with_context(|| format!("Could not resolve path '{}'", path.display()))?;
+                (name, EnvironmentAlias::Path(path))
+            },
+        };
+
+        let state_path = user_state_path(&flox);
+        let (lock, mut user_state) = lock_and_read_user_state_file(&state_path)?;
+        let target = display_alias(&alias);
+        let previous = user_state.environment_aliases.insert(name.clone(), alias);
+        write_user_state_file(&user_state, &state_path, lock)?;
+
+        if previous.is_some() {
+            message::updated(format!("Alias '{name}' now refers to {target}"));
+        } else {
+            message::created(format!("Added alias '{name}' for {target}"));
+        }
+        Ok(())
+    }
+}
+
+// Remove an alias
+#[derive(Debug, Clone, Bpaf)]
+pub struct AliasRemove {
+    /// Name of the alias
+    #[bpaf(positional("alias"))]
+    name: String,
+}
+
+impl AliasRemove {
+    #[instrument(name = "remove", skip_all)]
+    fn handle(self, flox: Flox) -> Result<()> {
+        subcommand_metric!("env::alias::remove");
+
+        let state_path = user_state_path(&flox);
+        let (lock, mut user_state) = lock_and_read_user_state_file(&state_path)?;
+        if user_state.environment_aliases.remove(&self.name).is_none() {
+            bail!("No environment alias named '{}'", self.name);
+        }
+        write_user_state_file(&user_state, &state_path, lock)?;
+
+        message::deleted(format!("Removed alias '{}'", self.name));
+        Ok(())
+    }
+}
+
+/// Format the environment an alias refers to
+pub(crate) fn display_alias(alias: &EnvironmentAlias) -> String {
+    match alias {
+        EnvironmentAlias::Path(path) => path.display().to_string(),
+        EnvironmentAlias::Remote(env_ref) => format!("{env_ref} (remote)"),
+    }
+}
@@ -1,5 +1,5 @@
 use std::borrow::Cow;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
 use std::path::Path;
 
@@ -13,11 +13,14 @@ use flox_rust_sdk::models::env_registry::{
     EnvRegistry,
 };
 use flox_rust_sdk::models::environment::DotFlox;
+use flox_rust_sdk::models::user_state::{read_user_state_file, user_state_path, EnvironmentAlias};
 use serde_json::json;
 use tracing::instrument;
 
+use super::activations::{query_watchdogs, WatchdogEntry};
 use super::{ActiveEnvironments, UninitializedEnvironment};
 use crate::commands::activated_environments;
+use crate::commands::environment::display_alias;
 use crate::subcommand_metric;
 use crate::utils::message;
 
@@ -54,13 +57,21 @@ impl Envs {
         let active = activated_environments();
 
         match self.mode {
-            Mode::Active => tracing::info_span!("active").in_scope(|| self.handle_active(active)),
+            Mode::Active => tracing::info_span!("active").in_scope(|| {
+                let watchdogs = query_watchdogs(&flox)?;
+                self.handle_active(active, &watchdogs)
+            }),
             Mode::All => tracing::info_span!("all").in_scope(|| {
                 let env_registry =
                     read_environment_registry(env_registry_path(&flox))?.unwrap_or_default();
                 let registered = get_registered_environments(&env_registry);
+                let aliases = read_user_state_file(user_state_path(&flox))?
+                    .unwrap_or_default()
+                    .environment_aliases;
+
+                let watchdogs = query_watchdogs(&flox)?;
 
-                self.handle_all(active, registered)
+                self.handle_all(active, registered, &aliases, &watchdogs)
             }),
         }
     }
@@ -70,7 +81,7 @@ impl Envs {
     /// If `--json` is passed, print a JSON list with objects for each active environment.
     /// Otherwise, print a list of active environments.
     /// If no environments are active, print an appropriate message.
-    fn handle_active(&self, active: ActiveEnvironments) -> Result<()> {
+    fn handle_active(&self, active: ActiveEnvironments, watchdogs: &[WatchdogEntry]) -> Result<()> {
         if self.json {
             println!("{:#}", json!(active));
             return Ok(());
@@ -82,23 +93,27 @@ impl Envs {
         }
 
         message::created("Active environments:");
-        let envs =
-            indent::indent_all_by(2, DisplayEnvironments::new(active.iter(), true).to_string());
-        println!("{envs}");
+        let envs = DisplayEnvironments::new(active.iter(), true).with_watchdogs(watchdogs);
+        println!("{}", indent::indent_all_by(2, envs.to_string()));
 
         Ok(())
     }
 
     /// Print all environments
     ///
-    /// If `--json` is passed, print a JSON object with `active` and `inactive` keys.
+    /// If `--json` is passed, print a JSON object with `active`, `inactive`,
+    /// `aliases` and `activations` keys.
     /// If any environments are active, print them first.
-    /// Then print all inactive environments.
+    /// Then print all inactive environments, followed by any environment aliases.
+    /// Environments with running activations are annotated with the number of
+    /// activations whose watchdog is running.
     /// If no environments are known to Flox, print an appropriate message.
     fn handle_all(
         &self,
         active: ActiveEnvironments,
         registered: impl Iterator<Item = UninitializedEnvironment>,
+        aliases: &BTreeMap<String, EnvironmentAlias>,
+        watchdogs: &[WatchdogEntry],
     ) -> Result<()> {
         let inactive = get_inactive_environments(registered, active.iter())?;
 
@@ -108,29 +123,35 @@ impl Envs {
                 json!({
                     "active": active,
                     "inactive": inactive,
+                    "aliases": aliases,
+                    "activations": watchdogs,
                 })
             );
             return Ok(());
         }
 
-        if active.iter().next().is_none() && inactive.is_empty() {
+        if active.iter().next().is_none() && inactive.is_empty() && aliases.is_empty() {
             message::plain("No environments known to Flox");
         }
 
         if active.iter().next().is_some() {
             message::created("Active environments:");
-            let envs =
-                indent::indent_all_by(2, DisplayEnvironments::new(active.iter(), true).to_string());
-            println!("{envs}");
+            let envs = DisplayEnvironments::new(active.iter(), true).with_watchdogs(watchdogs);
+            println!("{}", indent::indent_all_by(2, envs.to_string()));
         }
 
         if !inactive.is_empty() {
             message::plain("Inactive environments:");
-            let envs = indent::indent_all_by(
-                2,
-                DisplayEnvironments::new(inactive.iter(), false).to_string(),
-            );
-            println!("{envs}");
+            let envs = DisplayEnvironments::new(inactive.iter(), false).with_watchdogs(watchdogs);
+            println!("{}", indent::indent_all_by(2, envs.to_string()));
+        }
+
+        if !aliases.is_empty() {
+            message::plain("Aliases:");
+            let width = aliases.keys().map(|name| name.len()).max().unwrap_or(0);
+            for (name, alias) in aliases {
+                println!("  {name:<width$}  {}", display_alias(alias));
+            }
         }
 
         Ok(())
@@ -140,6 +161,7 @@ impl Envs {
 pub(crate) struct DisplayEnvironments<'a> {
     envs: Vec<&'a UninitializedEnvironment>,
     format_active: bool,
+    watchdogs: &'a [WatchdogEntry],
 }
 
 impl<'a> DisplayEnvironments<'a> {
@@ -150,6 +172,30 @@ impl<'a> DisplayEnvironments<'a> {
         Self {
             envs: envs.into_iter().collect(),
             format_active,
+            watchdogs: &[],
+        }
+    }
+
+    /// Annotate environments with the number of their activations
+    /// that are watched by a running watchdog
+    pub(crate) fn with_watchdogs(mut self, watchdogs: &'a [WatchdogEntry]) -> Self {
+        self.watchdogs = watchdogs;
+        self
+    }
+
+    fn format_activations(&self, env: &UninitializedEnvironment) -> String {
+        let Some(path) = env.path() else {
+            return String::new();
+        };
+        match self
+            .watchdogs
+            .iter()
+            .filter(|watchdog| watchdog.watches_environment(path))
+            .count()
+        {
+            0 => String::new(),
+            1 => "  (1 running activation)".to_string(),
+            n => format!("  ({n} running activations)"),
         }
     }
 }
@@ -169,13 +215,24 @@ impl<'a> Display for DisplayEnvironments<'a> {
             let Some(first) = envs.next() else {
                 return Ok(());
             };
-            let first_formatted =
-                format!("{:<widest$}  {}", first.name(), format_path(first.path())).bold();
+            let first_formatted = format!(
+                "{:<widest$}  {}{}",
+                first.name(),
+                format_path(first.path()),
+                self.format_activations(first)
+            )
+            .bold();
             writeln!(f, "{first_formatted}")?;
         }
 
         for env in envs {
-            writeln!(f, "{:<widest$}  {}", env.name(), format_path(env.path()))?;
+            writeln!(
+                f,
+                "{:<widest$}  {}{}",
+                env.name(),
+                format_path(env.path()),
+                self.format_activations(env)
+            )?;
         }
 
         Ok(())
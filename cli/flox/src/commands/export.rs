@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Error, Result};
+use bpaf::Bpaf;
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::devcontainer::{
+    ContainerEnvironment,
+    DevContainer,
+    DevContainerError,
+    DEVCONTAINER_DIR,
+};
+use flox_rust_sdk::models::environment::ConcreteEnvironment;
+use indoc::formatdoc;
+use tracing::instrument;
+
+use super::{environment_select, EnvironmentSelect};
+use crate::subcommand_metric;
+use crate::utils::message;
+
+// Export an environment for use with other tools
+#[derive(Bpaf, Clone)]
+pub struct Export {
+    #[bpaf(external(environment_select), fallback(Default::default()))]
+    environment: EnvironmentSelect,
+
+    /// Format to export the environment as, currently only 'devcontainer'
+    #[bpaf(long, argument("format"))]
+    format: ExportFormat,
+
+    /// Directory to write the export to
+    /// (default: the directory containing the environment,
+    /// or the current directory for environments on FloxHub)
+    #[bpaf(long, short, argument("dir"))]
+    output: Option<PathBuf>,
+
+    /// Overwrite an existing export
+    #[bpaf(long)]
+    force: bool,
+}
+
+/// Formats an environment can be exported as
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    /// A `.devcontainer` directory
+    Devcontainer,
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "devcontainer" => Ok(Self::Devcontainer),
+            _ => Err(anyhow!("unknown format '{s}', expected 'devcontainer'")),
+        }
+    }
+}
+
+impl Export {
+    #[instrument(name = "export", skip_all)]
+    pub fn handle(self, flox: Flox) -> Result<()> {
+        subcommand_metric!("export");
+
+        let env = self
+            .environment
+            .detect_concrete_environment(&flox, "Export")?;
+        let remote_ref = match &env {
+            ConcreteEnvironment::Remote(remote) => Some(remote.env_ref().to_string()),
+            ConcreteEnvironment::Path(_) | ConcreteEnvironment::Managed(_) => None,
+        };
+        let env = env.into_dyn_environment();
+
+        let (output, container_environment) = match remote_ref {
+            Some(env_ref) => {
+                let output = match self.output {
+                    Some(output) => output,
+                    None => std::env::current_dir().context("Couldn't get current directory")?,
+                };
+                (output, ContainerEnvironment::Remote(env_ref))
+            },
+            None => {
+                let parent = env.parent_path()?;
+                let output = self.output.unwrap_or_else(|| parent.clone());
+                let workspace = output
+                    .canonicalize()
+                    .with_context(|| format!("Couldn't find '{}'", output.display()))?;
+                // The environment is activated from the workspace mounted in the container
+                let Ok(relative) = parent.strip_prefix(&workspace) else {
+                    bail!(formatdoc! {"
+                        The environment in '{parent}' is not inside '{workspace}'.
+
+                        The environment is activated from the workspace folder of the container,
+                        so it has to be exported to its own directory or one of its parents.
+                    ", parent = parent.display(), workspace = workspace.display()});
+                };
+                (
+                    output,
+                    ContainerEnvironment::Workspace(relative.to_path_buf()),
+                )
+            },
+        };
+
+        let manifest = env.manifest(&flox)?;
+
+        match self.format {
+            ExportFormat::Devcontainer => {
+                let devcontainer =
+                    DevContainer::new(env.name().to_string(), &manifest, container_environment);
+                match devcontainer.write(&output, self.force) {
+                    Ok(_) => {},
+                    Err(err @ DevContainerError::AlreadyExists(_)) => Err(anyhow!(err)
+                        .context("A dev container already exists, use '--force' to overwrite it"))?,
+                    Err(err) => Err(err)?,
+                }
+                message::created(format!(
+                    "Exported dev container to '{}'",
+                    output.join(DEVCONTAINER_DIR).display()
+                ));
+            },
+        }
+
+        Ok(())
+    }
+}
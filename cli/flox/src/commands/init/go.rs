@@ -140,6 +140,7 @@ impl InitHook for Go {
         };
 
         InitCustomization {
+            vars: None,
             hook_on_activate: Some(GO_HOOK.to_string()),
             profile_common: None,
             profile_bash: None,
@@ -151,6 +152,7 @@ impl InitHook for Go {
                 pkg_path: "go".to_string(),
                 version: go_version,
                 systems: None,
+                ..Default::default()
             }]),
         }
     }
@@ -0,0 +1,1203 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use flox_rust_sdk::data::compare_versions;
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::environment::path_environment::InitCustomization;
+use flox_rust_sdk::models::manifest::CatalogPackage;
+use flox_rust_sdk::models::search::SearchResults;
+use flox_rust_sdk::providers::catalog::{ClientTrait, VersionsError};
+use indoc::formatdoc;
+use itertools::Itertools;
+use tracing::debug;
+
+use super::{prompt_for_provider, InitHook, Provide, Provider};
+use crate::utils::message;
+
+const DEVBOX_JSON_FILENAME: &str = "devbox.json";
+const MISE_TOML_FILENAME: &str = "mise.toml";
+const DOT_MISE_TOML_FILENAME: &str = ".mise.toml";
+const TOOL_VERSIONS_FILENAME: &str = ".tool-versions";
+
+/// Files that are detected in a project, in the order they are offered
+const IMPORT_FILENAMES: [&str; 4] = [
+    DEVBOX_JSON_FILENAME,
+    MISE_TOML_FILENAME,
+    DOT_MISE_TOML_FILENAME,
+    TOOL_VERSIONS_FILENAME,
+];
+
+/// Catalog packages of tools whose name in devbox, mise or asdf
+/// differs from the name of the package
+const TOOL_PACKAGES: [(&str, &str); 6] = [
+    ("golang", "go"),
+    ("java", "jdk"),
+    ("node", "nodejs"),
+    ("postgres", "postgresql"),
+    ("python", "python3"),
+    ("rust", "rustc"),
+];
+
+/// Bash builtins and keywords,
+/// which imported scripts must not shadow when they are defined as functions
+const SHELL_BUILTINS: &[&str] = &[
+    "alias", "bg", "bind", "break", "builtin", "caller", "case", "cd", "command", "continue",
+    "declare", "dirs", "do", "done", "echo", "elif", "else", "enable", "esac", "eval", "exec",
+    "exit", "export", "false", "fc", "fg", "fi", "for", "function", "getopts", "hash", "help",
+    "history", "if", "in", "jobs", "kill", "let", "local", "logout", "popd", "printf", "pushd",
+    "pwd", "read", "readonly", "return", "select", "set", "shift", "source", "test", "then",
+    "time", "trap", "true", "type", "unset", "until", "while",
+];
+
+#[derive(Debug)]
+pub(super) struct Import {
+    providers: Vec<Provide<ImportedEnvironment>>,
+    selected_provider: Option<ImportedEnvironment>,
+}
+
+impl Import {
+    /// Creates and returns the [Import] hook with an [ImportedEnvironment]
+    /// for every devbox, mise or asdf file found in `path`.
+    /// If no such file is found, returns [None].
+    pub async fn new(flox: &Flox, path: &Path) -> Option<Self> {
+        let mut providers = vec![];
+        for filename in IMPORT_FILENAMES {
+            let file = path.join(filename);
+            if !file.is_file() {
+                continue;
+            }
+            providers.push(Provide::from(
+                ImportedEnvironment::from_file(flox, &file).await.map(Some),
+            ));
+        }
+
+        debug!("Detected environments to import: {:#?}", providers);
+
+        if !providers
+            .iter()
+            .any(|provider| matches!(provider, Provide::Found(_)))
+        {
+            return None;
+        }
+
+        Some(Self {
+            providers,
+            selected_provider: None,
+        })
+    }
+
+    /// Creates the [Import] hook for a file passed to `flox init --import`
+    pub async fn from_file(flox: &Flox, file: &Path) -> Result<Self> {
+        let imported = ImportedEnvironment::from_file(flox, file).await?;
+        Ok(Self {
+            providers: vec![Provide::Found(imported)],
+            selected_provider: None,
+        })
+    }
+
+    /// Warn about the settings of the imported file that could not be translated
+    pub fn report_untranslated(&self) {
+        let imported = self.selected_provider.as_ref().or_else(|| {
+            self.providers.iter().find_map(|provider| match provider {
+                Provide::Found(provider) => Some(provider),
+                _ => None,
+            })
+        });
+        let Some(imported) = imported else {
+            return;
+        };
+        if imported.untranslated.is_empty() {
+            return;
+        }
+
+        message::warning(formatdoc! {"
+            Some settings of '{file}' could not be imported:
+            {untranslated}
+            Use 'flox edit' to add them to the environment manually.",
+            file = imported.file_name,
+            untranslated = describe_untranslated(&imported.untranslated),
+        });
+    }
+}
+
+impl InitHook for Import {
+    /// Empties the [Import::providers] and stores the selected provider in [Import::selected_provider]
+    async fn prompt_user(&mut self, _flox: &Flox, _path: &Path) -> Result<bool> {
+        let found_providers = std::mem::take(&mut self.providers)
+            .into_iter()
+            .filter_map(|provider| match provider {
+                Provide::Found(provider) => Some(provider),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.selected_provider = prompt_for_provider(
+            found_providers,
+            "Flox detected the following environment definition(s) of other tools:",
+            "Would you like Flox to import the environment?",
+        )?;
+        Ok(self.selected_provider.is_some())
+    }
+
+    /// Returns the customization of the selected provider or the first found provider
+    fn get_init_customization(&self) -> InitCustomization {
+        let selected = self
+            .selected_provider
+            .as_ref()
+            .map(|p| p.get_init_customization());
+        // self.providers will be empty if prompt_user() was called
+        let default = self.providers.iter().find_map(|provider| match provider {
+            Provide::Found(provider) => Some(provider.get_init_customization()),
+            _ => None,
+        });
+
+        selected
+            .or(default)
+            .expect("Should only be called if `prompt_user` returned `true`")
+    }
+}
+
+impl From<Result<Option<ImportedEnvironment>>> for Provide<ImportedEnvironment> {
+    fn from(result: Result<Option<ImportedEnvironment>>) -> Self {
+        match result {
+            Ok(Some(provider)) => Provide::Found(provider),
+            Ok(None) => Provide::NotFound,
+            Err(err) => Provide::Invalid(err),
+        }
+    }
+}
+
+/// The tools whose environment definitions can be imported
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportFormat {
+    /// `devbox.json`
+    Devbox,
+    /// `mise.toml` or `.mise.toml`
+    Mise,
+    /// asdf's `.tool-versions`
+    ToolVersions,
+}
+
+impl ImportFormat {
+    /// Detect the format of a file from its name
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.file_name()?.to_str()? {
+            DEVBOX_JSON_FILENAME => Some(Self::Devbox),
+            MISE_TOML_FILENAME | DOT_MISE_TOML_FILENAME => Some(Self::Mise),
+            TOOL_VERSIONS_FILENAME => Some(Self::ToolVersions),
+            _ => None,
+        }
+    }
+
+    fn tool_name(&self) -> &'static str {
+        match self {
+            ImportFormat::Devbox => "devbox",
+            ImportFormat::Mise => "mise",
+            ImportFormat::ToolVersions => "asdf",
+        }
+    }
+
+    fn parse(&self, content: &str) -> Result<ParsedEnvironment> {
+        match self {
+            ImportFormat::Devbox => parse_devbox_json(content),
+            ImportFormat::Mise => parse_mise_toml(content),
+            ImportFormat::ToolVersions => Ok(parse_tool_versions(content)),
+        }
+    }
+}
+
+/// A tool requested by an imported file
+#[derive(Debug, Clone, PartialEq)]
+struct RequestedTool {
+    /// The name of the tool in the imported file
+    name: String,
+    /// The catalog package providing the tool
+    pkg_path: String,
+    /// A full or partial version, e.g. `3.11.9` or `3.11`,
+    /// [None] for the latest version
+    version: Option<String>,
+    /// Systems to install the tool on, [None] for all systems
+    systems: Option<Vec<String>>,
+}
+
+impl RequestedTool {
+    fn new(name: &str, version: Option<String>) -> Self {
+        let pkg_path = TOOL_PACKAGES
+            .iter()
+            .find(|(tool, _)| *tool == name)
+            .map(|(_, pkg_path)| *pkg_path)
+            .unwrap_or(name);
+
+        Self {
+            name: name.to_string(),
+            pkg_path: pkg_path.to_string(),
+            version,
+            systems: None,
+        }
+    }
+
+    /// The install ID is the last component of the package path,
+    /// e.g. `pip` for `python311Packages.pip`
+    fn install_id(&self) -> &str {
+        self.pkg_path.rsplit('.').next().unwrap_or(&self.pkg_path)
+    }
+}
+
+/// The contents of an imported file, before its tools are resolved in the catalog
+#[derive(Debug, Default, PartialEq)]
+struct ParsedEnvironment {
+    tools: Vec<RequestedTool>,
+    vars: BTreeMap<String, String>,
+    hooks: Vec<String>,
+    scripts: BTreeMap<String, String>,
+    /// Descriptions of settings that can't be expressed in a manifest
+    untranslated: Vec<String>,
+}
+
+impl ParsedEnvironment {
+    /// Normalize a version as written in an imported file.
+    ///
+    /// `latest`, `lts` and empty versions request the latest version,
+    /// `v` and mise's `prefix:` are removed.
+    /// Anything but a version number, e.g. `system` or `ref:main`,
+    /// is reported and replaced by the latest version.
+    fn requested_version(&mut self, tool: &str, version: &str) -> Option<String> {
+        let version = version.trim();
+        let version = version.strip_prefix("prefix:").unwrap_or(version);
+        let version = version.strip_prefix('v').unwrap_or(version);
+        match version {
+            "" | "latest" | "lts" => None,
+            version if version.starts_with(|c: char| c.is_ascii_digit()) => {
+                Some(version.to_string())
+            },
+            version => {
+                self.untranslated.push(format!(
+                    "{tool}: version '{version}' is not supported, the latest version will be installed"
+                ));
+                None
+            },
+        }
+    }
+}
+
+/// Parse a `devbox.json`
+///
+/// Packages are nixpkgs attribute paths with an optional version, e.g. `nodejs@20`,
+/// `env`, `shell.init_hook` and `shell.scripts` are translated as they are,
+/// with `$DEVBOX_PROJECT_ROOT` replaced by `$FLOX_ENV_PROJECT`.
+fn parse_devbox_json(content: &str) -> Result<ParsedEnvironment> {
+    use serde_json::Value;
+
+    let devbox: serde_json::Map<String, Value> =
+        serde_json::from_str(content).context("Invalid devbox.json")?;
+    let mut parsed = ParsedEnvironment::default();
+
+    let from_devbox = |s: &str| {
+        s.replace("${DEVBOX_PROJECT_ROOT}", "${FLOX_ENV_PROJECT}")
+            .replace("$DEVBOX_PROJECT_ROOT", "$FLOX_ENV_PROJECT")
+    };
+    // Init hooks and scripts are strings or lists of commands
+    let commands = |value: &Value| match value {
+        Value::String(command) => Some(from_devbox(command)),
+        Value::Array(commands) => commands
+            .iter()
+            .map(|command| command.as_str().map(from_devbox))
+            .collect::<Option<Vec<_>>>()
+            .map(|commands| commands.join("\n")),
+        _ => None,
+    };
+
+    for (key, value) in devbox {
+        match (key.as_str(), value) {
+            ("packages", Value::Array(packages)) => {
+                for package in packages {
+                    let Some(package) = package.as_str() else {
+                        parsed
+                            .untranslated
+                            .push(format!("package {package} is not a string"));
+                        continue;
+                    };
+                    let (name, version) = match package.rsplit_once('@') {
+                        Some((name, version)) if !name.is_empty() => (name, Some(version)),
+                        _ => (package, None),
+                    };
+                    parse_devbox_package(&mut parsed, name, version, None);
+                }
+            },
+            ("packages", Value::Object(packages)) => {
+                for (name, spec) in packages {
+                    match spec {
+                        Value::String(version) => {
+                            parse_devbox_package(&mut parsed, &name, Some(&version), None)
+                        },
+                        Value::Object(spec) => {
+                            let version = spec.get("version").and_then(Value::as_str);
+                            let platforms = spec.get("platforms").and_then(|platforms| {
+                                platforms
+                                    .as_array()?
+                                    .iter()
+                                    .map(|platform| platform.as_str().map(String::from))
+                                    .collect::<Option<Vec<_>>>()
+                            });
+                            for option in spec.keys() {
+                                if !["version", "platforms"].contains(&option.as_str()) {
+                                    parsed.untranslated.push(format!("{name}: '{option}'"));
+                                }
+                            }
+                            parse_devbox_package(&mut parsed, &name, version, platforms);
+                        },
+                        _ => parsed
+                            .untranslated
+                            .push(format!("{name}: unsupported package definition")),
+                    }
+                }
+            },
+            ("env", Value::Object(env)) => {
+                for (name, value) in env {
+                    match value {
+                        Value::String(value) => {
+                            parsed.vars.insert(name, from_devbox(&value));
+                        },
+                        value => parsed
+                            .untranslated
+                            .push(format!("env.{name}: {value} is not a string")),
+                    }
+                }
+            },
+            ("shell", Value::Object(shell)) => {
+                for (key, value) in shell {
+                    match (key.as_str(), &value) {
+                        ("init_hook", value) => match commands(value) {
+                            Some(hook) => parsed.hooks.push(hook),
+                            None => parsed
+                                .untranslated
+                                .push("shell.init_hook: not a list of commands".to_string()),
+                        },
+                        ("scripts", Value::Object(scripts)) => {
+                            for (name, script) in scripts {
+                                match commands(script) {
+                                    Some(script) => {
+                                        parsed.scripts.insert(name.clone(), script);
+                                    },
+                                    None => parsed.untranslated.push(format!(
+                                        "shell.scripts.{name}: not a list of commands"
+                                    )),
+                                }
+                            }
+                        },
+                        (key, _) => parsed.untranslated.push(format!("shell.{key}")),
+                    }
+                }
+            },
+            ("$schema" | "name" | "description", _) => {},
+            (key, _) => parsed.untranslated.push(format!("'{key}'")),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_devbox_package(
+    parsed: &mut ParsedEnvironment,
+    name: &str,
+    version: Option<&str>,
+    systems: Option<Vec<String>>,
+) {
+    // Flake references and local paths, e.g. `github:org/repo#pkg` or `./flake`
+    if name.contains([':', '#', '/']) || name.starts_with('.') {
+        parsed.untranslated.push(format!(
+            "{name}: flake references can be installed with 'flox install {name}'"
+        ));
+        return;
+    }
+
+    let version = version.and_then(|version| parsed.requested_version(name, version));
+    parsed.tools.push(RequestedTool {
+        systems,
+        ..RequestedTool::new(name, version)
+    });
+}
+
+/// Parse a `mise.toml`
+///
+/// `[tools]` are installed from the catalog, plain `[env]` variables,
+/// `[tasks]` and the `enter` hook are translated.
+/// Tools of backends other than `core` and `asdf`,
+/// e.g. `npm:prettier`, and `[env]` directives are reported.
+fn parse_mise_toml(content: &str) -> Result<ParsedEnvironment> {
+    use toml::Value;
+
+    let mise: toml::Table = toml::from_str(content).context("Invalid mise.toml")?;
+    let mut parsed = ParsedEnvironment::default();
+
+    let from_mise = |s: &str| {
+        s.replace("{{config_root}}", "$FLOX_ENV_PROJECT")
+            .replace("{{ config_root }}", "$FLOX_ENV_PROJECT")
+    };
+    // Tasks and hooks are strings or lists of commands
+    let commands = |value: &Value| match value {
+        Value::String(command) => Some(from_mise(command)),
+        Value::Array(commands) => commands
+            .iter()
+            .map(|command| command.as_str().map(from_mise))
+            .collect::<Option<Vec<_>>>()
+            .map(|commands| commands.join("\n")),
+        _ => None,
+    };
+
+    for (key, value) in mise {
+        match (key.as_str(), value) {
+            ("tools", Value::Table(tools)) => {
+                for (name, spec) in tools {
+                    let name = name
+                        .strip_prefix("core:")
+                        .or_else(|| name.strip_prefix("asdf:"))
+                        .unwrap_or(&name);
+                    if let Some((backend, _)) = name.split_once(':') {
+                        parsed.untranslated.push(format!(
+                            "{name}: tools of the '{backend}' backend can't be installed from the catalog"
+                        ));
+                        continue;
+                    }
+
+                    let version = match spec {
+                        Value::String(version) => Some(version),
+                        Value::Integer(version) => Some(version.to_string()),
+                        Value::Float(version) => Some(version.to_string()),
+                        Value::Array(versions) => {
+                            let mut versions = versions.iter().filter_map(Value::as_str);
+                            let first = versions.next().map(String::from);
+                            let rest = versions.join(", ");
+                            if !rest.is_empty() {
+                                parsed.untranslated.push(format!(
+                                    "{name}: only the first version is installed, not {rest}"
+                                ));
+                            }
+                            first
+                        },
+                        Value::Table(spec) => {
+                            for option in spec.keys().filter(|option| *option != "version") {
+                                parsed.untranslated.push(format!("{name}: '{option}'"));
+                            }
+                            spec.get("version")
+                                .and_then(Value::as_str)
+                                .map(String::from)
+                        },
+                        _ => None,
+                    };
+                    let version =
+                        version.and_then(|version| parsed.requested_version(name, &version));
+                    parsed.tools.push(RequestedTool::new(name, version));
+                }
+            },
+            ("env", Value::Table(env)) => {
+                for (name, value) in env {
+                    let value = match value {
+                        _ if name == "_" => {
+                            parsed.untranslated.push(
+                                "env._: directives such as '_.path' and '_.file'".to_string(),
+                            );
+                            continue;
+                        },
+                        Value::String(value) => from_mise(&value),
+                        Value::Integer(value) => value.to_string(),
+                        Value::Float(value) => value.to_string(),
+                        value => {
+                            parsed
+                                .untranslated
+                                .push(format!("env.{name}: {value} is not a string"));
+                            continue;
+                        },
+                    };
+                    if value.contains("{{") {
+                        parsed
+                            .untranslated
+                            .push(format!("env.{name}: templates are not supported"));
+                        continue;
+                    }
+                    parsed.vars.insert(name, value);
+                }
+            },
+            ("tasks", Value::Table(tasks)) => {
+                for (name, task) in tasks {
+                    let run = match &task {
+                        Value::Table(task) => {
+                            for option in task
+                                .keys()
+                                .filter(|option| !["run", "description"].contains(&option.as_str()))
+                            {
+                                parsed
+                                    .untranslated
+                                    .push(format!("tasks.{name}: '{option}'"));
+                            }
+                            task.get("run")
+                        },
+                        task => Some(task),
+                    };
+                    match run.and_then(commands) {
+                        Some(script) => {
+                            parsed.scripts.insert(name, script);
+                        },
+                        None => parsed
+                            .untranslated
+                            .push(format!("tasks.{name}: not a list of commands")),
+                    }
+                }
+            },
+            ("hooks", Value::Table(hooks)) => {
+                for (name, hook) in hooks {
+                    match (name.as_str(), commands(&hook)) {
+                        ("enter", Some(hook)) => parsed.hooks.push(hook),
+                        (name, _) => parsed.untranslated.push(format!("hooks.{name}")),
+                    }
+                }
+            },
+            ("min_version", _) => {},
+            (key, _) => parsed.untranslated.push(format!("'{key}'")),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Parse asdf's `.tool-versions`
+///
+/// Each line names a tool followed by one or more versions,
+/// only the first of which is installed.
+fn parse_tool_versions(content: &str) -> ParsedEnvironment {
+    let mut parsed = ParsedEnvironment::default();
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(name) = fields.next() else {
+            continue;
+        };
+
+        let version = fields.next();
+        let rest = fields.join(", ");
+        if !rest.is_empty() {
+            parsed.untranslated.push(format!(
+                "{name}: only the first version is installed, not {rest}"
+            ));
+        }
+
+        let version = version.and_then(|version| parsed.requested_version(name, version));
+        parsed.tools.push(RequestedTool::new(name, version));
+    }
+
+    parsed
+}
+
+/// A package of an imported environment, resolved in the catalog
+#[derive(Debug, Clone, PartialEq)]
+struct ImportedPackage {
+    package: CatalogPackage,
+    /// The version that will be installed, used for display purposes only
+    display_version: String,
+}
+
+/// An environment definition of another tool, translated to manifest settings
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ImportedEnvironment {
+    format: ImportFormat,
+    file_name: String,
+    packages: Vec<ImportedPackage>,
+    vars: BTreeMap<String, String>,
+    hooks: Vec<String>,
+    scripts: BTreeMap<String, String>,
+    untranslated: Vec<String>,
+}
+
+impl ImportedEnvironment {
+    async fn from_file(flox: &Flox, file: &Path) -> Result<Self> {
+        let format = ImportFormat::from_path(file).ok_or_else(|| {
+            anyhow!(
+                "Can't import '{}', expected a devbox.json, mise.toml or .tool-versions file",
+                file.display()
+            )
+        })?;
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read '{}'", file.display()))?;
+        let file_name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let parsed = format
+            .parse(&content)
+            .with_context(|| format!("Failed to import '{}'", file.display()))?;
+
+        Self::resolve(flox, format, file_name, parsed).await
+    }
+
+    /// Resolve the versions of the requested tools in the catalog
+    async fn resolve(
+        flox: &Flox,
+        format: ImportFormat,
+        file_name: String,
+        parsed: ParsedEnvironment,
+    ) -> Result<Self> {
+        let mut untranslated = parsed.untranslated;
+        let mut packages = vec![];
+        for tool in parsed.tools {
+            if let Some(package) = resolve_tool(flox, &tool, &mut untranslated).await? {
+                packages.push(package);
+            }
+        }
+
+        // Functions don't work with every name
+        let mut scripts = parsed.scripts;
+        scripts.retain(|name, _| {
+            let valid = !SHELL_BUILTINS.contains(&name.as_str())
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || ['_', '-', ':', '.'].contains(&c));
+            if !valid {
+                untranslated.push(format!(
+                    "script '{name}': the name can't be used as a shell function"
+                ));
+            }
+            valid
+        });
+        // Scripts are only defined as bash and zsh functions
+        for name in scripts.keys() {
+            untranslated.push(format!("script '{name}': not available in fish and tcsh"));
+        }
+
+        Ok(Self {
+            format,
+            file_name,
+            packages,
+            vars: parsed.vars,
+            hooks: parsed.hooks,
+            scripts,
+            untranslated,
+        })
+    }
+
+    /// Variables that reference other variables can only be expanded by the shell,
+    /// so they are exported by the hook rather than added to `[vars]`.
+    fn partition_vars(&self) -> (BTreeMap<String, String>, Vec<(&String, &String)>) {
+        let (exported, vars): (Vec<_>, Vec<_>) =
+            self.vars.iter().partition(|(_, value)| value.contains('$'));
+        let vars = vars
+            .into_iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        (vars, exported)
+    }
+}
+
+/// Find the requested version of a tool in the catalog.
+///
+/// Partial versions such as `20` or `3.11` match the latest release with that prefix
+/// and are kept as a semver requirement, so upgrades stay within the release series.
+/// If the requested version isn't available, the latest version is used instead.
+///
+/// Returns [None] if the package doesn't exist in the catalog.
+async fn resolve_tool(
+    flox: &Flox,
+    tool: &RequestedTool,
+    untranslated: &mut Vec<String>,
+) -> Result<Option<ImportedPackage>> {
+    let results = match flox.catalog_client.package_versions(&tool.pkg_path).await {
+        Ok(results) => results,
+        Err(VersionsError::Versions(e)) if e.status() == 404 => SearchResults {
+            results: vec![],
+            count: None::<u64>,
+        },
+        Err(e) => Err(e)?,
+    };
+
+    let available = results
+        .results
+        .into_iter()
+        .filter(|result| result.system == flox.system)
+        .filter_map(|result| result.version)
+        .unique()
+        .collect::<Vec<_>>();
+
+    let Some(latest) = available
+        .iter()
+        .max_by(|a, b| compare_versions(a, b))
+        .cloned()
+    else {
+        untranslated.push(format!(
+            "{}: package '{}' was not found in the catalog",
+            tool.name, tool.pkg_path
+        ));
+        return Ok(None);
+    };
+
+    let package = |version: Option<String>, display_version: String| ImportedPackage {
+        package: CatalogPackage {
+            id: tool.install_id().to_string(),
+            pkg_path: tool.pkg_path.clone(),
+            version,
+            systems: tool.systems.clone(),
+            ..Default::default()
+        },
+        display_version,
+    };
+
+    let Some(requested) = &tool.version else {
+        return Ok(Some(package(None, latest)));
+    };
+
+    let series = format!("{requested}.");
+    let compatible = available
+        .iter()
+        .filter(|version| *version == requested || version.starts_with(&series))
+        .max_by(|a, b| compare_versions(a, b));
+
+    match compatible {
+        Some(compatible) => Ok(Some(package(
+            Some(version_requirement(requested, compatible)),
+            compatible.clone(),
+        ))),
+        None => {
+            untranslated.push(format!(
+                "{}: version {requested} is not available, {latest} will be installed instead",
+                tool.name
+            ));
+            Ok(Some(package(None, latest)))
+        },
+    }
+}
+
+/// The version requirement for a requested version available in the catalog:
+/// `=3.11.9` for an exact version, `~3.11` for a release series,
+/// or the version found in the catalog if it isn't semver.
+fn version_requirement(requested: &str, found: &str) -> String {
+    let requirement = if requested == found {
+        format!("={found}")
+    } else {
+        format!("~{requested}")
+    };
+    if semver::Version::parse(found).is_ok() && requirement.parse::<semver::VersionReq>().is_ok() {
+        requirement
+    } else {
+        found.to_string()
+    }
+}
+
+fn describe_untranslated(untranslated: &[String]) -> String {
+    untranslated
+        .iter()
+        .map(|setting| format!("  - {setting}"))
+        .join("\n")
+}
+
+impl Provider for ImportedEnvironment {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        self.format.tool_name().into()
+    }
+
+    fn describe_reason(&self) -> Cow<'_, str> {
+        self.file_name.as_str().into()
+    }
+
+    fn describe_customization(&self) -> Cow<'_, str> {
+        let mut lines = vec![];
+        if !self.packages.is_empty() {
+            lines.push(format!(
+                "Installs {}",
+                self.packages
+                    .iter()
+                    .map(|package| format!("{} ({})", package.package.id, package.display_version))
+                    .join(", ")
+            ));
+        }
+        if !self.vars.is_empty() {
+            lines.push(format!("Sets {}", self.vars.keys().join(", ")));
+        }
+        if !self.hooks.is_empty() {
+            lines.push("Adds the hook that runs on activation".to_string());
+        }
+        if !self.scripts.is_empty() {
+            lines.push(format!(
+                "Adds the scripts {} as bash and zsh functions",
+                self.scripts.keys().join(", ")
+            ));
+        }
+        if !self.untranslated.is_empty() {
+            lines.push(formatdoc! {"
+
+                Can't import:
+                {}", describe_untranslated(&self.untranslated)});
+        }
+        (lines.join("\n") + "\n").into()
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        let (vars, exported) = self.partition_vars();
+
+        let exports = exported
+            .into_iter()
+            .map(|(name, value)| format!("export {name}=\"{}\"", value.replace('"', "\\\"")));
+        let hook = exports
+            .chain(self.hooks.iter().cloned())
+            .collect::<Vec<_>>();
+
+        let functions = self
+            .scripts
+            .iter()
+            .map(|(name, script)| {
+                formatdoc! {"
+                    {name}() {{
+                    {}
+                    }}", indent::indent_all_by(2, script.trim_end())}
+            })
+            .join("\n\n");
+
+        let import_comment = format!("# Imported from {}", self.file_name);
+        let with_comment = |script: String| format!("{import_comment}\n{script}");
+
+        InitCustomization {
+            vars: (!vars.is_empty()).then_some(vars),
+            hook_on_activate: (!hook.is_empty()).then(|| with_comment(hook.join("\n"))),
+            profile_common: None,
+            profile_bash: (!functions.is_empty()).then(|| with_comment(functions.clone())),
+            profile_fish: None,
+            profile_tcsh: None,
+            profile_zsh: (!functions.is_empty()).then(|| with_comment(functions.clone())),
+            packages: (!self.packages.is_empty()).then(|| {
+                self.packages
+                    .iter()
+                    .map(|package| package.package.clone())
+                    .collect()
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::flox::test_helpers::flox_instance;
+    use flox_rust_sdk::models::search::SearchResult;
+    use flox_rust_sdk::providers::catalog::Client;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn tool(name: &str, pkg_path: &str, version: Option<&str>) -> RequestedTool {
+        RequestedTool {
+            name: name.to_string(),
+            pkg_path: pkg_path.to_string(),
+            version: version.map(String::from),
+            systems: None,
+        }
+    }
+
+    fn versions(system: &str, pkg_path: &str, versions: &[&str]) -> SearchResults {
+        SearchResults {
+            results: versions
+                .iter()
+                .map(|version| SearchResult {
+                    input: "nixpkgs".to_string(),
+                    system: system.to_string(),
+                    rel_path: vec![pkg_path.to_string()],
+                    pname: Some(pkg_path.to_string()),
+                    version: Some(version.to_string()),
+                    description: None,
+                    license: None,
+                })
+                .collect(),
+            count: Some(versions.len() as u64),
+        }
+    }
+
+    #[test]
+    fn import_format_from_file_name() {
+        assert_eq!(
+            ImportFormat::from_path(Path::new("project/devbox.json")),
+            Some(ImportFormat::Devbox)
+        );
+        assert_eq!(
+            ImportFormat::from_path(Path::new(".mise.toml")),
+            Some(ImportFormat::Mise)
+        );
+        assert_eq!(
+            ImportFormat::from_path(Path::new(".tool-versions")),
+            Some(ImportFormat::ToolVersions)
+        );
+        assert_eq!(ImportFormat::from_path(Path::new("package.json")), None);
+    }
+
+    #[test]
+    fn parse_devbox_json_packages_env_and_scripts() {
+        let parsed = parse_devbox_json(indoc! {r#"
+            {
+              "$schema": "https://raw.githubusercontent.com/jetify-com/devbox/main/.schema/devbox.schema.json",
+              "packages": ["nodejs@20", "python311Packages.pip@latest", "github:numtide/treefmt"],
+              "env": {
+                "NODE_ENV": "development",
+                "DATA_DIR": "$DEVBOX_PROJECT_ROOT/data"
+              },
+              "shell": {
+                "init_hook": ["echo 'Welcome'"],
+                "scripts": {
+                  "build": "npm run build",
+                  "test": ["npm ci", "npm test"]
+                }
+              },
+              "include": ["plugin:nginx"]
+            }
+        "#})
+        .unwrap();
+
+        assert_eq!(parsed, ParsedEnvironment {
+            tools: vec![
+                tool("nodejs", "nodejs", Some("20")),
+                tool("python311Packages.pip", "python311Packages.pip", None),
+            ],
+            vars: BTreeMap::from([
+                (
+                    "DATA_DIR".to_string(),
+                    "$FLOX_ENV_PROJECT/data".to_string()
+                ),
+                ("NODE_ENV".to_string(), "development".to_string()),
+            ]),
+            hooks: vec!["echo 'Welcome'".to_string()],
+            scripts: BTreeMap::from([
+                ("build".to_string(), "npm run build".to_string()),
+                ("test".to_string(), "npm ci\nnpm test".to_string()),
+            ]),
+            untranslated: vec![
+                "'include'".to_string(),
+                "github:numtide/treefmt: flake references can be installed with 'flox install github:numtide/treefmt'".to_string(),
+            ],
+        });
+    }
+
+    #[test]
+    fn parse_devbox_json_package_map_with_platforms() {
+        let parsed = parse_devbox_json(indoc! {r#"
+            {
+              "packages": {
+                "go": "1.22",
+                "glibcLocales": {
+                  "version": "latest",
+                  "platforms": ["x86_64-linux", "aarch64-linux"],
+                  "outputs": ["out"]
+                }
+              }
+            }
+        "#})
+        .unwrap();
+
+        assert_eq!(parsed.tools, vec![
+            RequestedTool {
+                systems: Some(vec![
+                    "x86_64-linux".to_string(),
+                    "aarch64-linux".to_string()
+                ]),
+                ..tool("glibcLocales", "glibcLocales", None)
+            },
+            tool("go", "go", Some("1.22")),
+        ]);
+        assert_eq!(parsed.untranslated, vec!["glibcLocales: 'outputs'"]);
+    }
+
+    #[test]
+    fn parse_mise_toml_tools_env_and_tasks() {
+        let parsed = parse_mise_toml(indoc! {r#"
+            min_version = "2024.1.0"
+
+            [tools]
+            node = "20"
+            python = ["3.11", "3.10"]
+            "npm:prettier" = "latest"
+            go = { version = "1.22.1" }
+
+            [env]
+            _.path = ["./bin"]
+            PORT = 8080
+            CONFIG = "{{config_root}}/config.yml"
+
+            [tasks]
+            lint = "npm run lint"
+            build = { run = ["npm ci", "npm run build"], depends = ["lint"] }
+
+            [hooks]
+            enter = "echo entered"
+            leave = "echo left"
+        "#})
+        .unwrap();
+
+        assert_eq!(parsed, ParsedEnvironment {
+            tools: vec![
+                tool("go", "go", Some("1.22.1")),
+                tool("node", "nodejs", Some("20")),
+                tool("python", "python3", Some("3.11")),
+            ],
+            vars: BTreeMap::from([
+                (
+                    "CONFIG".to_string(),
+                    "$FLOX_ENV_PROJECT/config.yml".to_string()
+                ),
+                ("PORT".to_string(), "8080".to_string()),
+            ]),
+            hooks: vec!["echo entered".to_string()],
+            scripts: BTreeMap::from([
+                ("build".to_string(), "npm ci\nnpm run build".to_string()),
+                ("lint".to_string(), "npm run lint".to_string()),
+            ]),
+            untranslated: vec![
+                "env._: directives such as '_.path' and '_.file'".to_string(),
+                "hooks.leave".to_string(),
+                "tasks.build: 'depends'".to_string(),
+                "npm:prettier: tools of the 'npm' backend can't be installed from the catalog"
+                    .to_string(),
+                "python: only the first version is installed, not 3.10".to_string(),
+            ],
+        });
+    }
+
+    #[test]
+    fn parse_tool_versions_with_comments_and_fallback_versions() {
+        let parsed = parse_tool_versions(indoc! {"
+            # runtimes
+            nodejs 20.11.1
+            python 3.11.9 3.10.14 # fallback
+            golang system
+
+            terraform v1.7.4
+        "});
+
+        assert_eq!(parsed, ParsedEnvironment {
+            tools: vec![
+                tool("nodejs", "nodejs", Some("20.11.1")),
+                tool("python", "python3", Some("3.11.9")),
+                tool("golang", "go", None),
+                tool("terraform", "terraform", Some("1.7.4")),
+            ],
+            untranslated: vec![
+                "python: only the first version is installed, not 3.10.14".to_string(),
+                "golang: version 'system' is not supported, the latest version will be installed"
+                    .to_string(),
+            ],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn version_requirement_for_series_and_exact_versions() {
+        assert_eq!(version_requirement("20", "20.11.1"), "~20");
+        assert_eq!(version_requirement("3.11", "3.11.9"), "~3.11");
+        assert_eq!(version_requirement("3.11.9", "3.11.9"), "=3.11.9");
+        // Not semver
+        assert_eq!(version_requirement("1.22", "1.22"), "1.22");
+        assert_eq!(version_requirement("2024", "2024.01.31"), "2024.01.31");
+    }
+
+    #[test]
+    fn customization_exports_expanded_vars_and_defines_functions() {
+        let imported = ImportedEnvironment {
+            format: ImportFormat::Devbox,
+            file_name: DEVBOX_JSON_FILENAME.to_string(),
+            packages: vec![],
+            vars: BTreeMap::from([
+                ("DATA_DIR".to_string(), "$FLOX_ENV_PROJECT/data".to_string()),
+                ("NODE_ENV".to_string(), "development".to_string()),
+            ]),
+            hooks: vec!["echo 'Welcome'".to_string()],
+            scripts: BTreeMap::from([("build".to_string(), "npm ci\nnpm run build".to_string())]),
+            untranslated: vec![],
+        };
+
+        let customization = imported.get_init_customization();
+        assert_eq!(
+            customization.vars,
+            Some(BTreeMap::from([(
+                "NODE_ENV".to_string(),
+                "development".to_string()
+            )]))
+        );
+        assert_eq!(customization.hook_on_activate.unwrap(), indoc! {r#"
+            # Imported from devbox.json
+            export DATA_DIR="$FLOX_ENV_PROJECT/data"
+            echo 'Welcome'"#});
+        assert_eq!(customization.profile_bash.unwrap(), indoc! {"
+            # Imported from devbox.json
+            build() {
+              npm ci
+              npm run build
+            }"});
+        assert_eq!(customization.packages, None);
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // Catalog tests
+    ///////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn resolve_partial_missing_and_unknown_versions() {
+        let (mut flox, _temp_dir_handle) = flox_instance();
+        let system = flox.system.clone();
+
+        if let Client::Mock(ref mut client) = flox.catalog_client {
+            client.push_search_response(versions(&system, "nodejs", &[
+                "22.1.0", "20.9.0", "20.11.1", "18.19.1",
+            ]));
+            client.push_search_response(versions(&system, "python3", &["3.12.3", "3.11.9"]));
+            client.push_search_response(versions(&system, "terraform", &[]));
+        }
+
+        let parsed = ParsedEnvironment {
+            tools: vec![
+                tool("node", "nodejs", Some("20")),
+                tool("python", "python3", Some("3.8")),
+                tool("terraform", "terraform", None),
+            ],
+            scripts: BTreeMap::from([
+                ("build".to_string(), "npm run build".to_string()),
+                ("test".to_string(), "npm test".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let imported = ImportedEnvironment::resolve(
+            &flox,
+            ImportFormat::Mise,
+            MISE_TOML_FILENAME.to_string(),
+            parsed,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(imported.packages, vec![
+            ImportedPackage {
+                package: CatalogPackage {
+                    id: "nodejs".to_string(),
+                    pkg_path: "nodejs".to_string(),
+                    version: Some("~20".to_string()),
+                    systems: None,
+                    ..Default::default()
+                },
+                display_version: "20.11.1".to_string(),
+            },
+            ImportedPackage {
+                package: CatalogPackage {
+                    id: "python3".to_string(),
+                    pkg_path: "python3".to_string(),
+                    version: None,
+                    systems: None,
+                    ..Default::default()
+                },
+                display_version: "3.12.3".to_string(),
+            },
+        ]);
+        assert_eq!(imported.scripts.keys().collect::<Vec<_>>(), vec!["build"]);
+        assert_eq!(imported.untranslated, vec![
+            "python: version 3.8 is not available, 3.12.3 will be installed instead",
+            "terraform: package 'terraform' was not found in the catalog",
+            "script 'test': the name can't be used as a shell function",
+            "script 'build': not available in fish and tcsh",
+        ]);
+    }
+}
@@ -0,0 +1,664 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::environment::path_environment::InitCustomization;
+use flox_rust_sdk::models::manifest::CatalogPackage;
+use indoc::formatdoc;
+use regex::Regex;
+use tracing::debug;
+
+use super::{
+    get_default_package,
+    get_default_package_if_compatible,
+    prompt_for_provider,
+    InitHook,
+    Provide,
+    ProvidedVersion,
+    Provider,
+};
+
+const POM_XML_FILENAME: &str = "pom.xml";
+const GRADLE_FILENAMES: [&str; 2] = ["build.gradle", "build.gradle.kts"];
+const JAVA_VERSION_FILENAME: &str = ".java-version";
+const SDKMANRC_FILENAME: &str = ".sdkmanrc";
+
+/// Properties of a `pom.xml` that set the Java release, in order of precedence
+const POM_JAVA_PROPERTIES: [&str; 2] = ["maven.compiler.release", "java.version"];
+
+/// Matches the language version of a Gradle toolchain block, e.g.
+/// `languageVersion = JavaLanguageVersion.of(17)` or
+/// `languageVersion.set(JavaLanguageVersion.of(17))`
+static GRADLE_TOOLCHAIN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"languageVersion\b[^\n]*JavaLanguageVersion\.of\(\s*"?(\d+)"?\s*\)"#).unwrap()
+});
+
+#[derive(Debug)]
+pub(super) struct Java {
+    providers: Vec<Provide<JavaProvider>>,
+    selected_provider: Option<JavaProvider>,
+}
+
+impl Java {
+    /// Creates and returns the [Java] hook with any detected
+    /// [Provider] instances.
+    /// If no providers are detected, returns [None].
+    pub async fn new(flox: &Flox, path: &Path) -> Option<Self> {
+        let providers = vec![
+            Provide::from(Maven::detect(flox, path).await),
+            Provide::from(Gradle::detect(flox, path).await),
+        ];
+
+        debug!("Detected Java providers: {:#?}", providers);
+
+        if !providers
+            .iter()
+            .any(|provider| matches!(provider, Provide::Found(_)))
+        {
+            return None;
+        }
+
+        Some(Self {
+            providers,
+            selected_provider: None,
+        })
+    }
+}
+
+impl InitHook for Java {
+    /// Empties the [Java::providers] and stores the selected provider in [Java::selected_provider]
+    async fn prompt_user(&mut self, _flox: &Flox, _path: &Path) -> Result<bool> {
+        let found_providers = std::mem::take(&mut self.providers)
+            .into_iter()
+            .filter_map(|provider| match provider {
+                Provide::Found(provider) => Some(provider),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.selected_provider = prompt_for_provider(
+            found_providers,
+            "Flox detected a Java project with the following build tool(s):",
+            "Would you like Flox to set up a standard Java environment?",
+        )?;
+        Ok(self.selected_provider.is_some())
+    }
+
+    /// Returns the customization of the selected provider or the first found provider
+    fn get_init_customization(&self) -> InitCustomization {
+        let selected = self
+            .selected_provider
+            .as_ref()
+            .map(|p| p.get_init_customization());
+        // self.providers will be empty if prompt_user() was called
+        let default = self.providers.iter().find_map(|provider| match provider {
+            Provide::Found(provider) => Some(provider.get_init_customization()),
+            _ => None,
+        });
+
+        selected
+            .or(default)
+            .expect("Should only be called if `prompt_user` returned `true`")
+    }
+}
+
+impl From<Result<Option<Maven>>> for Provide<JavaProvider> {
+    fn from(result: Result<Option<Maven>>) -> Self {
+        match result {
+            Ok(Some(provider)) => Provide::Found(JavaProvider::Maven(provider)),
+            Ok(None) => Provide::NotFound,
+            Err(err) => Provide::Invalid(err),
+        }
+    }
+}
+
+impl From<Result<Option<Gradle>>> for Provide<JavaProvider> {
+    fn from(result: Result<Option<Gradle>>) -> Self {
+        match result {
+            Ok(Some(provider)) => Provide::Found(JavaProvider::Gradle(provider)),
+            Ok(None) => Provide::NotFound,
+            Err(err) => Provide::Invalid(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum JavaProvider {
+    Maven(Maven),
+    Gradle(Gradle),
+}
+
+impl Provider for JavaProvider {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        match self {
+            JavaProvider::Maven(p) => p.describe_provider(),
+            JavaProvider::Gradle(p) => p.describe_provider(),
+        }
+    }
+
+    fn describe_reason(&self) -> Cow<'_, str> {
+        match self {
+            JavaProvider::Maven(p) => p.describe_reason(),
+            JavaProvider::Gradle(p) => p.describe_reason(),
+        }
+    }
+
+    fn describe_customization(&self) -> Cow<'_, str> {
+        match self {
+            JavaProvider::Maven(p) => p.describe_customization(),
+            JavaProvider::Gradle(p) => p.describe_customization(),
+        }
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        match self {
+            JavaProvider::Maven(p) => p.get_init_customization(),
+            JavaProvider::Gradle(p) => p.get_init_customization(),
+        }
+    }
+}
+
+/// A Java release requested by a project file
+#[derive(Debug, Clone, PartialEq)]
+struct RequestedJava {
+    /// Major release, e.g. `17`
+    major: u32,
+    /// Where the release was found, e.g. `pom.xml (maven.compiler.release)`
+    source: String,
+}
+
+/// The JDK provided for a project and the release that was asked for
+#[derive(Debug, Clone, PartialEq)]
+struct Jdk {
+    requested: Option<RequestedJava>,
+    /// [ProvidedVersion::Compatible] if a JDK of the requested release was found,
+    /// or the default JDK if no release was requested.
+    ///
+    /// [ProvidedVersion::Incompatible] with the default JDK as a substitute
+    /// if the requested release is not in the catalogs.
+    provided: ProvidedVersion,
+}
+
+impl Jdk {
+    /// Find a JDK in the catalogs for the first requested release.
+    ///
+    /// Releases are provided by the `jdk<major>` packages,
+    /// the `jdk` package is used if no release was requested.
+    async fn resolve(flox: &Flox, requested: Option<RequestedJava>) -> Result<Self> {
+        let Some(requested) = requested else {
+            return Ok(Self {
+                requested: None,
+                provided: ProvidedVersion::Compatible {
+                    requested: None,
+                    compatible: get_default_package(flox, &"jdk".into()).await?,
+                },
+            });
+        };
+
+        let pkg_path = format!("jdk{}", requested.major);
+        let provided =
+            match get_default_package_if_compatible(flox, vec![pkg_path.clone()], None).await? {
+                Some(compatible) => ProvidedVersion::Compatible {
+                    requested: Some(requested.major.to_string()),
+                    compatible,
+                },
+                None => {
+                    debug!(
+                        pkg_path,
+                        "requested JDK not found in the catalogs, using the default JDK"
+                    );
+                    ProvidedVersion::Incompatible {
+                        requested: requested.major.to_string(),
+                        substitute: get_default_package(flox, &"jdk".into()).await?,
+                    }
+                },
+            };
+
+        Ok(Self {
+            requested: Some(requested),
+            provided,
+        })
+    }
+
+    /// The package to install, the requested release if available
+    fn package(&self) -> CatalogPackage {
+        let pkg_path = match &self.provided {
+            ProvidedVersion::Compatible { compatible, .. } => compatible.rel_path.to_string(),
+            ProvidedVersion::Incompatible { .. } => "jdk".to_string(),
+        };
+        CatalogPackage {
+            id: "jdk".to_string(),
+            pkg_path,
+            version: None,
+            systems: None,
+            ..Default::default()
+        }
+    }
+
+    /// Explain which release was requested and why a different one is installed, if it is
+    fn describe(&self) -> String {
+        match (&self.requested, &self.provided) {
+            (
+                Some(RequestedJava { major, source }),
+                ProvidedVersion::Incompatible { substitute, .. },
+            ) => format!(
+                "Note: {source} requests Java {major}, which Flox could not provide, but can provide {} instead.\n",
+                substitute.display_version
+            ),
+            (Some(RequestedJava { major, source }), _) => {
+                format!("Java {major} is requested by {source}.\n")
+            },
+            (None, _) => String::new(),
+        }
+    }
+
+    /// Customization shared by all build tools:
+    /// the JDK, and `JAVA_HOME` pointing to it in every shell.
+    fn init_customization(&self, build_tool: &str) -> InitCustomization {
+        // Resolve the JDK's home from the `java` executable in the environment
+        let java_home = r#"readlink -f "$FLOX_ENV/bin/java" | sed 's|/bin/java$||'"#;
+
+        InitCustomization {
+            vars: None,
+            hook_on_activate: None,
+            profile_common: None,
+            profile_bash: Some(format!("export JAVA_HOME=\"$({java_home})\"")),
+            profile_fish: Some(format!("set -gx JAVA_HOME ({java_home})")),
+            profile_tcsh: Some(format!("setenv JAVA_HOME \"`{java_home}`\"")),
+            profile_zsh: Some(format!("export JAVA_HOME=\"$({java_home})\"")),
+            packages: Some(vec![self.package(), CatalogPackage {
+                id: build_tool.to_string(),
+                pkg_path: build_tool.to_string(),
+                version: None,
+                systems: None,
+                ..Default::default()
+            }]),
+        }
+    }
+}
+
+/// Information gathered from a `pom.xml`
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Maven {
+    jdk: Jdk,
+    /// Version of maven found in the catalog
+    maven_version: String,
+}
+
+impl Maven {
+    async fn detect(flox: &Flox, path: &Path) -> Result<Option<Self>> {
+        let pom_xml = path.join(POM_XML_FILENAME);
+        if !pom_xml.is_file() {
+            debug!("No pom.xml found at {:?}", path);
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&pom_xml)?;
+        let requested = pom_java_release(&content)
+            .map(Ok)
+            .or_else(|| requested_by_version_files(path).transpose())
+            .transpose()?;
+
+        let jdk = Jdk::resolve(flox, requested).await?;
+        let maven_version = get_default_package(flox, &"maven".into())
+            .await
+            .context("Did not find maven in the catalogs")?
+            .display_version;
+
+        Ok(Some(Self { jdk, maven_version }))
+    }
+}
+
+impl Provider for Maven {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        "maven".into()
+    }
+
+    fn describe_reason(&self) -> Cow<'static, str> {
+        "pom.xml".into()
+    }
+
+    fn describe_customization(&self) -> Cow<'static, str> {
+        formatdoc! {"
+            Installs a JDK ({}) with maven ({})
+            Sets JAVA_HOME to the JDK in the environment
+            {}", self.jdk.provided.display_version(), self.maven_version, self.jdk.describe()
+        }
+        .into()
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        self.jdk.init_customization("maven")
+    }
+}
+
+/// Information gathered from a `build.gradle` or `build.gradle.kts`
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Gradle {
+    /// The build file that was found
+    build_file: &'static str,
+    jdk: Jdk,
+    /// Version of gradle found in the catalog
+    gradle_version: String,
+}
+
+impl Gradle {
+    async fn detect(flox: &Flox, path: &Path) -> Result<Option<Self>> {
+        let Some(build_file) = GRADLE_FILENAMES
+            .into_iter()
+            .find(|filename| path.join(filename).is_file())
+        else {
+            debug!("No gradle build file found at {:?}", path);
+            return Ok(None);
+        };
+
+        let content = fs::read_to_string(path.join(build_file))?;
+        let requested = gradle_toolchain_release(&content)
+            .map(|major| RequestedJava {
+                major,
+                source: format!("the toolchain in {build_file}"),
+            })
+            .map(Ok)
+            .or_else(|| requested_by_version_files(path).transpose())
+            .transpose()?;
+
+        let jdk = Jdk::resolve(flox, requested).await?;
+        let gradle_version = get_default_package(flox, &"gradle".into())
+            .await
+            .context("Did not find gradle in the catalogs")?
+            .display_version;
+
+        Ok(Some(Self {
+            build_file,
+            jdk,
+            gradle_version,
+        }))
+    }
+}
+
+impl Provider for Gradle {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        "gradle".into()
+    }
+
+    fn describe_reason(&self) -> Cow<'static, str> {
+        self.build_file.into()
+    }
+
+    fn describe_customization(&self) -> Cow<'static, str> {
+        formatdoc! {"
+            Installs a JDK ({}) with gradle ({})
+            Sets JAVA_HOME to the JDK in the environment
+            {}", self.jdk.provided.display_version(), self.gradle_version, self.jdk.describe()
+        }
+        .into()
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        self.jdk.init_customization("gradle")
+    }
+}
+
+/// Read the Java release from the properties of a `pom.xml`.
+///
+/// Values referring to other properties, e.g. `${java.version}`, are skipped.
+fn pom_java_release(content: &str) -> Option<RequestedJava> {
+    POM_JAVA_PROPERTIES.into_iter().find_map(|property| {
+        let regex = Regex::new(&format!(
+            r"<{0}>\s*([^<]*?)\s*</{0}>",
+            regex::escape(property)
+        ))
+        .expect("property regex is valid");
+        let value = regex.captures(content)?.get(1)?.as_str();
+        Some(RequestedJava {
+            major: parse_java_major(value)?,
+            source: format!("{POM_XML_FILENAME} ({property})"),
+        })
+    })
+}
+
+/// Read the language version of a Gradle toolchain block
+fn gradle_toolchain_release(content: &str) -> Option<u32> {
+    GRADLE_TOOLCHAIN_REGEX
+        .captures(content)?
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+/// Read the Java release from `.java-version` or `.sdkmanrc`
+fn requested_by_version_files(path: &Path) -> Result<Option<RequestedJava>> {
+    let java_version = path.join(JAVA_VERSION_FILENAME);
+    if java_version.is_file() {
+        let content = fs::read_to_string(&java_version)?;
+        if let Some(major) = parse_java_major(content.trim()) {
+            return Ok(Some(RequestedJava {
+                major,
+                source: JAVA_VERSION_FILENAME.to_string(),
+            }));
+        }
+    }
+
+    let sdkmanrc = path.join(SDKMANRC_FILENAME);
+    if sdkmanrc.is_file() {
+        let content = fs::read_to_string(&sdkmanrc)?;
+        let major = content
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("java="))
+            .find_map(parse_java_major);
+        if let Some(major) = major {
+            return Ok(Some(RequestedJava {
+                major,
+                source: SDKMANRC_FILENAME.to_string(),
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Parse the major release from a Java version,
+/// ignoring vendor prefixes and suffixes as used by jenv and sdkman.
+///
+/// `17`, `17.0.2`, `temurin-17.0.2`, `17.0.2-tem` and `1.8` (Java 8) are understood.
+fn parse_java_major(version: &str) -> Option<u32> {
+    let version = version.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let mut components = version
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|component| component.parse::<u32>());
+
+    match components.next()?.ok()? {
+        // Legacy versioning of Java 8 and before
+        1 => components.next()?.ok(),
+        major => Some(major),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::data::System;
+    use flox_rust_sdk::flox::test_helpers::flox_instance;
+    use flox_rust_sdk::providers::catalog::test_helpers::resolved_pkg_group_with_dummy_package;
+    use flox_rust_sdk::providers::catalog::Client;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::commands::init::ProvidedPackage;
+
+    #[test]
+    fn java_major_parsed_from_version_formats() {
+        assert_eq!(parse_java_major("17"), Some(17));
+        assert_eq!(parse_java_major("21.0.2"), Some(21));
+        assert_eq!(parse_java_major("temurin-17.0.2"), Some(17));
+        assert_eq!(parse_java_major("17.0.2-tem"), Some(17));
+        assert_eq!(parse_java_major("1.8"), Some(8));
+        assert_eq!(parse_java_major("${java.version}"), None);
+    }
+
+    #[test]
+    fn pom_release_prefers_compiler_release() {
+        let pom = indoc! {"
+            <project>
+              <properties>
+                <java.version>11</java.version>
+                <maven.compiler.release>17</maven.compiler.release>
+              </properties>
+            </project>
+        "};
+        assert_eq!(
+            pom_java_release(pom),
+            Some(RequestedJava {
+                major: 17,
+                source: "pom.xml (maven.compiler.release)".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn pom_release_skips_property_references() {
+        let pom = indoc! {"
+            <project>
+              <properties>
+                <maven.compiler.release>${java.version}</maven.compiler.release>
+                <java.version>21</java.version>
+              </properties>
+            </project>
+        "};
+        assert_eq!(pom_java_release(pom).map(|java| java.major), Some(21));
+    }
+
+    #[test]
+    fn gradle_toolchain_release_parsed_from_groovy_and_kotlin() {
+        let groovy = indoc! {"
+            java {
+                toolchain {
+                    languageVersion = JavaLanguageVersion.of(17)
+                }
+            }
+        "};
+        let kotlin = indoc! {"
+            java {
+                toolchain {
+                    languageVersion.set(JavaLanguageVersion.of(21))
+                }
+            }
+        "};
+        assert_eq!(gradle_toolchain_release(groovy), Some(17));
+        assert_eq!(gradle_toolchain_release(kotlin), Some(21));
+    }
+
+    #[test]
+    fn sdkmanrc_read_when_no_java_version_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(SDKMANRC_FILENAME),
+            "maven=3.9.6\njava=21.0.2-tem\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            requested_by_version_files(temp_dir.path()).unwrap(),
+            Some(RequestedJava {
+                major: 21,
+                source: ".sdkmanrc".to_string(),
+            })
+        );
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // Catalog tests
+    ///////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn maven_provider_installs_requested_jdk_with_catalog() {
+        let (mut flox, temp_dir_handle) = flox_instance();
+        let path = temp_dir_handle.path();
+        fs::write(
+            path.join(POM_XML_FILENAME),
+            "<project><properties><maven.compiler.release>17</maven.compiler.release></properties></project>",
+        )
+        .unwrap();
+
+        if let Client::Mock(ref mut client) = flox.catalog_client {
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "default",
+                &System::from("aarch64-darwin"),
+                "default",
+                "jdk17",
+                "17.0.11+9",
+            )]);
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "maven",
+                &System::from("aarch64-darwin"),
+                "maven",
+                "maven",
+                "3.9.6",
+            )]);
+        }
+
+        let maven = Maven::detect(&flox, path).await.unwrap().unwrap();
+        assert_eq!(maven.jdk.provided, ProvidedVersion::Compatible {
+            requested: Some("17".to_string()),
+            compatible: ProvidedPackage::new("default", vec!["jdk17"], "17.0.11+9"),
+        });
+
+        let customization = maven.get_init_customization();
+        assert_eq!(customization.packages.unwrap(), vec![
+            CatalogPackage {
+                id: "jdk".to_string(),
+                pkg_path: "jdk17".to_string(),
+                version: None,
+                systems: None,
+                ..Default::default()
+            },
+            CatalogPackage {
+                id: "maven".to_string(),
+                pkg_path: "maven".to_string(),
+                version: None,
+                systems: None,
+                ..Default::default()
+            },
+        ]);
+        assert!(customization.profile_bash.unwrap().contains("JAVA_HOME"));
+    }
+
+    #[tokio::test]
+    async fn gradle_provider_substitutes_unavailable_jdk_with_catalog() {
+        let (mut flox, temp_dir_handle) = flox_instance();
+        let path = temp_dir_handle.path();
+        fs::write(path.join("build.gradle.kts"), "plugins { java }\n").unwrap();
+        fs::write(path.join(JAVA_VERSION_FILENAME), "9\n").unwrap();
+
+        if let Client::Mock(ref mut client) = flox.catalog_client {
+            // jdk9 is not available
+            client.push_resolve_response(vec![]);
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "jdk",
+                &System::from("aarch64-darwin"),
+                "jdk",
+                "jdk",
+                "21.0.3+9",
+            )]);
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "gradle",
+                &System::from("aarch64-darwin"),
+                "gradle",
+                "gradle",
+                "8.7",
+            )]);
+        }
+
+        let gradle = Gradle::detect(&flox, path).await.unwrap().unwrap();
+        assert_eq!(gradle.jdk.provided, ProvidedVersion::Incompatible {
+            requested: "9".to_string(),
+            substitute: ProvidedPackage::new("jdk", vec!["jdk"], "21.0.3+9"),
+        });
+        assert_eq!(gradle.jdk.package().pkg_path, "jdk");
+        assert!(gradle.describe_customization().contains(".java-version"));
+    }
+}
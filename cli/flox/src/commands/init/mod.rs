@@ -1,8 +1,10 @@
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use anyhow::{anyhow, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use bpaf::Bpaf;
 use flox_rust_sdk::data::AttrPath;
 use flox_rust_sdk::flox::{EnvironmentName, Flox, DEFAULT_NAME};
@@ -17,6 +19,7 @@ use flox_rust_sdk::providers::catalog::{
     PackageResolutionInfo,
 };
 use indoc::formatdoc;
+use itertools::Itertools;
 use log::debug;
 use path_dedot::ParseDot;
 use toml_edit::{DocumentMut, Formatted, Item, Table, Value};
@@ -24,16 +27,26 @@ use tracing::{info_span, instrument};
 
 use crate::commands::environment_description;
 use crate::subcommand_metric;
-use crate::utils::dialog::Dialog;
+use crate::utils::dialog::{Dialog, Select};
 use crate::utils::message;
 
 mod go;
+mod import;
+mod java;
 mod node;
+mod php;
 mod python;
+mod ruby;
+mod rust;
 
 use go::Go;
+use import::Import;
+use java::Java;
 use node::Node;
+use php::Php;
 use python::Python;
+use ruby::Ruby;
+use rust::Rust;
 
 const AUTO_SETUP_HINT: &str = "Use '--auto-setup' to apply Flox recommendations in the future.";
 
@@ -42,24 +55,55 @@ const AUTO_SETUP_HINT: &str = "Use '--auto-setup' to apply Flox recommendations
 #[allow(clippy::large_enum_variant)]
 enum InitHookType {
     Go(Go),
+    Import(Import),
+    Java(Java),
     Node(Node),
+    Php(Php),
     Python(Python),
+    Ruby(Ruby),
+    Rust(Rust),
+}
+
+impl InitHookType {
+    /// The name used to select this hook with '--with' and '--without'
+    fn name(&self) -> &'static str {
+        match self {
+            InitHookType::Go(_) => "go",
+            InitHookType::Import(_) => "import",
+            InitHookType::Java(_) => "java",
+            InitHookType::Node(_) => "node",
+            InitHookType::Php(_) => "php",
+            InitHookType::Python(_) => "python",
+            InitHookType::Ruby(_) => "ruby",
+            InitHookType::Rust(_) => "rust",
+        }
+    }
 }
 
 impl InitHook for InitHookType {
     async fn prompt_user(&mut self, flox: &Flox, path: &Path) -> Result<bool> {
         match self {
             InitHookType::Go(hook) => hook.prompt_user(flox, path).await,
+            InitHookType::Import(hook) => hook.prompt_user(flox, path).await,
+            InitHookType::Java(hook) => hook.prompt_user(flox, path).await,
             InitHookType::Node(hook) => hook.prompt_user(flox, path).await,
+            InitHookType::Php(hook) => hook.prompt_user(flox, path).await,
             InitHookType::Python(hook) => hook.prompt_user(flox, path).await,
+            InitHookType::Ruby(hook) => hook.prompt_user(flox, path).await,
+            InitHookType::Rust(hook) => hook.prompt_user(flox, path).await,
         }
     }
 
     fn get_init_customization(&self) -> InitCustomization {
         match self {
             InitHookType::Go(hook) => hook.get_init_customization(),
+            InitHookType::Import(hook) => hook.get_init_customization(),
+            InitHookType::Java(hook) => hook.get_init_customization(),
             InitHookType::Node(hook) => hook.get_init_customization(),
+            InitHookType::Php(hook) => hook.get_init_customization(),
             InitHookType::Python(hook) => hook.get_init_customization(),
+            InitHookType::Ruby(hook) => hook.get_init_customization(),
+            InitHookType::Rust(hook) => hook.get_init_customization(),
         }
     }
 }
@@ -77,17 +121,77 @@ pub struct Init {
     #[bpaf(long("name"), short('n'), argument("name"))]
     env_name: Option<String>,
 
+    // '--auto' or '--no-auto', prompts for each suggestion if neither is given
+    #[bpaf(external(auto_setup), optional)]
+    auto_setup: Option<AutoSetup>,
+
+    /// Only use suggestions for these languages, without prompting
+    /// (go, java, node, php, python, ruby, rust,
+    /// or import for devbox.json, mise.toml and .tool-versions)
+    #[bpaf(long("with"), argument("language>,<..."))]
+    with: Vec<String>,
+
+    /// Don't use suggestions for these languages
+    #[bpaf(long("without"), argument("language>,<..."))]
+    without: Vec<String>,
+
+    /// Print the suggested customization in the given format (toml or json)
+    /// instead of creating the environment
+    #[bpaf(long("print-customization"), argument("format"))]
+    print_customization: Option<CustomizationFormat>,
+
+    /// Import packages, variables and scripts from a devbox.json,
+    /// mise.toml or .tool-versions file
+    #[bpaf(long("import"), argument("file"))]
+    import: Option<PathBuf>,
+}
+
+/// Whether to apply language suggestions without prompting
+#[derive(Bpaf, Clone, Debug, PartialEq)]
+enum AutoSetup {
     /// Apply Flox recommendations for the environment based on what languages
     /// are being used in the containing directory
-    #[bpaf(long)]
-    auto_setup: bool,
+    #[bpaf(long("auto"), long("auto-setup"))]
+    Auto,
+    /// Don't detect languages or suggest customizations
+    #[bpaf(long("no-auto"))]
+    NoAuto,
 }
 
+/// Output formats of '--print-customization'
+#[derive(Clone, Debug, PartialEq)]
+enum CustomizationFormat {
+    Toml,
+    Json,
+}
+
+impl FromStr for CustomizationFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toml" => Ok(Self::Toml),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow!("unknown format '{s}', expected 'toml' or 'json'")),
+        }
+    }
+}
+
+/// Names of the language hooks, as used with '--with' and '--without'
+const HOOK_NAMES: [&str; 8] = [
+    "go", "import", "java", "node", "php", "python", "ruby", "rust",
+];
+
 impl Init {
     #[instrument(name = "init", skip_all)]
     pub async fn handle(self, flox: Flox) -> Result<()> {
         subcommand_metric!("init");
 
+        let (with, without) = self.hook_selection()?;
+        if self.auto_setup == Some(AutoSetup::NoAuto) && !with.is_empty() {
+            bail!("'--no-auto' can't be combined with '--with'");
+        }
+
         let dir = self
             .dir
             .clone()
@@ -108,19 +212,41 @@ impl Init {
             EnvironmentName::from_str(&name)?
         };
 
-        // Don't run language hooks in home dir
-        let customization = if dir != home_dir || self.auto_setup {
-            self.run_language_hooks(&flox, &dir)
-                .await
-                .unwrap_or_else(|e| {
+        // Fail early if the file to import can't be read
+        let import = match &self.import {
+            Some(file) => Some(Import::from_file(&flox, file).await?),
+            None => None,
+        };
+
+        // Don't run language hooks in home dir, unless explicitly requested
+        let explicit =
+            self.auto_setup == Some(AutoSetup::Auto) || !with.is_empty() || import.is_some();
+        let customization = if self.auto_setup == Some(AutoSetup::NoAuto) && import.is_none() {
+            debug!("Skipping language hooks");
+            InitCustomization::default()
+        } else if dir != home_dir || explicit {
+            let hooks = self
+                .run_language_hooks(&flox, &dir, &with, &without, import)
+                .await;
+            match hooks {
+                Ok(customization) => customization,
+                // Don't print a partial template
+                Err(e) if self.print_customization.is_some() => Err(e)?,
+                Err(e) => {
                     message::warning(format!("Failed to generate init suggestions: {e}"));
                     InitCustomization::default()
-                })
+                },
+            }
         } else {
             debug!("Skipping language hooks in home directory");
             InitCustomization::default()
         };
 
+        if let Some(format) = &self.print_customization {
+            println!("{}", Self::print_customization(&customization, format)?);
+            return Ok(());
+        }
+
         let env = if customization.packages.is_some() {
             info_span!(
                 "init_with_suggested_packages",
@@ -156,29 +282,138 @@ impl Init {
         Ok(())
     }
 
-    /// Run all language hooks and return a single combined customization
-    async fn run_language_hooks(&self, flox: &Flox, path: &Path) -> Result<InitCustomization> {
+    /// Collect the languages passed to '--with' and '--without',
+    /// which may each be given multiple times or as comma separated lists.
+    fn hook_selection(&self) -> Result<(HashSet<String>, HashSet<String>)> {
+        let parse = |names: &[String]| -> Result<HashSet<String>> {
+            let names = names
+                .iter()
+                .flat_map(|names| names.split(','))
+                .map(|name| name.trim().to_lowercase())
+                .filter(|name| !name.is_empty())
+                .collect::<HashSet<_>>();
+            if let Some(unknown) = names
+                .iter()
+                .find(|name| !HOOK_NAMES.contains(&name.as_str()))
+            {
+                bail!(
+                    "Unknown language '{unknown}', expected one of: {}",
+                    HOOK_NAMES.join(", ")
+                );
+            }
+            Ok(names)
+        };
+        Ok((parse(&self.with)?, parse(&self.without)?))
+    }
+
+    /// Format a customization as it would be added to the manifest
+    fn print_customization(
+        customization: &InitCustomization,
+        format: &CustomizationFormat,
+    ) -> Result<String> {
+        let toml = format_customization(customization)?;
+        match format {
+            CustomizationFormat::Toml => Ok(toml),
+            CustomizationFormat::Json => {
+                let table: toml::Table = toml::from_str(&toml)?;
+                Ok(serde_json::to_string_pretty(&table)?)
+            },
+        }
+    }
+
+    /// Run the selected language hooks and return a single combined customization
+    ///
+    /// A file passed to '--import' replaces the detection of files to import,
+    /// and is applied without prompting.
+    async fn run_language_hooks(
+        &self,
+        flox: &Flox,
+        path: &Path,
+        with: &HashSet<String>,
+        without: &HashSet<String>,
+        import: Option<Import>,
+    ) -> Result<InitCustomization> {
+        let selected = |name: &str| {
+            self.auto_setup != Some(AutoSetup::NoAuto)
+                && (with.is_empty() || with.contains(name))
+                && !without.contains(name)
+        };
+        let explicit_import = import.is_some();
+        // Without a way to prompt, '--print-customization' prints all suggestions,
+        // as if '--auto' was passed
+        let accept_all = self.auto_setup == Some(AutoSetup::Auto)
+            || (self.print_customization.is_some() && !Dialog::can_prompt());
+
         let mut hooks: Vec<InitHookType> = vec![];
 
-        if let Some(node) = Node::new(flox, path).await? {
-            hooks.push(InitHookType::Node(node));
+        if let Some(import) = import {
+            hooks.push(InitHookType::Import(import));
+        } else if selected("import") {
+            if let Some(import) = Import::new(flox, path).await {
+                hooks.push(InitHookType::Import(import));
+            }
+        }
+
+        if selected("node") {
+            if let Some(node) = Node::new(flox, path).await? {
+                hooks.push(InitHookType::Node(node));
+            }
+        }
+
+        if selected("python") {
+            if let Some(python) = Python::new(flox, path).await {
+                hooks.push(InitHookType::Python(python));
+            }
+        }
+
+        if selected("go") {
+            if let Some(go) = Go::new(flox, path).await? {
+                hooks.push(InitHookType::Go(go));
+            }
+        }
+
+        if selected("java") {
+            if let Some(java) = Java::new(flox, path).await {
+                hooks.push(InitHookType::Java(java));
+            }
+        }
+
+        if selected("ruby") {
+            if let Some(ruby) = Ruby::new(flox, path).await {
+                hooks.push(InitHookType::Ruby(ruby));
+            }
         }
 
-        if let Some(python) = Python::new(flox, path).await {
-            hooks.push(InitHookType::Python(python));
+        if selected("php") {
+            if let Some(php) = Php::new(flox, path).await {
+                hooks.push(InitHookType::Php(php));
+            }
         }
 
-        if let Some(go) = Go::new(flox, path).await? {
-            hooks.push(InitHookType::Go(go));
+        if selected("rust") {
+            if let Some(rust) = Rust::new(flox, path).await? {
+                hooks.push(InitHookType::Rust(rust));
+            }
         }
 
         let mut customizations = vec![];
 
         for mut hook in hooks {
-            // Run hooks if we can't prompt
-            if self.auto_setup || (Dialog::can_prompt() && hook.prompt_user(flox, path).await?) {
-                customizations.push(hook.get_init_customization())
+            // Hooks selected with '--with' are applied without prompting,
+            // others only if the user accepts them
+            let unprompted = accept_all
+                || with.contains(hook.name())
+                || (explicit_import && matches!(hook, InitHookType::Import(_)));
+            let accepted =
+                unprompted || (Dialog::can_prompt() && hook.prompt_user(flox, path).await?);
+            if !accepted {
+                continue;
             }
+            // The prompt already lists what can't be imported
+            if let (true, InitHookType::Import(import)) = (unprompted, &hook) {
+                import.report_untranslated();
+            }
+            customizations.push(hook.get_init_customization())
         }
 
         Ok(Self::combine_customizations(customizations))
@@ -186,6 +421,7 @@ impl Init {
 
     /// Deduplicate packages and concatenate customization scripts into a single string
     fn combine_customizations(customizations: Vec<InitCustomization>) -> InitCustomization {
+        let mut vars = BTreeMap::<String, String>::new();
         let mut custom_hook_on_activate_scripts: Vec<String> = vec![];
         let mut custom_profile_common_scripts: Vec<String> = vec![];
         let mut custom_profile_bash_scripts: Vec<String> = vec![];
@@ -195,6 +431,9 @@ impl Init {
         // Deduplicate packages with a set
         let mut packages_set = HashSet::<CatalogPackage>::new();
         for customization in customizations {
+            if let Some(customization_vars) = customization.vars {
+                vars.extend(customization_vars)
+            }
             if let Some(packages) = customization.packages {
                 packages_set.extend(packages)
             }
@@ -278,6 +517,7 @@ impl Init {
             .then(|| packages_set.into_iter().collect::<Vec<CatalogPackage>>());
 
         InitCustomization {
+            vars: (!vars.is_empty()).then_some(vars),
             hook_on_activate: custom_hook_on_activate,
             profile_common: custom_profile_common,
             profile_bash: custom_profile_bash,
@@ -296,6 +536,139 @@ trait InitHook {
     fn get_init_customization(&self) -> InitCustomization;
 }
 
+/// Flattened result of a provider detection
+///
+/// Combines [Result] and [Option] into a single enum
+#[derive(Debug)]
+enum Provide<T> {
+    /// Found a valid provider
+    Found(T),
+    /// Found a provider, but it's invalid
+    /// e.g. found a pyproject.toml, but it's not a valid poetry file
+    // We don't necessarily want to forget the error,
+    // but currently we don't do anything with it either.
+    #[allow(dead_code)]
+    Invalid(Error),
+    /// Provider not found
+    NotFound,
+}
+
+impl<P: Provider + 'static> From<Result<Option<P>>> for Provide<Box<dyn Provider>> {
+    fn from(result: Result<Option<P>>) -> Self {
+        match result {
+            Ok(Some(provider)) => Provide::Found(Box::new(provider)),
+            Ok(None) => Provide::NotFound,
+            Err(err) => Provide::Invalid(err),
+        }
+    }
+}
+
+/// A tool that can set up a project in one of several ways,
+/// explaining why it was detected and what it would add to the environment
+trait Provider: Debug {
+    fn describe_provider(&self) -> Cow<'static, str>;
+
+    fn describe_reason(&self) -> Cow<'_, str>;
+
+    fn describe_customization(&self) -> Cow<'_, str>;
+
+    fn get_init_customization(&self) -> InitCustomization;
+}
+
+/// Describe the found providers and let the user pick one of them,
+/// or show the modifications each of them would make.
+///
+/// Returns [None] if the user declines all providers.
+fn prompt_for_provider<P: Provider>(
+    mut found_providers: Vec<P>,
+    detected_message: &str,
+    setup_message: &str,
+) -> Result<Option<P>> {
+    fn describe_provider(provider: &impl Provider) -> String {
+        format!(
+            "* {} ({})\n\n{}",
+            provider.describe_provider(),
+            provider.describe_reason(),
+            textwrap::indent(&provider.describe_customization(), "  ")
+        )
+    }
+
+    message::plain(formatdoc! {"
+        {detected_message}
+
+        {}
+    ", found_providers.iter().map(describe_provider).join("\n")});
+
+    let message = formatdoc! {"
+        {setup_message}
+        You can always change the environment's manifest with 'flox edit'"};
+
+    let accept_options = found_providers
+        .iter()
+        .map(|provider| format!("Yes - with {}", provider.describe_provider()))
+        .collect::<Vec<_>>();
+
+    let n_accept_options = accept_options.len();
+
+    let show_modifications_options = found_providers
+        .iter()
+        .map(|provider| {
+            format!(
+                "Show suggested modifications for {}",
+                provider.describe_provider()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let cancel_option = ["No".to_string()];
+
+    let options = accept_options
+        .iter()
+        .chain(cancel_option.iter())
+        .chain(show_modifications_options.iter())
+        .collect::<Vec<_>>();
+
+    loop {
+        let dialog = Dialog {
+            message: &message,
+            help_message: Some(AUTO_SETUP_HINT),
+            typed: Select {
+                options: options.clone(),
+            },
+        };
+
+        let (choice, _) = dialog.raw_prompt()?;
+
+        match choice {
+            choice if choice < n_accept_options => {
+                return Ok(Some(found_providers.swap_remove(choice)));
+            },
+            c if c == n_accept_options => {
+                return Ok(None);
+            },
+            choice_with_offset => {
+                let choice = choice_with_offset - (n_accept_options + 1);
+
+                let provider = &found_providers[choice];
+                message::plain(format_customization(&provider.get_init_customization())?);
+            },
+        }
+    }
+}
+
+/// Convert a pessimistic version constraint, as used by RubyGems (`~>`)
+/// and Composer (`~`), to a semver requirement.
+///
+/// `~> 3.2` allows any `3.x` release from `3.2` on,
+/// `~> 3.2.1` allows any `3.2.x` release from `3.2.1` on.
+fn pessimistic_requirement(version: &str) -> String {
+    if version.split('.').count() <= 2 {
+        format!("^{version}")
+    } else {
+        format!("~{version}")
+    }
+}
+
 /// Create a temporary TOML document containing just the contents of the passed
 /// [InitCustomization], and return it as a string.
 fn format_customization(customization: &InitCustomization) -> Result<String> {
@@ -304,12 +677,34 @@ fn format_customization(customization: &InitCustomization) -> Result<String> {
             .iter()
             .map(|p| PackageToInstall::Catalog(p.clone()))
             .collect::<Vec<_>>();
-        let with_packages = insert_packages("", &packages)?;
-        with_packages.new_toml.unwrap_or(DocumentMut::new())
+        // Parsing a manifest requires a version, which isn't part of the customization
+        let with_packages = insert_packages("version = 1", &packages)?;
+        let mut toml = with_packages.new_toml.unwrap_or(DocumentMut::new());
+        toml.remove("version");
+        toml
     } else {
         DocumentMut::new()
     };
 
+    // Add the "vars" section to the toml document.
+    if let Some(vars) = &customization.vars {
+        let vars_table = {
+            let vars_field = toml
+                .entry("vars")
+                .or_insert_with(|| Item::Table(Table::new()));
+            let vars_field_type = vars_field.type_name();
+            vars_field.as_table_mut().context(format!(
+                "'vars' must be a table, but found {vars_field_type} instead"
+            ))?
+        };
+        for (name, value) in vars {
+            vars_table.insert(
+                name,
+                Item::Value(Value::String(Formatted::new(value.clone()))),
+            );
+        }
+    }
+
     // Add the "hook" section to the toml document.
     let hook_table = {
         let hook_field = toml
@@ -464,6 +859,7 @@ impl From<ProvidedPackage> for CatalogPackage {
             pkg_path: value.rel_path.into(),
             version: value.version,
             systems: None,
+            ..Default::default()
         }
     }
 }
@@ -652,6 +1048,7 @@ async fn try_find_compatible_version(
 #[cfg(test)]
 mod tests {
 
+    use bpaf::Parser;
     use indoc::indoc;
     use pretty_assertions::assert_eq;
 
@@ -680,11 +1077,76 @@ mod tests {
         }
     }
 
+    fn init_with_selection(with: &[&str], without: &[&str]) -> Init {
+        Init {
+            dir: None,
+            env_name: None,
+            auto_setup: None,
+            with: with.iter().map(ToString::to_string).collect(),
+            without: without.iter().map(ToString::to_string).collect(),
+            print_customization: None,
+            import: None,
+        }
+    }
+
+    #[test]
+    fn auto_setup_defaults_to_prompting() {
+        let parse = |args: &[&str]| {
+            init()
+                .to_options()
+                .run_inner(args)
+                .map(|init| init.auto_setup)
+        };
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(parse(&["--auto"]).unwrap(), Some(AutoSetup::Auto));
+        assert_eq!(parse(&["--no-auto"]).unwrap(), Some(AutoSetup::NoAuto));
+        assert!(parse(&["--prompt"]).is_err());
+    }
+
+    #[test]
+    fn hook_selection_splits_comma_separated_languages() {
+        let init = init_with_selection(&["python,node", "Go"], &["rust"]);
+        let (with, without) = init.hook_selection().unwrap();
+
+        assert_eq!(
+            with,
+            HashSet::from(["python".to_string(), "node".to_string(), "go".to_string()])
+        );
+        assert_eq!(without, HashSet::from(["rust".to_string()]));
+    }
+
+    #[test]
+    fn hook_selection_rejects_unknown_languages() {
+        let init = init_with_selection(&["cobol"], &[]);
+        assert!(init.hook_selection().is_err());
+    }
+
+    #[test]
+    fn print_customization_as_json() {
+        let customization = InitCustomization {
+            hook_on_activate: Some("echo hello".to_string()),
+            packages: Some(vec![CatalogPackage {
+                id: "go".to_string(),
+                pkg_path: "go".to_string(),
+                version: Some("^1.21".to_string()),
+                systems: None,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        let json = Init::print_customization(&customization, &CustomizationFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["install"]["go"]["version"], "^1.21");
+        assert_eq!(json["hook"]["on-activate"], "  echo hello\n");
+    }
+
     /// combine_customizations() deduplicates a package and correctly concatenates customization scripts
     #[test]
     fn test_combine_customizations() {
         let customizations = vec![
             InitCustomization {
+                vars: None,
                 hook_on_activate: Some("hook_on_activate1".to_string()),
                 profile_common: Some("profile_common1".to_string()),
                 profile_bash: Some("profile_bash1".to_string()),
@@ -697,16 +1159,19 @@ mod tests {
                         pkg_path: "python311Packages.pip".to_string(),
                         version: None,
                         systems: None,
+                        ..Default::default()
                     },
                     CatalogPackage {
                         id: "package2".to_string(),
                         pkg_path: "path2".to_string(),
                         version: None,
                         systems: None,
+                        ..Default::default()
                     },
                 ]),
             },
             InitCustomization {
+                vars: None,
                 hook_on_activate: Some("hook_on_activate2".to_string()),
                 profile_common: Some("profile_common2".to_string()),
                 profile_bash: Some("profile_bash2".to_string()),
@@ -719,12 +1184,14 @@ mod tests {
                         pkg_path: "python311Packages.pip".to_string(),
                         version: None,
                         systems: None,
+                        ..Default::default()
                     },
                     CatalogPackage {
                         id: "package1".to_string(),
                         pkg_path: "path1".to_string(),
                         version: None,
                         systems: None,
+                        ..Default::default()
                     },
                 ]),
             },
@@ -733,6 +1200,7 @@ mod tests {
         let mut combined = Init::combine_customizations(customizations);
         combined.packages.as_mut().unwrap().sort();
         assert_eq!(combined, InitCustomization {
+            vars: None,
             // Yes, this is incredibly brittle, but it's to make sure we get the newlines right
             hook_on_activate: Some(
                 indoc! {r#"
@@ -812,18 +1280,21 @@ mod tests {
                     pkg_path: "path1".to_string(),
                     version: None,
                     systems: None,
+                    ..Default::default()
                 },
                 CatalogPackage {
                     id: "package2".to_string(),
                     pkg_path: "path2".to_string(),
                     version: None,
                     systems: None,
+                    ..Default::default()
                 },
                 CatalogPackage {
                     id: "pip".to_string(),
                     pkg_path: "python311Packages.pip".to_string(),
                     version: None,
                     systems: None,
+                    ..Default::default()
                 },
             ]),
         });
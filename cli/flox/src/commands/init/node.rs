@@ -651,6 +651,7 @@ impl InitHook for Node {
                     // providing the default
                     version: yarn_install.yarn.version.clone(),
                     systems: None,
+                    ..Default::default()
                 });
                 Some(YARN_HOOK.to_string())
             },
@@ -664,12 +665,14 @@ impl InitHook for Node {
                         pkg_path: result.rel_path.clone().into(),
                         version: result.version.clone(),
                         systems: None,
+                        ..Default::default()
                     },
                     None => CatalogPackage {
                         id: "nodejs".to_string(),
                         pkg_path: "nodejs".to_string(),
                         version: None,
                         systems: None,
+                        ..Default::default()
                     },
                 };
                 packages.push(nodejs_to_install);
@@ -682,6 +685,7 @@ impl InitHook for Node {
         };
 
         InitCustomization {
+            vars: None,
             hook_on_activate,
             profile_common: None,
             profile_bash: None,
@@ -777,11 +781,13 @@ mod tests {
             }
             .get_init_customization(),
             InitCustomization {
+                vars: None,
                 packages: Some(vec![CatalogPackage {
                     id: "yarn".to_string(),
                     pkg_path: "yarn.path".to_string(),
                     version: Some("1".to_string()),
                     systems: None,
+                    ..Default::default()
                 }]),
                 hook_on_activate: Some(YARN_HOOK.to_string()),
                 profile_common: None,
@@ -829,11 +835,13 @@ mod tests {
             }
             .get_init_customization(),
             InitCustomization {
+                vars: None,
                 packages: Some(vec![CatalogPackage {
                     id: "nodejs".to_string(),
                     pkg_path: "nodejs.path".to_string(),
                     version: Some("1".to_string()),
                     systems: None,
+                    ..Default::default()
                 }]),
                 hook_on_activate: Some(NPM_HOOK.to_string()),
                 profile_common: None,
@@ -863,11 +871,13 @@ mod tests {
             }
             .get_init_customization(),
             InitCustomization {
+                vars: None,
                 packages: Some(vec![CatalogPackage {
                     id: "nodejs".to_string(),
                     pkg_path: "nodejs.path".to_string(),
                     version: Some("1".to_string()),
                     systems: None,
+                    ..Default::default()
                 }]),
                 hook_on_activate: None,
                 profile_common: None,
@@ -0,0 +1,405 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::environment::path_environment::InitCustomization;
+use flox_rust_sdk::models::manifest::CatalogPackage;
+use indoc::{formatdoc, indoc};
+use serde::Deserialize;
+use tracing::debug;
+
+use super::{
+    get_default_package,
+    pessimistic_requirement,
+    prompt_for_provider,
+    try_find_compatible_version,
+    InitHook,
+    Provide,
+    ProvidedVersion,
+    Provider,
+};
+
+const COMPOSER_JSON_FILENAME: &str = "composer.json";
+const COMPOSER_PKG_PATH: &str = "phpPackages.composer";
+
+#[derive(Debug)]
+pub(super) struct Php {
+    providers: Vec<Provide<Composer>>,
+    selected_provider: Option<Composer>,
+}
+
+impl Php {
+    /// Creates and returns the [Php] hook with any detected
+    /// [Provider] instances.
+    /// If no providers are detected, returns [None].
+    pub async fn new(flox: &Flox, path: &Path) -> Option<Self> {
+        let providers = vec![Provide::from(Composer::detect(flox, path).await)];
+
+        debug!("Detected PHP providers: {:#?}", providers);
+
+        if !providers
+            .iter()
+            .any(|provider| matches!(provider, Provide::Found(_)))
+        {
+            return None;
+        }
+
+        Some(Self {
+            providers,
+            selected_provider: None,
+        })
+    }
+}
+
+impl InitHook for Php {
+    /// Empties the [Php::providers] and stores the selected provider in [Php::selected_provider]
+    async fn prompt_user(&mut self, _flox: &Flox, _path: &Path) -> Result<bool> {
+        let found_providers = std::mem::take(&mut self.providers)
+            .into_iter()
+            .filter_map(|provider| match provider {
+                Provide::Found(provider) => Some(provider),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.selected_provider = prompt_for_provider(
+            found_providers,
+            "Flox detected a PHP project with the following PHP provider(s):",
+            "Would you like Flox to set up a standard PHP environment?",
+        )?;
+        Ok(self.selected_provider.is_some())
+    }
+
+    /// Returns the customization of the selected provider or the first found provider
+    fn get_init_customization(&self) -> InitCustomization {
+        let selected = self
+            .selected_provider
+            .as_ref()
+            .map(|p| p.get_init_customization());
+        // self.providers will be empty if prompt_user() was called
+        let default = self.providers.iter().find_map(|provider| match provider {
+            Provide::Found(provider) => Some(provider.get_init_customization()),
+            _ => None,
+        });
+
+        selected
+            .or(default)
+            .expect("Should only be called if `prompt_user` returned `true`")
+    }
+}
+
+impl From<Result<Option<Composer>>> for Provide<Composer> {
+    fn from(result: Result<Option<Composer>>) -> Self {
+        match result {
+            Ok(Some(provider)) => Provide::Found(provider),
+            Ok(None) => Provide::NotFound,
+            Err(err) => Provide::Invalid(err),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ComposerJson {
+    #[serde(default)]
+    require: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Information gathered from a `composer.json`
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Composer {
+    /// Provided php version
+    ///
+    /// [ProvidedVersion::Compatible] if a version compatible with `require.php`
+    /// in the composer.json was found in the catalogs,
+    /// or the default version if `require.php` is not set.
+    ///
+    /// [ProvidedVersion::Incompatible] if no compatible version was found,
+    /// but a default version was found.
+    provided_php_version: ProvidedVersion,
+
+    /// Version of composer found in the catalog
+    composer_version: String,
+}
+
+impl Composer {
+    async fn detect(flox: &Flox, path: &Path) -> Result<Option<Self>> {
+        let composer_json = path.join(COMPOSER_JSON_FILENAME);
+        if !composer_json.is_file() {
+            debug!("No composer.json found at {:?}", path);
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&composer_json)?;
+        let composer_json: ComposerJson =
+            serde_json::from_str(&content).context("Flox found an invalid composer.json")?;
+        let required = composer_json
+            .require
+            .get("php")
+            .and_then(|php| php.as_str())
+            .map(ToString::to_string);
+
+        let provided_php_version = match required {
+            Some(required) => Self::provide_php(flox, required).await?,
+            None => ProvidedVersion::Compatible {
+                requested: None,
+                compatible: get_default_package(flox, &"php".into()).await?,
+            },
+        };
+
+        let composer_version = get_default_package(flox, &COMPOSER_PKG_PATH.into())
+            .await
+            .context("Did not find composer in the catalogs")?
+            .display_version;
+
+        Ok(Some(Self {
+            provided_php_version,
+            composer_version,
+        }))
+    }
+
+    /// Find a php version matching a Composer constraint.
+    ///
+    /// Alternatives (`||`) are tried starting with the last one,
+    /// which conventionally allows the newest versions.
+    async fn provide_php(flox: &Flox, required: String) -> Result<ProvidedVersion> {
+        for requirement in composer_requirements(&required).into_iter().rev() {
+            if let Some(compatible) = try_find_compatible_version(flox, "php", &requirement).await?
+            {
+                return Ok(ProvidedVersion::Compatible {
+                    requested: Some(requirement),
+                    compatible,
+                });
+            }
+        }
+
+        debug!("composer.json requires php {required}, but no compatible version found in the catalogs");
+
+        Ok(ProvidedVersion::Incompatible {
+            requested: required,
+            substitute: get_default_package(flox, &"php".into()).await?,
+        })
+    }
+}
+
+impl Provider for Composer {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        "composer".into()
+    }
+
+    fn describe_reason(&self) -> Cow<'static, str> {
+        COMPOSER_JSON_FILENAME.into()
+    }
+
+    fn describe_customization(&self) -> Cow<'static, str> {
+        let mut message = formatdoc! {"
+            Installs php ({}) with composer ({})
+            Adds a hook to install the project's dependencies with composer
+        ", self.provided_php_version.display_version(), self.composer_version };
+
+        if let ProvidedVersion::Incompatible {
+            substitute,
+            requested,
+        } = &self.provided_php_version
+        {
+            message.push('\n');
+            message.push_str(&format!(
+                "Note: Flox could not provide requested version {requested}, but can provide {sub_version} instead.",
+                sub_version = substitute.display_version,
+            ));
+            message.push('\n');
+        }
+
+        message.into()
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        let php_version = match &self.provided_php_version {
+            ProvidedVersion::Incompatible { .. } => None, /* do not lock if no compatible version was found */
+            ProvidedVersion::Compatible { requested, .. } => requested.clone(),
+        };
+
+        InitCustomization {
+            vars: None,
+            hook_on_activate: Some(
+                indoc! {r#"
+                # Keep Composer's global state in the Flox environment cache
+                export COMPOSER_HOME="$FLOX_ENV_CACHE/composer"
+
+                # Install PHP dependencies
+                composer install --quiet"#}
+                .to_string(),
+            ),
+            profile_common: None,
+            profile_bash: None,
+            profile_fish: None,
+            profile_tcsh: None,
+            profile_zsh: None,
+            packages: Some(vec![
+                CatalogPackage {
+                    id: "php".to_string(),
+                    pkg_path: "php".to_string(),
+                    version: php_version,
+                    systems: None,
+                    ..Default::default()
+                },
+                CatalogPackage {
+                    id: "composer".to_string(),
+                    pkg_path: COMPOSER_PKG_PATH.to_string(),
+                    version: None,
+                    systems: None,
+                    ..Default::default()
+                },
+            ]),
+        }
+    }
+}
+
+/// Convert a Composer version constraint into semver requirements,
+/// one for each alternative separated by `||`.
+///
+/// Constraints within an alternative are separated by spaces or commas.
+/// Alternatives that can't be expressed in semver, e.g. hyphen ranges, are skipped.
+fn composer_requirements(constraint: &str) -> Vec<String> {
+    constraint
+        .split("||")
+        .filter_map(|alternative| {
+            if alternative.contains(" - ") {
+                return None;
+            }
+
+            let requirement = alternative
+                .split([' ', ','])
+                .filter(|part| !part.is_empty())
+                .map(|part| {
+                    let (operator, version) =
+                        part.split_at(part.find(|c: char| c.is_ascii_digit()).unwrap_or(0));
+                    let version = version.trim_start_matches('v');
+                    match operator.trim_end_matches('v') {
+                        "~" => pessimistic_requirement(version),
+                        "" if version.contains('*') => version.to_string(),
+                        "" => format!("={version}"),
+                        operator => format!("{operator}{version}"),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            match requirement.parse::<semver::VersionReq>() {
+                Ok(_) => Some(requirement),
+                Err(err) => {
+                    debug!(alternative, %err, "php constraint is not valid semver");
+                    None
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::data::System;
+    use flox_rust_sdk::flox::test_helpers::flox_instance;
+    use flox_rust_sdk::providers::catalog::test_helpers::resolved_pkg_group_with_dummy_package;
+    use flox_rust_sdk::providers::catalog::Client;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::commands::init::ProvidedPackage;
+
+    #[test]
+    fn composer_constraints_converted_to_semver() {
+        assert_eq!(composer_requirements("^8.1"), vec!["^8.1"]);
+        assert_eq!(composer_requirements("~8.1"), vec!["^8.1"]);
+        assert_eq!(composer_requirements("~8.1.2"), vec!["~8.1.2"]);
+        assert_eq!(composer_requirements(">=8.0 <8.3"), vec![">=8.0, <8.3"]);
+        assert_eq!(composer_requirements("8.2.*"), vec!["8.2.*"]);
+        assert_eq!(composer_requirements("^7.4 || ^8.0"), vec!["^7.4", "^8.0"]);
+        assert_eq!(composer_requirements("8.0 - 8.2"), Vec::<String>::new());
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // Catalog tests
+    ///////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn composer_tries_newest_alternative_first_with_catalog() {
+        let (mut flox, temp_dir_handle) = flox_instance();
+        let path = temp_dir_handle.path();
+        fs::write(
+            path.join(COMPOSER_JSON_FILENAME),
+            r#"{ "require": { "php": "^7.4 || ^8.1", "laravel/framework": "^10.0" } }"#,
+        )
+        .unwrap();
+
+        if let Client::Mock(ref mut client) = flox.catalog_client {
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "php",
+                &System::from("aarch64-darwin"),
+                "php",
+                "php",
+                "8.3.7",
+            )]);
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "composer",
+                &System::from("aarch64-darwin"),
+                "composer",
+                COMPOSER_PKG_PATH,
+                "2.7.6",
+            )]);
+        }
+
+        let composer = Composer::detect(&flox, path).await.unwrap().unwrap();
+        assert_eq!(composer, Composer {
+            provided_php_version: ProvidedVersion::Compatible {
+                requested: Some("^8.1".to_string()),
+                compatible: ProvidedPackage::new("php", vec!["php"], "8.3.7"),
+            },
+            composer_version: "2.7.6".to_string(),
+        });
+    }
+
+    #[tokio::test]
+    async fn composer_substitutes_default_php_with_catalog() {
+        let (mut flox, temp_dir_handle) = flox_instance();
+        let path = temp_dir_handle.path();
+        fs::write(
+            path.join(COMPOSER_JSON_FILENAME),
+            r#"{ "require": { "php": "^5.6" } }"#,
+        )
+        .unwrap();
+
+        if let Client::Mock(ref mut client) = flox.catalog_client {
+            // php 5 is not available
+            client.push_resolve_response(vec![]);
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "php",
+                &System::from("aarch64-darwin"),
+                "php",
+                "php",
+                "8.3.7",
+            )]);
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "composer",
+                &System::from("aarch64-darwin"),
+                "composer",
+                COMPOSER_PKG_PATH,
+                "2.7.6",
+            )]);
+        }
+
+        let composer = Composer::detect(&flox, path).await.unwrap().unwrap();
+        assert_eq!(
+            composer.provided_php_version,
+            ProvidedVersion::Incompatible {
+                requested: "^5.6".to_string(),
+                substitute: ProvidedPackage::new("php", vec!["php"], "8.3.7"),
+            }
+        );
+        assert_eq!(
+            composer.get_init_customization().packages.unwrap()[0].version,
+            None
+        );
+    }
+}
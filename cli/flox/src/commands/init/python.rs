@@ -13,15 +13,14 @@ use log::debug;
 use regex::Regex;
 
 use super::{
-    format_customization,
     get_default_package_if_compatible,
+    prompt_for_provider,
     try_find_compatible_version,
     InitHook,
+    Provide,
     ProvidedVersion,
-    AUTO_SETUP_HINT,
+    Provider,
 };
-use crate::utils::dialog::{Dialog, Select};
-use crate::utils::message;
 
 #[derive(Debug)]
 pub(super) struct Python {
@@ -60,7 +59,7 @@ impl Python {
 impl InitHook for Python {
     /// Empties the [Python::providers] and stores the selected provider in [Python::selected_provider]
     async fn prompt_user(&mut self, _flox: &Flox, _path: &Path) -> Result<bool> {
-        let mut found_providers = std::mem::take(&mut self.providers)
+        let found_providers = std::mem::take(&mut self.providers)
             .into_iter()
             .filter_map(|provider| match provider {
                 Provide::Found(provider) => Some(provider),
@@ -68,79 +67,12 @@ impl InitHook for Python {
             })
             .collect::<Vec<_>>();
 
-        fn describe_provider(provider: &impl Provider) -> String {
-            format!(
-                "* {} ({})\n\n{}",
-                provider.describe_provider(),
-                provider.describe_reason(),
-                textwrap::indent(&provider.describe_customization(), "  ")
-            )
-        }
-
-        message::plain(formatdoc! {"
-            Flox detected a Python project with the following Python provider(s):
-
-            {}
-        ", found_providers.iter().map(describe_provider).join("\n")});
-
-        let message = formatdoc! {"
-            Would you like Flox to set up a standard Python environment?
-            You can always change the environment's manifest with 'flox edit'"};
-
-        let accept_options = found_providers
-            .iter()
-            .map(|provider| format!("Yes - with {}", provider.describe_provider()))
-            .collect::<Vec<_>>();
-
-        let n_accept_options = accept_options.len();
-
-        let show_modifications_options = found_providers
-            .iter()
-            .map(|provider| {
-                format!(
-                    "Show suggested modifications for {}",
-                    provider.describe_provider()
-                )
-            })
-            .collect::<Vec<_>>();
-
-        let cancel_option = ["No".to_string()];
-
-        let options = accept_options
-            .iter()
-            .chain(cancel_option.iter())
-            .chain(show_modifications_options.iter())
-            .collect::<Vec<_>>();
-
-        loop {
-            let dialog = Dialog {
-                message: &message,
-                help_message: Some(AUTO_SETUP_HINT),
-                typed: Select {
-                    options: options.clone(),
-                },
-            };
-
-            let (choice, _) = dialog.raw_prompt()?;
-
-            match choice {
-                choice if choice < n_accept_options => {
-                    let _ = self
-                        .selected_provider
-                        .insert(found_providers.swap_remove(choice));
-                    return Ok(true);
-                },
-                c if c == n_accept_options => {
-                    return Ok(false);
-                },
-                choice_with_offset => {
-                    let choice = choice_with_offset - (n_accept_options + 1);
-
-                    let provider = &found_providers[choice];
-                    message::plain(format_customization(&provider.get_init_customization())?);
-                },
-            }
-        }
+        self.selected_provider = prompt_for_provider(
+            found_providers,
+            "Flox detected a Python project with the following Python provider(s):",
+            "Would you like Flox to set up a standard Python environment?",
+        )?;
+        Ok(self.selected_provider.is_some())
     }
 
     /// Returns the customization of the selected provider or the first found provider
@@ -161,33 +93,6 @@ impl InitHook for Python {
     }
 }
 
-/// Flattened result of a provider detection
-///
-/// Combines [Result] and [Option] into a single enum
-#[derive(Debug)]
-enum Provide<T> {
-    /// Found a valid provider
-    Found(T),
-    /// Found a provider, but it's invalid
-    /// e.g. found a pyproject.toml, but it's not a valid poetry file
-    // We don't necessarily want to forget the error,
-    // but currently we don't do anything with it either.
-    #[allow(dead_code)]
-    Invalid(Error),
-    /// Provider not found
-    NotFound,
-}
-
-impl<P: Provider + 'static> From<Result<Option<P>>> for Provide<Box<dyn Provider>> {
-    fn from(result: Result<Option<P>>) -> Self {
-        match result {
-            Ok(Some(provider)) => Provide::Found(Box::new(provider)),
-            Ok(None) => Provide::NotFound,
-            Err(err) => Provide::Invalid(err),
-        }
-    }
-}
-
 impl From<Result<Option<PoetryPyProject>>> for Provide<PythonProvider> {
     fn from(result: Result<Option<PoetryPyProject>>) -> Self {
         match result {
@@ -259,16 +164,6 @@ impl Provider for PythonProvider {
     }
 }
 
-trait Provider: Debug {
-    fn describe_provider(&self) -> Cow<'static, str>;
-
-    fn describe_reason(&self) -> Cow<'_, str>;
-
-    fn describe_customization(&self) -> Cow<'_, str>;
-
-    fn get_init_customization(&self) -> InitCustomization;
-}
-
 /// Information gathered from a pyproject.toml file for poetry
 /// <https://packaging.python.org/en/latest/guides/distributing-packages-using-setuptools/#configuring-setup-py>
 #[derive(Debug, Clone, PartialEq)]
@@ -403,6 +298,7 @@ impl Provider for PoetryPyProject {
         };
 
         InitCustomization {
+            vars: None,
             hook_on_activate: Some(
                 indoc! {r#"
                 # Setup a Python virtual environment
@@ -453,12 +349,14 @@ impl Provider for PoetryPyProject {
                     pkg_path: "python3".to_string(),
                     version: python_version,
                     systems: None,
+                    ..Default::default()
                 },
                 CatalogPackage {
                     id: "poetry".to_string(),
                     pkg_path: "poetry".to_string(),
                     version: None,
                     systems: None,
+                    ..Default::default()
                 },
             ]),
         }
@@ -601,6 +499,7 @@ impl Provider for PyProject {
         };
 
         InitCustomization {
+            vars: None,
             hook_on_activate: Some(
                 indoc! {r#"
                 # Setup a Python virtual environment
@@ -651,6 +550,7 @@ impl Provider for PyProject {
                 pkg_path: "python3".to_string(),
                 version: python_version,
                 systems: None,
+                ..Default::default()
             }]),
         }
     }
@@ -755,6 +655,7 @@ impl Provider for Requirements {
             })
             .join("\n");
         InitCustomization {
+            vars: None,
             hook_on_activate: Some(
                 formatdoc! {r#"
                 # Setup a Python virtual environment
@@ -803,6 +704,7 @@ impl Provider for Requirements {
                 pkg_path: "python3".to_string(),
                 version: None,
                 systems: None,
+                ..Default::default()
             }]),
         }
     }
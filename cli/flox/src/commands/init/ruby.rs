@@ -0,0 +1,577 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::environment::path_environment::InitCustomization;
+use flox_rust_sdk::models::manifest::CatalogPackage;
+use indoc::{formatdoc, indoc};
+use tracing::debug;
+
+use super::{
+    get_default_package,
+    pessimistic_requirement,
+    prompt_for_provider,
+    try_find_compatible_version,
+    InitHook,
+    Provide,
+    ProvidedVersion,
+    Provider,
+};
+
+const GEMFILE_FILENAME: &str = "Gemfile";
+const RUBY_VERSION_FILENAME: &str = ".ruby-version";
+
+#[derive(Debug)]
+pub(super) struct Ruby {
+    providers: Vec<Provide<RubyProvider>>,
+    selected_provider: Option<RubyProvider>,
+}
+
+impl Ruby {
+    /// Creates and returns the [Ruby] hook with any detected
+    /// [Provider] instances.
+    /// If no providers are detected, returns [None].
+    pub async fn new(flox: &Flox, path: &Path) -> Option<Self> {
+        let providers = vec![
+            Provide::from(Bundler::detect(flox, path).await),
+            Provide::from(RubyVersionFile::detect(flox, path).await),
+        ];
+
+        debug!("Detected Ruby providers: {:#?}", providers);
+
+        if !providers
+            .iter()
+            .any(|provider| matches!(provider, Provide::Found(_)))
+        {
+            return None;
+        }
+
+        Some(Self {
+            providers,
+            selected_provider: None,
+        })
+    }
+}
+
+impl InitHook for Ruby {
+    /// Empties the [Ruby::providers] and stores the selected provider in [Ruby::selected_provider]
+    async fn prompt_user(&mut self, _flox: &Flox, _path: &Path) -> Result<bool> {
+        let found_providers = std::mem::take(&mut self.providers)
+            .into_iter()
+            .filter_map(|provider| match provider {
+                Provide::Found(provider) => Some(provider),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.selected_provider = prompt_for_provider(
+            found_providers,
+            "Flox detected a Ruby project with the following Ruby provider(s):",
+            "Would you like Flox to set up a standard Ruby environment?",
+        )?;
+        Ok(self.selected_provider.is_some())
+    }
+
+    /// Returns the customization of the selected provider or the first found provider
+    fn get_init_customization(&self) -> InitCustomization {
+        let selected = self
+            .selected_provider
+            .as_ref()
+            .map(|p| p.get_init_customization());
+        // self.providers will be empty if prompt_user() was called
+        let default = self.providers.iter().find_map(|provider| match provider {
+            Provide::Found(provider) => Some(provider.get_init_customization()),
+            _ => None,
+        });
+
+        selected
+            .or(default)
+            .expect("Should only be called if `prompt_user` returned `true`")
+    }
+}
+
+impl From<Result<Option<Bundler>>> for Provide<RubyProvider> {
+    fn from(result: Result<Option<Bundler>>) -> Self {
+        match result {
+            Ok(Some(provider)) => Provide::Found(RubyProvider::Bundler(provider)),
+            Ok(None) => Provide::NotFound,
+            Err(err) => Provide::Invalid(err),
+        }
+    }
+}
+
+impl From<Result<Option<RubyVersionFile>>> for Provide<RubyProvider> {
+    fn from(result: Result<Option<RubyVersionFile>>) -> Self {
+        match result {
+            Ok(Some(provider)) => Provide::Found(RubyProvider::RubyVersionFile(provider)),
+            Ok(None) => Provide::NotFound,
+            Err(err) => Provide::Invalid(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum RubyProvider {
+    Bundler(Bundler),
+    RubyVersionFile(RubyVersionFile),
+}
+
+impl Provider for RubyProvider {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        match self {
+            RubyProvider::Bundler(p) => p.describe_provider(),
+            RubyProvider::RubyVersionFile(p) => p.describe_provider(),
+        }
+    }
+
+    fn describe_reason(&self) -> Cow<'_, str> {
+        match self {
+            RubyProvider::Bundler(p) => p.describe_reason(),
+            RubyProvider::RubyVersionFile(p) => p.describe_reason(),
+        }
+    }
+
+    fn describe_customization(&self) -> Cow<'_, str> {
+        match self {
+            RubyProvider::Bundler(p) => p.describe_customization(),
+            RubyProvider::RubyVersionFile(p) => p.describe_customization(),
+        }
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        match self {
+            RubyProvider::Bundler(p) => p.get_init_customization(),
+            RubyProvider::RubyVersionFile(p) => p.get_init_customization(),
+        }
+    }
+}
+
+/// Provided ruby version
+///
+/// [ProvidedVersion::Compatible] if a version compatible with the requirement
+/// of the project was found in the catalogs, or no version was required.
+///
+/// [ProvidedVersion::Incompatible] if no compatible version was found,
+/// but a default version was found.
+async fn provide_ruby(flox: &Flox, requested: Option<String>) -> Result<ProvidedVersion> {
+    let Some(requested) = requested else {
+        return Ok(ProvidedVersion::Compatible {
+            requested: None,
+            compatible: get_default_package(flox, &"ruby".into()).await?,
+        });
+    };
+
+    if let Some(compatible) = try_find_compatible_version(flox, "ruby", &requested).await? {
+        return Ok(ProvidedVersion::Compatible {
+            requested: Some(requested),
+            compatible,
+        });
+    }
+
+    debug!("project requires ruby version {requested}, but no compatible version found in the catalogs");
+
+    Ok(ProvidedVersion::Incompatible {
+        requested,
+        substitute: get_default_package(flox, &"ruby".into()).await?,
+    })
+}
+
+/// Note shown if the requested ruby version can't be provided
+fn describe_incompatible(provided: &ProvidedVersion) -> String {
+    match provided {
+        ProvidedVersion::Incompatible {
+            requested,
+            substitute,
+        } => format!(
+            "\nNote: Flox could not provide requested version {requested}, but can provide {} instead.\n",
+            substitute.display_version
+        ),
+        ProvidedVersion::Compatible { .. } => String::new(),
+    }
+}
+
+/// The ruby package, constrained to the requested version if it is available
+fn ruby_package(provided: &ProvidedVersion) -> CatalogPackage {
+    let version = match provided {
+        ProvidedVersion::Incompatible { .. } => None, /* do not lock if no compatible version was found */
+        ProvidedVersion::Compatible { requested, .. } => requested.clone(),
+    };
+
+    CatalogPackage {
+        id: "ruby".to_string(),
+        pkg_path: "ruby".to_string(),
+        version,
+        systems: None,
+        ..Default::default()
+    }
+}
+
+/// Information gathered from a `Gemfile`
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Bundler {
+    /// The file the ruby version requirement was read from, if any
+    version_source: Option<&'static str>,
+
+    provided_ruby_version: ProvidedVersion,
+
+    /// Version of bundler found in the catalog
+    bundler_version: String,
+}
+
+impl Bundler {
+    async fn detect(flox: &Flox, path: &Path) -> Result<Option<Self>> {
+        let gemfile = path.join(GEMFILE_FILENAME);
+        if !gemfile.is_file() {
+            debug!("No Gemfile found at {:?}", path);
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&gemfile)?;
+        let (version_source, requested) = match parse_gemfile_ruby(&content) {
+            Some(GemfileRuby::Requirement(requested)) => (Some(GEMFILE_FILENAME), Some(requested)),
+            // `ruby file: ".ruby-version"` reads the version from another file,
+            // which is almost always .ruby-version
+            Some(GemfileRuby::File) | None => match read_ruby_version_file(path)? {
+                Some(requested) => (Some(RUBY_VERSION_FILENAME), Some(requested)),
+                None => (None, None),
+            },
+        };
+
+        let provided_ruby_version = provide_ruby(flox, requested).await?;
+        let bundler_version = get_default_package(flox, &"bundler".into())
+            .await
+            .context("Did not find bundler in the catalogs")?
+            .display_version;
+
+        Ok(Some(Self {
+            version_source,
+            provided_ruby_version,
+            bundler_version,
+        }))
+    }
+}
+
+impl Provider for Bundler {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        "bundler".into()
+    }
+
+    fn describe_reason(&self) -> Cow<'static, str> {
+        match self.version_source {
+            Some(RUBY_VERSION_FILENAME) => "Gemfile and .ruby-version".into(),
+            _ => "Gemfile".into(),
+        }
+    }
+
+    fn describe_customization(&self) -> Cow<'static, str> {
+        formatdoc! {"
+            Installs ruby ({}) with bundler ({})
+            Adds a hook to install gems into the environment's cache
+            {}", self.provided_ruby_version.display_version(), self.bundler_version,
+            describe_incompatible(&self.provided_ruby_version)
+        }
+        .into()
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        InitCustomization {
+            vars: None,
+            hook_on_activate: Some(
+                indoc! {r#"
+                # Install gems into the Flox environment cache
+                export BUNDLE_PATH="$FLOX_ENV_CACHE/bundle"
+
+                bundle install --quiet"#}
+                .to_string(),
+            ),
+            profile_common: None,
+            profile_bash: None,
+            profile_fish: None,
+            profile_tcsh: None,
+            profile_zsh: None,
+            packages: Some(vec![
+                ruby_package(&self.provided_ruby_version),
+                CatalogPackage {
+                    id: "bundler".to_string(),
+                    pkg_path: "bundler".to_string(),
+                    version: None,
+                    systems: None,
+                    ..Default::default()
+                },
+            ]),
+        }
+    }
+}
+
+/// Information gathered from a `.ruby-version` in a project without a `Gemfile`
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct RubyVersionFile {
+    provided_ruby_version: ProvidedVersion,
+}
+
+impl RubyVersionFile {
+    async fn detect(flox: &Flox, path: &Path) -> Result<Option<Self>> {
+        // Projects with a Gemfile are handled by [Bundler]
+        if path.join(GEMFILE_FILENAME).is_file() {
+            return Ok(None);
+        }
+
+        if !path.join(RUBY_VERSION_FILENAME).is_file() {
+            debug!("No .ruby-version found at {:?}", path);
+            return Ok(None);
+        }
+
+        let requested = read_ruby_version_file(path)?;
+        let provided_ruby_version = provide_ruby(flox, requested).await?;
+
+        Ok(Some(Self {
+            provided_ruby_version,
+        }))
+    }
+}
+
+impl Provider for RubyVersionFile {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        "ruby".into()
+    }
+
+    fn describe_reason(&self) -> Cow<'static, str> {
+        RUBY_VERSION_FILENAME.into()
+    }
+
+    fn describe_customization(&self) -> Cow<'static, str> {
+        formatdoc! {"
+            Installs ruby ({})
+            Installs gems into the environment's cache
+            {}", self.provided_ruby_version.display_version(),
+            describe_incompatible(&self.provided_ruby_version)
+        }
+        .into()
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        InitCustomization {
+            vars: None,
+            hook_on_activate: Some(
+                indoc! {r#"
+                # Install gems into the Flox environment cache
+                export GEM_HOME="$FLOX_ENV_CACHE/gems"
+                export PATH="$GEM_HOME/bin:$PATH""#}
+                .to_string(),
+            ),
+            profile_common: None,
+            profile_bash: None,
+            profile_fish: None,
+            profile_tcsh: None,
+            profile_zsh: None,
+            packages: Some(vec![ruby_package(&self.provided_ruby_version)]),
+        }
+    }
+}
+
+/// The `ruby` directive of a Gemfile
+#[derive(Debug, Clone, PartialEq)]
+enum GemfileRuby {
+    /// A version requirement converted to semver
+    Requirement(String),
+    /// The version is read from a file, e.g. `ruby file: ".ruby-version"`
+    File,
+}
+
+/// Parse the `ruby` directive of a Gemfile.
+///
+/// Returns [None] if there is no directive, it requires another engine such as jruby,
+/// or its requirement can't be converted to semver.
+fn parse_gemfile_ruby(content: &str) -> Option<GemfileRuby> {
+    let directive = content.lines().map(str::trim).find_map(|line| {
+        let args = line.strip_prefix("ruby")?;
+        args.starts_with([' ', '(']).then_some(args)
+    })?;
+
+    if directive.contains("engine:") {
+        debug!("Gemfile requires a ruby engine other than MRI");
+        return None;
+    }
+    if directive.contains("file:") {
+        return Some(GemfileRuby::File);
+    }
+
+    // Requirements are the quoted strings in the directive
+    let requirements = directive
+        .split(['"', '\''])
+        .skip(1)
+        .step_by(2)
+        .map(|requirement| {
+            let requirement = requirement.trim();
+            if let Some(version) = requirement.strip_prefix("~>") {
+                pessimistic_requirement(version.trim())
+            } else if requirement.starts_with(['<', '>', '=']) {
+                requirement.to_string()
+            } else {
+                format!("={}", strip_patchlevel(requirement))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if requirements.is_empty() {
+        return None;
+    }
+
+    let requirement = requirements.join(", ");
+    match requirement.parse::<semver::VersionReq>() {
+        Ok(_) => Some(GemfileRuby::Requirement(requirement)),
+        Err(err) => {
+            debug!(requirement, %err, "Gemfile ruby requirement is not valid semver");
+            None
+        },
+    }
+}
+
+/// Read `.ruby-version` as a version requirement.
+///
+/// The file contains a version such as `3.2.2` or `ruby-3.2.2`,
+/// a partial version such as `3.2` matches any patch release.
+fn read_ruby_version_file(path: &Path) -> Result<Option<String>> {
+    let ruby_version = path.join(RUBY_VERSION_FILENAME);
+    if !ruby_version.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(ruby_version)?;
+    let version = content.trim();
+    let version = version.strip_prefix("ruby-").unwrap_or(version);
+
+    let requirement = format!("={}", strip_patchlevel(version));
+    if requirement.parse::<semver::VersionReq>().is_err() {
+        debug!(version, "unsupported .ruby-version");
+        return Ok(None);
+    }
+    Ok(Some(requirement))
+}
+
+/// Remove the patchlevel of a ruby version, e.g. `2.7.1p83` or `2.7.1-p83`
+fn strip_patchlevel(version: &str) -> &str {
+    version
+        .split_once('p')
+        .map(|(version, _)| version.trim_end_matches('-'))
+        .unwrap_or(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::data::System;
+    use flox_rust_sdk::flox::test_helpers::flox_instance;
+    use flox_rust_sdk::providers::catalog::test_helpers::resolved_pkg_group_with_dummy_package;
+    use flox_rust_sdk::providers::catalog::Client;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::commands::init::ProvidedPackage;
+
+    #[test]
+    fn gemfile_ruby_directive_converted_to_semver() {
+        let exact = indoc! {r#"
+            source "https://rubygems.org"
+            ruby "3.2.2"
+            gem "rails", "~> 7.1"
+        "#};
+        assert_eq!(
+            parse_gemfile_ruby(exact),
+            Some(GemfileRuby::Requirement("=3.2.2".to_string()))
+        );
+
+        let pessimistic = "ruby '~> 3.2', '< 3.3.5'\n";
+        assert_eq!(
+            parse_gemfile_ruby(pessimistic),
+            Some(GemfileRuby::Requirement("^3.2, < 3.3.5".to_string()))
+        );
+    }
+
+    #[test]
+    fn gemfile_ruby_directive_from_file_or_other_engine() {
+        assert_eq!(
+            parse_gemfile_ruby("ruby file: \".ruby-version\"\n"),
+            Some(GemfileRuby::File)
+        );
+        assert_eq!(
+            parse_gemfile_ruby("ruby \"3.1.4\", engine: \"jruby\", engine_version: \"9.4.5.0\"\n"),
+            None
+        );
+        assert_eq!(parse_gemfile_ruby("gem \"rubyzip\"\n"), None);
+    }
+
+    #[test]
+    fn ruby_version_file_strips_prefix_and_patchlevel() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(RUBY_VERSION_FILENAME),
+            "ruby-2.7.1p83\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_ruby_version_file(temp_dir.path()).unwrap(),
+            Some("=2.7.1".to_string())
+        );
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // Catalog tests
+    ///////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn bundler_uses_gemfile_ruby_version_with_catalog() {
+        let (mut flox, temp_dir_handle) = flox_instance();
+        let path = temp_dir_handle.path();
+        fs::write(path.join(GEMFILE_FILENAME), "ruby \"3.2.2\"\n").unwrap();
+
+        if let Client::Mock(ref mut client) = flox.catalog_client {
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "ruby",
+                &System::from("aarch64-darwin"),
+                "ruby",
+                "ruby",
+                "3.2.2",
+            )]);
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "bundler",
+                &System::from("aarch64-darwin"),
+                "bundler",
+                "bundler",
+                "2.5.9",
+            )]);
+        }
+
+        let bundler = Bundler::detect(&flox, path).await.unwrap().unwrap();
+        assert_eq!(bundler, Bundler {
+            version_source: Some(GEMFILE_FILENAME),
+            provided_ruby_version: ProvidedVersion::Compatible {
+                requested: Some("=3.2.2".to_string()),
+                compatible: ProvidedPackage::new("ruby", vec!["ruby"], "3.2.2"),
+            },
+            bundler_version: "2.5.9".to_string(),
+        });
+
+        let customization = bundler.get_init_customization();
+        assert!(customization
+            .hook_on_activate
+            .unwrap()
+            .contains("BUNDLE_PATH=\"$FLOX_ENV_CACHE/bundle\""));
+        assert_eq!(
+            customization.packages.unwrap()[0].version.as_deref(),
+            Some("=3.2.2")
+        );
+    }
+
+    #[tokio::test]
+    async fn ruby_version_file_not_detected_with_gemfile() {
+        let (flox, temp_dir_handle) = flox_instance();
+        let path = temp_dir_handle.path();
+        fs::write(path.join(GEMFILE_FILENAME), "").unwrap();
+        fs::write(path.join(RUBY_VERSION_FILENAME), "3.2.2\n").unwrap();
+
+        let provider = RubyVersionFile::detect(&flox, path).await.unwrap();
+        assert_eq!(provider, None);
+    }
+}
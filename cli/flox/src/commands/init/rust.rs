@@ -0,0 +1,542 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::environment::path_environment::InitCustomization;
+use flox_rust_sdk::models::manifest::CatalogPackage;
+use flox_rust_sdk::utils::traceable_path;
+use indoc::indoc;
+use serde::Deserialize;
+use tracing::debug;
+
+use super::{
+    get_default_package,
+    prompt_for_provider,
+    try_find_compatible_version,
+    InitHook,
+    ProvidedVersion,
+    Provider,
+};
+
+const CARGO_TOML_FILENAME: &str = "Cargo.toml";
+const CARGO_LOCK_FILENAME: &str = "Cargo.lock";
+/// Toolchain files in the order rustup looks them up
+const RUST_TOOLCHAIN_FILENAMES: [&str; 2] = ["rust-toolchain", "rust-toolchain.toml"];
+
+const RUST_HOOK: &str = indoc! {"
+    # Point CARGO_HOME to Flox environment cache
+    export CARGO_HOME=\"$FLOX_ENV_CACHE/cargo\""
+};
+
+/// Toolchain components installed alongside rustc and cargo
+const RUST_TOOLS: [&str; 2] = ["rustfmt", "clippy"];
+
+/// Crates that link against native libraries,
+/// and the packages needed to build them.
+const NATIVE_BUILD_INPUTS: [(&str, &[&str]); 3] = [
+    ("openssl-sys", &["pkg-config", "openssl"]),
+    ("libz-sys", &["pkg-config", "zlib"]),
+    ("libsqlite3-sys", &["pkg-config", "sqlite"]),
+];
+
+/// The Rust hook handles installation and configuration suggestions for projects using Cargo.
+/// The general flow of the Rust hook is:
+///
+/// - [Self::new]: Detects a `Cargo.toml` in the current working directory,
+///   the toolchain requested by a `rust-toolchain(.toml)` file or `rust-version`,
+///   and native dependencies listed in `Cargo.lock`.
+/// - [Self::prompt_user]: Prints the customization from [Self::get_init_customization]
+///   if user commands to do so. Else, return `true` or `false` based on whether
+///   the user desires or not the presented customization.
+/// - [Self::get_init_customization]: Returns a Rust specific customization.
+#[derive(Debug, Clone)]
+pub(super) struct Rust {
+    /// The file the requested toolchain version was read from, if any
+    version_source: Option<&'static str>,
+    /// The rustc version that will be installed
+    rustc: ProvidedVersion,
+    /// Packages required by native dependencies in `Cargo.lock`
+    native_build_inputs: Vec<&'static str>,
+}
+
+impl Rust {
+    /// Creates and returns the [Rust] hook if a `Cargo.toml` is found.
+    /// If no compatible toolchain is available for the requested version,
+    /// the default toolchain is suggested instead.
+    pub async fn new(flox: &Flox, path: &Path) -> Result<Option<Self>> {
+        let cargo_toml_path = path.join(CARGO_TOML_FILENAME);
+        if !cargo_toml_path.is_file() {
+            debug!(
+                path = traceable_path(&cargo_toml_path),
+                "Cargo.toml not located"
+            );
+            return Ok(None);
+        }
+        debug!(
+            path = traceable_path(&cargo_toml_path),
+            "Cargo.toml located"
+        );
+
+        let rust_version = parse_rust_version(&fs::read_to_string(&cargo_toml_path)?)?;
+        let toolchain = RustToolchain::try_from_path(path)?;
+
+        let (version_source, requested) =
+            match requested_version(toolchain.as_ref(), rust_version.as_deref()) {
+                Some((RequestSource::Toolchain, requested)) => (
+                    toolchain.map(|toolchain| toolchain.filename),
+                    Some(requested),
+                ),
+                Some((RequestSource::RustVersion, requested)) => {
+                    (Some(CARGO_TOML_FILENAME), Some(requested))
+                },
+                None => (None, None),
+            };
+
+        let rustc = match requested {
+            Some(requested) => {
+                match try_find_compatible_version(flox, "rustc", &requested).await? {
+                    Some(compatible) => ProvidedVersion::Compatible {
+                        requested: Some(requested),
+                        compatible,
+                    },
+                    None => ProvidedVersion::Incompatible {
+                        requested,
+                        substitute: get_default_package(flox, &"rustc".into()).await?,
+                    },
+                }
+            },
+            None => ProvidedVersion::Compatible {
+                requested: None,
+                compatible: get_default_package(flox, &"rustc".into()).await?,
+            },
+        };
+
+        let cargo_lock_path = path.join(CARGO_LOCK_FILENAME);
+        let native_build_inputs = if cargo_lock_path.is_file() {
+            native_build_inputs(&fs::read_to_string(cargo_lock_path)?)
+        } else {
+            vec![]
+        };
+
+        Ok(Some(Self {
+            version_source,
+            rustc,
+            native_build_inputs,
+        }))
+    }
+
+    /// The version constraint to put on rustc and cargo,
+    /// [None] if the requested version isn't available.
+    fn version_constraint(&self) -> Option<String> {
+        match &self.rustc {
+            ProvidedVersion::Compatible { requested, .. } => requested.clone(),
+            ProvidedVersion::Incompatible { .. } => None,
+        }
+    }
+}
+
+impl InitHook for Rust {
+    /// Returns `true` if the user accepts the prompt. In that case,
+    /// the hook customizes the manifest with the default Rust environment.
+    async fn prompt_user(&mut self, _flox: &Flox, _path: &Path) -> Result<bool> {
+        let accepted = prompt_for_provider(
+            vec![&*self],
+            "Flox detected a Rust project:",
+            "Would you like Flox to set up a standard Rust environment?",
+        )?;
+        Ok(accepted.is_some())
+    }
+
+    /// Returns an [InitCustomization] with the Rust toolchain,
+    /// the native build inputs and a hook setting `CARGO_HOME`.
+    fn get_init_customization(&self) -> InitCustomization {
+        let version = self.version_constraint();
+
+        let toolchain = ["rustc", "cargo"].into_iter().map(|name| CatalogPackage {
+            id: name.to_string(),
+            pkg_path: name.to_string(),
+            version: version.clone(),
+            systems: None,
+            ..Default::default()
+        });
+        let unversioned = RUST_TOOLS
+            .into_iter()
+            .chain(self.native_build_inputs.iter().copied())
+            .map(|name| CatalogPackage {
+                id: name.to_string(),
+                pkg_path: name.to_string(),
+                version: None,
+                systems: None,
+                ..Default::default()
+            });
+
+        InitCustomization {
+            vars: None,
+            hook_on_activate: Some(RUST_HOOK.to_string()),
+            profile_common: None,
+            profile_bash: None,
+            profile_fish: None,
+            profile_tcsh: None,
+            profile_zsh: None,
+            packages: Some(toolchain.chain(unversioned).collect()),
+        }
+    }
+}
+
+/// Lets the Rust hook be offered by [prompt_for_provider].
+///
+/// Implemented for references so that calls to `get_init_customization`
+/// on [Rust] resolve to [InitHook::get_init_customization].
+impl Provider for &Rust {
+    fn describe_provider(&self) -> Cow<'static, str> {
+        "cargo".into()
+    }
+
+    fn describe_reason(&self) -> Cow<'_, str> {
+        match self.version_source {
+            Some(source) if source != CARGO_TOML_FILENAME => {
+                format!("{CARGO_TOML_FILENAME}, {source}").into()
+            },
+            _ => CARGO_TOML_FILENAME.into(),
+        }
+    }
+
+    fn describe_customization(&self) -> Cow<'_, str> {
+        let mut description = format!(
+            "Installs rustc and cargo ({}) with {}\n",
+            self.rustc.display_version(),
+            RUST_TOOLS.join(" and ")
+        );
+        if !self.native_build_inputs.is_empty() {
+            description.push_str(&format!(
+                "Installs {} to build native dependencies\n",
+                self.native_build_inputs.join(", ")
+            ));
+        }
+        description.push_str("Sets CARGO_HOME to the environment's cache\n");
+        if let (ProvidedVersion::Incompatible { requested, .. }, Some(source)) =
+            (&self.rustc, self.version_source)
+        {
+            description.push_str(&format!(
+                "{source} requests Rust {requested}, which Flox can't provide\n"
+            ));
+        }
+        description.into()
+    }
+
+    fn get_init_customization(&self) -> InitCustomization {
+        InitHook::get_init_customization(*self)
+    }
+}
+
+/// Where a requested Rust version came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RequestSource {
+    Toolchain,
+    RustVersion,
+}
+
+/// The toolchain pinned by a `rust-toolchain` or `rust-toolchain.toml` file
+#[derive(Debug, Clone, PartialEq)]
+struct RustToolchain {
+    filename: &'static str,
+    /// e.g. `stable`, `nightly-2024-01-01` or `1.75.0`
+    channel: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RustToolchainFile {
+    toolchain: RustToolchainTable,
+}
+
+#[derive(Debug, Deserialize)]
+struct RustToolchainTable {
+    channel: Option<String>,
+}
+
+impl RustToolchain {
+    /// Read the first toolchain file found in `path`.
+    /// Returns [None] if there is none or it doesn't specify a channel.
+    fn try_from_path(path: &Path) -> Result<Option<Self>> {
+        for filename in RUST_TOOLCHAIN_FILENAMES {
+            let toolchain_path = path.join(filename);
+            if !toolchain_path.is_file() {
+                continue;
+            }
+            debug!(
+                path = traceable_path(&toolchain_path),
+                "rust toolchain file located"
+            );
+            let content = fs::read_to_string(&toolchain_path)?;
+            return Ok(Self::parse_channel(&content).map(|channel| Self { filename, channel }));
+        }
+        Ok(None)
+    }
+
+    /// Parse the channel of a toolchain file.
+    ///
+    /// `rust-toolchain` may either be a TOML file like `rust-toolchain.toml`,
+    /// or contain only the channel name.
+    fn parse_channel(content: &str) -> Option<String> {
+        if let Ok(file) = toml::from_str::<RustToolchainFile>(content) {
+            return file.toolchain.channel;
+        }
+
+        let channel = content.trim();
+        if channel.is_empty() || channel.contains(char::is_whitespace) {
+            debug!("invalid rust toolchain file");
+            return None;
+        }
+        Some(channel.to_string())
+    }
+}
+
+/// Read `rust-version` from a `Cargo.toml`,
+/// falling back to the version shared by a workspace.
+fn parse_rust_version(cargo_toml: &str) -> Result<Option<String>> {
+    let manifest: toml::Table =
+        toml::from_str(cargo_toml).context("Flox found an invalid Cargo.toml")?;
+
+    let rust_version = |package: Option<&toml::Value>| {
+        package?
+            .get("rust-version")?
+            .as_str()
+            .map(ToString::to_string)
+    };
+
+    Ok(rust_version(manifest.get("package")).or_else(|| {
+        rust_version(
+            manifest
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package")),
+        )
+    }))
+}
+
+/// Convert a toolchain channel or `rust-version` into a semver requirement.
+///
+/// A channel with a version pins that version, `1.75` allowing any patch release.
+/// Named channels such as `stable` or `nightly` can't be mapped to catalog versions,
+/// so `rust-version`, the minimum supported version, is used instead if present.
+fn requested_version(
+    toolchain: Option<&RustToolchain>,
+    rust_version: Option<&str>,
+) -> Option<(RequestSource, String)> {
+    let pinned = toolchain.and_then(|toolchain| {
+        let channel = toolchain.channel.as_str();
+        let requirement = match channel.split('.').count() {
+            2 => format!("~{channel}"),
+            3 => format!("={channel}"),
+            _ => return None,
+        };
+        requirement.parse::<semver::VersionReq>().ok()?;
+        Some((RequestSource::Toolchain, requirement))
+    });
+
+    pinned.or_else(|| {
+        let requirement = format!(">={}", rust_version?);
+        if requirement.parse::<semver::VersionReq>().is_err() {
+            debug!(requirement, "invalid rust-version");
+            return None;
+        }
+        Some((RequestSource::RustVersion, requirement))
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<CargoLockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoLockPackage {
+    name: String,
+}
+
+/// Collect the packages needed to build the native dependencies in a `Cargo.lock`
+fn native_build_inputs(cargo_lock: &str) -> Vec<&'static str> {
+    let cargo_lock = match toml::from_str::<CargoLock>(cargo_lock) {
+        Ok(cargo_lock) => cargo_lock,
+        Err(err) => {
+            debug!(%err, "could not parse Cargo.lock");
+            return vec![];
+        },
+    };
+
+    let mut inputs = vec![];
+    for (crate_name, packages) in NATIVE_BUILD_INPUTS {
+        if !cargo_lock
+            .package
+            .iter()
+            .any(|package| package.name == crate_name)
+        {
+            continue;
+        }
+        for package in packages {
+            if !inputs.contains(package) {
+                inputs.push(*package);
+            }
+        }
+    }
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::data::System;
+    use flox_rust_sdk::flox::test_helpers::flox_instance;
+    use flox_rust_sdk::providers::catalog::test_helpers::resolved_pkg_group_with_dummy_package;
+    use flox_rust_sdk::providers::catalog::Client;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::commands::init::ProvidedPackage;
+
+    fn toolchain(channel: &str) -> RustToolchain {
+        RustToolchain {
+            filename: "rust-toolchain.toml",
+            channel: channel.to_string(),
+        }
+    }
+
+    #[test]
+    fn toolchain_channel_parsed_from_toml_and_plain_files() {
+        let toml = indoc! {r#"
+            [toolchain]
+            channel = "1.75.0"
+            components = ["rustfmt"]
+        "#};
+        assert_eq!(
+            RustToolchain::parse_channel(toml),
+            Some("1.75.0".to_string())
+        );
+        assert_eq!(
+            RustToolchain::parse_channel("nightly-2024-01-01\n"),
+            Some("nightly-2024-01-01".to_string())
+        );
+    }
+
+    #[test]
+    fn requested_version_pins_toolchain_channel() {
+        assert_eq!(
+            requested_version(Some(&toolchain("1.75.0")), Some("1.70")),
+            Some((RequestSource::Toolchain, "=1.75.0".to_string()))
+        );
+        assert_eq!(
+            requested_version(Some(&toolchain("1.75")), None),
+            Some((RequestSource::Toolchain, "~1.75".to_string()))
+        );
+    }
+
+    #[test]
+    fn requested_version_falls_back_to_rust_version_for_named_channels() {
+        assert_eq!(
+            requested_version(Some(&toolchain("stable")), Some("1.70")),
+            Some((RequestSource::RustVersion, ">=1.70".to_string()))
+        );
+        assert_eq!(requested_version(Some(&toolchain("nightly")), None), None);
+    }
+
+    #[test]
+    fn rust_version_read_from_package_or_workspace() {
+        let package = indoc! {r#"
+            [package]
+            name = "foo"
+            rust-version = "1.70"
+        "#};
+        assert_eq!(
+            parse_rust_version(package).unwrap(),
+            Some("1.70".to_string())
+        );
+
+        let workspace = indoc! {r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.package]
+            rust-version = "1.72"
+        "#};
+        assert_eq!(
+            parse_rust_version(workspace).unwrap(),
+            Some("1.72".to_string())
+        );
+    }
+
+    #[test]
+    fn native_build_inputs_detected_in_cargo_lock() {
+        let cargo_lock = indoc! {r#"
+            version = 3
+
+            [[package]]
+            name = "openssl-sys"
+            version = "0.9.102"
+
+            [[package]]
+            name = "libz-sys"
+            version = "1.1.18"
+        "#};
+        assert_eq!(native_build_inputs(cargo_lock), vec![
+            "pkg-config",
+            "openssl",
+            "zlib"
+        ]);
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // Catalog tests
+    ///////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn rust_hook_not_created_without_cargo_toml() {
+        let (flox, temp_dir_handle) = flox_instance();
+
+        let hook = Rust::new(&flox, temp_dir_handle.path()).await.unwrap();
+        assert!(hook.is_none());
+    }
+
+    #[tokio::test]
+    async fn rust_hook_pins_toolchain_version_with_catalog() {
+        let (mut flox, temp_dir_handle) = flox_instance();
+        let path = temp_dir_handle.path();
+
+        fs::write(path.join(CARGO_TOML_FILENAME), indoc! {r#"
+            [package]
+            name = "foo"
+        "#})
+        .unwrap();
+        fs::write(path.join("rust-toolchain"), "1.75.0\n").unwrap();
+        fs::write(path.join(CARGO_LOCK_FILENAME), indoc! {r#"
+            [[package]]
+            name = "openssl-sys"
+            version = "0.9.102"
+        "#})
+        .unwrap();
+
+        if let Client::Mock(ref mut client) = flox.catalog_client {
+            client.push_resolve_response(vec![resolved_pkg_group_with_dummy_package(
+                "rustc",
+                &System::from("aarch64-darwin"),
+                "rustc",
+                "rustc",
+                "1.75.0",
+            )]);
+        }
+
+        let hook = Rust::new(&flox, path).await.unwrap().unwrap();
+        assert_eq!(hook.rustc, ProvidedVersion::Compatible {
+            requested: Some("=1.75.0".to_string()),
+            compatible: ProvidedPackage::new("rustc", vec!["rustc"], "1.75.0"),
+        });
+
+        let customization = hook.get_init_customization();
+        assert_eq!(customization.hook_on_activate.as_deref(), Some(RUST_HOOK));
+        let packages = customization.packages.unwrap();
+        let cargo = packages.iter().find(|p| p.id == "cargo").unwrap();
+        assert_eq!(cargo.version.as_deref(), Some("=1.75.0"));
+        assert!(packages.iter().any(|p| p.id == "openssl"));
+        assert!(packages.iter().any(|p| p.id == "pkg-config"));
+    }
+}
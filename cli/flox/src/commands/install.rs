@@ -68,6 +68,23 @@ pub struct Install {
     #[bpaf(external(pkg_with_id_option), many)]
     id: Vec<PkgWithIdOption>,
 
+    /// Install the packages to <group> instead of the 'toplevel' group
+    #[bpaf(long, argument("group"))]
+    group: Option<String>,
+
+    /// Set the priority of the packages, lower values take precedence
+    #[bpaf(long, argument("priority"))]
+    priority: Option<u64>,
+
+    /// Only install the packages on the given comma separated systems
+    #[bpaf(long, argument("systems"))]
+    systems: Option<CommaSeparated>,
+
+    /// Install the given comma separated outputs, e.g. 'out,man'
+    /// instead of the outputs the packages install by default
+    #[bpaf(long, argument("outputs"))]
+    outputs: Option<CommaSeparated>,
+
     #[bpaf(positional("packages"))]
     packages: Vec<String>,
 }
@@ -91,6 +108,27 @@ pub struct PkgWithIdOption {
     pub pkg: String,
 }
 
+/// A comma separated list of values, e.g. `out,man`
+#[derive(Debug, Clone, PartialEq)]
+struct CommaSeparated(Vec<String>);
+
+impl FromStr for CommaSeparated {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            bail!("expected a comma separated list of values");
+        }
+        Ok(Self(values))
+    }
+}
+
 impl Install {
     #[instrument(name = "install", skip_all)]
     pub async fn handle(self, mut flox: Flox) -> Result<()> {
@@ -103,7 +141,7 @@ impl Install {
         );
 
         // Ensure the user is logged in for the following remote operations
-        if let EnvironmentSelect::Remote(_) = self.environment {
+        if self.environment.is_remote(&flox)? {
             ensure_floxhub_token(&mut flox).await?;
         }
 
@@ -127,6 +165,7 @@ impl Install {
         if packages_to_install.is_empty() {
             bail!("Must specify at least one package");
         }
+        self.apply_descriptor_options(&mut packages_to_install)?;
 
         let concrete_environment = match self
             .environment
@@ -241,6 +280,7 @@ impl Install {
                     "Package with id '{}' already installed to environment {description}",
                     pkg.id()
                 ));
+                self.warn_descriptor_options_not_applied(pkg.id());
             }
         }
 
@@ -251,6 +291,70 @@ impl Install {
         Ok(())
     }
 
+    /// The flags of the descriptor options that were passed,
+    /// and the keys of package descriptors they set
+    fn descriptor_options(&self) -> Vec<(&'static str, &'static str)> {
+        [
+            ("--group", "pkg-group", self.group.is_some()),
+            ("--priority", "priority", self.priority.is_some()),
+            ("--systems", "systems", self.systems.is_some()),
+            ("--outputs", "outputs", self.outputs.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(flag, key, passed)| passed.then_some((flag, key)))
+        .collect()
+    }
+
+    /// Apply `--group`, `--priority`, `--systems` and `--outputs` to all packages.
+    ///
+    /// These options are only supported by catalog packages.
+    fn apply_descriptor_options(&self, packages: &mut [PackageToInstall]) -> Result<()> {
+        if self.descriptor_options().is_empty() {
+            return Ok(());
+        }
+
+        for package in packages.iter_mut() {
+            let package = match package {
+                PackageToInstall::Catalog(package) => package,
+                other => bail!(formatdoc! {"
+                    '--group', '--priority', '--systems' and '--outputs'
+                    are only supported for packages from the catalog.
+
+                    Use 'flox edit' or 'flox manifest set' to configure '{}'.
+                ", other.id()}),
+            };
+            package.pkg_group = self.group.clone();
+            package.priority = self.priority;
+            if let Some(CommaSeparated(systems)) = &self.systems {
+                package.systems = Some(systems.clone());
+            }
+            package.outputs = self.outputs.clone().map(|CommaSeparated(outputs)| outputs);
+        }
+        Ok(())
+    }
+
+    /// Warn that descriptor options are not applied to an already installed package,
+    /// and point to `flox manifest set` instead.
+    fn warn_descriptor_options_not_applied(&self, install_id: &str) {
+        let options = self.descriptor_options();
+        if options.is_empty() {
+            return;
+        }
+        let flags = options
+            .iter()
+            .map(|(flag, _)| format!("'{flag}'"))
+            .join(", ");
+        let commands = options
+            .iter()
+            .map(|(_, key)| format!("  flox manifest set install.{install_id}.{key} <value>"))
+            .join("\n");
+        message::warning(formatdoc! {"
+            {flags} not applied to '{install_id}' because it is already installed.
+            Change the installed package with:
+            {commands}
+        "});
+    }
+
     fn format_packages_for_tracing(packages: &[PackageToInstall]) -> String {
         packages
             .iter()
@@ -586,7 +690,7 @@ mod tests {
     use flox_rust_sdk::providers::catalog::SystemEnum;
 
     use super::{add_activation_to_rc_file, ensure_rc_file_exists};
-    use crate::commands::install::{package_list_for_prompt, Install};
+    use crate::commands::install::{package_list_for_prompt, CommaSeparated, Install};
 
     /// [Install::generate_warnings] shouldn't warn for packages not in packages_to_install
     #[test]
@@ -610,6 +714,7 @@ mod tests {
             pkg_path: "foo".to_string(),
             version: None,
             systems: None,
+            ..Default::default()
         }];
         assert_eq!(
             Install::generate_warnings(&locked_packages, &packages_to_install),
@@ -638,6 +743,7 @@ mod tests {
             pkg_path: "foo".to_string(),
             version: None,
             systems: None,
+            ..Default::default()
         }];
         assert_eq!(
             Install::generate_warnings(&locked_packages, &packages_to_install),
@@ -658,6 +764,7 @@ mod tests {
             pkg_path: "foo".to_string(),
             version: None,
             systems: None,
+            ..Default::default()
         }];
         assert_eq!(
             Install::generate_warnings(&locked_packages, &packages_to_install),
@@ -686,6 +793,7 @@ mod tests {
             pkg_path: "foo".to_string(),
             version: None,
             systems: None,
+            ..Default::default()
         }];
         assert_eq!(
             Install::generate_warnings(&locked_packages, &packages_to_install),
@@ -742,4 +850,45 @@ mod tests {
         add_activation_to_rc_file(&rc_file_path, "be activated").unwrap();
         assert!(backup.exists());
     }
+
+    #[test]
+    fn comma_separated_values() {
+        assert_eq!(
+            "out, man,".parse::<CommaSeparated>().unwrap(),
+            CommaSeparated(vec!["out".to_string(), "man".to_string()])
+        );
+        assert!(",".parse::<CommaSeparated>().is_err());
+    }
+
+    /// [Install::apply_descriptor_options] sets the options of catalog packages
+    /// and rejects other packages
+    #[test]
+    fn apply_descriptor_options() {
+        let install = Install {
+            environment: Default::default(),
+            id: vec![],
+            group: Some("tools".to_string()),
+            priority: Some(3),
+            systems: None,
+            outputs: Some(CommaSeparated(vec!["out".to_string(), "man".to_string()])),
+            packages: vec![],
+        };
+
+        let mut packages = vec![PackageToInstall::Catalog("hello".parse().unwrap())];
+        install.apply_descriptor_options(&mut packages).unwrap();
+        let PackageToInstall::Catalog(package) = &packages[0] else {
+            panic!("expected a catalog package");
+        };
+        assert_eq!(package.pkg_group.as_deref(), Some("tools"));
+        assert_eq!(package.priority, Some(3));
+        assert_eq!(package.systems, None);
+        assert_eq!(
+            package.outputs,
+            Some(vec!["out".to_string(), "man".to_string()])
+        );
+
+        let mut packages =
+            vec![PackageToInstall::parse(&"x86_64-linux".to_string(), "github:flox/flox").unwrap()];
+        assert!(install.apply_descriptor_options(&mut packages).is_err());
+    }
 }
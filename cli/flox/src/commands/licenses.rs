@@ -0,0 +1,150 @@
+use std::io::{stdout, Write};
+
+use anyhow::Result;
+use bpaf::Bpaf;
+use flox_rust_sdk::data::System;
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::license::{LicenseReport, PackageLicense};
+use tracing::instrument;
+
+use super::{environment_select, EnvironmentSelect};
+use crate::subcommand_metric;
+use crate::utils::message;
+
+// Show the licenses of the packages in an environment
+#[derive(Bpaf, Clone)]
+pub struct Licenses {
+    #[bpaf(external(environment_select), fallback(Default::default()))]
+    environment: EnvironmentSelect,
+
+    /// System to show licenses for (default: current system)
+    #[bpaf(long, argument("system"))]
+    system: Option<System>,
+
+    /// Format output as JSON
+    #[bpaf(long)]
+    json: bool,
+}
+
+impl Licenses {
+    #[instrument(name = "licenses", skip_all)]
+    pub fn handle(self, flox: Flox) -> Result<()> {
+        subcommand_metric!("licenses");
+
+        let mut env = self
+            .environment
+            .detect_concrete_environment(&flox, "Show licenses of")?
+            .into_dyn_environment();
+
+        let system = self.system.unwrap_or_else(|| flox.system.clone());
+        let lockfile = env.lockfile(&flox)?;
+        let report = LicenseReport::new(&lockfile, &system)?;
+
+        if self.json {
+            println!("{:#}", serde_json::json!(report));
+            return Ok(());
+        }
+
+        if report.packages.is_empty() {
+            message::warning(format!("No packages are installed for '{system}'."));
+            return Ok(());
+        }
+
+        Self::print_report(stdout().lock(), &report)?;
+        Ok(())
+    }
+
+    /// Print one line per package with aligned columns
+    ///
+    /// e.g.
+    /// ```text
+    /// hello    2.12.1  GPL-3.0-or-later  allowed
+    /// openssl  3.0.7   Apache-2.0        not allowed
+    /// ```
+    fn print_report(mut out: impl Write, report: &LicenseReport) -> Result<()> {
+        let rows = report
+            .packages
+            .iter()
+            .map(
+                |PackageLicense {
+                     install_id,
+                     version,
+                     license,
+                     verdict,
+                     ..
+                 }| {
+                    [
+                        install_id.clone(),
+                        version.clone().unwrap_or_else(|| "N/A".to_string()),
+                        license.clone().unwrap_or_else(|| "N/A".to_string()),
+                        verdict
+                            .map(|verdict| verdict.to_string())
+                            .unwrap_or_else(|| "unknown".to_string()),
+                    ]
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let widths = (0..3)
+            .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        for [install_id, version, license, verdict] in rows {
+            writeln!(
+                &mut out,
+                "{install_id:<w0$}  {version:<w1$}  {license:<w2$}  {verdict}",
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::models::license::LicenseVerdict;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn print_report_aligned() {
+        let report = LicenseReport {
+            system: "x86_64-linux".to_string(),
+            packages: vec![
+                PackageLicense {
+                    install_id: "hello".to_string(),
+                    pname: Some("hello".to_string()),
+                    version: Some("2.12.1".to_string()),
+                    license: Some("GPL-3.0-or-later".to_string()),
+                    verdict: Some(LicenseVerdict::Exception),
+                },
+                PackageLicense {
+                    install_id: "ssl".to_string(),
+                    pname: Some("openssl".to_string()),
+                    version: Some("3.0.7".to_string()),
+                    license: Some("Apache-2.0".to_string()),
+                    verdict: Some(LicenseVerdict::NotAllowed),
+                },
+                PackageLicense {
+                    install_id: "tool".to_string(),
+                    pname: None,
+                    version: None,
+                    license: None,
+                    verdict: None,
+                },
+            ],
+        };
+
+        let mut out = Vec::new();
+        Licenses::print_report(&mut out, &report).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), indoc! {"
+            hello  2.12.1  GPL-3.0-or-later  allowed by exception
+            ssl    3.0.7   Apache-2.0        not allowed
+            tool   N/A     N/A               unknown
+        "});
+    }
+}
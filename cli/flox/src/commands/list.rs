@@ -11,6 +11,7 @@ use flox_rust_sdk::models::lockfile::{
     PackageInfo,
     PackageToList,
 };
+use flox_rust_sdk::models::manifest::RawManifest;
 use flox_rust_sdk::providers::flox_cpp_utils::LockedInstallable;
 use indoc::formatdoc;
 use itertools::Itertools;
@@ -33,7 +34,8 @@ pub struct List {
 
 #[derive(Bpaf, Clone, PartialEq, Debug)]
 pub enum ListMode {
-    /// Show the raw contents of the manifest
+    /// Show the raw contents of the manifest,
+    /// with overrides for the current system applied
     #[bpaf(long, short)]
     Config,
 
@@ -63,7 +65,14 @@ impl List {
 
         let manifest_contents = env.manifest_contents(&flox)?;
         if self.list_mode == ListMode::Config {
-            println!("{manifest_contents}");
+            // Only manifests with overrides are reformatted,
+            // everything else is shown exactly as written
+            match manifest_contents.parse::<RawManifest>() {
+                Ok(raw) if raw.contains_key("target") => {
+                    println!("{}", *raw.for_system(&flox.system)?);
+                },
+                _ => println!("{manifest_contents}"),
+            }
             return Ok(());
         }
 
@@ -0,0 +1,212 @@
+use anyhow::{bail, Result};
+use bpaf::Bpaf;
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::environment::{ConcreteEnvironment, EditResult};
+use flox_rust_sdk::models::manifest::{self, Manifest};
+use flox_rust_sdk::models::manifest_lint::{lint_manifest, LintProblem, LintSeverity};
+use tracing::instrument;
+
+use super::services::warn_manifest_changes_for_services;
+use super::{
+    activated_environments,
+    environment_select,
+    EnvironmentSelect,
+    UninitializedEnvironment,
+};
+use crate::commands::ensure_floxhub_token;
+use crate::subcommand_metric;
+use crate::utils::message;
+
+// Inspect, edit and validate manifests
+#[derive(Bpaf, Clone)]
+pub enum ManifestCommands {
+    /// Print the JSON Schema of the manifest
+    #[bpaf(command)]
+    Schema,
+
+    /// Report all problems in the manifest of an environment
+    #[bpaf(command)]
+    Lint {
+        #[bpaf(external(environment_select), fallback(Default::default()))]
+        environment: EnvironmentSelect,
+
+        /// Format output as JSON
+        #[bpaf(long)]
+        json: bool,
+    },
+
+    /// Print the value of a key in the manifest
+    #[bpaf(command)]
+    Get {
+        #[bpaf(external(environment_select), fallback(Default::default()))]
+        environment: EnvironmentSelect,
+
+        /// Format output as JSON
+        #[bpaf(long)]
+        json: bool,
+
+        /// Dotted key to print, e.g. 'install.hello.pkg-path'
+        #[bpaf(positional("key"))]
+        key: String,
+    },
+
+    /// Set a key in the manifest and lock the environment
+    #[bpaf(command)]
+    Set {
+        #[bpaf(external(environment_select), fallback(Default::default()))]
+        environment: EnvironmentSelect,
+
+        /// Dotted key to set, e.g. 'vars.FOO'
+        #[bpaf(positional("key"))]
+        key: String,
+
+        /// TOML value to set, values that aren't valid TOML are set as strings
+        #[bpaf(positional("value"))]
+        value: String,
+    },
+
+    /// Remove a key from the manifest and lock the environment
+    #[bpaf(command)]
+    Unset {
+        #[bpaf(external(environment_select), fallback(Default::default()))]
+        environment: EnvironmentSelect,
+
+        /// Dotted key to remove, e.g. 'vars.FOO'
+        #[bpaf(positional("key"))]
+        key: String,
+    },
+}
+
+impl ManifestCommands {
+    #[instrument(name = "manifest", skip_all)]
+    pub async fn handle(self, mut flox: Flox) -> Result<()> {
+        match self {
+            ManifestCommands::Schema => {
+                subcommand_metric!("manifest::schema");
+                println!("{:#}", Manifest::json_schema());
+            },
+            ManifestCommands::Lint { environment, json } => {
+                subcommand_metric!("manifest::lint");
+
+                let env = environment
+                    .detect_concrete_environment(&flox, "Lint")?
+                    .into_dyn_environment();
+                let problems = lint_manifest(&env.manifest_contents(&flox)?);
+
+                if json {
+                    println!("{:#}", serde_json::json!(problems));
+                } else {
+                    for problem in problems.iter() {
+                        println!("{}", format_problem(problem));
+                    }
+                }
+
+                let errors = problems
+                    .iter()
+                    .filter(|problem| problem.severity == LintSeverity::Error)
+                    .count();
+                if errors > 0 {
+                    bail!("Found {errors} error(s) in the manifest");
+                }
+                if !json && problems.is_empty() {
+                    message::updated("No problems found in the manifest");
+                }
+            },
+            ManifestCommands::Get {
+                environment,
+                json,
+                key,
+            } => {
+                subcommand_metric!("manifest::get");
+
+                let env = environment
+                    .detect_concrete_environment(&flox, "Read the manifest of")?
+                    .into_dyn_environment();
+                let value = manifest::get_value(&env.manifest_contents(&flox)?, &key)?;
+
+                if json {
+                    println!("{:#}", serde_json::json!(value));
+                    return Ok(());
+                }
+                match value {
+                    toml::Value::String(string) => println!("{string}"),
+                    toml::Value::Table(table) => print!("{}", toml::to_string(&table)?),
+                    value => println!("{value}"),
+                }
+            },
+            ManifestCommands::Set {
+                environment,
+                key,
+                value,
+            } => {
+                subcommand_metric!("manifest::set");
+
+                let mut env = Self::detect_environment(&mut flox, environment).await?;
+                let contents = env.dyn_environment_ref().manifest_contents(&flox)?;
+                let doc = manifest::set_value(&contents, &key, &value)?;
+                Self::apply_edit(&flox, &mut env, doc.to_string())?;
+            },
+            ManifestCommands::Unset { environment, key } => {
+                subcommand_metric!("manifest::unset");
+
+                let mut env = Self::detect_environment(&mut flox, environment).await?;
+                let contents = env.dyn_environment_ref().manifest_contents(&flox)?;
+                let doc = manifest::unset_value(&contents, &key)?;
+                Self::apply_edit(&flox, &mut env, doc.to_string())?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Detect the environment to edit,
+    /// making sure we are logged in to edit environments on FloxHub
+    async fn detect_environment(
+        flox: &mut Flox,
+        environment: EnvironmentSelect,
+    ) -> Result<ConcreteEnvironment> {
+        if environment.is_remote(flox)? {
+            ensure_floxhub_token(flox).await?;
+        };
+        Ok(environment.detect_concrete_environment(flox, "Edit")?)
+    }
+
+    /// Replace the manifest of `env` with `contents`,
+    /// which locks and builds the environment before committing the change
+    fn apply_edit(flox: &Flox, env: &mut ConcreteEnvironment, contents: String) -> Result<()> {
+        let active_environment = UninitializedEnvironment::from_concrete_environment(env)?;
+        let env = env.dyn_environment_ref_mut();
+
+        match env.edit(flox, contents)? {
+            EditResult::Unchanged => {
+                message::warning("No changes made to environment.");
+                return Ok(());
+            },
+            EditResult::ReActivateRequired { .. }
+                if activated_environments().is_active(&active_environment) =>
+            {
+                message::warning(
+                    "Please 'exit' the environment and run 'flox activate' to see these changes.",
+                )
+            },
+            EditResult::ReActivateRequired { .. } | EditResult::Success { .. } => {
+                message::updated("Environment successfully updated.")
+            },
+        }
+
+        warn_manifest_changes_for_services(flox, env);
+        Ok(())
+    }
+}
+
+/// Format a problem as `<severity>: <path>: <message>`,
+/// omitting the path for problems with the manifest as a whole
+fn format_problem(problem: &LintProblem) -> String {
+    if problem.path.is_empty() {
+        format!("{}: {}", problem.severity, problem.message)
+    } else {
+        format!(
+            "{}: {}: {}",
+            problem.severity, problem.path, problem.message
+        )
+    }
+}
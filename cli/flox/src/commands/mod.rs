@@ -1,18 +1,25 @@
 mod activate;
+mod activations;
+mod audit;
 mod auth;
 mod build;
 mod containerize;
 mod delete;
 mod edit;
+mod environment;
 mod envs;
+mod export;
 mod general;
 mod init;
 mod install;
+mod licenses;
 mod list;
 mod lock_manifest;
+mod manifest;
 mod publish;
 mod pull;
 mod push;
+mod sbom;
 mod search;
 mod services;
 mod show;
@@ -21,7 +28,7 @@ mod update;
 mod upgrade;
 mod upload;
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -29,6 +36,7 @@ use std::{env, fmt, fs, io, mem};
 
 use anyhow::{anyhow, bail, Context, Result};
 use bpaf::{Args, Bpaf, ParseFailure, Parser};
+use flox_core::content_hash;
 use flox_rust_sdk::data::{CanonicalPath, FloxVersion};
 use flox_rust_sdk::flox::{
     EnvironmentName,
@@ -44,6 +52,7 @@ use flox_rust_sdk::flox::{
     FLOX_VERSION,
 };
 use flox_rust_sdk::models::env_registry::{EnvRegistry, ENV_REGISTRY_FILENAME};
+use flox_rust_sdk::models::environment::generations::Generations;
 use flox_rust_sdk::models::environment::managed_environment::ManagedEnvironment;
 use flox_rust_sdk::models::environment::path_environment::PathEnvironment;
 use flox_rust_sdk::models::environment::remote_environment::RemoteEnvironment;
@@ -58,6 +67,16 @@ use flox_rust_sdk::models::environment::{
     DOT_FLOX,
     FLOX_ACTIVE_ENVIRONMENTS_VAR,
 };
+use flox_rust_sdk::models::lockfile::Lockfile;
+use flox_rust_sdk::models::manifest::{
+    Manifest,
+    ManifestHook,
+    ManifestInclude,
+    ManifestProfile,
+    ManifestTargets,
+    SecretReference,
+};
+use flox_rust_sdk::models::user_state::{read_user_state_file, user_state_path, EnvironmentAlias};
 use flox_rust_sdk::models::{env_registry, environment_ref};
 use futures::Future;
 use indoc::{formatdoc, indoc};
@@ -76,6 +95,8 @@ use crate::commands::general::update_config;
 use crate::config::{
     Config,
     EnvironmentTrust,
+    PinnedTrust,
+    TrustPolicy,
     FLOX_CONFIG_FILE,
     FLOX_DIR_NAME,
     FLOX_DISABLE_METRICS_VAR,
@@ -88,7 +109,7 @@ use crate::utils::init::{
     init_uuid,
     telemetry_opt_out_needs_migration,
 };
-use crate::utils::metrics::{AWSDatalakeConnection, Client, Hub, METRICS_UUID_FILE_NAME};
+use crate::utils::metrics::{connection_for_sink, Client, Hub, METRICS_UUID_FILE_NAME};
 use crate::utils::{message, TRAILING_NETWORK_CALL_TIMEOUT};
 
 // Relative to flox executable
@@ -108,7 +129,7 @@ static FLOX_DESCRIPTION: &'_ str = indoc! {"
 
 /// Manually documented commands that are to keep the help text short
 const ADDITIONAL_COMMANDS: &str = indoc! {"
-    auth, config, envs, upgrade
+    activations, audit, auth, config, envs, export, licenses, manifest, sbom, upgrade
 "};
 
 fn vec_len<T>(x: Vec<T>) -> usize {
@@ -255,16 +276,25 @@ impl FloxArgs {
             tokio::fs::remove_file(&config.flox.data_dir.join(METRICS_UUID_FILE_NAME)).await?;
         }
 
-        if !config.flox.disable_metrics {
+        let metrics_connection = if config.flox.disable_metrics {
+            None
+        } else {
+            connection_for_sink(&config.flox.metrics_sink.clone().unwrap_or_default())
+        };
+
+        if let Some(connection) = metrics_connection {
             debug!("Metrics collection enabled");
 
             init_telemetry_uuid(&config.flox.data_dir, &config.flox.cache_dir)?;
 
-            let connection = AWSDatalakeConnection::default();
             let client = Client::new_with_config(&config, connection)?;
             Hub::global().set_client(client);
         } else {
             debug!("Metrics collection disabled");
+        }
+
+        // Keep the watchdog and other child processes from reporting to flox
+        if !config.flox.reports_to_flox() {
             unsafe {
                 env::set_var(FLOX_DISABLE_METRICS_VAR, "true");
             }
@@ -290,25 +320,23 @@ impl FloxArgs {
                 .clone()
                 .unwrap_or_else(|| DEFAULT_FLOXHUB_URL.clone()),
             git_url_override,
-        )?;
+        )?
+        .with_git_remote(config.flox.git_remote.clone());
 
-        let floxhub_token = config
-            .flox
-            .floxhub_token
+        let floxhub_token = auth::read_floxhub_token(&config)
             .as_deref()
             .map(FloxhubToken::from_str)
             .transpose();
+        let token_storage = auth::token_source(&config).storage();
 
         let floxhub_token = match floxhub_token {
             Err(FloxhubTokenError::Expired) => {
                 message::warning("Your FloxHub token has expired. You may need to log in again.");
-                if let Err(e) = update_config(
-                    &config.flox.config_dir,
-                    &temp_dir_path,
-                    "floxhub_token",
-                    None::<String>,
-                ) {
-                    log::debug!("Could not remove token from user config: {e}");
+                if let Err(e) =
+                    auth::token_store(token_storage, &config.flox.config_dir, &temp_dir_path)
+                        .remove()
+                {
+                    log::debug!("Could not remove token: {e}");
                 }
                 None
             },
@@ -317,13 +345,11 @@ impl FloxArgs {
                     Your FloxHub token is invalid: {token_error}
                     You may need to log in again.
                 "});
-                if let Err(e) = update_config(
-                    &config.flox.config_dir,
-                    &temp_dir_path,
-                    "floxhub_token",
-                    None::<String>,
-                ) {
-                    log::debug!("Could not remove token from user config: {e}");
+                if let Err(e) =
+                    auth::token_store(token_storage, &config.flox.config_dir, &temp_dir_path)
+                        .remove()
+                {
+                    log::debug!("Could not remove token: {e}");
                 }
                 None
             },
@@ -823,6 +849,9 @@ enum SharingCommands {
     /// Containerize an environment
     #[bpaf(command, footer("Run 'man flox-containerize' for more details."))]
     Containerize(#[bpaf(external(containerize::containerize))] containerize::Containerize),
+    /// Manage environments on FloxHub
+    #[bpaf(command)]
+    Env(#[bpaf(external(environment::env_commands))] environment::EnvCommands),
 }
 
 impl SharingCommands {
@@ -831,6 +860,7 @@ impl SharingCommands {
             SharingCommands::Push(args) => args.handle(flox).await?,
             SharingCommands::Pull(args) => args.handle(flox).await?,
             SharingCommands::Containerize(args) => args.handle(flox).await?,
+            SharingCommands::Env(args) => args.handle(flox).await?,
         }
         Ok(())
     }
@@ -867,6 +897,30 @@ enum AdditionalCommands {
     /// Show active and available environments
     #[bpaf(command, hide, footer("Run 'man flox-envs' for more details."))]
     Envs(#[bpaf(external(envs::envs))] envs::Envs),
+
+    /// Generate a software bill of materials for an environment
+    #[bpaf(command, hide, footer("Run 'man flox-sbom' for more details."))]
+    Sbom(#[bpaf(external(sbom::sbom))] sbom::Sbom),
+
+    /// Check the packages of an environment for known vulnerabilities
+    #[bpaf(command, hide, footer("Run 'man flox-audit' for more details."))]
+    Audit(#[bpaf(external(audit::audit))] audit::Audit),
+
+    /// Show the licenses of the packages in an environment
+    #[bpaf(command, hide, footer("Run 'man flox-licenses' for more details."))]
+    Licenses(#[bpaf(external(licenses::licenses))] licenses::Licenses),
+
+    /// Export an environment for use with other tools
+    #[bpaf(command, hide, footer("Run 'man flox-export' for more details."))]
+    Export(#[bpaf(external(export::export))] export::Export),
+
+    /// Inspect, edit and lint the manifest of an environment
+    #[bpaf(command, hide, footer("Run 'man flox-manifest' for more details."))]
+    Manifest(#[bpaf(external(manifest::manifest_commands))] manifest::ManifestCommands),
+
+    /// Show the running activations of environments
+    #[bpaf(command, hide, footer("Run 'man flox-activations' for more details."))]
+    Activations(#[bpaf(external(activations::activations))] activations::Activations),
 }
 
 impl AdditionalCommands {
@@ -881,6 +935,12 @@ impl AdditionalCommands {
             AdditionalCommands::Config(args) => args.handle(config, flox).await?,
             AdditionalCommands::Documentation(args) => args.handle(),
             AdditionalCommands::Envs(args) => args.handle(flox)?,
+            AdditionalCommands::Sbom(args) => args.handle(flox)?,
+            AdditionalCommands::Audit(args) => args.handle(config, flox)?,
+            AdditionalCommands::Licenses(args) => args.handle(flox)?,
+            AdditionalCommands::Export(args) => args.handle(flox)?,
+            AdditionalCommands::Manifest(args) => args.handle(flox).await?,
+            AdditionalCommands::Activations(args) => args.handle(flox)?,
             AdditionalCommands::Update(args) => args.handle(flox).await?,
             AdditionalCommands::Upgrade(args) => args.handle(flox).await?,
         }
@@ -978,7 +1038,7 @@ impl Version {
     }
 }
 
-#[derive(Debug, Default, Bpaf, Clone)]
+#[derive(Debug, Default, Bpaf, Clone, PartialEq)]
 pub enum EnvironmentSelect {
     Dir(
         /// Path containing a .flox/ directory
@@ -990,6 +1050,11 @@ pub enum EnvironmentSelect {
         #[bpaf(long("remote"), short('r'), argument("owner>/<name"))]
         environment_ref::EnvironmentRef,
     ),
+    Alias(
+        /// An environment alias created with 'flox env alias add'
+        #[bpaf(long("env"), short('e'), argument("alias"))]
+        String,
+    ),
     #[default]
     #[bpaf(hide)]
     Unspecified,
@@ -1001,11 +1066,39 @@ pub enum EnvironmentSelectError {
     EnvironmentError(#[from] EnvironmentError),
     #[error("Did not find an environment in the current directory.")]
     EnvNotFoundInCurrentDirectory,
+    #[error("No environment alias named '{0}'")]
+    AliasNotFound(String),
     #[error(transparent)]
     Anyhow(#[from] anyhow::Error),
 }
 
 impl EnvironmentSelect {
+    /// Replace an [EnvironmentSelect::Alias] with the environment it refers to
+    pub fn resolve_alias(&self, flox: &Flox) -> Result<EnvironmentSelect, EnvironmentSelectError> {
+        let EnvironmentSelect::Alias(alias) = self else {
+            return Ok(self.clone());
+        };
+
+        let user_state = read_user_state_file(user_state_path(flox))
+            .map_err(anyhow::Error::new)?
+            .unwrap_or_default();
+        match user_state.environment_aliases.get(alias) {
+            Some(EnvironmentAlias::Path(path)) => Ok(EnvironmentSelect::Dir(path.clone())),
+            Some(EnvironmentAlias::Remote(env_ref)) => {
+                Ok(EnvironmentSelect::Remote(env_ref.clone()))
+            },
+            None => Err(EnvironmentSelectError::AliasNotFound(alias.clone())),
+        }
+    }
+
+    /// Whether a remote environment is selected, either directly or via an alias
+    pub fn is_remote(&self, flox: &Flox) -> Result<bool, EnvironmentSelectError> {
+        Ok(matches!(
+            self.resolve_alias(flox)?,
+            EnvironmentSelect::Remote(_)
+        ))
+    }
+
     /// Open a concrete environment, not detecting the currently active
     /// environment.
     ///
@@ -1019,6 +1112,7 @@ impl EnvironmentSelect {
     ) -> Result<ConcreteEnvironment, EnvironmentSelectError> {
         match self {
             EnvironmentSelect::Dir(path) => Ok(open_path(flox, path)?),
+            EnvironmentSelect::Alias(_) => self.resolve_alias(flox)?.to_concrete_environment(flox),
             EnvironmentSelect::Unspecified => {
                 let current_dir = env::current_dir().context("could not get current directory")?;
                 let maybe_found_environment = find_dot_flox(&current_dir)?;
@@ -1056,6 +1150,9 @@ impl EnvironmentSelect {
     ) -> Result<ConcreteEnvironment, EnvironmentSelectError> {
         match self {
             EnvironmentSelect::Dir(path) => Ok(open_path(flox, path)?),
+            EnvironmentSelect::Alias(_) => self
+                .resolve_alias(flox)?
+                .detect_concrete_environment(flox, message),
             // If the user doesn't specify an environment, check if there's an
             // already activated environment or an environment in the current
             // directory.
@@ -1476,6 +1573,9 @@ fn activated_environments() -> ActiveEnvironments {
 /// Check whether the given [EnvironmentRef] is trusted.
 ///
 /// If not, prompt the user to trust or deny abort or ask again.
+/// Environments trusted at a specific version are trusted
+/// as long as their hooks, profile scripts and includes are unchanged,
+/// otherwise the user is prompted again and can review the changes.
 ///
 /// This function returns [`Ok`] if the environment is trusted
 /// and a formatted error message if not.
@@ -1486,8 +1586,6 @@ pub(super) async fn ensure_environment_trust(
 ) -> Result<()> {
     let env_ref = EnvironmentRef::new_from_parts(environment.owner().clone(), environment.name());
 
-    let trust = config.flox.trusted_environments.get(&env_ref);
-
     // Official Flox environments are trusted by default
     // Only applies to the current flox owned FloxHub,
     // so this rule might need to be revisited in the future.
@@ -1503,28 +1601,51 @@ pub(super) async fn ensure_environment_trust(
         }
     }
 
-    if matches!(trust, Some(EnvironmentTrust::Trust)) {
-        debug!("environment {env_ref} is trusted by config");
-        return Ok(());
+    let trust_policy = config.flox.trust_policy(&env_ref);
+    match trust_policy {
+        Some(TrustPolicy::Always(EnvironmentTrust::Trust)) => {
+            debug!("environment {env_ref} is trusted by config");
+            return Ok(());
+        },
+        Some(TrustPolicy::Always(EnvironmentTrust::Deny)) => {
+            debug!("environment {env_ref} is denied by config");
+
+            let message = formatdoc! {"
+                Environment {env_ref} is not trusted.
+
+                Run 'flox config --set trusted_environments.{env_ref} trust' to trust it."};
+            bail!("{message}");
+        },
+        Some(TrustPolicy::Pinned(_)) | None => {},
     }
 
-    if matches!(trust, Some(EnvironmentTrust::Deny)) {
-        debug!("environment {env_ref} is denied by config");
+    // Only read the environment if trust depends on its contents
+    let current_manifest = environment.manifest_contents(flox)?;
+    let current_scripts = current_activation_scripts(environment)?;
 
-        let message = formatdoc! {"
-            Environment {env_ref} is not trusted.
+    // The activation scripts of the last trusted version, if they changed since then
+    let mut last_trusted_scripts = None;
 
-            Run 'flox config --set trusted_environments.{env_ref} trust' to trust it."};
-        bail!("{message}");
+    if let Some(TrustPolicy::Pinned(pinned)) = trust_policy {
+        match trusted_activation_scripts(environment, pinned)? {
+            Some(trusted) if trusted == current_scripts => {
+                debug!("hooks of environment {env_ref} are unchanged since it was trusted");
+                return Ok(());
+            },
+            Some(trusted) => last_trusted_scripts = Some(trusted),
+            None => debug!("trusted version of environment {env_ref} not found"),
+        }
     }
 
     #[derive(Debug, PartialEq)]
     enum Choices {
         Trust,
+        TrustVersion,
         Deny,
         TrustTemporarily,
         Abort,
         ShowConfig,
+        ShowHooksDiff,
     }
 
     #[derive(Debug, derive_more::AsRef)]
@@ -1540,8 +1661,13 @@ pub(super) async fn ensure_environment_trust(
         }
     }
 
+    let reason = if last_trusted_scripts.is_some() {
+        format!("The hooks of environment {env_ref} changed since it was last trusted.")
+    } else {
+        format!("Environment {env_ref} is not trusted.")
+    };
     let message = formatdoc! {"
-        Environment {env_ref} is not trusted.
+        {reason}
 
         flox environments do not run in a sandbox.
         Activation hooks can run arbitrary code on your machine.
@@ -1555,18 +1681,28 @@ pub(super) async fn ensure_environment_trust(
 
     loop {
         let message = format!("Do you trust {env_ref}?");
+        let mut options = vec![
+            Choice("Do not trust, ask again next time", Choices::Abort),
+            Choice("Do not trust, save choice", Choices::Deny),
+            Choice("Trust, ask again next time", Choices::TrustTemporarily),
+            Choice(
+                "Trust this version, ask again when hooks change",
+                Choices::TrustVersion,
+            ),
+            Choice("Trust, save choice", Choices::Trust),
+            Choice("Show the manifest", Choices::ShowConfig),
+        ];
+        if last_trusted_scripts.is_some() {
+            options.push(Choice(
+                "Show changes to hooks since last trusted",
+                Choices::ShowHooksDiff,
+            ));
+        }
+
         let choice = Dialog {
             message: &message,
             help_message: None,
-            typed: Select {
-                options: vec![
-                    Choice("Do not trust, ask again next time", Choices::Abort),
-                    Choice("Do not trust, save choice", Choices::Deny),
-                    Choice("Trust, ask again next time", Choices::TrustTemporarily),
-                    Choice("Trust, save choice", Choices::Trust),
-                    Choice("Show the manifest", Choices::ShowConfig),
-                ],
-            },
+            typed: Select { options },
         }
         .prompt()
         .await?;
@@ -1579,19 +1715,35 @@ pub(super) async fn ensure_environment_trust(
                     &flox.config_dir,
                     &flox.temp_dir,
                     format!("trusted_environments.'{env_ref}'"),
-                    Some(EnvironmentTrust::Trust),
+                    Some(TrustPolicy::Always(EnvironmentTrust::Trust)),
                 )
                 .context("Could not write token to config")?;
                 let _ = mem::replace(config, Config::parse()?);
                 info!("Trusted environment {env_ref} (saved choice)",);
                 return Ok(());
             },
+            Choices::TrustVersion => {
+                let generation = environment.generations().metadata()?.current_gen;
+                update_config(
+                    &flox.config_dir,
+                    &flox.temp_dir,
+                    format!("trusted_environments.'{env_ref}'"),
+                    Some(TrustPolicy::Pinned(PinnedTrust {
+                        generation: generation.map(|generation| *generation),
+                        scripts_hash: Some(content_hash(&current_scripts)),
+                    })),
+                )
+                .context("Could not write token to config")?;
+                let _ = mem::replace(config, Config::parse()?);
+                info!("Trusted environment {env_ref} until its hooks change (saved choice)");
+                return Ok(());
+            },
             Choices::Deny => {
                 update_config(
                     &flox.config_dir,
                     &flox.temp_dir,
                     format!("trusted_environments.'{env_ref}'"),
-                    Some(EnvironmentTrust::Deny),
+                    Some(TrustPolicy::Always(EnvironmentTrust::Deny)),
                 )
                 .context("Could not write token to config")?;
                 let _ = mem::replace(config, Config::parse()?);
@@ -1602,9 +1754,130 @@ pub(super) async fn ensure_environment_trust(
                 return Ok(());
             },
             Choices::Abort => bail!("Denied {env_ref} (temporary)"),
-            Choices::ShowConfig => eprintln!("{}", environment.manifest_contents(flox)?),
+            Choices::ShowConfig => eprintln!("{current_manifest}"),
+            Choices::ShowHooksDiff => {
+                if let Some(ref trusted) = last_trusted_scripts {
+                    eprintln!("{}", line_diff(trusted, &current_scripts));
+                }
+            },
+        }
+    }
+}
+
+/// Find the activation scripts of the version of an environment
+/// a [PinnedTrust] refers to.
+///
+/// Returns [None] if the generation doesn't exist,
+/// or no generation matches the trusted scripts hash.
+fn trusted_activation_scripts(
+    environment: &RemoteEnvironment,
+    pinned: &PinnedTrust,
+) -> Result<Option<String>> {
+    let generations = environment.generations();
+    let matches_hash = |scripts: &str| match pinned.scripts_hash {
+        Some(ref hash) => content_hash(scripts) == *hash,
+        None => true,
+    };
+
+    if let Some(generation) = pinned.generation {
+        let Ok(scripts) = generation_activation_scripts(&generations, generation) else {
+            return Ok(None);
+        };
+        return Ok(Some(scripts).filter(|scripts| matches_hash(scripts)));
+    }
+
+    if pinned.scripts_hash.is_some() {
+        // Prefer the latest generation with the trusted scripts
+        for generation in generations.metadata()?.generations.keys().rev() {
+            let scripts = generation_activation_scripts(&generations, **generation)?;
+            if matches_hash(&scripts) {
+                return Ok(Some(scripts));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Render the activation scripts of the current generation of an environment
+fn current_activation_scripts(environment: &RemoteEnvironment) -> Result<String> {
+    let generations = environment.generations();
+    let Some(generation) = generations.metadata()?.current_gen else {
+        bail!("Environment has no generations");
+    };
+    generation_activation_scripts(&generations, *generation)
+}
+
+/// Render the activation scripts of a generation of an environment.
+///
+/// Scripts are read from the locked manifest,
+/// so that they include hooks and profile scripts of included environments.
+fn generation_activation_scripts(generations: &Generations, generation: usize) -> Result<String> {
+    let lockfile: Lockfile = serde_json::from_str(&generations.lockfile(generation)?)
+        .context("Could not parse lockfile")?;
+    activation_scripts(&lockfile.manifest)
+}
+
+/// Render the parts of a manifest that run code upon activation,
+/// so that they can be compared and diffed between versions
+fn activation_scripts(manifest: &Manifest) -> Result<String> {
+    #[derive(Serialize)]
+    struct ActivationScripts<'a> {
+        hook: &'a ManifestHook,
+        profile: &'a ManifestProfile,
+        include: &'a ManifestInclude,
+        #[serde(skip_serializing_if = "ManifestTargets::is_empty")]
+        target: &'a ManifestTargets,
+        /// Variables that are fetched by running a command
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        secrets: BTreeMap<&'a String, &'a SecretReference>,
+    }
+
+    let scripts = toml::to_string_pretty(&ActivationScripts {
+        hook: &manifest.hook,
+        profile: &manifest.profile,
+        include: &manifest.include,
+        target: &manifest.target,
+        secrets: manifest.vars.secrets().collect(),
+    })?;
+    Ok(scripts)
+}
+
+/// A line based diff between `old` and `new`,
+/// with removed lines prefixed with `-` and added lines with `+`
+fn line_diff(old: &str, new: &str) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // lcs[i][j] is the length of the longest common subsequence
+    // of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
         }
     }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push(format!("+ {}", new[j]));
+            j += 1;
+        } else {
+            diff.push(format!("- {}", old[i]));
+            i += 1;
+        }
+    }
+    diff.join("\n")
 }
 
 /// Ensure a floxhub_token is present
@@ -1635,7 +1908,11 @@ pub(super) async fn ensure_floxhub_token(flox: &mut Flox) -> Result<&FloxhubToke
             log::debug!("floxhub token is not present; prompting user");
 
             message::plain("You are not logged in to FloxHub. Logging in...");
-            let token = auth::login_flox(flox).await?;
+            let storage = Config::parse()?
+                .flox
+                .floxhub_token_storage
+                .unwrap_or_default();
+            let token = auth::login_flox(flox, storage).await?;
             Ok(token)
         },
     }
@@ -1649,13 +1926,56 @@ pub fn environment_description(environment: &ConcreteEnvironment) -> Result<Stri
 #[cfg(test)]
 mod tests {
 
+    use flox_rust_sdk::flox::test_helpers::flox_instance;
     use flox_rust_sdk::flox::EnvironmentName;
     use flox_rust_sdk::models::environment::PathPointer;
+    use flox_rust_sdk::models::user_state::{lock_and_read_user_state_file, write_user_state_file};
     use sentry::test::with_captured_events;
     use tempfile::tempdir;
 
     use super::*;
 
+    #[test]
+    fn line_diff_marks_added_and_removed_lines() {
+        let diff = line_diff("a\nb\nc", "a\nc\nd");
+        assert_eq!(diff, "  a\n- b\n  c\n+ d");
+    }
+
+    #[test]
+    fn alias_resolves_to_stored_environment() {
+        let (flox, _temp_dir) = flox_instance();
+        let env_ref: EnvironmentRef = "owner/name".parse().unwrap();
+
+        let state_path = user_state_path(&flox);
+        let (lock, mut user_state) = lock_and_read_user_state_file(&state_path).unwrap();
+        user_state.environment_aliases.insert(
+            "remote".to_string(),
+            EnvironmentAlias::Remote(env_ref.clone()),
+        );
+        user_state.environment_aliases.insert(
+            "local".to_string(),
+            EnvironmentAlias::Path(PathBuf::from("/some/project")),
+        );
+        write_user_state_file(&user_state, &state_path, lock).unwrap();
+
+        let select = EnvironmentSelect::Alias("remote".to_string());
+        assert!(select.is_remote(&flox).unwrap());
+        assert_eq!(
+            select.resolve_alias(&flox).unwrap(),
+            EnvironmentSelect::Remote(env_ref)
+        );
+        assert_eq!(
+            EnvironmentSelect::Alias("local".to_string())
+                .resolve_alias(&flox)
+                .unwrap(),
+            EnvironmentSelect::Dir(PathBuf::from("/some/project"))
+        );
+        assert!(matches!(
+            EnvironmentSelect::Alias("missing".to_string()).resolve_alias(&flox),
+            Err(EnvironmentSelectError::AliasNotFound(_))
+        ));
+    }
+
     /// is_active() behaves as expected when using set_last_active()
     #[test]
     fn test_is_active() {
@@ -7,6 +7,7 @@ use flox_rust_sdk::flox::{EnvironmentRef, Flox};
 use flox_rust_sdk::models::environment::managed_environment::{
     ManagedEnvironment,
     ManagedEnvironmentError,
+    MergeResult,
     PullResult,
 };
 use flox_rust_sdk::models::environment::{
@@ -21,6 +22,7 @@ use flox_rust_sdk::models::environment::{
 };
 use flox_rust_sdk::models::manifest;
 use indoc::{formatdoc, indoc};
+use itertools::Itertools;
 use log::debug;
 use toml_edit::DocumentMut;
 use tracing::{info_span, instrument};
@@ -69,6 +71,12 @@ pub struct Pull {
     #[bpaf(long, short)]
     force: bool,
 
+    /// Merge upstream changes into an existing environment
+    /// that has diverged from the remote version.
+    /// Conflicting changes are marked in the manifest and have to be resolved with 'flox edit'.
+    #[bpaf(long)]
+    merge: bool,
+
     /// Create a copy of the upstream environment.
     #[bpaf(short, long)]
     copy: bool,
@@ -146,6 +154,7 @@ impl Pull {
                     dir.join(DOT_FLOX),
                     pointer.clone(),
                     self.force,
+                    self.merge,
                 )?;
             },
         }
@@ -157,15 +166,37 @@ impl Pull {
     ///
     /// Opens the environment and calls [ManagedEnvironment::pull] on it,
     /// which will update the lockfile.
+    /// If the environment diverged and `merge` is set,
+    /// upstream changes are merged using [ManagedEnvironment::merge] instead.
     fn pull_existing_environment(
         flox: &Flox,
         dot_flox_path: PathBuf,
         pointer: ManagedPointer,
         force: bool,
-    ) -> Result<(), EnvironmentError> {
+        merge: bool,
+    ) -> Result<()> {
         let mut env = ManagedEnvironment::open(flox, pointer.clone(), dot_flox_path)?;
 
-        let state = env.pull(flox, force)?;
+        let state = match env.pull(flox, force) {
+            Err(ManagedEnvironmentError::Diverged) if merge => match env.merge(flox)? {
+                MergeResult::UpToDate => PullResult::UpToDate,
+                MergeResult::Updated => PullResult::Updated,
+                MergeResult::Merged => {
+                    message::updated(formatdoc! {"
+                        Merged changes to {owner}/{name} from {floxhub_host}
+
+                        Use 'flox push' to publish the merged environment.
+                        ",
+                        owner = pointer.owner, name = pointer.name,
+                        floxhub_host = upstream_description(flox, &pointer),
+                    });
+                    warn_manifest_changes_for_services(flox, &env);
+                    return Ok(());
+                },
+                MergeResult::Conflicts(paths) => bail!(merge_conflicts_message(&paths)),
+            },
+            result => result?,
+        };
 
         match state {
             PullResult::Updated => {
@@ -185,7 +216,7 @@ impl Pull {
                     You can activate this environment with 'flox activate'
                     ",
                     owner = pointer.owner, name = pointer.name,
-                    floxhub_host = flox.floxhub.base_url(),
+                    floxhub_host = upstream_description(flox, &pointer),
                     suffix = if force { " (forced)" } else { "" }
                 });
 
@@ -208,7 +239,7 @@ impl Pull {
     ///
     /// If the directory already exists, this will fail early.
     /// If opening the environment fails, the .flox/ directory will be cleaned up.
-    fn pull_new_environment(
+    pub(super) fn pull_new_environment(
         flox: &Flox,
         env_path: PathBuf,
         env_ref: EnvironmentRef,
@@ -303,7 +334,11 @@ impl Pull {
         } else {
             format!(
                 "Pulled {env_ref} from {floxhub_host}.",
-                floxhub_host = flox.floxhub.base_url()
+                floxhub_host = flox
+                    .floxhub
+                    .git_remote()
+                    .map(String::from)
+                    .unwrap_or_else(|| flox.floxhub.base_url().to_string())
             )
         };
 
@@ -546,6 +581,26 @@ impl Pull {
     }
 }
 
+/// The FloxHub host or the generic git remote an environment is pulled from
+fn upstream_description(flox: &Flox, pointer: &ManagedPointer) -> String {
+    match &pointer.git_remote {
+        Some(git_remote) => git_remote.clone(),
+        None => flox.floxhub.base_url().to_string(),
+    }
+}
+
+/// Message shown when merging local and upstream changes resulted in conflicts
+pub(super) fn merge_conflicts_message(paths: &[String]) -> String {
+    let paths = paths.iter().map(|path| format!("  * {path}")).join("\n");
+    formatdoc! {"
+        Local and upstream changes conflict in:
+        {paths}
+
+        The conflicting entries are marked in the manifest.
+        Use 'flox edit' to resolve the conflicts, then 'flox push' to publish the environment.
+    "}
+}
+
 /// Additional (user facing) context for the result of [Pull::handle_pull_result].
 ///
 /// This is used to construct the message to show to the user
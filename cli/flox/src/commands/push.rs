@@ -1,13 +1,14 @@
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Result};
 use bpaf::Bpaf;
 use flox_rust_sdk::data::CanonicalPath;
 use flox_rust_sdk::flox::{EnvironmentOwner, Flox};
 use flox_rust_sdk::models::environment::managed_environment::{
     ManagedEnvironment,
     ManagedEnvironmentError,
+    MergeResult,
 };
 use flox_rust_sdk::models::environment::{
     path_environment,
@@ -21,6 +22,7 @@ use indoc::formatdoc;
 use log::debug;
 use tracing::instrument;
 
+use super::pull::merge_conflicts_message;
 use crate::commands::ensure_floxhub_token;
 use crate::subcommand_metric;
 use crate::utils::errors::format_core_error;
@@ -40,6 +42,11 @@ pub struct Push {
     /// Forcibly overwrite the remote copy of the environment
     #[bpaf(long, short)]
     force: bool,
+
+    /// Merge upstream changes into the environment before pushing,
+    /// if it has diverged from the remote copy
+    #[bpaf(long)]
+    merge: bool,
 }
 
 impl Push {
@@ -47,24 +54,38 @@ impl Push {
     pub async fn handle(self, mut flox: Flox) -> Result<()> {
         subcommand_metric!("push");
 
-        // Ensure the user is logged in for the following remote operations
-        ensure_floxhub_token(&mut flox).await?;
-
-        // Start a span that doesn't include authentication
-        let span = tracing::info_span!("post-auth");
-        let _guard = span.enter();
-
         let dir = self.dir.unwrap_or_else(|| std::env::current_dir().unwrap());
 
         let dot_flox = DotFlox::open_in(dir)?;
         let canonical_dot_flox_path =
             CanonicalPath::new(&dot_flox.path).expect("DotFlox path was just opened");
 
+        // Generic git remotes use the user's git credentials instead of a FloxHub token
+        let uses_git_remote = match &dot_flox.pointer {
+            EnvironmentPointer::Managed(managed_pointer) => managed_pointer.git_remote.is_some(),
+            EnvironmentPointer::Path(_) => flox.floxhub.git_remote().is_some(),
+        };
+
+        // Ensure the user is logged in for the following remote operations
+        if !uses_git_remote {
+            ensure_floxhub_token(&mut flox).await?;
+        }
+
+        // Start a span that doesn't include authentication
+        let span = tracing::info_span!("post-auth");
+        let _guard = span.enter();
+
         match dot_flox.pointer {
             EnvironmentPointer::Managed(managed_pointer) => {
                 let message = Self::push_existing_message(&managed_pointer, self.force);
 
-                Self::push_managed_env(&flox, managed_pointer, &dot_flox.path, self.force)?;
+                Self::push_managed_env(
+                    &flox,
+                    managed_pointer,
+                    &dot_flox.path,
+                    self.force,
+                    self.merge,
+                )?;
 
                 message::updated(message);
             },
@@ -72,13 +93,12 @@ impl Push {
             EnvironmentPointer::Path(path_pointer) => {
                 let owner = if let Some(owner) = self.owner {
                     owner
+                } else if let Some(token) = flox.floxhub_token.as_ref() {
+                    EnvironmentOwner::from_str(token.handle())?
+                } else if uses_git_remote {
+                    bail!("Use '--owner' to set the owner of the environment in the git repository")
                 } else {
-                    EnvironmentOwner::from_str(
-                        flox.floxhub_token
-                            .as_ref()
-                            .context("Need to be loggedin")?
-                            .handle(),
-                    )?
+                    bail!("Need to be loggedin")
                 };
 
                 let env = Self::push_make_managed(
@@ -100,10 +120,19 @@ impl Push {
         managed_pointer: ManagedPointer,
         dot_flox_dir: &Path,
         force: bool,
+        merge: bool,
     ) -> Result<()> {
         let mut env = ManagedEnvironment::open(flox, managed_pointer.clone(), dot_flox_dir)?;
-        env.push(flox, force)
-            .map_err(|err| Self::convert_error(err, managed_pointer, false))?;
+        let result = match env.push(flox, force) {
+            Err(ManagedEnvironmentError::Diverged) if merge => {
+                if let MergeResult::Conflicts(paths) = env.merge(flox)? {
+                    bail!(merge_conflicts_message(&paths));
+                }
+                env.push(flox, false)
+            },
+            result => result,
+        };
+        result.map_err(|err| Self::convert_error(err, managed_pointer, false))?;
 
         Ok(())
     }
@@ -174,9 +203,10 @@ impl Push {
         let name = &env.name;
 
         let suffix = if force { " (forced)" } else { "" };
+        let destination = env.git_remote.as_deref().unwrap_or("FloxHub");
 
         formatdoc! {"
-            Updates to {name} successfully pushed to FloxHub{suffix}
+            Updates to {name} successfully pushed to {destination}{suffix}
 
             Use 'flox pull {owner}/{name}' to get this environment in any other location.
         "}
@@ -191,6 +221,15 @@ impl Push {
 
         let suffix = if force { " (forced)" } else { "" };
 
+        if let Some(git_remote) = &env.git_remote {
+            return formatdoc! {"
+                {name} successfully pushed to {git_remote}{suffix}
+
+                Use 'flox pull {owner}/{name}' with 'git_remote' set to '{git_remote}'
+                to get this environment in any other location.
+            "};
+        }
+
         formatdoc! {"
             {name} successfully pushed to FloxHub{suffix}
 
@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use bpaf::Bpaf;
+use flox_rust_sdk::data::System;
+use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::sbom::{self, DocumentInfo, SbomFormat, StoreClosure};
+use indoc::formatdoc;
+use tracing::instrument;
+
+use super::{environment_select, EnvironmentSelect};
+use crate::subcommand_metric;
+use crate::utils::message;
+
+// Generate a software bill of materials for an environment
+#[derive(Bpaf, Clone)]
+pub struct Sbom {
+    #[bpaf(external(environment_select), fallback(Default::default()))]
+    environment: EnvironmentSelect,
+
+    /// Format of the SBOM, either 'cyclonedx-json' or 'spdx-json'
+    #[bpaf(long, argument("format"))]
+    format: SbomFormat,
+
+    /// System to list packages for (default: current system)
+    #[bpaf(long, argument("system"))]
+    system: Option<System>,
+
+    /// Include the runtime closure of all packages, read from the local Nix store.
+    /// Builds the environment if necessary.
+    #[bpaf(long)]
+    closure: bool,
+
+    /// Write the SBOM to <file> instead of stdout
+    #[bpaf(long, short, argument("file"))]
+    output: Option<PathBuf>,
+}
+
+impl Sbom {
+    #[instrument(name = "sbom", skip_all)]
+    pub fn handle(self, flox: Flox) -> Result<()> {
+        subcommand_metric!("sbom");
+
+        let system = self.system.unwrap_or_else(|| flox.system.clone());
+        if self.closure && system != flox.system {
+            bail!(formatdoc! {"
+                Can't include the runtime closure for '{system}' on '{current}'.
+
+                The runtime closure is read from the local Nix store,
+                which only contains packages for the current system.
+            ", current = flox.system});
+        }
+
+        let mut env = self
+            .environment
+            .detect_concrete_environment(&flox, "Generate SBOM for")?
+            .into_dyn_environment();
+
+        let lockfile = env.lockfile(&flox)?;
+        let mut sbom = sbom::Sbom::from_lockfile(env.name().to_string(), &lockfile, &system);
+
+        if sbom.packages.is_empty() {
+            message::warning(format!(
+                "No packages are installed for '{system}', the SBOM will be empty."
+            ));
+        }
+
+        if self.closure && !sbom.packages.is_empty() {
+            // Make sure all packages are present in the local store
+            env.build(&flox)?;
+            let closure = StoreClosure::query(sbom.output_paths())?;
+            sbom = sbom.with_closure(closure);
+        }
+
+        let document = sbom.render(self.format, &DocumentInfo::default())?;
+
+        match self.output {
+            Some(path) => {
+                fs::write(&path, document + "\n")
+                    .with_context(|| format!("Failed to write SBOM to '{}'", path.display()))?;
+                message::created(format!("Wrote SBOM to '{}'", path.display()));
+            },
+            None => println!("{document}"),
+        }
+
+        Ok(())
+    }
+}
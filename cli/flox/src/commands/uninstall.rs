@@ -38,7 +38,7 @@ impl Uninstall {
         );
 
         // Ensure the user is logged in for the following remote operations
-        if let EnvironmentSelect::Remote(_) = self.environment {
+        if self.environment.is_remote(&flox)? {
             ensure_floxhub_token(&mut flox).await?;
         };
 
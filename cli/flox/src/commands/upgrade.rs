@@ -15,6 +15,11 @@ pub struct Upgrade {
     #[bpaf(external(environment_select), fallback(Default::default()))]
     environment: EnvironmentSelect,
 
+    /// Fetch the latest version of included environments
+    /// instead of upgrading packages
+    #[bpaf(long)]
+    includes: bool,
+
     /// ID of a package or pkg-group name to upgrade
     #[bpaf(positional("package or pkg-group"))]
     groups_or_iids: Vec<String>,
@@ -29,7 +34,7 @@ impl Upgrade {
         );
 
         // Ensure the user is logged in for the following remote operations
-        if let EnvironmentSelect::Remote(_) = self.environment {
+        if self.environment.is_remote(&flox)? {
             ensure_floxhub_token(&mut flox).await?;
         };
 
@@ -41,10 +46,10 @@ impl Upgrade {
 
         let mut environment = concrete_environment.into_dyn_environment();
 
-        let span = info_span!(
-            "upgrade",
-            environment = %description,
-            progress = format!(
+        let progress = if self.includes {
+            "Upgrading included environments".to_string()
+        } else {
+            format!(
                 "Upgrading {} package(s) or group(s)",
                 if self.groups_or_iids.is_empty() {
                     "all".to_string()
@@ -52,7 +57,8 @@ impl Upgrade {
                     format!("{}", self.groups_or_iids.len())
                 }
             )
-        );
+        };
+        let span = info_span!("upgrade", environment = %description, progress = progress);
         let result = span.in_scope(|| {
             environment.upgrade(
                 &flox,
@@ -61,9 +67,26 @@ impl Upgrade {
                     .iter()
                     .map(String::as_str)
                     .collect::<Vec<_>>(),
+                self.includes,
             )
         })?;
 
+        if self.includes {
+            if result.includes.is_empty() {
+                message::plain(format!(
+                    "ℹ️  No included environments need to be upgraded in environment {description}."
+                ));
+            } else {
+                for include in result.includes {
+                    message::plain(format!(
+                        "⬆️  Upgraded included environment '{include}' in environment {description}."
+                    ));
+                }
+                warn_manifest_changes_for_services(&flox, environment.as_ref());
+            }
+            return Ok(());
+        }
+
         let upgraded = result.packages;
 
         if upgraded.is_empty() {
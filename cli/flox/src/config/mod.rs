@@ -47,6 +47,10 @@ pub struct FloxConfig {
     /// Disable collecting and sending usage metrics
     #[serde(default)]
     pub disable_metrics: bool,
+    /// Where collected usage metrics are sent (default: flox)
+    ///
+    /// Ignored if `disable_metrics` is set.
+    pub metrics_sink: Option<MetricsSink>,
     /// Directory where flox should store ephemeral data (default:
     /// `$XDG_CACHE_HOME/flox` e.g. `~/.cache/flox`)
     pub cache_dir: PathBuf,
@@ -69,16 +73,24 @@ pub struct FloxConfig {
     /// and then validate the token as we build the [flox_rust_sdk::flox::Flox] instance.
     pub floxhub_token: Option<String>,
 
+    /// Where `flox auth login` stores the FloxHub token (default: config)
+    pub floxhub_token_storage: Option<TokenStorage>,
+
     /// How many items `flox search` should show by default
     pub search_limit: SearchLimit,
 
     /// Remote environments that are trusted for activation
+    ///
+    /// Keys are either `owner/name` or `owner/*` to apply to all environments of an owner.
     #[serde(default)]
-    pub trusted_environments: HashMap<EnvironmentRef, EnvironmentTrust>,
+    pub trusted_environments: HashMap<EnvironmentRef, TrustPolicy>,
 
     /// The URL of the FloxHub instance to use
     pub floxhub_url: Option<Url>,
 
+    /// A generic git repository to store environments in instead of FloxHub
+    pub git_remote: Option<String>,
+
     /// The URL of the catalog instance to use
     // Using a URL here adds an extra trailing slash,
     // so just use a String.
@@ -93,15 +105,122 @@ pub struct FloxConfig {
 
     /// Hide environments named 'default' from the shell prompt
     pub hide_default_prompt: Option<bool>,
+
+    /// Vulnerability database used by `flox audit`,
+    /// a JSON file or a directory of JSON files
+    pub audit_database: Option<PathBuf>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum EnvironmentTrust {
     Trust,
     Deny,
 }
 
+/// Whether a remote environment may be activated
+///
+/// Either an unconditional [EnvironmentTrust],
+/// or trust limited to a specific version of the environment, e.g.
+///
+/// ```toml
+/// [trusted_environments]
+/// "team/*" = "trust"
+/// "team/env" = { generation = 4 }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TrustPolicy {
+    Always(EnvironmentTrust),
+    Pinned(PinnedTrust),
+}
+
+/// Trust in a specific version of an environment
+///
+/// Later versions remain trusted as long as their hooks,
+/// profile scripts and includes are unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinnedTrust {
+    /// The trusted generation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    /// The hash of the trusted activation scripts, see [flox_core::content_hash]
+    ///
+    /// Scripts are taken from the locked manifest,
+    /// including those of included environments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scripts_hash: Option<String>,
+}
+
+impl FloxConfig {
+    /// Return the trust policy for an environment.
+    ///
+    /// A policy for the exact environment takes precedence
+    /// over a policy for all environments of its owner.
+    pub fn trust_policy(&self, env_ref: &EnvironmentRef) -> Option<&TrustPolicy> {
+        self.trusted_environments.get(env_ref).or_else(|| {
+            let owner_wildcard = EnvironmentRef::new(env_ref.owner().as_str(), "*").ok()?;
+            self.trusted_environments.get(&owner_wildcard)
+        })
+    }
+
+    /// Whether usage metrics, error reports and the metrics UUID are sent to flox.
+    ///
+    /// Only the default [MetricsSink::Flox] reports to flox.
+    pub fn reports_to_flox(&self) -> bool {
+        !self.disable_metrics
+            && self
+                .metrics_sink
+                .as_ref()
+                .map_or(true, |sink| *sink == MetricsSink::Flox)
+    }
+}
+
+/// Storage backends for the FloxHub token
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenStorage {
+    /// Store the token as `floxhub_token` in the user's `flox.toml`
+    #[default]
+    Config,
+    /// Store the token in the system keyring
+    Keyring,
+}
+
+/// Destination of usage metrics
+///
+/// Only [MetricsSink::Flox] sends data to flox,
+/// all other sinks also turn off error reporting to flox.
+///
+/// Configured as a table in `flox.toml`, e.g.
+///
+/// ```toml
+/// [metrics_sink]
+/// type = "otlp"
+/// endpoint = "http://localhost:4318/v1/logs"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MetricsSink {
+    /// Send metrics and error reports to flox
+    #[default]
+    Flox,
+    /// Append metrics as JSON lines to a local file
+    File { path: PathBuf },
+    /// Export metrics as log records to an OpenTelemetry collector via OTLP/HTTP
+    Otlp {
+        /// The full URL of the logs endpoint,
+        /// usually `http(s)://<collector>:4318/v1/logs`
+        endpoint: Url,
+        /// Additional headers sent with every request, e.g. for authentication
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Don't collect metrics
+    None,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum EnvironmentPromptConfig {
@@ -442,6 +561,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_metrics_sink() {
+        let tempdir = tempfile::tempdir().unwrap();
+        fs::write(tempdir.path().join(FLOX_CONFIG_FILE), indoc! {r#"
+            [metrics_sink]
+            type = "otlp"
+            endpoint = "http://localhost:4318/v1/logs"
+            headers = { authorization = "Bearer token" }
+        "#})
+        .unwrap();
+
+        temp_env::with_vars(
+            [
+                (
+                    "HOME",
+                    Some(tempdir.path().as_os_str().to_string_lossy().as_ref()),
+                ),
+                (
+                    FLOX_CONFIG_DIR_VAR,
+                    Some(tempdir.path().as_os_str().to_string_lossy().as_ref()),
+                ),
+            ],
+            || {
+                let config = Config::parse().unwrap();
+                assert_eq!(
+                    config.flox.metrics_sink,
+                    Some(MetricsSink::Otlp {
+                        endpoint: Url::parse("http://localhost:4318/v1/logs").unwrap(),
+                        headers: HashMap::from([(
+                            "authorization".to_string(),
+                            "Bearer token".to_string()
+                        )]),
+                    })
+                );
+            },
+        );
+    }
+
     #[test]
     fn test_writing_value() {
         let config_content = Config::write_to(
@@ -508,6 +665,59 @@ mod tests {
         assert!(matches!(config_content, Err(ReadWriteError::InvalidKey(_))));
     }
 
+    #[test]
+    fn trust_policy_prefers_exact_environment_over_owner() {
+        let config = FloxConfig {
+            trusted_environments: toml::from_str(indoc! {r#"
+                "team/*" = "trust"
+                "team/pinned" = { generation = 4 }
+                "team/denied" = "deny"
+            "#})
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let policy = |env_ref: &str| config.trust_policy(&env_ref.parse().unwrap()).cloned();
+
+        assert_eq!(
+            policy("team/other"),
+            Some(TrustPolicy::Always(EnvironmentTrust::Trust))
+        );
+        assert_eq!(
+            policy("team/denied"),
+            Some(TrustPolicy::Always(EnvironmentTrust::Deny))
+        );
+        assert_eq!(
+            policy("team/pinned"),
+            Some(TrustPolicy::Pinned(PinnedTrust {
+                generation: Some(4),
+                scripts_hash: None
+            }))
+        );
+        assert_eq!(policy("other/env"), None);
+    }
+
+    #[test]
+    fn only_flox_sink_reports_to_flox() {
+        let config = |disable_metrics, metrics_sink| FloxConfig {
+            disable_metrics,
+            metrics_sink,
+            ..Default::default()
+        };
+
+        assert!(config(false, None).reports_to_flox());
+        assert!(config(false, Some(MetricsSink::Flox)).reports_to_flox());
+        assert!(!config(true, Some(MetricsSink::Flox)).reports_to_flox());
+        assert!(!config(false, Some(MetricsSink::None)).reports_to_flox());
+        assert!(!config(
+            false,
+            Some(MetricsSink::File {
+                path: "metrics.jsonl".into()
+            })
+        )
+        .reports_to_flox());
+    }
+
     #[test]
     fn test_remove() {
         let config_before = indoc! {"
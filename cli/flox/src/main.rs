@@ -80,14 +80,14 @@ fn main() -> ExitCode {
         return ExitCode::from(1);
     }
 
-    let disable_metrics = config::Config::parse()
+    let reports_to_flox = config::Config::parse()
         .unwrap_or_default()
         .flox
-        .disable_metrics;
+        .reports_to_flox();
 
     // Sentry client must be initialized before starting an async runtime or spawning threads
     // https://docs.sentry.io/platforms/rust/#async-main-function
-    let _sentry_guard = (!disable_metrics).then(init_sentry);
+    let _sentry_guard = reports_to_flox.then(init_sentry);
     let _metrics_guard = Hub::global().try_guard().ok();
 
     // Pass down the verbosity level to all pkgdb calls
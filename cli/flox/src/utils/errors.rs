@@ -1,4 +1,5 @@
 use flox_rust_sdk::data::CanonicalizeError;
+use flox_rust_sdk::models::environment::include::IncludeError;
 use flox_rust_sdk::models::environment::managed_environment::{
     ManagedEnvironmentError,
     GENERATION_LOCK_FILENAME,
@@ -386,9 +387,31 @@ pub fn format_core_error(err: &CoreEnvironmentError) -> String {
                 To upgrade all packages, run:
                     $ flox upgrade
             "},
+            UpgradeError::IncludesWithPackages => formatdoc! {"
+                Included environments can't be upgraded together with packages.
+                Run 'flox upgrade --includes' without specifying packages or groups.
+            "},
         },
         // User facing
         CoreEnvironmentError::Services(err) => display_chain(err),
+        CoreEnvironmentError::Include(err) => format_include_error(err),
+        CoreEnvironmentError::Secrets(err) => display_chain(err),
+    }
+}
+
+fn format_include_error(err: &IncludeError) -> String {
+    match err {
+        IncludeError::Conflict { name, .. } => formatdoc! {"
+            {err}.
+
+            Define '{name}' in the manifest of this environment to resolve the conflict.
+        "},
+        IncludeError::NoBaseDirectory(_) => formatdoc! {"
+            {err}.
+
+            Use an absolute path or include the environment from a local environment.
+        "},
+        _ => display_chain(err),
     }
 }
 
@@ -504,6 +527,8 @@ pub fn format_managed_error(err: &ManagedEnvironmentError) -> String {
             This can happen if the environment is modified and pushed from another machine.
 
             To resolve this issue, either
+             * run 'flox pull --merge' or 'flox push --merge'
+               to merge the local and remote changes.
              * run 'flox pull --force'
                to discard local changes
                and reset the environment to the latest upstream version.
@@ -512,6 +537,15 @@ pub fn format_managed_error(err: &ManagedEnvironmentError) -> String {
                Attention: this will discard any changes made on the remote machine
                and cause conflicts when the remote machine tries to pull or push!
         "},
+        ManagedEnvironmentError::CopyGenerations(_) => display_chain(err),
+        ManagedEnvironmentError::Merge(_) => display_chain(err),
+        ManagedEnvironmentError::WriteMergedManifest(err) => formatdoc! {"
+            Failed to write the merged manifest: {err}
+
+            Please ensure that you have write permissions to '.flox/env/manifest.toml'.
+        "},
+        ManagedEnvironmentError::ReadPendingMerge(_)
+        | ManagedEnvironmentError::WritePendingMerge(_) => display_chain(err),
         ManagedEnvironmentError::AccessDenied => formatdoc! {"
             Access denied to the remote environment.
 
@@ -673,6 +707,11 @@ pub fn format_environment_select_error(err: &EnvironmentSelectError) -> String {
         EnvironmentSelectError::EnvNotFoundInCurrentDirectory => formatdoc! {"
             Did not find an environment in the current directory.
         "},
+        EnvironmentSelectError::AliasNotFound(alias) => formatdoc! {"
+            No environment alias named '{alias}'.
+
+            Run 'flox envs' to list aliases or 'flox env alias add' to create one.
+        "},
         EnvironmentSelectError::Anyhow(err) => err
             .chain()
             .skip(1)
@@ -738,12 +777,16 @@ pub fn format_locked_manifest_error(err: &LockedManifestError) -> String {
         "},
 
         LockedManifestError::SystemUnavailableInManifest { .. } => display_chain(err),
+        LockedManifestError::TargetSystemUnavailable { .. } => display_chain(err),
+        LockedManifestError::UnknownOutputs { .. } => display_chain(err),
 
         LockedManifestError::ResolutionFailed(_) => display_chain(err),
         LockedManifestError::EmptyPage => display_chain(err),
         // User facing
         LockedManifestError::LicenseNotAllowed(..) => display_chain(err),
         // User facing
+        LockedManifestError::LicenseDenied(..) => display_chain(err),
+        // User facing
         LockedManifestError::BrokenNotAllowed(_) => display_chain(err),
         // User facing
         LockedManifestError::UnfreeNotAllowed(_) => display_chain(err),
@@ -37,9 +37,9 @@ pub fn init_catalog_client(config: &Config) -> Result<Client, anyhow::Error> {
         Ok(MockClient::new(Some(path))?.into())
     } else {
         let extra_headers = {
-            // If metrics are not disabled, pass along the metrics UUID so it can be
+            // If metrics are sent to flox, pass along the metrics UUID so it can be
             // sent in catalog request headers, as well as the Sentry span info
-            if !config.flox.disable_metrics {
+            if config.flox.reports_to_flox() {
                 let mut metrics_headers = BTreeMap::new();
                 metrics_headers.insert(
                     "flox-device-uuid".to_string(),
@@ -19,7 +19,7 @@ use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
 use super::TRAILING_NETWORK_CALL_TIMEOUT;
-use crate::config::Config;
+use crate::config::{Config, MetricsSink};
 
 pub const METRICS_EVENTS_FILE_NAME: &str = "metrics-events-v2.json";
 pub const METRICS_UUID_FILE_NAME: &str = "metrics-uuid";
@@ -468,37 +468,14 @@ impl Connection for AWSDatalakeConnection {
         debug!("Sending metrics to {}", &self.endpoint_url);
         debug!("Metrics: {events:#}");
 
-        // Wrap the blocking reqwest client in a thread where we can provide our
-        // own timeout to kill the thread.
-        // The blocking reqwest client makes a call to getaddrinfo which uses the
-        // system libc. This doesn't respect the timeout of the reqwest client
-        // and can block our program for however long it can take DNS to timeout:
-        // https://github.com/flox/flox/pull/1769#issuecomment-2260675622
-        // This can often be greater than 15 or 30 seconds. It shows up often when
-        // a local resolver is configured to forward to the internet, but Wi-Fi
-        // is disabled, but also could occur whenever DNS doesn't respond.
-        let thread_timeout = self.timeout;
-        let thread_endpoint_url = self.endpoint_url.clone();
-        let thread_api_key = self.api_key.clone();
-
-        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
-        std::thread::spawn(move || {
-            let result = reqwest::blocking::ClientBuilder::new()
-                .timeout(thread_timeout)
-                .build()
-                .unwrap()
-                .put(thread_endpoint_url)
-                .header("content-type", "application/json")
-                .header("x-api-key", thread_api_key)
-                .header("user-agent", format!("flox-cli/{}", &*FLOX_VERSION))
+        let endpoint_url = self.endpoint_url.clone();
+        let api_key = self.api_key.clone();
+        let result = send_with_timeout(self.timeout, move |client| {
+            client
+                .put(endpoint_url)
+                .header("x-api-key", api_key)
                 .json(&events)
-                .send();
-            let _ = sender.send(result); // ignore if the receiver is dropped
-        });
-
-        let result = receiver
-            .recv_timeout(self.timeout)
-            .context("metrics api request")??;
+        })?;
 
         tracing::debug!(?result, "Metrics sent");
 
@@ -520,6 +497,200 @@ impl Default for AWSDatalakeConnection {
     }
 }
 
+/// Send the request built by `build_request` and wait for at most `timeout`
+/// for the response.
+fn send_with_timeout(
+    timeout: TimeoutDuration,
+    build_request: impl FnOnce(reqwest::blocking::Client) -> reqwest::blocking::RequestBuilder
+        + Send
+        + 'static,
+) -> Result<reqwest::blocking::Response> {
+    // Wrap the blocking reqwest client in a thread where we can provide our
+    // own timeout to kill the thread.
+    // The blocking reqwest client makes a call to getaddrinfo which uses the
+    // system libc. This doesn't respect the timeout of the reqwest client
+    // and can block our program for however long it can take DNS to timeout:
+    // https://github.com/flox/flox/pull/1769#issuecomment-2260675622
+    // This can often be greater than 15 or 30 seconds. It shows up often when
+    // a local resolver is configured to forward to the internet, but Wi-Fi
+    // is disabled, but also could occur whenever DNS doesn't respond.
+    let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+    std::thread::spawn(move || {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(timeout)
+            .build()
+            .unwrap();
+        let result = build_request(client)
+            .header("content-type", "application/json")
+            .header("user-agent", format!("flox-cli/{}", &*FLOX_VERSION))
+            .send();
+        let _ = sender.send(result); // ignore if the receiver is dropped
+    });
+
+    let result = receiver
+        .recv_timeout(timeout)
+        .context("metrics api request")??;
+    Ok(result)
+}
+
+/// Connection that appends metrics as JSON lines to a local file
+///
+/// Entries are written in the same format as the [MetricsBuffer],
+/// so the file can be processed with the same tools.
+#[derive(Debug)]
+pub struct FileConnection {
+    pub path: PathBuf,
+}
+
+impl Connection for FileConnection {
+    fn send(&mut self, entries: Vec<&MetricEntry>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .with_context(|| format!("could not open metrics file {}", self.path.display()))?;
+
+        let mut lines = String::new();
+        for entry in entries {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())
+            .context("could not write metrics to file")?;
+
+        debug!("Metrics written to {}", self.path.display());
+        Ok(())
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// Connection to an OpenTelemetry collector
+///
+/// Every [MetricEntry] is exported as a log record using the JSON encoding
+/// of OTLP/HTTP, see <https://opentelemetry.io/docs/specs/otlp/#otlphttp>.
+#[derive(Debug)]
+pub struct OtlpConnection {
+    pub timeout: TimeoutDuration,
+    /// URL of the logs endpoint of the collector, e.g. `http://localhost:4318/v1/logs`
+    pub endpoint_url: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl OtlpConnection {
+    /// Render the entries as an `ExportLogsServiceRequest`
+    fn export_request(entries: Vec<&MetricEntry>) -> serde_json::Value {
+        let log_records = entries
+            .into_iter()
+            .map(|entry| {
+                let time_unix_nano = entry.timestamp.unix_timestamp_nanos().to_string();
+
+                let mut attributes = vec![
+                    otlp_attribute("flox.subcommand", &entry.subcommand),
+                    otlp_attribute("flox.cli.uuid", &Some(entry.uuid.to_string())),
+                    otlp_attribute("flox.version", &Some(entry.flox_version.clone())),
+                    otlp_attribute("os.type", &entry.os_family),
+                    otlp_attribute("os.kernel_version", &entry.os_family_release),
+                    otlp_attribute("os.name", &entry.os),
+                    otlp_attribute("os.version", &entry.os_version),
+                ];
+                let mut extras = entry.extras.iter().collect::<Vec<_>>();
+                extras.sort();
+                attributes.extend(extras.into_iter().map(|(key, value)| {
+                    otlp_attribute(&format!("flox.extras.{key}"), &Some(value.clone()))
+                }));
+
+                json!({
+                    "timeUnixNano": time_unix_nano,
+                    "observedTimeUnixNano": time_unix_nano,
+                    "severityText": "INFO",
+                    "body": { "stringValue": "cli-invocation" },
+                    "attributes": attributes.into_iter().flatten().collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "resourceLogs": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": "flox-cli" } },
+                        { "key": "service.version", "value": { "stringValue": FLOX_VERSION.to_string() } },
+                    ],
+                },
+                "scopeLogs": [{
+                    "scope": { "name": "flox-cli" },
+                    "logRecords": log_records,
+                }],
+            }],
+        })
+    }
+}
+
+/// An OTLP string attribute, or [None] if the value is not set
+fn otlp_attribute(key: &str, value: &Option<String>) -> Option<serde_json::Value> {
+    value
+        .as_ref()
+        .map(|value| json!({ "key": key, "value": { "stringValue": value } }))
+}
+
+impl Connection for OtlpConnection {
+    fn send(&mut self, entries: Vec<&MetricEntry>) -> Result<()> {
+        let export_request = Self::export_request(entries);
+
+        debug!("Sending metrics to {}", &self.endpoint_url);
+        debug!("Metrics: {export_request:#}");
+
+        let endpoint_url = self.endpoint_url.clone();
+        let headers = self.headers.clone();
+        let result = send_with_timeout(self.timeout, move |client| {
+            headers
+                .into_iter()
+                .fold(client.post(endpoint_url), |request, (name, value)| {
+                    request.header(name, value)
+                })
+                .json(&export_request)
+        })?;
+
+        // Unlike the flox backend, collectors report invalid requests,
+        // keep the metrics buffered in that case.
+        result
+            .error_for_status()
+            .context("OpenTelemetry collector rejected metrics")?;
+        tracing::debug!("Metrics sent");
+
+        Ok(())
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// Create the [Connection] for the configured [MetricsSink]
+///
+/// Returns [None] if metrics should not be collected.
+pub fn connection_for_sink(sink: &MetricsSink) -> Option<Box<dyn Connection>> {
+    match sink {
+        MetricsSink::Flox => Some(AWSDatalakeConnection::default().boxed()),
+        MetricsSink::File { path } => Some(FileConnection { path: path.clone() }.boxed()),
+        MetricsSink::Otlp { endpoint, headers } => Some(
+            OtlpConnection {
+                timeout: TRAILING_NETWORK_CALL_TIMEOUT,
+                endpoint_url: endpoint.to_string(),
+                headers: headers.clone(),
+            }
+            .boxed(),
+        ),
+        MetricsSink::None => None,
+    }
+}
+
 #[derive(Debug)]
 pub struct Client {
     pub uuid: Uuid,
@@ -530,14 +701,14 @@ pub struct Client {
 
 impl Client {
     /// Create a new client with defaults read from the config
-    pub fn new_with_config(config: &Config, connection: impl Connection + 'static) -> Result<Self> {
+    pub fn new_with_config(config: &Config, connection: Box<dyn Connection>) -> Result<Self> {
         let uuid = read_metrics_uuid(config)?;
         let metrics_dir = config.flox.cache_dir.clone();
         Ok(Client {
             uuid,
             metrics_dir,
             max_age: DEFAULT_BUFFER_EXPIRY,
-            connection: connection.boxed(),
+            connection,
         })
     }
 
@@ -585,6 +756,7 @@ impl Drop for MetricGuard {
 mod tests {
     use std::fs;
 
+    use httpmock::prelude::*;
     use tempfile::TempDir;
     use tracing_subscriber::layer::SubscriberExt;
 
@@ -765,7 +937,7 @@ mod tests {
             ..Default::default()
         };
 
-        let client = Client::new_with_config(&config, TestConnection::default()).unwrap();
+        let client = Client::new_with_config(&config, TestConnection::default().boxed()).unwrap();
 
         assert_eq!(client.uuid, uuid);
         assert_eq!(client.metrics_dir, cache_dir);
@@ -826,6 +998,110 @@ mod tests {
         assert_eq!(entry_bar.subcommand, event_bar.subcommand);
     }
 
+    /// Test that [FileConnection] appends entries as JSON lines
+    #[test]
+    fn test_file_connection_appends_entries() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("metrics").join("events.jsonl");
+        let mut connection = FileConnection { path: path.clone() };
+
+        let entry_foo = make_entry("foo");
+        let entry_bar = make_entry("bar");
+        connection.send(vec![&entry_foo]).unwrap();
+        connection.send(vec![&entry_bar]).unwrap();
+
+        let entries = serde_json::Deserializer::from_str(&fs::read_to_string(path).unwrap())
+            .into_iter::<MetricEntry>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(entries, vec![entry_foo, entry_bar]);
+    }
+
+    /// Test that [OtlpConnection] posts log records to the collector
+    #[test]
+    fn test_otlp_connection_exports_logs() {
+        let server = MockServer::start();
+        let mut entry = make_entry("foo");
+        entry.extras.insert("bar".to_string(), "baz".to_string());
+
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/logs")
+                .header("authorization", "Bearer token")
+                .header("content-type", "application/json")
+                .json_body_partial(
+                    json!({
+                        "resourceLogs": [{
+                            "scopeLogs": [{
+                                "logRecords": [{
+                                    "body": { "stringValue": "cli-invocation" },
+                                    "attributes": [
+                                        { "key": "flox.subcommand", "value": { "stringValue": "foo" } },
+                                    ],
+                                }],
+                            }],
+                        }],
+                    })
+                    .to_string(),
+                )
+                .body_contains(r#""key":"flox.extras.bar","value":{"stringValue":"baz"}"#);
+            then.status(200);
+        });
+
+        let mut connection = OtlpConnection {
+            timeout: TimeoutDuration::from_secs(5),
+            endpoint_url: server.url("/v1/logs"),
+            headers: HashMap::from([("authorization".to_string(), "Bearer token".to_string())]),
+        };
+        connection.send(vec![&entry]).unwrap();
+
+        mock.assert();
+    }
+
+    /// Test that metrics rejected by the collector are kept in the buffer
+    #[test]
+    fn test_otlp_connection_rejected_keeps_buffer() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/logs");
+            then.status(400);
+        });
+
+        let (mut client, _tempdir) = create_client();
+        client.connection = OtlpConnection {
+            timeout: TimeoutDuration::from_secs(5),
+            endpoint_url: server.url("/v1/logs"),
+            headers: HashMap::new(),
+        }
+        .boxed();
+
+        client.record_metric(make_event("foo")).unwrap();
+        assert!(client.flush(true).is_err());
+        mock.assert();
+
+        let buffer = MetricsBuffer::read(&client.metrics_dir).unwrap();
+        assert_eq!(buffer.buffer.len(), 1);
+    }
+
+    /// Test that sinks are mapped to the expected connections
+    #[test]
+    fn test_connection_for_sink() {
+        assert!(connection_for_sink(&MetricsSink::None).is_none());
+
+        let connection = connection_for_sink(&MetricsSink::File {
+            path: PathBuf::from("/metrics.jsonl"),
+        })
+        .unwrap();
+        let connection: Box<FileConnection> = connection.into_any().downcast().unwrap();
+        assert_eq!(connection.path, PathBuf::from("/metrics.jsonl"));
+
+        let connection = connection_for_sink(&MetricsSink::Flox).unwrap();
+        assert!(connection
+            .into_any()
+            .downcast::<AWSDatalakeConnection>()
+            .is_ok());
+    }
+
     /// Test that [Hub::try_guard] returns a guard as expected
     /// And that the guard flushes the metrics on drop when the buffer is expired.
     /// And that the guard does not flush the metrics when the buffer is not expired.
@@ -1588,6 +1588,36 @@ EOF
 
 # ---------------------------------------------------------------------------- #
 
+# bats test_tags=activate,activate:vars
+@test "vars: references are resolved but not stored in the activation state" {
+  project_setup
+  echo 'FROM_FILE=file-secret-value' > "$PROJECT_DIR/.env"
+  MANIFEST_CONTENTS="$(cat << "EOF"
+    version = 1
+
+    [vars]
+    FROM_FILE = { env-file = [".env"] }
+    FROM_COMMAND = { command = "echo", ref = "command-secret-value" }
+    WITH_DEFAULT = "${WITH_DEFAULT:-default-value}"
+EOF
+  )"
+  echo "$MANIFEST_CONTENTS" | "$FLOX_BIN" edit -f -
+
+  FLOX_SHELL="bash" run --separate-stderr "$FLOX_BIN" activate -- bash -c '
+    echo "$FROM_FILE $FROM_COMMAND $WITH_DEFAULT"
+    grep -r -e file-secret-value -e command-secret-value "$_FLOX_ACTIVATION_STATE_DIR" \
+      || echo "no secrets in the activation state"
+  '
+  assert_success
+  assert_line "file-secret-value command-secret-value default-value"
+  assert_line "no secrets in the activation state"
+
+  run grep -r -e file-secret-value -e command-secret-value "$PROJECT_DIR/.flox"
+  assert_failure
+}
+
+# ---------------------------------------------------------------------------- #
+
 # bats test_tags=activate:scripts:on-activate,activate:scripts:on-activate:bash
 @test "'hook.on-activate' modifies environment variables (bash)" {
   project_setup